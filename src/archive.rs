@@ -0,0 +1,184 @@
+use crate::types::PolError;
+use bitcoin::hashes::{sha256, Hash};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+const INDEX_FILE: &str = "index.jsonl";
+const OBJECTS_DIR: &str = "objects";
+
+/// A report version as first fetched from one source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub mint: String,
+    pub source: String,
+    /// SHA-256 of the exact bytes served, naming the archived object
+    pub hash: sha256::Hash,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Content-addressed store of every report version fetched from mints.
+/// `objects/<hash>.json` holds the bytes exactly as served, so signatures
+/// stay checkable, and `index.jsonl` records when each source started
+/// serving each version. A relay serves several versions at once, so a
+/// version is indexed once per source rather than on every change.
+pub struct Archive {
+    dir: PathBuf,
+    /// Versions indexed per mint and source
+    indexed: HashSet<(String, String, sha256::Hash)>,
+}
+
+impl Archive {
+    pub async fn open<P: Into<PathBuf>>(dir: P) -> Result<Self, PolError> {
+        let dir = dir.into();
+        tokio::fs::create_dir_all(dir.join(OBJECTS_DIR))
+            .await
+            .map_err(|e| PolError::ArchiveFailed(format!("{}: {}", dir.display(), e)))?;
+
+        let mut archive = Self {
+            dir,
+            indexed: HashSet::new(),
+        };
+        for entry in archive.entries().await? {
+            archive
+                .indexed
+                .insert((entry.mint, entry.source, entry.hash));
+        }
+        Ok(archive)
+    }
+
+    pub fn object_path(&self, hash: &sha256::Hash) -> PathBuf {
+        self.dir.join(OBJECTS_DIR).join(format!("{}.json", hash))
+    }
+
+    /// Whether the version hashing to `hash` is already indexed for
+    /// `source`.
+    pub fn contains(&self, mint: &str, source: &str, hash: &sha256::Hash) -> bool {
+        self.indexed
+            .contains(&(mint.to_string(), source.to_string(), *hash))
+    }
+
+    /// Archives `data` as fetched from `source`. Returns the new index
+    /// entry, or `None` if the version is already indexed for the source.
+    pub async fn store(
+        &mut self,
+        mint: &str,
+        source: &str,
+        data: &[u8],
+    ) -> Result<Option<ArchiveEntry>, PolError> {
+        let hash = sha256::Hash::hash(data);
+        if self.contains(mint, source, &hash) {
+            return Ok(None);
+        }
+
+        let path = self.object_path(&hash);
+        if !path.exists() {
+            // Write then rename so a crash never leaves a truncated object
+            // under a valid hash
+            let partial = path.with_extension("partial");
+            tokio::fs::write(&partial, data)
+                .await
+                .map_err(|e| archive_error(&partial, e))?;
+            tokio::fs::rename(&partial, &path)
+                .await
+                .map_err(|e| archive_error(&path, e))?;
+        }
+
+        let entry = ArchiveEntry {
+            mint: mint.to_string(),
+            source: source.to_string(),
+            hash,
+            fetched_at: Utc::now(),
+        };
+        let mut line =
+            serde_json::to_vec(&entry).map_err(|e| PolError::ArchiveFailed(e.to_string()))?;
+        line.push(b'\n');
+        let index = self.dir.join(INDEX_FILE);
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index)
+            .await
+            .map_err(|e| archive_error(&index, e))?;
+        file.write_all(&line)
+            .await
+            .map_err(|e| archive_error(&index, e))?;
+
+        self.indexed
+            .insert((mint.to_string(), source.to_string(), hash));
+        Ok(Some(entry))
+    }
+
+    /// Every index entry, oldest first.
+    pub async fn entries(&self) -> Result<Vec<ArchiveEntry>, PolError> {
+        let index = self.dir.join(INDEX_FILE);
+        let data = match tokio::fs::read_to_string(&index).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(archive_error(&index, e)),
+        };
+        data.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line)
+                    .map_err(|e| PolError::ArchiveFailed(format!("{}: {}", index.display(), e)))
+            })
+            .collect()
+    }
+
+    pub async fn read(&self, hash: &sha256::Hash) -> Result<Vec<u8>, PolError> {
+        let path = self.object_path(hash);
+        tokio::fs::read(&path)
+            .await
+            .map_err(|e| archive_error(&path, e))
+    }
+}
+
+fn archive_error(path: &Path, e: std::io::Error) -> PolError {
+    PolError::ArchiveFailed(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_archive_keeps_every_version_once() {
+        let temp_dir = tempdir().unwrap();
+        let mut archive = Archive::open(temp_dir.path()).await.unwrap();
+
+        let first = archive.store("mint", "https://a", b"v1").await.unwrap();
+        assert!(first.is_some());
+        assert!(archive
+            .store("mint", "https://a", b"v1")
+            .await
+            .unwrap()
+            .is_none());
+        // Another source serving the same bytes shares the object
+        let mirrored = archive.store("mint", "https://b", b"v1").await.unwrap();
+        assert_eq!(mirrored.unwrap().hash, first.unwrap().hash);
+        archive.store("mint", "https://a", b"v2").await.unwrap();
+        // A source serving several versions at once indexes each once
+        assert!(archive.contains("mint", "https://a", &sha256::Hash::hash(b"v1")));
+        assert!(archive
+            .store("mint", "https://a", b"v1")
+            .await
+            .unwrap()
+            .is_none());
+
+        let mut reopened = Archive::open(temp_dir.path()).await.unwrap();
+        assert_eq!(reopened.entries().await.unwrap().len(), 3);
+        assert!(reopened
+            .store("mint", "https://a", b"v2")
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            reopened.read(&sha256::Hash::hash(b"v1")).await.unwrap(),
+            b"v1"
+        );
+    }
+}
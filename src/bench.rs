@@ -0,0 +1,150 @@
+use crate::output::{self, OutputFormat};
+use bitcoin::Amount;
+use cashu_pol::PolService;
+use cdk::nuts::{nut00::Proof, nut01::PublicKey, nut02::Id};
+use cdk::secret::Secret;
+use cdk::Amount as CashuAmount;
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use std::error::Error;
+use std::path::Path;
+use std::time::Instant;
+
+const MINT_SAT: u64 = 1000;
+const BURN_SAT: u64 = 400;
+
+pub struct BenchConfig {
+    pub epochs: u64,
+    pub mints_per_epoch: u64,
+    pub burns_per_epoch: u64,
+    pub epoch_days: i64,
+}
+
+#[derive(Serialize)]
+struct BenchResult {
+    epochs: u64,
+    mints: u64,
+    burns: u64,
+    mint_secs: f64,
+    burn_secs: f64,
+    report_secs: f64,
+    db_bytes: u64,
+}
+
+/// A proof with a fresh random secret and a placeholder signature, good
+/// enough for driving the service without a mint.
+pub fn synthetic_proof(keyset_id: Id, amount: u64) -> Result<Proof, Box<dyn Error>> {
+    Ok(Proof::new(
+        CashuAmount::from(amount),
+        keyset_id,
+        Secret::generate(),
+        PublicKey::from_slice(&[2; 33])?,
+    ))
+}
+
+/// Populates a throwaway database and prints throughput, report latency
+/// and on-disk size.
+pub async fn run(config: &BenchConfig, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let db_path = std::env::temp_dir().join(format!("cashu-pol-bench-{}.db", std::process::id()));
+    let result = measure(config, &db_path).await;
+    let _ = std::fs::remove_file(&db_path);
+
+    match format {
+        OutputFormat::Json => output::print(format, &result?),
+        OutputFormat::Text => {
+            print_summary(&result?);
+            Ok(())
+        }
+    }
+}
+
+async fn measure(config: &BenchConfig, db_path: &Path) -> Result<BenchResult, Box<dyn Error>> {
+    let service = PolService::with_path(config.epoch_days, config.epochs as usize, db_path)?;
+    let epoch_duration = Duration::days(config.epoch_days);
+    let start = Utc::now() - epoch_duration * (config.epochs as i32 - 1) - Duration::hours(1);
+    service.initialize_from(start).await?;
+
+    let keyset_id = Id::from_bytes(&[0; 8])?;
+    let began = Instant::now();
+    for epoch in 0..config.epochs {
+        let epoch_start = start + epoch_duration * epoch as i32;
+        for i in 0..config.mints_per_epoch {
+            let proof = synthetic_proof(keyset_id, MINT_SAT)?;
+            service
+                .record_mint_proof_at(
+                    proof,
+                    Amount::from_sat(MINT_SAT),
+                    epoch_start + Duration::seconds(i as i64),
+                )
+                .await?;
+        }
+    }
+    let mint_secs = began.elapsed().as_secs_f64();
+
+    let began = Instant::now();
+    for epoch in 0..config.epochs {
+        let epoch_start = start + epoch_duration * epoch as i32;
+        for i in 0..config.burns_per_epoch {
+            service
+                .record_burn_proof_at(
+                    format!("bench-{}-{}", epoch, i),
+                    Amount::from_sat(BURN_SAT),
+                    epoch_start + Duration::seconds(i as i64),
+                )
+                .await?;
+        }
+    }
+    let burn_secs = began.elapsed().as_secs_f64();
+
+    let began = Instant::now();
+    service.generate_report().await?;
+    let report_secs = began.elapsed().as_secs_f64();
+
+    drop(service);
+    Ok(BenchResult {
+        epochs: config.epochs,
+        mints: config.epochs * config.mints_per_epoch,
+        burns: config.epochs * config.burns_per_epoch,
+        mint_secs,
+        burn_secs,
+        report_secs,
+        db_bytes: std::fs::metadata(db_path)?.len(),
+    })
+}
+
+fn per_second(count: u64, secs: f64) -> String {
+    if secs > 0.0 {
+        format!("{:.0}/s", count as f64 / secs)
+    } else {
+        "-".to_string()
+    }
+}
+
+fn print_summary(result: &BenchResult) {
+    let rows = [
+        ("epochs", result.epochs.to_string()),
+        ("mint proofs", result.mints.to_string()),
+        ("burn proofs", result.burns.to_string()),
+        (
+            "mint throughput",
+            per_second(result.mints, result.mint_secs),
+        ),
+        (
+            "burn throughput",
+            per_second(result.burns, result.burn_secs),
+        ),
+        (
+            "report latency",
+            format!("{:.1} ms", result.report_secs * 1000.0),
+        ),
+        (
+            "db size",
+            format!("{:.1} KiB", result.db_bytes as f64 / 1024.0),
+        ),
+    ];
+
+    println!("{:<18} {:>14}", "metric", "value");
+    for (metric, value) in rows {
+        println!("{:<18} {:>14}", metric, value);
+    }
+}
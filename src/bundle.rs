@@ -0,0 +1,422 @@
+use crate::signer::{verify_signature, Signer};
+use crate::spec::SPEC_VERSION;
+use crate::types::{
+    EpochAttestation, FinalizedEpoch, HistoryHead, KeysetRecord, PolError, ReportMismatch,
+    ReportSignature, SignaturePolicy, SignedReport,
+};
+use bitcoin::hashes::{sha256, Hash};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+pub const BUNDLE_VERSION: u32 = 1;
+pub const BUNDLE_EXTENSION: &str = "polreport";
+
+const MANIFEST: &str = "manifest.json";
+// Signature over the SHA-256 of the manifest bytes, so it stays outside them
+const MANIFEST_SIGNATURE: &str = "manifest.sig";
+const REPORT: &str = "report.json";
+const SEALS: &str = "seals.json";
+const ATTESTATIONS: &str = "attestations.json";
+const PARAMETERS: &str = "parameters.json";
+const RESERVES_DIR: &str = "reserves/";
+
+/// Lists every other file in a bundle with its SHA-256, so a bundle cut
+/// short or edited after the fact is caught. The report signer signs it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub files: BTreeMap<String, sha256::Hash>,
+}
+
+/// What a verifier needs besides the report to check inclusion and
+/// consistency proofs handed out later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofParameters {
+    pub spec_version: String,
+    /// Head of the history log that consistency proofs extend
+    pub history: Option<HistoryHead>,
+    /// Keysets that signed the proofs the report commits to
+    pub keysets: Vec<KeysetRecord>,
+}
+
+/// Outcome of [`ReportBundle::verify`], with what was checked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleVerification {
+    pub files: usize,
+    pub epochs: usize,
+    pub signatures: usize,
+    pub seals: usize,
+    pub attestations: usize,
+    pub reserves: usize,
+    pub mismatches: Vec<ReportMismatch>,
+}
+
+/// A `.polreport` file: a tar archive holding a signed report, the seals
+/// and attestations over its epochs, proof parameters and any reserve
+/// attestations, each as its own file so they stay readable with standard
+/// tools. The report is stored as signed, byte for byte.
+#[derive(Debug, Clone)]
+pub struct ReportBundle {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl ReportBundle {
+    /// `reserves` are `(name, contents)` pairs bundled verbatim under
+    /// `reserves/`; only their hashes are checked on verification.
+    /// `signer` signs the manifest and should be one of the report's.
+    pub async fn create(
+        report: &SignedReport,
+        seals: &[FinalizedEpoch],
+        parameters: &ProofParameters,
+        reserves: Vec<(String, Vec<u8>)>,
+        signer: &dyn Signer,
+    ) -> Result<Self, PolError> {
+        let attestations: Vec<&EpochAttestation> = report
+            .report
+            .epoch_reports
+            .iter()
+            .flat_map(|e| &e.attestations)
+            .collect();
+
+        let mut files = BTreeMap::new();
+        files.insert(REPORT.to_string(), to_json(report)?);
+        files.insert(SEALS.to_string(), to_json(&seals)?);
+        files.insert(ATTESTATIONS.to_string(), to_json(&attestations)?);
+        files.insert(PARAMETERS.to_string(), to_json(parameters)?);
+        for (name, data) in reserves {
+            if name.is_empty() || name.contains('/') || name == ".." {
+                return Err(PolError::InvalidBundle(format!(
+                    "Invalid reserve file name: {:?}",
+                    name
+                )));
+            }
+            files.insert(format!("{}{}", RESERVES_DIR, name), data);
+        }
+
+        let manifest = BundleManifest {
+            version: BUNDLE_VERSION,
+            created_at: Utc::now(),
+            files: files
+                .iter()
+                .map(|(path, data)| (path.clone(), sha256::Hash::hash(data)))
+                .collect(),
+        };
+        let manifest = to_json(&manifest)?;
+        let signature = ReportSignature {
+            public_key: signer.public_key(),
+            signature: signer.sign(&sha256::Hash::hash(&manifest)).await?,
+        };
+        files.insert(MANIFEST.to_string(), manifest);
+        files.insert(MANIFEST_SIGNATURE.to_string(), to_json(&signature)?);
+
+        Ok(Self { files })
+    }
+
+    pub fn read<R: Read>(input: R) -> Result<Self, PolError> {
+        let mut archive = tar::Archive::new(input);
+        let mut files = BTreeMap::new();
+        let entries = archive
+            .entries()
+            .map_err(|e| PolError::InvalidBundle(e.to_string()))?;
+        for entry in entries {
+            let mut entry = entry.map_err(|e| PolError::InvalidBundle(e.to_string()))?;
+            let path = entry
+                .path()
+                .map_err(|e| PolError::InvalidBundle(e.to_string()))?
+                .to_string_lossy()
+                .into_owned();
+            let mut data = Vec::new();
+            entry
+                .read_to_end(&mut data)
+                .map_err(|e| PolError::InvalidBundle(format!("{}: {}", path, e)))?;
+            files.insert(path, data);
+        }
+        Ok(Self { files })
+    }
+
+    /// Writes the manifest first, then every other file in path order.
+    pub fn write<W: Write>(&self, output: W) -> Result<(), PolError> {
+        let manifest = self.manifest()?;
+        let mtime = manifest.created_at.timestamp().max(0) as u64;
+        let mut builder = tar::Builder::new(output);
+        let manifest_file = self.files.get_key_value(MANIFEST);
+        for (path, data) in manifest_file
+            .into_iter()
+            .chain(self.files.iter().filter(|(path, _)| *path != MANIFEST))
+        {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, data.as_slice())
+                .map_err(|e| PolError::ExportFailed(format!("{}: {}", path, e)))?;
+        }
+        builder
+            .into_inner()
+            .and_then(|mut output| output.flush())
+            .map_err(|e| PolError::ExportFailed(e.to_string()))
+    }
+
+    pub fn manifest(&self) -> Result<BundleManifest, PolError> {
+        self.parse(MANIFEST)
+    }
+
+    pub fn report(&self) -> Result<SignedReport, PolError> {
+        self.parse(REPORT)
+    }
+
+    /// Checks the manifest's signature and the files against it, the report
+    /// against itself and the caller's trusted signature policy, and every
+    /// seal and attestation against the epoch commitments in the report.
+    /// The manifest and every seal must be signed by a trusted key.
+    pub fn verify(&self, trusted: &SignaturePolicy) -> Result<BundleVerification, PolError> {
+        let manifest = self.manifest()?;
+        let mut mismatches = Vec::new();
+        if manifest.version > BUNDLE_VERSION {
+            mismatches.push(ReportMismatch::new(
+                None,
+                "version",
+                format!(
+                    "bundle version {} is newer than supported",
+                    manifest.version
+                ),
+            ));
+        }
+        if let Some(detail) = self.manifest_signature_error(trusted)? {
+            mismatches.push(ReportMismatch::new(None, "manifest", detail));
+        }
+        for (path, data) in &self.files {
+            if path == MANIFEST || path == MANIFEST_SIGNATURE {
+                continue;
+            }
+            match manifest.files.get(path) {
+                Some(hash) if *hash == sha256::Hash::hash(data) => {}
+                Some(_) => mismatches.push(ReportMismatch::new(
+                    None,
+                    "integrity",
+                    format!("{} does not match its manifest hash", path),
+                )),
+                None => mismatches.push(ReportMismatch::new(
+                    None,
+                    "integrity",
+                    format!("{} is not listed in the manifest", path),
+                )),
+            }
+        }
+        for path in manifest.files.keys() {
+            if !self.files.contains_key(path) {
+                mismatches.push(ReportMismatch::new(
+                    None,
+                    "integrity",
+                    format!("{} is missing", path),
+                ));
+            }
+        }
+
+        let signed = self.report()?;
+        if let Err(e) = signed.verify(trusted) {
+            mismatches.push(ReportMismatch::new(None, "signatures", e.to_string()));
+        }
+        mismatches.extend(signed.report.check_consistency()?);
+        let commitments: BTreeMap<u64, sha256::Hash> = signed
+            .report
+            .epoch_reports
+            .iter()
+            .map(|e| (e.epoch_id, e.commitment))
+            .collect();
+
+        let seals: Vec<FinalizedEpoch> = self.parse(SEALS)?;
+        for seal in &seals {
+            if commitments.get(&seal.epoch_id) != Some(&seal.commitment) {
+                mismatches.push(ReportMismatch::new(
+                    Some(seal.epoch_id),
+                    "seal",
+                    "sealed commitment differs from the report".to_string(),
+                ));
+            }
+            let detail = match &seal.signature {
+                None => Some("seal is unsigned".to_string()),
+                Some(signature) if !trusted.signers.contains(&signature.public_key) => {
+                    Some(format!("sealed by untrusted key {}", signature.public_key))
+                }
+                Some(signature) => verify_signature(
+                    &seal.commitment,
+                    &signature.signature,
+                    &signature.public_key,
+                )
+                .err()
+                .map(|e| e.to_string()),
+            };
+            if let Some(detail) = detail {
+                mismatches.push(ReportMismatch::new(Some(seal.epoch_id), "seal", detail));
+            }
+        }
+
+        let attestations: Vec<EpochAttestation> = self.parse(ATTESTATIONS)?;
+        for attestation in &attestations {
+            let detail = match attestation.verify() {
+                Err(e) => Some(e.to_string()),
+                Ok(())
+                    if commitments.get(&attestation.epoch_id) != Some(&attestation.commitment) =>
+                {
+                    Some("attested commitment differs from the report".to_string())
+                }
+                Ok(()) => None,
+            };
+            if let Some(detail) = detail {
+                mismatches.push(ReportMismatch::new(
+                    Some(attestation.epoch_id),
+                    "attestation",
+                    detail,
+                ));
+            }
+        }
+        let parameters: ProofParameters = self.parse(PARAMETERS)?;
+        if parameters.spec_version != SPEC_VERSION {
+            mismatches.push(ReportMismatch::new(
+                None,
+                "parameters",
+                format!("unsupported spec version {}", parameters.spec_version),
+            ));
+        }
+
+        Ok(BundleVerification {
+            files: self.files.len(),
+            epochs: commitments.len(),
+            signatures: signed.signatures.len(),
+            seals: seals.len(),
+            attestations: attestations.len(),
+            reserves: self
+                .files
+                .keys()
+                .filter(|path| path.starts_with(RESERVES_DIR))
+                .count(),
+            mismatches,
+        })
+    }
+
+    /// Why the manifest signature does not hold up, if it does not.
+    fn manifest_signature_error(
+        &self,
+        trusted: &SignaturePolicy,
+    ) -> Result<Option<String>, PolError> {
+        if !self.files.contains_key(MANIFEST_SIGNATURE) {
+            return Ok(Some("manifest is unsigned".to_string()));
+        }
+        let signature: ReportSignature = self.parse(MANIFEST_SIGNATURE)?;
+        if !trusted.signers.contains(&signature.public_key) {
+            return Ok(Some(format!(
+                "manifest signed by untrusted key {}",
+                signature.public_key
+            )));
+        }
+        let digest = sha256::Hash::hash(&self.files[MANIFEST]);
+        Ok(
+            verify_signature(&digest, &signature.signature, &signature.public_key)
+                .err()
+                .map(|e| e.to_string()),
+        )
+    }
+
+    fn parse<T: DeserializeOwned>(&self, path: &str) -> Result<T, PolError> {
+        let data = self
+            .files
+            .get(path)
+            .ok_or_else(|| PolError::InvalidBundle(format!("{} is missing", path)))?;
+        serde_json::from_slice(data)
+            .map_err(|e| PolError::InvalidBundle(format!("{}: {}", path, e)))
+    }
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, PolError> {
+    serde_json::to_vec_pretty(value).map_err(|e| PolError::ExportFailed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::PolService;
+    use crate::signer::{LocalSigner, Signer};
+    use bitcoin::Amount;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_bundle_round_trip_and_tamper() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let signer = LocalSigner::generate();
+        let policy = SignaturePolicy::single(signer.public_key());
+        service.set_signature_policy(policy.clone()).await;
+        service.set_signer(Arc::new(signer)).await;
+        service
+            .record_burn_proof("spent".to_string(), Amount::from_sat(5))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+        service.finalize_epoch(0).await.unwrap();
+
+        let bundle = service
+            .report_bundle(vec![("reserves.json".to_string(), b"{}".to_vec())])
+            .await
+            .unwrap();
+        let mut data = Vec::new();
+        bundle.write(&mut data).unwrap();
+
+        let read = ReportBundle::read(data.as_slice()).unwrap();
+        let verification = read.verify(&policy).unwrap();
+        assert!(verification.mismatches.is_empty(), "{:?}", verification);
+
+        let stranger = SignaturePolicy::single(LocalSigner::generate().public_key());
+        let checks: Vec<String> = read
+            .verify(&stranger)
+            .unwrap()
+            .mismatches
+            .into_iter()
+            .map(|m| m.check)
+            .collect();
+        assert_eq!(checks, ["manifest", "signatures", "seal"]);
+        assert_eq!((verification.seals, verification.reserves), (1, 1));
+
+        let mut tampered = read.clone();
+        tampered
+            .files
+            .insert(format!("{}reserves.json", RESERVES_DIR), b"[]".to_vec());
+        let checks: Vec<String> = tampered
+            .verify(&policy)
+            .unwrap()
+            .mismatches
+            .into_iter()
+            .map(|m| m.check)
+            .collect();
+        assert_eq!(checks, ["integrity"]);
+
+        // Rehashing the manifest over a swapped file breaks its signature
+        let mut manifest = tampered.manifest().unwrap();
+        manifest.files.insert(
+            format!("{}reserves.json", RESERVES_DIR),
+            sha256::Hash::hash(b"[]"),
+        );
+        tampered
+            .files
+            .insert(MANIFEST.to_string(), to_json(&manifest).unwrap());
+        let checks: Vec<String> = tampered
+            .verify(&policy)
+            .unwrap()
+            .mismatches
+            .into_iter()
+            .map(|m| m.check)
+            .collect();
+        assert_eq!(checks, ["manifest"]);
+
+        // Building a bundle publishes nothing
+        assert!(service.storage().get_publications(0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::types::BoxError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, PoisonError, RwLock};
+
+static REGISTERED: RwLock<Vec<Arc<dyn ValueCodec>>> = RwLock::new(Vec::new());
+
+/// A serialization format for stored records.
+pub trait Codec {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, BoxError>;
+
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, BoxError>;
+}
+
+/// Compact, but fields cannot be added to a stored type without breaking
+/// every record written before.
+pub struct Bincode;
+
+/// Smaller than bincode for the many small integers in proofs, with the
+/// same lack of schema evolution.
+pub struct Postcard;
+
+/// Self-describing: structs are written as maps, so fields added later
+/// with `#[serde(default)]` still read from old records.
+pub struct MessagePack;
+
+/// A format supplied by the application rather than this crate. Records
+/// pass through a `serde_json::Value` on the way, so the format has to be
+/// self-describing.
+pub trait ValueCodec: Send + Sync {
+    /// Recorded in the database, so it must never change
+    fn name(&self) -> &'static str;
+
+    fn encode_value(&self, value: &Value) -> Result<Vec<u8>, BoxError>;
+
+    fn decode_value(&self, data: &[u8]) -> Result<Value, BoxError>;
+}
+
+/// Makes `codec` selectable, and databases recorded with its name
+/// openable, for the rest of the process. Register before opening storage.
+pub fn register_codec(codec: Arc<dyn ValueCodec>) -> Result<CodecKind, String> {
+    let name = codec.name();
+    if BUILT_IN.iter().any(|kind| kind.as_str() == name) {
+        return Err(format!("Codec {} is built in", name));
+    }
+    let mut registered = REGISTERED.write().unwrap_or_else(PoisonError::into_inner);
+    registered.retain(|c| c.name() != name);
+    registered.push(codec);
+    Ok(CodecKind::Registered(name))
+}
+
+fn registered(name: &str) -> Result<Arc<dyn ValueCodec>, BoxError> {
+    REGISTERED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|c| c.name() == name)
+        .cloned()
+        .ok_or_else(|| format!("Codec {} is not registered", name).into())
+}
+
+impl Codec for Bincode {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, BoxError> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, BoxError> {
+        Ok(bincode::deserialize(data)?)
+    }
+}
+
+impl Codec for Postcard {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, BoxError> {
+        Ok(postcard::to_stdvec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, BoxError> {
+        Ok(postcard::from_bytes(data)?)
+    }
+}
+
+impl Codec for MessagePack {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, BoxError> {
+        Ok(rmp_serde::to_vec_named(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, BoxError> {
+        Ok(rmp_serde::from_slice(data)?)
+    }
+}
+
+/// The codec a database stores its records with, recorded in the database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CodecKind {
+    /// Written by every database created before codecs were selectable
+    #[default]
+    Bincode,
+    Postcard,
+    MessagePack,
+    /// A codec added with [`register_codec`], by name
+    Registered(&'static str),
+}
+
+const BUILT_IN: [CodecKind; 3] = [
+    CodecKind::Bincode,
+    CodecKind::Postcard,
+    CodecKind::MessagePack,
+];
+
+impl CodecKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bincode => "bincode",
+            Self::Postcard => "postcard",
+            Self::MessagePack => "msgpack",
+            Self::Registered(name) => name,
+        }
+    }
+
+    pub fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, BoxError> {
+        match self {
+            Self::Bincode => Bincode::encode(value),
+            Self::Postcard => Postcard::encode(value),
+            Self::MessagePack => MessagePack::encode(value),
+            Self::Registered(name) => registered(name)?.encode_value(&serde_json::to_value(value)?),
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, BoxError> {
+        match self {
+            Self::Bincode => Bincode::decode(data),
+            Self::Postcard => Postcard::decode(data),
+            Self::MessagePack => MessagePack::decode(data),
+            Self::Registered(name) => Ok(serde_json::from_value(
+                registered(name)?.decode_value(data)?,
+            )?),
+        }
+    }
+}
+
+impl fmt::Display for CodecKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CodecKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bincode" => Ok(Self::Bincode),
+            "postcard" => Ok(Self::Postcard),
+            "msgpack" => Ok(Self::MessagePack),
+            other => registered(other)
+                .map(|codec| Self::Registered(codec.name()))
+                .map_err(|_| format!("Unknown codec: {}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_sample_mint_proof;
+    use crate::types::{BurnProof, MilliSats, MintProof, MintQuoteInfo};
+    use cdk::nuts::nut02::Id;
+    use chrono::Utc;
+    use serde::Deserialize;
+
+    /// JSON bytes, standing in for an application's own format.
+    struct Json;
+
+    impl ValueCodec for Json {
+        fn name(&self) -> &'static str {
+            "json"
+        }
+
+        fn encode_value(&self, value: &Value) -> Result<Vec<u8>, BoxError> {
+            Ok(serde_json::to_vec(value)?)
+        }
+
+        fn decode_value(&self, data: &[u8]) -> Result<Value, BoxError> {
+            Ok(serde_json::from_slice(data)?)
+        }
+    }
+
+    #[test]
+    fn test_codecs_round_trip() {
+        let burn = BurnProof {
+            secret: "secret".to_string(),
+            amount: MilliSats::from_sat(21),
+            timestamp: Utc::now(),
+            melt: None,
+        };
+        let mut mint = create_sample_mint_proof(Id::from_bytes(&[0; 8]).unwrap(), 8u64.into());
+        mint.quote = Some(MintQuoteInfo {
+            quote_id: Some("quote".to_string()),
+            ..Default::default()
+        });
+        let json = register_codec(Arc::new(Json)).unwrap();
+        for codec in [
+            CodecKind::Bincode,
+            CodecKind::Postcard,
+            CodecKind::MessagePack,
+            json,
+        ] {
+            let data = codec.encode(&vec![burn.clone()]).unwrap();
+            assert_eq!(
+                codec.decode::<Vec<BurnProof>>(&data).unwrap(),
+                vec![burn.clone()]
+            );
+            let data = codec.encode(&vec![mint.clone()]).unwrap();
+            assert_eq!(
+                codec.decode::<Vec<MintProof>>(&data).unwrap(),
+                vec![mint.clone()]
+            );
+            assert_eq!(codec.as_str().parse::<CodecKind>().unwrap(), codec);
+        }
+    }
+
+    #[test]
+    fn test_msgpack_reads_records_missing_new_fields() {
+        #[derive(Serialize)]
+        struct Old {
+            amount: u64,
+        }
+        #[derive(Deserialize)]
+        struct New {
+            amount: u64,
+            #[serde(default)]
+            note: Option<String>,
+        }
+
+        let data = MessagePack::encode(&Old { amount: 7 }).unwrap();
+        let new: New = MessagePack::decode(&data).unwrap();
+        assert_eq!((new.amount, new.note), (7, None));
+    }
+}
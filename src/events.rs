@@ -0,0 +1,148 @@
+use crate::types::{BurnProof, ListedBurn, ListedMint, MintProof, PolReport, SignedReport};
+use bitcoin::Amount;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+pub(crate) const EVENT_CHANNEL_CAPACITY: usize = 1024;
+// Reports are large and produced rarely; slow subscribers skip to the latest
+pub(crate) const REPORT_CHANNEL_CAPACITY: usize = 16;
+
+/// Proofs are listed by Y, never with their secret or `C`, since `serve`
+/// streams these events to any client of its feed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PolEvent {
+    MintRecorded {
+        epoch_id: u64,
+        proof: ListedMint,
+    },
+    BurnRecorded {
+        epoch_id: u64,
+        proof: ListedBurn,
+    },
+    EpochRotated {
+        previous_epoch_id: u64,
+        new_epoch_id: u64,
+        pruned_epoch_ids: Vec<u64>,
+        timestamp: DateTime<Utc>,
+    },
+    ReportGenerated {
+        epoch_count: usize,
+        total_outstanding_balance: Amount,
+        timestamp: DateTime<Utc>,
+    },
+    Alert {
+        epoch_id: u64,
+        message: String,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+/// A report as delivered to `PolService::subscribe_reports`. Publishing a
+/// signed report also broadcasts the unsigned report it was built from.
+#[derive(Debug, Clone)]
+pub enum GeneratedReport {
+    Unsigned(PolReport),
+    Signed(SignedReport),
+}
+
+impl GeneratedReport {
+    pub fn report(&self) -> &PolReport {
+        match self {
+            Self::Unsigned(report) => report,
+            Self::Signed(signed) => &signed.report,
+        }
+    }
+}
+
+pub(crate) type HookFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+pub(crate) type Hook<A> = Arc<dyn Fn(A) -> HookFuture + Send + Sync>;
+
+/// Callbacks registered by embedders, invoked in registration order after
+/// the corresponding operation has been persisted.
+#[derive(Default)]
+pub(crate) struct Hooks {
+    pub(crate) mint_recorded: Vec<Hook<(u64, MintProof)>>,
+    pub(crate) burn_recorded: Vec<Hook<(u64, BurnProof)>>,
+    pub(crate) epoch_rotated: Vec<Hook<(u64, u64)>>,
+    pub(crate) report_generated: Vec<Hook<PolReport>>,
+}
+
+pub(crate) async fn run_hooks<A: Clone>(hooks: &[Hook<A>], arg: A) {
+    for hook in hooks {
+        hook(arg.clone()).await;
+    }
+}
+
+/// Writes every received event as one JSON object per line until the
+/// sending side is dropped.
+pub async fn write_json_lines<W>(
+    mut events: broadcast::Receiver<PolEvent>,
+    writer: &mut W,
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(skipped, "Event stream lagged, events were dropped");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        let mut line = serde_json::to_vec(&event)?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+        writer.flush().await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_json_lines() {
+        let (sender, receiver) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        sender
+            .send(PolEvent::EpochRotated {
+                previous_epoch_id: 0,
+                new_epoch_id: 1,
+                pruned_epoch_ids: vec![],
+                timestamp: Utc::now(),
+            })
+            .unwrap();
+        sender
+            .send(PolEvent::Alert {
+                epoch_id: 1,
+                message: "test".to_string(),
+                timestamp: Utc::now(),
+            })
+            .unwrap();
+        drop(sender);
+
+        let mut output = Vec::new();
+        write_json_lines(receiver, &mut output).await.unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["type"], "epoch_rotated");
+        assert_eq!(lines[1]["type"], "alert");
+    }
+}
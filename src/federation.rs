@@ -0,0 +1,195 @@
+use crate::types::{PolError, SignaturePolicy, SignedReport};
+use bitcoin::Amount;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One mint's report as input to a federation rollup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationMember {
+    /// Label for the mint, usually its URL; must be unique
+    pub mint: String,
+    /// Unit the report's amounts are denominated in
+    pub unit: String,
+    pub report: SignedReport,
+    /// Keys pinned for this mint out of band; the report must carry enough
+    /// of their signatures to be counted
+    pub trusted: SignaturePolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MintTotal {
+    pub mint: String,
+    pub unit: String,
+    pub epochs: usize,
+    /// Closing balance of the mint's latest epoch, including liabilities of
+    /// epochs it has pruned
+    pub outstanding: Amount,
+}
+
+/// Combined liabilities per unit at the moment an epoch of some member
+/// closed. Each mint contributes the closing balance of its latest epoch
+/// that had closed by then; mints with no closed epoch yet are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationPoint {
+    pub time: DateTime<Utc>,
+    pub outstanding: BTreeMap<String, Amount>,
+    pub mints: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationReport {
+    pub mints: Vec<MintTotal>,
+    /// Outstanding liabilities per unit across all mints
+    pub outstanding: BTreeMap<String, Amount>,
+    /// Combined balances with every member's epochs aligned by time
+    pub timeline: Vec<FederationPoint>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Rolls several mints' reports up into one federation view. Epoch ids are
+/// local to each mint, so epochs are aligned by their end times instead.
+/// Fails unless every report is signed by its mint's pinned keys.
+pub fn aggregate(members: &[FederationMember]) -> Result<FederationReport, PolError> {
+    let mut seen = BTreeSet::new();
+    for member in members {
+        if !seen.insert(member.mint.as_str()) {
+            return Err(PolError::ReportGenerationFailed(format!(
+                "Mint {} appears more than once",
+                member.mint
+            )));
+        }
+        member
+            .report
+            .verify(&member.trusted)
+            .map_err(|e| PolError::InvalidSignature(format!("{}: {}", member.mint, e)))?;
+    }
+
+    let mut outstanding: BTreeMap<String, Amount> = BTreeMap::new();
+    let mints = members
+        .iter()
+        .map(|member| {
+            let balance = member
+                .report
+                .report
+                .epoch_reports
+                .last()
+                .map_or(Amount::ZERO, |e| e.closing_balance);
+            *outstanding.entry(member.unit.clone()).or_default() += balance;
+            MintTotal {
+                mint: member.mint.clone(),
+                unit: member.unit.clone(),
+                epochs: member.report.report.epoch_reports.len(),
+                outstanding: balance,
+            }
+        })
+        .collect();
+
+    let times: BTreeSet<DateTime<Utc>> = members
+        .iter()
+        .flat_map(|m| {
+            m.report
+                .report
+                .epoch_reports
+                .iter()
+                .filter_map(|e| e.end_time)
+        })
+        .collect();
+    let timeline = times
+        .into_iter()
+        .map(|time| {
+            let mut point = FederationPoint {
+                time,
+                outstanding: BTreeMap::new(),
+                mints: 0,
+            };
+            for member in members {
+                let closed = member
+                    .report
+                    .report
+                    .epoch_reports
+                    .iter()
+                    .rev()
+                    .find(|e| e.end_time.is_some_and(|end| end <= time));
+                if let Some(epoch) = closed {
+                    *point.outstanding.entry(member.unit.clone()).or_default() +=
+                        epoch.closing_balance;
+                    point.mints += 1;
+                }
+            }
+            point
+        })
+        .collect();
+
+    Ok(FederationReport {
+        mints,
+        outstanding,
+        timeline,
+        timestamp: Utc::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::Signer;
+    use crate::{create_sample_mint_proof, LocalSigner, PolService};
+    use cdk::{nuts::nut02::Id, Amount as CashuAmount};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    async fn member(mint: &str, unit: &str, amounts: &[u64]) -> FederationMember {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let signer = Arc::new(LocalSigner::generate());
+        service.set_signer(signer.clone()).await;
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        for &amount in amounts {
+            let proof = create_sample_mint_proof(keyset_id, CashuAmount::from(amount));
+            service
+                .record_mint_proof(proof.proof, proof.amount)
+                .await
+                .unwrap();
+            service.rotate_epoch().await.unwrap();
+        }
+
+        FederationMember {
+            mint: mint.to_string(),
+            unit: unit.to_string(),
+            report: service.generate_signed_report().await.unwrap(),
+            trusted: SignaturePolicy::single(signer.public_key()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_by_unit_and_time() {
+        let members = vec![
+            member("a", "sat", &[100, 20]).await,
+            member("b", "sat", &[5]).await,
+            member("c", "usd", &[7]).await,
+        ];
+
+        let federation = aggregate(&members).unwrap();
+        assert_eq!(federation.mints.len(), 3);
+        assert_eq!(federation.outstanding["sat"], Amount::from_sat(125));
+        assert_eq!(federation.outstanding["usd"], Amount::from_sat(7));
+
+        // Four epochs closed across the members, the last with all of them in
+        assert_eq!(federation.timeline.len(), 4);
+        let last = federation.timeline.last().unwrap();
+        assert_eq!(last.mints, 3);
+        assert_eq!(last.outstanding["sat"], Amount::from_sat(125));
+
+        let duplicate = vec![members[0].clone(), members[0].clone()];
+        assert!(aggregate(&duplicate).is_err());
+
+        // A report not signed by the mint's pinned key is refused
+        let mut forged = members.clone();
+        forged[1].trusted = SignaturePolicy::single(LocalSigner::generate().public_key());
+        assert!(matches!(
+            aggregate(&forged),
+            Err(PolError::InvalidSignature(_))
+        ));
+    }
+}
@@ -0,0 +1,258 @@
+use axum::extract::{ConnectInfo, Query, Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use cashu_pol::{MilliSats, PolError, PolService, RateLimiter, SeriesPoint};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+const METRICS: [&str; 3] = ["outstanding", "issuance", "redemption"];
+
+/// Current version of the HTTP API. Breaking changes ship under a new
+/// prefix while the old one keeps being served.
+const API_VERSION: &str = "v1";
+
+/// Routes of the Grafana JSON datasource (`/`, `/search`, `/query`), plus
+/// `/series` for plain HTTP clients, all under `/v1`. The unversioned paths
+/// still answer but are deprecated. Every route is rate limited per client.
+pub fn router(service: Arc<PolService>, limiter: Arc<RateLimiter>) -> Router {
+    let routes = Router::new()
+        .route("/", get(|| async { "OK" }))
+        .route("/search", post(search))
+        .route("/query", post(query))
+        .route("/series", get(series))
+        .with_state(service);
+
+    Router::new()
+        .nest(&format!("/{}", API_VERSION), routes.clone())
+        .merge(routes)
+        .layer(middleware::from_fn(version_headers))
+        .layer(middleware::from_fn_with_state(limiter, rate_limit))
+}
+
+/// Tags responses with the API version that served them, and points
+/// clients of unversioned paths at their successor.
+async fn version_headers(request: Request, next: Next) -> Response {
+    let prefix = format!("/{}", API_VERSION);
+    let path = request.uri().path().to_string();
+    let versioned = path == prefix || path.starts_with(&format!("{}/", prefix));
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("api-version", HeaderValue::from_static(API_VERSION));
+    if !versioned {
+        headers.insert("deprecation", HeaderValue::from_static("true"));
+        if let Ok(link) = HeaderValue::from_str(&format!(
+            "<{}{}>; rel=\"successor-version\"",
+            prefix,
+            path.trim_end_matches('/')
+        )) {
+            headers.insert(header::LINK, link);
+        }
+    }
+    response
+}
+
+async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Connections without a peer address are local and not limited
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if let Some(Err(wait)) = peer.map(|ip| limiter.check(ip)) {
+        let retry_after = wait.as_secs().max(1).to_string();
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after)],
+            "rate_limited: too many requests",
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+async fn search() -> Json<Value> {
+    Json(json!(METRICS))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryRequest {
+    range: QueryRange,
+    interval_ms: Option<i64>,
+    targets: Vec<QueryTarget>,
+}
+
+#[derive(Deserialize)]
+struct QueryRange {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct QueryTarget {
+    target: String,
+}
+
+/// Answers with one `[value, unix_ms]` series per target, values in sats.
+async fn query(
+    State(service): State<Arc<PolService>>,
+    Json(request): Json<QueryRequest>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    // Non-positive steps are refused by the series itself
+    let step = Duration::milliseconds(request.interval_ms.unwrap_or(3_600_000).max(0));
+    let points = service
+        .liability_series(request.range.from, request.range.to, step)
+        .map_err(error_response)?;
+
+    let mut series = Vec::new();
+    for target in &request.targets {
+        let value: fn(&SeriesPoint) -> MilliSats = match target.target.as_str() {
+            "outstanding" => |p| p.outstanding,
+            "issuance" => |p| p.issued,
+            "redemption" => |p| p.redeemed,
+            other => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Unknown metric: {}", other),
+                ))
+            }
+        };
+        let datapoints: Vec<Value> = points
+            .iter()
+            .map(|p| {
+                json!([
+                    value(p).to_msat() as f64 / 1000.0,
+                    p.time.timestamp_millis()
+                ])
+            })
+            .collect();
+        series.push(json!({ "target": target.target, "datapoints": datapoints }));
+    }
+    Ok(Json(Value::Array(series)))
+}
+
+#[derive(Deserialize)]
+struct SeriesQuery {
+    from: DateTime<Utc>,
+    to: Option<DateTime<Utc>>,
+    #[serde(default = "default_step_secs")]
+    step_secs: i64,
+}
+
+fn default_step_secs() -> i64 {
+    3600
+}
+
+async fn series(
+    State(service): State<Arc<PolService>>,
+    Query(query): Query<SeriesQuery>,
+) -> Result<Json<Vec<SeriesPoint>>, (StatusCode, String)> {
+    service
+        .liability_series(
+            query.from,
+            query.to.unwrap_or_else(Utc::now),
+            Duration::milliseconds(query.step_secs.max(0).saturating_mul(1000)),
+        )
+        .map(Json)
+        .map_err(error_response)
+}
+
+fn error_response(e: PolError) -> (StatusCode, String) {
+    let status = StatusCode::from_u16(e.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, format!("{}: {}", e.code(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cashu_pol::RateLimit;
+    use tempfile::tempdir;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_router_answers_series_and_errors() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let app = router(
+            Arc::new(service),
+            Arc::new(RateLimiter::new(RateLimit::default())),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+        });
+
+        let start = Utc::now() - Duration::days(1);
+        let from = start.to_rfc3339();
+        let to = (start + Duration::days(1)).to_rfc3339();
+        let client = reqwest::Client::new();
+        let series = client
+            .get(format!("{}/v1/series", base))
+            .query(&[("from", from.as_str()), ("to", to.as_str())])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(series.status(), StatusCode::OK);
+        assert_eq!(series.headers()["api-version"], API_VERSION);
+        assert_eq!(series.json::<Vec<SeriesPoint>>().await.unwrap().len(), 24);
+
+        let bad_step = client
+            .get(format!("{}/series", base))
+            .query(&[
+                ("from", from.as_str()),
+                ("to", to.as_str()),
+                ("step_secs", "0"),
+            ])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(bad_step.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(bad_step.headers()["deprecation"], "true");
+        assert!(bad_step
+            .text()
+            .await
+            .unwrap()
+            .starts_with("invalid_range: "));
+
+        // A step far past the range is one bucket, not an overflow
+        let huge_step = client
+            .get(format!("{}/v1/series", base))
+            .query(&[
+                ("from", from.as_str()),
+                ("to", to.as_str()),
+                ("step_secs", "9223372036854775"),
+            ])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(huge_step.status(), StatusCode::OK);
+        assert_eq!(huge_step.json::<Vec<SeriesPoint>>().await.unwrap().len(), 1);
+
+        let unknown = client
+            .post(format!("{}/v1/query", base))
+            .json(&json!({
+                "range": { "from": from, "to": to },
+                "targets": [{ "target": "reserves" }],
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::types::PolError;
+use cdk::nuts::nut01::PublicKey;
+use cdk::nuts::nut02::Id;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Deserialize)]
+struct KeysetsResponse {
+    keysets: Vec<KeysetInfo>,
+}
+
+#[derive(Deserialize)]
+struct KeysetInfo {
+    id: Id,
+    unit: Option<String>,
+    active: bool,
+}
+
+#[derive(Deserialize)]
+struct KeysResponse {
+    keysets: Vec<KeysetResponse>,
+}
+
+#[derive(Deserialize)]
+struct KeysetResponse {
+    id: Id,
+    unit: Option<String>,
+    keys: HashMap<String, PublicKey>,
+}
+
+/// Largest batch of Ys sent in one NUT-07 request
+const CHECK_STATE_BATCH: usize = 100;
+
+#[derive(Serialize)]
+struct CheckStateRequest<'a> {
+    #[serde(rename = "Ys")]
+    ys: &'a [PublicKey],
+}
+
+#[derive(Deserialize)]
+struct CheckStateResponse {
+    states: Vec<ProofState>,
+}
+
+#[derive(Deserialize)]
+struct ProofState {
+    #[serde(rename = "Y")]
+    y: PublicKey,
+    state: String,
+}
+
+/// A keyset as the mint lists it. NUT-02 says whether a keyset is still
+/// used for new proofs but not since when.
+pub(crate) struct MintKeyset {
+    pub id: Id,
+    pub unit: Option<String>,
+    pub active: bool,
+    pub keys: BTreeMap<u64, PublicKey>,
+}
+
+async fn get_json<T: DeserializeOwned>(url: &str) -> Result<T, PolError> {
+    reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| PolError::MintUnreachable(e.to_string()))?
+        .json()
+        .await
+        .map_err(|e| PolError::MintUnreachable(e.to_string()))
+}
+
+/// Fetches every keyset the mint lists at its NUT-02 `/v1/keysets`
+/// endpoint, active or not, with their NUT-01 keys.
+pub(crate) async fn fetch(mint_url: &str) -> Result<Vec<MintKeyset>, PolError> {
+    let mint_url = mint_url.trim_end_matches('/');
+    let info: KeysetsResponse = get_json(&format!("{}/v1/keysets", mint_url)).await?;
+    let mut keys: HashMap<Id, KeysetResponse> = HashMap::new();
+    for keyset in get_json::<KeysResponse>(&format!("{}/v1/keys", mint_url))
+        .await?
+        .keysets
+    {
+        keys.insert(keyset.id, keyset);
+    }
+
+    let mut keysets = Vec::with_capacity(info.keysets.len());
+    for listed in info.keysets {
+        // `/v1/keys` only serves active keysets
+        let response = match keys.remove(&listed.id) {
+            Some(response) => response,
+            None => get_json::<KeysResponse>(&format!("{}/v1/keys/{}", mint_url, listed.id))
+                .await?
+                .keysets
+                .into_iter()
+                .find(|keyset| keyset.id == listed.id)
+                .ok_or_else(|| PolError::KeysetNotFound(listed.id.to_string()))?,
+        };
+        let keys = response
+            .keys
+            .into_iter()
+            .map(|(amount, key)| {
+                let amount = amount.parse::<u64>().map_err(|_| {
+                    PolError::MintUnreachable(format!(
+                        "Keyset {} lists a non-numeric amount {}",
+                        listed.id, amount
+                    ))
+                })?;
+                Ok((amount, key))
+            })
+            .collect::<Result<BTreeMap<_, _>, PolError>>()?;
+        keysets.push(MintKeyset {
+            id: listed.id,
+            unit: listed.unit.or(response.unit),
+            active: listed.active,
+            keys,
+        });
+    }
+
+    Ok(keysets)
+}
+
+/// Asks the mint's NUT-07 `/v1/checkstate` endpoint which of `ys` it has
+/// seen spent. Pending proofs are not counted as spent.
+pub(crate) async fn spent(mint_url: &str, ys: &[PublicKey]) -> Result<Vec<PublicKey>, PolError> {
+    let url = format!("{}/v1/checkstate", mint_url.trim_end_matches('/'));
+    let client = reqwest::Client::new();
+
+    let mut spent = Vec::new();
+    for batch in ys.chunks(CHECK_STATE_BATCH) {
+        let response: CheckStateResponse = client
+            .post(&url)
+            .json(&CheckStateRequest { ys: batch })
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| PolError::MintUnreachable(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| PolError::MintUnreachable(e.to_string()))?;
+
+        spent.extend(
+            response
+                .states
+                .into_iter()
+                .filter(|state| state.state == "SPENT")
+                .map(|state| state.y),
+        );
+    }
+
+    Ok(spent)
+}
@@ -1,12 +1,63 @@
+mod archive;
+mod bundle;
+mod codec;
+mod events;
+mod federation;
+mod keysets;
+mod merkle;
+mod monitor;
+mod observers;
+mod pedersen;
+mod rate_limit;
+mod rates;
+mod reconcile;
 mod service;
+mod signer;
+mod sink;
+mod smt;
+mod spec;
+mod sql;
 mod storage;
+#[cfg(any(test, feature = "test-utils"))]
 mod test_utils;
 mod types;
 
-pub use service::PolService;
-pub use storage::Storage;
+pub use archive::{Archive, ArchiveEntry};
+pub use bundle::{
+    BundleManifest, BundleVerification, ProofParameters, ReportBundle, BUNDLE_EXTENSION,
+    BUNDLE_VERSION,
+};
+pub use codec::{register_codec, Bincode, Codec, CodecKind, MessagePack, Postcard, ValueCodec};
+pub use events::{write_json_lines, GeneratedReport, PolEvent};
+pub use federation::{aggregate, FederationMember, FederationPoint, FederationReport, MintTotal};
+pub use merkle::SumNode;
+pub use monitor::{fetch_published, fetch_versions, parse_report, Equivocation, SeenCommitment};
+pub use observers::{
+    compare, CommitmentSighting, EpochAssessment, ObserverComparison, ObserverView, Trust,
+};
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use rates::{RateSource, StaticRate};
+pub use reconcile::{Discrepancy, IssuedEntry, MintLedger, ReconciliationReport, SpentEntry};
+pub use service::{EpochReportStream, PolService};
+pub use signer::{cosign, sign_report, verify_signature, LocalSigner, RemoteSigner, Signer};
+pub use sink::{FileSink, HttpSink, IpfsSink, NostrSink, ReportSink, SinkState};
+pub use spec::{SpecBurn, SpecEpoch, SpecKeyset, SpecMint, SpecReport, SPEC_UNIT, SPEC_VERSION};
+pub use sql::write_sql_dump;
+pub use storage::{Appended, Compression, RetryPolicy, Storage};
+#[cfg(any(test, feature = "test-utils"))]
 pub use test_utils::*;
-pub use types::{BurnProof, EpochReport, MintProof, PolError, PolReport};
+pub use types::{
+    AmountCommitment, AuditEntry, AuditMismatch, AuditOperation, BitProof, BoxError,
+    BurnIndexProof, BurnProof, CarriedCommitment, ConfidentialEpoch, ConsistencyProof,
+    CumulativeBalance, EpochAggregates, EpochAttestation, EpochDetails, EpochFootprint,
+    EpochIdMode, EpochListing, EpochRecord, EpochReport, EpochStatus, EpochSummary,
+    ExternalObservation, FiatAnnotation, FinalizedEpoch, HistoryEntry, HistoryHead, HistoryProof,
+    InclusionProof, KeysetRecord, LeafKind, LiabilityBound, ListedBurn, ListedMint, MeltQuoteInfo,
+    MilliSats, MintProof, MintQuoteInfo, Page, PolError, PolReport, ProofLookup, ProofRecord,
+    PruneRule, PublicationStatus, RangeProof, Receipt, ReportMismatch, ReportSignature,
+    SelfAuditReport, SeriesPoint, ServiceStatus, SignaturePolicy, SignedReport, StorageStats,
+    TokenDirection,
+};
 
 #[cfg(test)]
 mod tests {
@@ -119,4 +170,41 @@ mod tests {
         // First epoch in report should have 3000 sat outstanding
         assert_eq!(report.epoch_reports[0].outstanding_balance.to_sat(), 3000);
     }
+
+    #[tokio::test]
+    async fn test_report_consistency_catches_tampering() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        for amount in [5000u64, 3000] {
+            let mint_proof = create_sample_mint_proof(keyset_id, CashuAmount::from(amount));
+            service
+                .record_mint_proof(mint_proof.proof, mint_proof.amount)
+                .await
+                .unwrap();
+            service.rotate_epoch().await.unwrap();
+        }
+        service
+            .record_burn_proof("spent".to_string(), bitcoin::Amount::from_sat(1000))
+            .await
+            .unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        assert!(report.check_consistency().unwrap().is_empty());
+
+        let mut tampered = report.clone();
+        tampered.epoch_reports[1].opening_balance = bitcoin::Amount::from_sat(1);
+        tampered.epoch_reports[2].burn_proofs.clear();
+        let checks: Vec<String> = tampered
+            .check_consistency()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.check)
+            .collect();
+        assert!(checks.contains(&"epoch_chain".to_string()));
+        assert!(checks.contains(&"commitment".to_string()));
+        assert!(checks.contains(&"closing_balance".to_string()));
+    }
 }
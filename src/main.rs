@@ -1,10 +1,39 @@
+use bitcoin::secp256k1::{schnorr::Signature, XOnlyPublicKey};
 use bitcoin::Amount;
-use cashu_pol::PolService;
-use clap::Parser;
+use cashu_pol::{
+    aggregate, compare, cosign, verify_signature, write_json_lines, BurnIndexProof, CodecKind,
+    Compression, ConsistencyProof, EpochIdMode, FederationMember, InclusionProof, LocalSigner,
+    MeltQuoteInfo, MintLedger, MintQuoteInfo, ObserverView, PolReport, PolService, ProofRecord,
+    PruneRule, Receipt, ReportBundle, SeenCommitment, SignaturePolicy, SignedReport, Signer,
+    SpecReport, StaticRate, TokenDirection, BUNDLE_EXTENSION,
+};
+use cdk::mint_url::MintUrl;
+use cdk::nuts::nut01::PublicKey;
+use chrono::{DateTime, Utc};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use output::OutputFormat;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::BTreeSet;
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
 use tracing::{info, warn};
 use tracing_subscriber::{self, EnvFilter};
+use verdict::Verdict;
+
+mod bench;
+mod grafana;
+mod mirror;
+mod output;
+mod serve;
+mod simulate;
+mod tail;
+mod tui;
+mod verdict;
 
 #[derive(Parser)]
 #[command(author, version, about = "Cashu Proof of Liabilities Tool")]
@@ -13,6 +42,10 @@ struct Cli {
     #[arg(short = 'd', long, default_value = "30")]
     epoch_days: i64,
 
+    /// Derive epoch ids from time (unix_time / epoch length) instead of counting from 0
+    #[arg(long)]
+    time_derived_epoch_ids: bool,
+
     /// Maximum number of epochs to keep in history
     #[arg(short = 'n', long, default_value = "24")]
     max_history: usize,
@@ -25,27 +58,729 @@ struct Cli {
     #[arg(short = 's', long)]
     burn_secret: Option<String>,
 
+    /// URL of the mint this database tracks; tokens from other mints are refused
+    #[arg(long, value_name = "URL")]
+    mint_url: Option<MintUrl>,
+
+    /// Record every proof in a cashuA/cashuB token as minted
+    #[arg(long, value_name = "TOKEN")]
+    mint_token: Option<String>,
+
+    /// Record every proof in a cashuA/cashuB token as burned
+    #[arg(long, value_name = "TOKEN")]
+    burn_token: Option<String>,
+
+    /// Mint or melt quote id the token's proofs belong to
+    #[arg(long, value_name = "ID")]
+    quote_id: Option<String>,
+
+    /// Payment hash of the invoice behind the token's quote
+    #[arg(long, value_name = "HASH")]
+    payment_hash: Option<String>,
+
+    /// Payment method of the mint quote, e.g. bolt11
+    #[arg(long, value_name = "METHOD", requires = "mint_token")]
+    payment_method: Option<String>,
+
+    /// Preimage of the melt's payment, proving it settled
+    #[arg(long, value_name = "PREIMAGE", requires = "burn_token")]
+    preimage: Option<String>,
+
     /// Path to the database file
     #[arg(short = 'p', long, default_value = "cashu-pol.db")]
     db_path: PathBuf,
 
+    /// Compression of records stored in the database (none, zstd); kept by the database once set
+    #[arg(long, value_name = "CODEC")]
+    compression: Option<Compression>,
+
+    /// Serialization format of records stored in the database (bincode, postcard,
+    /// msgpack); kept by the database once set
+    #[arg(long, value_name = "CODEC")]
+    codec: Option<CodecKind>,
+
     /// Log level (error, warn, info, debug, trace)
     #[arg(short = 'l', long, default_value = "info")]
     log_level: String,
+
+    /// Only log errors
+    #[arg(short = 'q', long, global = true)]
+    quiet: bool,
+
+    /// Format of command results on stdout [default: json, text for bench]
+    #[arg(short = 'o', long, value_enum, global = true)]
+    output: Option<OutputFormat>,
+
+    /// File holding a hex-encoded secret key used to sign the report
+    #[arg(long, value_name = "PATH")]
+    signing_key: Option<PathBuf>,
+
+    /// Auditor public key allowed to co-sign reports (repeatable)
+    #[arg(long = "auditor-key", value_name = "PUBKEY")]
+    auditor_keys: Vec<XOnlyPublicKey>,
+
+    /// Number of valid signatures a report needs to verify
+    #[arg(long, default_value = "1")]
+    signature_threshold: usize,
+
+    /// BTC price in minor units of --fiat-currency (e.g. cents), used to
+    /// annotate reports with an approximate fiat value
+    #[arg(long, value_name = "PRICE")]
+    fiat_rate: Option<u64>,
+
+    /// Currency code of --fiat-rate
+    #[arg(long, default_value = "USD")]
+    fiat_currency: String,
+
+    /// Digits after the decimal point in --fiat-currency
+    #[arg(long, default_value = "2")]
+    fiat_decimals: u8,
+
+    /// Layout of the printed report
+    #[arg(long, value_enum, default_value = "native")]
+    report_format: ReportFormat,
+
+    /// Publish per-epoch amounts only as Pedersen commitments
+    #[arg(long)]
+    confidential: bool,
+
+    /// Publish per-epoch proof counts and totals instead of proof lists, building
+    /// the report one epoch at a time
+    #[arg(long)]
+    aggregates_only: bool,
+
+    /// Emit every event as a JSON line to this path (a file or named pipe), or "-" for stdout
+    #[arg(long, value_name = "PATH")]
+    events: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Interactive terminal dashboard with live epoch stats
+    Tui {
+        /// Seconds between refreshes of the displayed data
+        #[arg(long, default_value = "1")]
+        refresh_secs: u64,
+    },
+    /// Add a co-signature to a signed report file, updating it in place
+    Cosign {
+        /// Signed report JSON file
+        report: PathBuf,
+
+        /// File holding the co-signer's hex-encoded secret key
+        #[arg(long, value_name = "PATH")]
+        key: PathBuf,
+    },
+    /// Check a report file offline: proofs against commitments and balances,
+    /// the epoch chain, and signatures when it is signed
+    #[command(alias = "verify")]
+    VerifyReport {
+        /// Report JSON file, signed or unsigned
+        report: PathBuf,
+
+        /// Require a valid signature by this x-only key
+        #[arg(long, value_name = "PUBKEY")]
+        mint_pubkey: Option<XOnlyPublicKey>,
+
+        /// Signature policy JSON ({"threshold":N,"signers":[...]}) a signed
+        /// report must satisfy; defaults to 1-of-1 for --mint-pubkey
+        #[arg(long, value_name = "PATH")]
+        policy: Option<PathBuf>,
+
+        /// Inclusion proofs printed by `prove`, checked against the report
+        #[arg(long, value_name = "PATH")]
+        inclusion_proofs: Option<PathBuf>,
+
+        /// Hex-encoded Y the inclusion proofs are for; defaults to the receipt's
+        #[arg(long, requires = "inclusion_proofs")]
+        y: Option<String>,
+
+        /// Issuance or redemption receipt the report must honour
+        #[arg(long, value_name = "PATH")]
+        receipt: Option<PathBuf>,
+
+        /// Burn index proofs printed by `prove-unburned`, checked against
+        /// the report's epoch commitments
+        #[arg(long, value_name = "PATH")]
+        burn_index_proofs: Option<PathBuf>,
+
+        /// Earlier report by the same mint the history must extend
+        #[arg(long, value_name = "PATH", requires = "consistency_proof")]
+        previous: Option<PathBuf>,
+
+        /// Proof printed by `prove-consistency` linking --previous to the report
+        #[arg(long, value_name = "PATH", requires = "previous")]
+        consistency_proof: Option<PathBuf>,
+    },
+    /// Pack a signed report into one .polreport file, or check one
+    Bundle {
+        #[command(subcommand)]
+        action: BundleCommand,
+    },
+    /// Store an auditor's signature over an epoch commitment
+    Attest {
+        /// Epoch the attestation covers
+        epoch_id: u64,
+
+        /// Auditor's x-only public key
+        #[arg(long, value_name = "PUBKEY")]
+        public_key: XOnlyPublicKey,
+
+        /// Schnorr signature over the epoch commitment
+        #[arg(long)]
+        signature: Signature,
+
+        /// Optional free-form statement from the auditor
+        #[arg(long)]
+        statement: Option<String>,
+    },
+    /// Record proofs read from stdin into the current epoch
+    ///
+    /// Each line is a JSON object: {"type":"mint","proof":{...}} or
+    /// {"type":"burn","secret":"...","amount":SATS}. Lines are recorded in
+    /// batches, so a bad line stops the run after earlier batches were
+    /// recorded.
+    Record {
+        /// Read JSON lines from stdin
+        #[arg(long, required = true)]
+        stdin: bool,
+
+        /// Records per storage transaction
+        #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(1..))]
+        batch_size: u64,
+    },
+    /// List proofs recorded within a time range
+    Proofs {
+        /// Which proofs to list
+        #[arg(value_enum)]
+        kind: ProofKind,
+
+        /// Start of the range (RFC 3339, inclusive)
+        #[arg(long)]
+        from: DateTime<Utc>,
+
+        /// End of the range (RFC 3339, exclusive)
+        #[arg(long)]
+        to: DateTime<Utc>,
+
+        /// Number of matching records to skip
+        #[arg(long, default_value = "0")]
+        offset: usize,
+
+        /// Maximum number of records to return
+        #[arg(long, default_value = "100")]
+        limit: usize,
+    },
+    /// List mint proofs issued against a quote
+    Quote {
+        /// Mint quote id or bolt11 payment hash
+        quote_id_or_payment_hash: String,
+    },
+    /// List burn proofs redeemed in a melt
+    Melt {
+        /// Melt quote id or payment hash
+        quote_id_or_payment_hash: String,
+    },
+    /// Show where a proof was minted and burned
+    Lookup {
+        /// Proof secret or hex-encoded Y
+        secret_or_y: String,
+    },
+    /// Print Merkle inclusion proofs for a proof's Y
+    Prove {
+        /// Hex-encoded Y = hash_to_curve(secret); secrets are not accepted
+        y: String,
+    },
+    /// Print a sparse Merkle proof that a Y was not burned in an epoch
+    ProveUnburned {
+        /// Hex-encoded Y = hash_to_curve(secret)
+        y: String,
+
+        /// Epoch the proof is for
+        #[arg(long)]
+        epoch_id: u64,
+    },
+    /// Print a proof that the history of closed epochs only grew since it
+    /// had the given size
+    ProveConsistency {
+        /// History size published in the older report
+        old_size: u64,
+    },
+    /// Range-prove that net liabilities are at most the given bound
+    Bound {
+        /// Claimed upper bound, in sats
+        upper_bound_sat: u64,
+    },
+    /// Register the mint's active keysets and retire ones it dropped
+    SyncKeysets {
+        /// Mint base URL
+        mint_url: String,
+    },
+    /// Roll several mints' reports up into one federation report
+    Aggregate {
+        /// MINT[:UNIT]=PATH to a signed report, one per mint; UNIT defaults to sat
+        #[arg(required = true, value_name = "MINT[:UNIT]=PATH")]
+        members: Vec<String>,
+
+        /// MINT=KEY pinning the x-only key that must have signed MINT's report
+        #[arg(long = "mint-pubkey", required = true, value_name = "MINT=KEY")]
+        mint_pubkeys: Vec<String>,
+    },
+    /// Poll a mint's public API and record what it reveals, until ctrl-c
+    ///
+    /// Keysets are synced and proofs the mint reports spent are recorded as
+    /// burns. Only proofs already in --db-path can be checked, so the
+    /// resulting liabilities are a partial approximation.
+    Follow {
+        /// Mint base URL
+        mint_url: String,
+
+        /// Seconds between polls
+        #[arg(long, default_value = "60")]
+        interval_secs: u64,
+    },
+    /// Watch a mint's published reports and alert when it publishes two
+    /// different commitments for the same epoch
+    Monitor {
+        /// Label for the mint, usually its URL
+        #[arg(long)]
+        mint: String,
+
+        /// The mint's x-only key; reports it did not sign are ignored
+        #[arg(long, value_name = "PUBKEY")]
+        mint_pubkey: XOnlyPublicKey,
+
+        /// URLs or Nostr relays (ws:// or wss://) the mint's report is
+        /// published at
+        #[arg(required = true)]
+        sources: Vec<String>,
+
+        /// Seconds between fetches
+        #[arg(long, default_value = "300")]
+        interval_secs: u64,
+    },
+    /// List the commitments the monitor has seen
+    Seen {
+        /// Only this mint's commitments
+        #[arg(long)]
+        mint: Option<String>,
+    },
+    /// Diff what independent observers saw one mint publish and assess
+    /// each epoch
+    CompareObservers {
+        /// OBSERVER=PATH, where PATH holds a report the observer fetched or
+        /// the output of `seen`
+        #[arg(required = true, value_name = "OBSERVER=PATH")]
+        views: Vec<String>,
+        /// Mint to compare when `seen` output covers several
+        #[arg(long)]
+        mint: Option<String>,
+    },
+    /// List registered keysets and when they were active
+    Keysets,
+    /// Recompute derived artifacts from stored proofs and compare them
+    SelfAudit,
+    /// Compare the database with a dump of the mint's spent and issued records
+    Reconcile {
+        /// Path or http(s) URL of the mint ledger JSON
+        ledger: String,
+    },
+    /// Merge adjacent epochs into the first of them
+    Merge {
+        /// Epoch ids to merge
+        #[arg(required = true, num_args = 2..)]
+        epoch_ids: Vec<u64>,
+
+        /// Merge even if a signed report already covered these epochs
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove the oldest closed epochs, printing each one removed
+    #[command(group(clap::ArgGroup::new("rule").required(true).multiple(false)))]
+    Prune {
+        /// Remove epochs that ended more than this many days ago, up to a century
+        #[arg(
+            long,
+            group = "rule",
+            value_name = "DAYS",
+            value_parser = clap::value_parser!(u64).range(..=36_500)
+        )]
+        older_than_days: Option<u64>,
+
+        /// Keep only this many of the newest epochs, counting the open one
+        #[arg(long, group = "rule", value_name = "COUNT")]
+        keep: Option<usize>,
+
+        /// Only print what would be removed
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Also remove sealed epochs covered by a published signed report
+        #[arg(long)]
+        force: bool,
+    },
+    /// Rebuild epoch boundaries from proof timestamps under the configured epoch length
+    Resegment {
+        /// Proceed even if a signed report already covered existing epochs
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show the open epoch, time until rotation, outstanding balance, proof
+    /// counts, database size and publication status
+    Status,
+    /// Show database size, per-epoch footprint and growth projections
+    Stats {
+        /// Disk budget for the database file, in MiB, to project against
+        #[arg(long, value_name = "MIB")]
+        disk_limit_mib: Option<u64>,
+    },
+    /// Print each epoch's report as a JSON line as soon as it is built, holding one
+    /// epoch in memory at a time
+    StreamReport,
+    /// Inspect retained epochs
+    #[command(alias = "epoch")]
+    Epochs {
+        #[command(subcommand)]
+        action: EpochsCommand,
+    },
+    /// Print the log of administrative operations on epoch history
+    AuditLog,
+    /// Dump every epoch, commitment and proof for loading elsewhere
+    Export {
+        /// Dump format
+        #[arg(long, value_enum, default_value = "sql")]
+        format: ExportFormat,
+
+        /// File to write the dump to; defaults to stdout
+        #[arg(long, value_name = "PATH")]
+        out: Option<PathBuf>,
+    },
+    /// Measure record throughput, report latency and db size on a temporary database
+    Bench {
+        /// Number of epochs to populate
+        #[arg(long, default_value = "24", value_parser = clap::value_parser!(u64).range(1..))]
+        epochs: u64,
+
+        /// Mint proofs recorded per epoch
+        #[arg(long, default_value = "1000")]
+        mints_per_epoch: u64,
+
+        /// Burn proofs recorded per epoch
+        #[arg(long, default_value = "500")]
+        burns_per_epoch: u64,
+    },
+    /// Drive the service with a synthetic mint/burn workload, then report
+    ///
+    /// Proofs are recorded into --db-path, so point it at a scratch database.
+    Simulate {
+        /// How long to run, in seconds
+        #[arg(long, default_value = "60")]
+        duration_secs: u64,
+
+        /// Mean mints per second (Poisson arrivals)
+        #[arg(long, default_value = "10")]
+        mint_rate: f64,
+
+        /// Mean burns per second (Poisson arrivals)
+        #[arg(long, default_value = "5")]
+        burn_rate: f64,
+
+        /// Rotate the epoch every this many seconds
+        #[arg(long)]
+        rotate_secs: Option<u64>,
+
+        /// How proof amounts are drawn
+        #[arg(long, value_enum, default_value = "powers-of-two")]
+        distribution: simulate::AmountDistribution,
+
+        /// Largest proof amount in sats
+        #[arg(long, default_value = "1024")]
+        max_amount: u64,
+    },
+    /// Seal a closed epoch so it can no longer change
+    Finalize {
+        /// Epoch to seal
+        epoch_id: u64,
+    },
+    /// Replace seals made before epoch commitments covered sums
+    Reseal,
+    /// Delete the epoch copies kept from before chunking, which an older
+    /// build would read after a downgrade
+    DropLegacyEpochs,
+    /// Run the enabled components and listeners from one config file until ctrl-c
+    Serve {
+        /// JSON config enabling rotation, publication, keyset sync, the event
+        /// feed and the Grafana datasource; listeners bind a TCP address or
+        /// unix:<path>
+        #[arg(long, value_name = "PATH")]
+        config: PathBuf,
+    },
+    /// Attach to a running `serve` and print recorded proofs, rotations and
+    /// alerts as they happen, until ctrl-c
+    ///
+    /// The daemon's config must enable its event feed.
+    Tail {
+        /// Address of the daemon's event feed, or unix:<path> for a Unix socket
+        #[arg(long, default_value = serve::DEFAULT_EVENT_FEED)]
+        connect: String,
+    },
+    /// Crawl mints' published reports on a schedule, archiving every
+    /// version by content hash and checking each for equivocation
+    Mirror {
+        /// JSON config listing the archive directory and the mints to crawl
+        #[arg(long, value_name = "PATH")]
+        config: PathBuf,
+    },
+    /// Print a shell completion script to stdout
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Write man pages for the tool and each subcommand
+    Man {
+        /// Directory to write the pages into
+        #[arg(default_value = ".")]
+        out_dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum BundleCommand {
+    /// Sign a new report without publishing it, then write it to one file
+    /// with the seals, attestations and proof parameters needed to check it
+    Create {
+        /// Bundle file; the .polreport extension is added when missing
+        out: PathBuf,
+
+        /// Reserve attestation file to include as is (repeatable)
+        #[arg(long = "reserve", value_name = "PATH")]
+        reserves: Vec<PathBuf>,
+    },
+    /// Check a bundle offline: the signed manifest and file hashes, the
+    /// report against itself and a pinned signature policy, and seals and
+    /// attestations against the report. The manifest and seals must be
+    /// signed by a key in the policy
+    Verify {
+        /// .polreport file
+        bundle: PathBuf,
+
+        /// The mint's x-only key; the report must carry its signature
+        #[arg(long, value_name = "PUBKEY", required_unless_present = "policy")]
+        mint_pubkey: Option<XOnlyPublicKey>,
+
+        /// Signature policy JSON the report must satisfy instead
+        #[arg(long, value_name = "PATH", conflicts_with = "mint_pubkey")]
+        policy: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum EpochsCommand {
+    /// List every retained epoch with its proof counts, balance and status
+    List,
+    /// Print one epoch's commitments, seal, attestations and totals
+    Show {
+        epoch_id: u64,
+
+        /// Also list the epoch's mint and burn proofs, a page at a time
+        #[arg(long)]
+        proofs: bool,
+
+        /// Number of proofs of each kind to skip
+        #[arg(long, default_value = "0", requires = "proofs")]
+        offset: usize,
+
+        /// Maximum number of proofs of each kind to list
+        #[arg(long, default_value = "100", requires = "proofs")]
+        limit: usize,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// This tool's own JSON shape
+    Native,
+    /// The Cashu proof-of-liabilities proposal's layout
+    Spec,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// CREATE TABLE and INSERT statements any SQL engine can load
+    Sql,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ProofKind {
+    Mint,
+    Burn,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
 
-    // Initialize logging
+    let events_to_stdout = cli.events.as_deref() == Some(Path::new("-"));
+    let output = cli.output.unwrap_or_default();
+
+    // Logs go to stderr so stdout only carries command results
+    let filter = if cli.quiet {
+        EnvFilter::new("error")
+    } else {
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&cli.log_level))
+    };
     tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&cli.log_level)),
-        )
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
         .init();
 
     info!("Starting Cashu Proof of Liabilities Tool");
+
+    match &cli.command {
+        Some(Command::Completions { shell }) => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(*shell, &mut command, name, &mut std::io::stdout());
+            return Ok(());
+        }
+        Some(Command::Man { out_dir }) => return write_man_pages(out_dir),
+        Some(Command::Aggregate {
+            members,
+            mint_pubkeys,
+        }) => {
+            let mut keys = Vec::new();
+            for pin in mint_pubkeys {
+                let (mint, key) = pin
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected MINT=KEY, got {}", pin))?;
+                keys.push((mint.to_string(), key.parse::<XOnlyPublicKey>()?));
+            }
+            let members = members
+                .iter()
+                .map(|member| load_federation_member(member, &keys))
+                .collect::<Result<Vec<_>, _>>()?;
+            return output::print(output, &aggregate(&members)?);
+        }
+        Some(Command::CompareObservers { views, mint }) => {
+            let views = match views
+                .iter()
+                .map(|view| load_observer_view(view, mint.as_deref()))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(views) => views,
+                Err(e) => Verdict::error(e).exit(output),
+            };
+            let comparison = compare(&views);
+            let conflicting: Vec<Value> = comparison
+                .conflicting()
+                .map(|e| {
+                    serde_json::json!({
+                        "epoch_id": e.epoch_id,
+                        "check": "observers",
+                        "detail": format!(
+                            "{} different commitments seen{}",
+                            e.sightings.len(),
+                            if e.attributable { ", signed by the same key" } else { "" }
+                        ),
+                    })
+                })
+                .collect();
+            Verdict::from_checks(
+                [
+                    ("observers", comparison.observers.len()),
+                    ("epochs", comparison.epochs.len()),
+                ],
+                &conflicting,
+            )
+            .with_details(&comparison)
+            .exit(output)
+        }
+        Some(Command::Cosign { report, key }) => return cosign_report(report, key, output).await,
+        // The daemon holds the database open, so tail only talks to it
+        Some(Command::Tail { connect }) => return tail::run(connect, output).await,
+        Some(Command::VerifyReport {
+            report,
+            mint_pubkey,
+            policy,
+            inclusion_proofs,
+            y,
+            receipt,
+            burn_index_proofs,
+            previous,
+            consistency_proof,
+        }) => verify_report(
+            report,
+            &VerifyOptions {
+                mint_pubkey: *mint_pubkey,
+                policy: policy.as_deref(),
+                inclusion_proofs: inclusion_proofs.as_deref(),
+                y: y.as_deref(),
+                receipt: receipt.as_deref(),
+                burn_index_proofs: burn_index_proofs.as_deref(),
+                previous: previous.as_deref(),
+                consistency_proof: consistency_proof.as_deref(),
+            },
+        )
+        .exit(output),
+        Some(Command::Bundle {
+            action:
+                BundleCommand::Verify {
+                    bundle,
+                    mint_pubkey,
+                    policy,
+                },
+        }) => {
+            let trusted = trusted_policy(*mint_pubkey, policy.as_deref()).and_then(|trusted| {
+                trusted.ok_or_else(|| "pass --mint-pubkey or --policy".to_string())
+            });
+            let verification = trusted.and_then(|trusted| {
+                std::fs::File::open(bundle)
+                    .map_err(|e| format!("{}: {}", bundle.display(), e))
+                    .and_then(|file| {
+                        ReportBundle::read(std::io::BufReader::new(file))
+                            .and_then(|bundle| bundle.verify(&trusted))
+                            .map_err(|e| e.to_string())
+                    })
+            });
+            match verification {
+                Ok(v) => Verdict::from_checks(
+                    [
+                        ("files", v.files),
+                        ("epochs", v.epochs),
+                        ("signatures", v.signatures),
+                        ("seals", v.seals),
+                        ("attestations", v.attestations),
+                        ("reserves", v.reserves),
+                    ],
+                    &v.mismatches,
+                ),
+                Err(e) => Verdict::error(e),
+            }
+            .exit(output)
+        }
+        Some(Command::Bench {
+            epochs,
+            mints_per_epoch,
+            burns_per_epoch,
+        }) => {
+            return bench::run(
+                &bench::BenchConfig {
+                    epochs: *epochs,
+                    mints_per_epoch: *mints_per_epoch,
+                    burns_per_epoch: *burns_per_epoch,
+                    epoch_days: cli.epoch_days,
+                },
+                // Bench printed a table before --output existed
+                cli.output.unwrap_or(OutputFormat::Text),
+            )
+            .await;
+        }
+        _ => {}
+    }
+
     info!(
         epoch_days = cli.epoch_days,
         max_history = cli.max_history,
@@ -54,9 +789,480 @@ async fn main() -> Result<(), Box<dyn Error>> {
     );
 
     // Create a new PoL service with configured parameters
-    let service = PolService::with_path(cli.epoch_days, cli.max_history, cli.db_path)?;
+    let service = PolService::open(cli.epoch_days, cli.max_history, cli.db_path).await?;
+    if let Some(codec) = cli.codec {
+        service.set_storage_codec(codec)?;
+    }
+    if let Some(compression) = cli.compression {
+        service.set_storage_compression(compression)?;
+    }
+    if cli.time_derived_epoch_ids {
+        service.set_epoch_id_mode(EpochIdMode::TimeDerived).await;
+    }
     service.initialize().await?;
 
+    if let Some(path) = &cli.signing_key {
+        let signer = LocalSigner::from_file(path)?;
+        let mut signers = vec![signer.public_key()];
+        signers.extend(cli.auditor_keys.iter().copied());
+        service
+            .set_signature_policy(SignaturePolicy::new(cli.signature_threshold, signers)?)
+            .await;
+        service.set_signer(Arc::new(signer)).await;
+    }
+
+    if let Some(rate) = cli.fiat_rate {
+        service
+            .set_rate_source(Arc::new(StaticRate::new(
+                cli.fiat_currency.clone(),
+                cli.fiat_decimals,
+                rate,
+            )))
+            .await;
+    }
+
+    if cli.confidential {
+        service.set_confidential_reports(true).await;
+    }
+
+    if cli.aggregates_only {
+        service.set_aggregate_reports(true).await;
+    }
+
+    if let Some(mint_url) = &cli.mint_url {
+        service.set_mint_url(mint_url.clone()).await;
+    }
+
+    let simulation = match &cli.command {
+        Some(Command::Simulate {
+            duration_secs,
+            mint_rate,
+            burn_rate,
+            rotate_secs,
+            distribution,
+            max_amount,
+        }) => Some(simulate::SimConfig {
+            duration: StdDuration::from_secs(*duration_secs),
+            mint_rate: *mint_rate,
+            burn_rate: *burn_rate,
+            rotate_every: rotate_secs.map(StdDuration::from_secs),
+            distribution: *distribution,
+            max_amount: *max_amount,
+        }),
+        _ => None,
+    };
+
+    // Read the config up front so a bad file fails before anything runs
+    let serve_config = match &cli.command {
+        Some(Command::Serve { config }) => {
+            let config = serve::ServeConfig::load(config)?;
+            if config.publication.enabled && cli.signing_key.is_none() {
+                return Err("publication requires --signing-key".into());
+            }
+            Some(config)
+        }
+        _ => None,
+    };
+
+    match cli.command {
+        Some(Command::Tui { refresh_secs }) => {
+            return tui::run(&service, StdDuration::from_secs(refresh_secs)).await;
+        }
+        Some(Command::Mirror { config }) => {
+            return mirror::run(&service, mirror::MirrorConfig::load(&config)?, output).await;
+        }
+        Some(Command::Attest {
+            epoch_id,
+            public_key,
+            signature,
+            statement,
+        }) => {
+            let attestation = service
+                .submit_attestation(epoch_id, public_key, signature, statement)
+                .await?;
+            output::print(output, &attestation)?;
+            return Ok(());
+        }
+        Some(Command::Record { batch_size, .. }) => {
+            let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+            let mut batch = Vec::new();
+            let mut recorded = 0;
+            let mut line_number = 0;
+            while let Some(line) = lines.next_line().await? {
+                line_number += 1;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record: ProofRecord = serde_json::from_str(&line)
+                    .map_err(|e| format!("line {}: {}", line_number, e))?;
+                batch.push(record);
+                if batch.len() as u64 >= batch_size {
+                    recorded += service.record_batch(std::mem::take(&mut batch)).await?;
+                }
+            }
+            recorded += service.record_batch(batch).await?;
+            info!(recorded, "Recorded proofs from stdin");
+            output::print(output, &serde_json::json!({ "recorded": recorded }))?;
+            return Ok(());
+        }
+        Some(Command::Proofs {
+            kind,
+            from,
+            to,
+            offset,
+            limit,
+        }) => {
+            match kind {
+                ProofKind::Mint => output::print(
+                    output,
+                    &service
+                        .get_mint_proofs_between(from, to, offset, limit)
+                        .await?,
+                )?,
+                ProofKind::Burn => output::print(
+                    output,
+                    &service
+                        .get_burn_proofs_between(from, to, offset, limit)
+                        .await?,
+                )?,
+            }
+            return Ok(());
+        }
+        Some(Command::Quote {
+            quote_id_or_payment_hash,
+        }) => {
+            let records = service
+                .mint_proofs_for_quote(&quote_id_or_payment_hash)
+                .await?;
+            output::print(output, &records)?;
+            return Ok(());
+        }
+        Some(Command::Melt {
+            quote_id_or_payment_hash,
+        }) => {
+            let records = service
+                .burn_proofs_for_melt(&quote_id_or_payment_hash)
+                .await?;
+            output::print(output, &records)?;
+            return Ok(());
+        }
+        Some(Command::Lookup { secret_or_y }) => {
+            let lookup = service.lookup(&secret_or_y).await?;
+            output::print(output, &lookup)?;
+            return Ok(());
+        }
+        Some(Command::Prove { y }) => {
+            let verdict = match PublicKey::from_hex(&y) {
+                Ok(y) => match service.inclusion_proofs(&y).await {
+                    Ok(proofs) if proofs.is_empty() => Verdict::not_found([("proofs", 0)]),
+                    Ok(proofs) => Verdict::from_checks::<Value>([("proofs", proofs.len())], &[])
+                        .with_details(proofs),
+                    Err(e) => Verdict::error(e),
+                },
+                Err(e) => Verdict::error(e),
+            };
+            verdict.exit(output);
+        }
+        Some(Command::ProveUnburned { y, epoch_id }) => {
+            let verdict = match PublicKey::from_hex(&y) {
+                Ok(y) => match service.burn_index_proof(epoch_id, &y).await {
+                    Ok(proof) => {
+                        let mismatches: Vec<Value> = match proof.amount {
+                            Some(amount) => vec![serde_json::json!({
+                                "check": "burned",
+                                "detail": format!("{} was burned in epoch {}", amount, epoch_id),
+                            })],
+                            None => Vec::new(),
+                        };
+                        Verdict::from_checks([("epochs", 1)], &mismatches).with_details(proof)
+                    }
+                    Err(e) => Verdict::error(e),
+                },
+                Err(e) => Verdict::error(e),
+            };
+            verdict.exit(output);
+        }
+        Some(Command::ProveConsistency { old_size }) => {
+            output::print(output, &service.consistency_proof(old_size).await?)?;
+            return Ok(());
+        }
+        Some(Command::Bound { upper_bound_sat }) => {
+            let bound = service
+                .prove_liability_bound(Amount::from_sat(upper_bound_sat))
+                .await?;
+            output::print(output, &bound)?;
+            return Ok(());
+        }
+        Some(Command::SyncKeysets { mint_url }) => {
+            let registered = service.sync_keysets(&mint_url).await?;
+            info!(registered = registered.len(), "Keysets synced");
+            output::print(output, &registered)?;
+            return Ok(());
+        }
+        Some(Command::SelfAudit) => {
+            let verdict = match service.self_audit().await {
+                Ok(report) => Verdict::from_checks(
+                    [
+                        ("epochs", report.checked_epochs),
+                        ("seals", report.checked_seals),
+                        ("attestations", report.checked_attestations),
+                    ],
+                    &report.mismatches,
+                ),
+                Err(e) => Verdict::error(e),
+            };
+            verdict.exit(output);
+        }
+        Some(Command::Reconcile { ledger }) => {
+            let report = match MintLedger::load(&ledger).await {
+                Ok(ledger) => service.reconcile(&ledger).await,
+                Err(e) => Err(e),
+            };
+            let verdict = match report {
+                Ok(report) => Verdict::from_checks(
+                    [
+                        ("burns", report.checked_burns),
+                        ("keysets", report.checked_keysets),
+                    ],
+                    &report.discrepancies,
+                ),
+                Err(e) => Verdict::error(e),
+            };
+            verdict.exit(output);
+        }
+        Some(Command::Monitor {
+            mint,
+            mint_pubkey,
+            sources,
+            interval_secs,
+        }) => {
+            let trusted = SignaturePolicy::single(mint_pubkey);
+            let mut interval = tokio::time::interval(StdDuration::from_secs(interval_secs.max(1)));
+            loop {
+                tokio::select! {
+                    result = tokio::signal::ctrl_c() => return Ok(result?),
+                    _ = interval.tick() => {}
+                }
+                match service.monitor_mint(&mint, &trusted, &sources).await {
+                    Ok(equivocations) => {
+                        for equivocation in &equivocations {
+                            output::print(output, equivocation)?;
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "Monitor poll failed"),
+                }
+            }
+        }
+        Some(Command::Follow {
+            mint_url,
+            interval_secs,
+        }) => {
+            let mut interval = tokio::time::interval(StdDuration::from_secs(interval_secs.max(1)));
+            loop {
+                tokio::select! {
+                    result = tokio::signal::ctrl_c() => return Ok(result?),
+                    _ = interval.tick() => {}
+                }
+                match service.follow_mint(&mint_url).await {
+                    Ok(observation) => output::print(output, &observation)?,
+                    Err(e) => warn!(error = %e, "Poll failed"),
+                }
+            }
+        }
+        Some(Command::Seen { mint }) => {
+            output::print(output, &service.seen_commitments(mint.as_deref())?)?;
+            return Ok(());
+        }
+        Some(Command::Keysets) => {
+            output::print(output, &service.keysets()?)?;
+            return Ok(());
+        }
+        Some(Command::Merge { epoch_ids, force }) => {
+            let merged = service.merge_epochs(&epoch_ids, force).await?;
+            info!(epoch_id = merged, "Epochs merged");
+            output::print(output, &serde_json::json!({ "epoch_id": merged }))?;
+            return Ok(());
+        }
+        Some(Command::Prune {
+            older_than_days,
+            keep,
+            dry_run,
+            force,
+        }) => {
+            // The "rule" group makes clap require exactly one of the two
+            let rule = match older_than_days {
+                Some(days) => PruneRule::OlderThan(chrono::Duration::days(days as i64)),
+                None => {
+                    PruneRule::KeepLatest(keep.ok_or("--older-than-days or --keep is required")?)
+                }
+            };
+            let pruned = service.prune_epochs(rule, force, dry_run).await?;
+            info!(epoch_count = pruned.len(), dry_run, "Epochs pruned");
+            output::print(
+                output,
+                &serde_json::json!({ "dry_run": dry_run, "epochs": pruned }),
+            )?;
+            return Ok(());
+        }
+        Some(Command::Resegment { force }) => {
+            let epoch_ids = service.resegment_epochs(force).await?;
+            info!(epoch_count = epoch_ids.len(), "Epochs re-segmented");
+            output::print(output, &serde_json::json!({ "epoch_ids": epoch_ids }))?;
+            return Ok(());
+        }
+        Some(Command::Status) => {
+            output::print(output, &service.status().await?)?;
+            return Ok(());
+        }
+        Some(Command::Stats { disk_limit_mib }) => {
+            let disk_limit_bytes = disk_limit_mib
+                .map(|mib| {
+                    mib.checked_mul(1024 * 1024)
+                        .ok_or("--disk-limit-mib is too large")
+                })
+                .transpose()?;
+            let stats = service.storage_stats(disk_limit_bytes)?;
+            output::print(output, &stats)?;
+            return Ok(());
+        }
+        Some(Command::StreamReport) => {
+            let mut stream = service.report_stream().await?;
+            let mut stdout = tokio::io::stdout();
+            while let Some(report) = stream.next_epoch().await {
+                let mut line = serde_json::to_vec(&report?)?;
+                line.push(b'\n');
+                stdout.write_all(&line).await?;
+            }
+            stdout.flush().await?;
+            return Ok(());
+        }
+        Some(Command::Bundle {
+            action: BundleCommand::Create { out, reserves },
+        }) => {
+            let reserves = reserves
+                .iter()
+                .map(|path| {
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    std::fs::read(path)
+                        .map(|data| (name, data))
+                        .map_err(|e| format!("{}: {}", path.display(), e))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let bundle = service.report_bundle(reserves).await?;
+            let out = match out.extension() {
+                Some(_) => out,
+                None => out.with_extension(BUNDLE_EXTENSION),
+            };
+            bundle.write(std::io::BufWriter::new(std::fs::File::create(&out)?))?;
+            info!(path = %out.display(), "Bundle written");
+            output::print(output, &bundle.manifest()?)?;
+            return Ok(());
+        }
+        Some(Command::Epochs { action }) => {
+            match action {
+                EpochsCommand::List => output::print(output, &service.epoch_listing().await?)?,
+                EpochsCommand::Show {
+                    epoch_id,
+                    proofs,
+                    offset,
+                    limit,
+                } => output::print(
+                    output,
+                    &service
+                        .epoch_details(epoch_id, proofs.then_some((offset, limit)))
+                        .await?,
+                )?,
+            }
+            return Ok(());
+        }
+        Some(Command::AuditLog) => {
+            output::print(output, &service.audit_log()?)?;
+            return Ok(());
+        }
+        Some(Command::Export { format, out }) => {
+            match format {
+                ExportFormat::Sql => match out {
+                    Some(path) => {
+                        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                        service.export_sql(&mut file)?;
+                    }
+                    None => service.export_sql(&mut std::io::stdout().lock())?,
+                },
+            }
+            return Ok(());
+        }
+        Some(Command::Finalize { epoch_id }) => {
+            let seal = service.finalize_epoch(epoch_id).await?;
+            output::print(output, &seal)?;
+            return Ok(());
+        }
+        Some(Command::Reseal) => {
+            let resealed = service.reseal_epochs().await?;
+            info!(resealed = resealed.len(), "Legacy seals replaced");
+            output::print(output, &resealed)?;
+            return Ok(());
+        }
+        Some(Command::DropLegacyEpochs) => {
+            let dropped = service.drop_legacy_epochs()?;
+            info!(dropped, "Legacy epochs dropped");
+            output::print(output, &dropped)?;
+            return Ok(());
+        }
+        Some(Command::Completions { .. })
+        | Some(Command::Man { .. })
+        | Some(Command::Aggregate { .. })
+        | Some(Command::CompareObservers { .. })
+        | Some(Command::Bundle {
+            action: BundleCommand::Verify { .. },
+        })
+        | Some(Command::Cosign { .. })
+        | Some(Command::Tail { .. })
+        | Some(Command::VerifyReport { .. })
+        | Some(Command::Bench { .. })
+        | Some(Command::Simulate { .. })
+        | Some(Command::Serve { .. })
+        | None => {}
+    }
+
+    let event_writer = match cli.events {
+        Some(path) => {
+            let events = service.subscribe_events();
+            Some(if events_to_stdout {
+                tokio::spawn(async move {
+                    let mut stdout = tokio::io::stdout();
+                    write_json_lines(events, &mut stdout).await
+                })
+            } else {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .await?;
+                tokio::spawn(async move { write_json_lines(events, &mut file).await })
+            })
+        }
+        None => None,
+    };
+
+    if let Some(config) = &simulation {
+        simulate::run(&service, config).await?;
+    }
+
+    if let Some(config) = serve_config {
+        let service = Arc::new(service);
+        serve::run(service.clone(), config).await?;
+
+        // Dropping the service closes the event channel and lets the writer finish
+        drop(service);
+        if let Some(handle) = event_writer {
+            handle.await??;
+        }
+        return Ok(());
+    }
+
     // For demonstration, create test data if requested
     if let Some(amount) = cli.mint_amount {
         let amount = Amount::from_sat(amount);
@@ -76,14 +1282,413 @@ async fn main() -> Result<(), Box<dyn Error>> {
         service.record_burn_proof(secret, amount).await?;
     }
 
-    // Generate the report
+    let quoted = cli.quote_id.is_some() || cli.payment_hash.is_some();
+    let quote = (quoted || cli.payment_method.is_some()).then(|| MintQuoteInfo {
+        quote_id: cli.quote_id.clone(),
+        payment_hash: cli.payment_hash.clone(),
+        method: cli.payment_method.clone(),
+    });
+    let melt = (quoted || cli.preimage.is_some()).then(|| MeltQuoteInfo {
+        quote_id: cli.quote_id.clone(),
+        payment_hash: cli.payment_hash.clone(),
+        preimage: cli.preimage.clone(),
+    });
+    for (token, direction) in [
+        (&cli.mint_token, TokenDirection::Mint(quote)),
+        (&cli.burn_token, TokenDirection::Burn(melt)),
+    ] {
+        if let Some(token) = token {
+            let recorded = service.record_from_token(token, direction.clone()).await?;
+            info!(recorded, ?direction, "Recorded token proofs");
+        }
+    }
+
+    // Generate the report, signed when a key was provided
     info!("Generating report");
-    let report = service.generate_report().await?;
+    let report = match (cli.signing_key.is_some(), cli.report_format) {
+        (true, ReportFormat::Native) => {
+            output::render(output, &service.generate_signed_report().await?)?
+        }
+        (true, ReportFormat::Spec) => {
+            output::render(output, &service.generate_signed_spec_report().await?)?
+        }
+        (false, ReportFormat::Native) => output::render(output, &service.generate_report().await?)?,
+        (false, ReportFormat::Spec) => output::render(
+            output,
+            &SpecReport::from_report(&service.generate_report().await?)?,
+        )?,
+    };
+
+    // Print the report, unless stdout is carrying the event stream
+    if !events_to_stdout {
+        println!("{}", report);
+    }
 
-    // Print the report as JSON
-    let json = serde_json::to_string_pretty(&report)?;
-    println!("{}", json);
+    // Dropping the service closes the event channel and lets the writer finish
+    drop(service);
+    if let Some(handle) = event_writer {
+        handle.await??;
+    }
 
     info!("Operation completed successfully");
     Ok(())
 }
+
+/// Optional extra checks for `verify_report`.
+struct VerifyOptions<'a> {
+    mint_pubkey: Option<XOnlyPublicKey>,
+    policy: Option<&'a Path>,
+    inclusion_proofs: Option<&'a Path>,
+    y: Option<&'a str>,
+    receipt: Option<&'a Path>,
+    burn_index_proofs: Option<&'a Path>,
+    previous: Option<&'a Path>,
+    consistency_proof: Option<&'a Path>,
+}
+
+/// The policy a signed report is checked against: read from `--policy`, or
+/// 1-of-1 for `--mint-pubkey`. Never the policy the report carries.
+fn trusted_policy(
+    mint_pubkey: Option<XOnlyPublicKey>,
+    policy: Option<&Path>,
+) -> Result<Option<SignaturePolicy>, String> {
+    match (policy, mint_pubkey) {
+        (Some(path), _) => read_json(path)
+            .and_then(|value| {
+                serde_json::from_value::<SignaturePolicy>(value)
+                    .map_err(|e| format!("{}: {}", path.display(), e))
+            })
+            .and_then(|policy| {
+                SignaturePolicy::new(policy.threshold, policy.signers).map_err(|e| e.to_string())
+            })
+            .map(Some),
+        (None, Some(key)) => Ok(Some(SignaturePolicy::single(key))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Checks a report file without opening the database: its internal
+/// consistency, its signatures against a pinned policy when it is signed,
+/// and optionally that a given mint key signed it, that inclusion proofs
+/// hold against it and that it honours a receipt.
+fn verify_report(report_path: &Path, options: &VerifyOptions) -> Verdict {
+    let (report, signed) = match read_report(report_path) {
+        Ok(report) => report,
+        Err(e) => return Verdict::error(e),
+    };
+    let trusted = match trusted_policy(options.mint_pubkey, options.policy) {
+        Ok(trusted) => trusted,
+        Err(e) => return Verdict::error(e),
+    };
+
+    let mut mismatches: Vec<Value> = match report.check_consistency() {
+        Ok(mismatches) => mismatches
+            .into_iter()
+            .map(|m| serde_json::to_value(m).unwrap_or(Value::Null))
+            .collect(),
+        Err(e) => return Verdict::error(e),
+    };
+
+    let signatures = signed.as_ref().map_or(0, |s| s.signatures.len());
+    match &signed {
+        Some(signed) => {
+            let checked = match &trusted {
+                Some(trusted) => signed.verify(trusted).map_err(|e| e.to_string()),
+                None => Err("no trusted key pinned; pass --mint-pubkey or --policy".to_string()),
+            };
+            if let Err(detail) = checked {
+                mismatches.push(serde_json::json!({ "check": "signatures", "detail": detail }));
+            }
+            if let Some(mint_pubkey) = options.mint_pubkey {
+                let signed_by_mint = signed.signatures.iter().any(|s| {
+                    s.public_key == mint_pubkey
+                        && verify_signature(&signed.commitment, &s.signature, &s.public_key).is_ok()
+                });
+                if !signed_by_mint {
+                    mismatches.push(serde_json::json!({
+                        "check": "mint_signature",
+                        "detail": format!("no valid signature by {}", mint_pubkey),
+                    }));
+                }
+            }
+        }
+        None if options.mint_pubkey.is_some() => mismatches.push(serde_json::json!({
+            "check": "mint_signature",
+            "detail": "report is unsigned",
+        })),
+        None => {}
+    }
+
+    let receipt: Option<Receipt> = match options.receipt.map(|path| {
+        read_json(path).and_then(|value| {
+            serde_json::from_value(value).map_err(|e| format!("{}: {}", path.display(), e))
+        })
+    }) {
+        Some(Ok(receipt)) => Some(receipt),
+        Some(Err(e)) => return Verdict::error(e),
+        None => None,
+    };
+
+    let mut proofs = Vec::new();
+    if let Some(path) = options.inclusion_proofs {
+        let y = match (options.y, &receipt) {
+            (Some(y), _) => PublicKey::from_hex(y).map_err(|e| e.to_string()),
+            (None, Some(receipt)) => Ok(receipt.y),
+            (None, None) => Err("--inclusion-proofs needs --y or --receipt".to_string()),
+        };
+        let y = match y {
+            Ok(y) => y,
+            Err(e) => return Verdict::error(e),
+        };
+        proofs = match load_proofs::<InclusionProof>(path) {
+            Ok(proofs) => proofs,
+            Err(e) => return Verdict::error(format!("{}: {}", path.display(), e)),
+        };
+        for proof in &proofs {
+            let epoch = report
+                .epoch_reports
+                .iter()
+                .find(|e| e.epoch_id == proof.epoch_id);
+            let Some(epoch) = epoch.filter(|e| proof.verify(&y, &e.commitment)) else {
+                mismatches.push(serde_json::json!({
+                    "epoch_id": proof.epoch_id,
+                    "check": "inclusion",
+                    "detail": format!("{:?} proof does not verify against the report", proof.kind),
+                }));
+                continue;
+            };
+            if !proof.matches_balance(epoch) {
+                mismatches.push(serde_json::json!({
+                    "epoch_id": proof.epoch_id,
+                    "check": "inclusion_totals",
+                    "detail": format!(
+                        "committed totals {} minted, {} burned do not net to {}",
+                        proof.mint_root.sum, proof.burn_root.sum, epoch.outstanding_balance
+                    ),
+                }));
+            }
+        }
+    }
+
+    if let Some(receipt) = &receipt {
+        if let Err(e) = receipt.verify() {
+            mismatches.push(serde_json::json!({ "check": "receipt", "detail": e.to_string() }));
+        } else if options
+            .mint_pubkey
+            .is_some_and(|key| key != receipt.signature.public_key)
+        {
+            mismatches.push(serde_json::json!({
+                "check": "receipt",
+                "detail": "receipt is not signed by the mint key",
+            }));
+        } else if !receipt.verify_in_report(&report, &proofs) {
+            mismatches.push(serde_json::json!({
+                "epoch_id": receipt.epoch_id,
+                "check": "receipt",
+                "detail": format!("{:?} record is not counted in the report", receipt.kind),
+            }));
+        }
+    }
+
+    let mut burn_index_proofs = Vec::new();
+    if let Some(path) = options.burn_index_proofs {
+        burn_index_proofs = match load_proofs::<BurnIndexProof>(path) {
+            Ok(proofs) => proofs,
+            Err(e) => return Verdict::error(format!("{}: {}", path.display(), e)),
+        };
+        for proof in &burn_index_proofs {
+            let verified = report
+                .epoch_reports
+                .iter()
+                .find(|e| e.epoch_id == proof.epoch_id)
+                .is_some_and(|e| proof.verify(&e.commitment));
+            if !verified {
+                mismatches.push(serde_json::json!({
+                    "epoch_id": proof.epoch_id,
+                    "check": "burn_index",
+                    "detail": format!("proof for {} does not verify against the report", proof.y),
+                }));
+            }
+        }
+    }
+
+    let mut history_checked = 0;
+    if let (Some(previous), Some(path)) = (options.previous, options.consistency_proof) {
+        let previous = match read_report(previous) {
+            Ok((previous, _)) => previous,
+            Err(e) => return Verdict::error(e),
+        };
+        let proof: ConsistencyProof = match read_json(path).and_then(|value| {
+            serde_json::from_value(value).map_err(|e| format!("{}: {}", path.display(), e))
+        }) {
+            Ok(proof) => proof,
+            Err(e) => return Verdict::error(e),
+        };
+        history_checked = 1;
+        if !proof.verify_reports(&previous, &report) {
+            mismatches.push(serde_json::json!({
+                "check": "history",
+                "detail": format!(
+                    "history at size {} is not an append-only extension of size {}",
+                    proof.new.size, proof.old.size
+                ),
+            }));
+        }
+    }
+
+    Verdict::from_checks(
+        [
+            ("signatures", signatures),
+            ("epochs", report.epoch_reports.len()),
+            ("inclusion_proofs", proofs.len()),
+            ("receipts", receipt.iter().count()),
+            ("burn_index_proofs", burn_index_proofs.len()),
+            ("consistency_proofs", history_checked),
+        ],
+        &mismatches,
+    )
+}
+
+/// Reads a report file, signed or not; unsigned reports just have no
+/// signatures to check.
+fn read_report(path: &Path) -> Result<(PolReport, Option<SignedReport>), String> {
+    let value = read_json(path)?;
+    match serde_json::from_value::<SignedReport>(value.clone()) {
+        Ok(signed) => Ok((signed.report.clone(), Some(signed))),
+        Err(_) => serde_json::from_value(value)
+            .map(|report| (report, None))
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+    }
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Parses `MINT[:UNIT]=PATH`, reads the signed report at PATH and pins the
+/// key given for MINT in `keys`.
+fn load_federation_member(
+    spec: &str,
+    keys: &[(String, XOnlyPublicKey)],
+) -> Result<FederationMember, Box<dyn Error>> {
+    let (label, path) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected MINT[:UNIT]=PATH, got {}", spec))?;
+    // Mint URLs contain colons, so the unit is whatever follows the last one
+    // when it is not part of the URL
+    let (mint, unit) = match label.rsplit_once(':') {
+        Some((mint, unit)) if !unit.contains('/') && !unit.chars().all(|c| c.is_ascii_digit()) => {
+            (mint, unit)
+        }
+        _ => (label, "sat"),
+    };
+    let key = keys
+        .iter()
+        .find(|(pinned, _)| pinned == mint)
+        .map(|(_, key)| *key)
+        .ok_or_else(|| format!("no --mint-pubkey given for {}", mint))?;
+
+    let report: SignedReport = serde_json::from_str(&std::fs::read_to_string(path)?)
+        .map_err(|e| format!("{}: expected a signed report: {}", path, e))?;
+    Ok(FederationMember {
+        mint: mint.to_string(),
+        unit: unit.to_string(),
+        report,
+        trusted: SignaturePolicy::single(key),
+    })
+}
+
+/// Loads one observer's view from `OBSERVER=PATH`, where PATH holds either
+/// a report or `seen` output. The latter is narrowed to `mint`, or must
+/// cover a single mint when none is given.
+fn load_observer_view(spec: &str, mint: Option<&str>) -> Result<ObserverView, String> {
+    let (observer, path) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected OBSERVER=PATH, got {}", spec))?;
+    let path = Path::new(path);
+    let value = read_json(path)?;
+    if value.is_array() {
+        let seen: Vec<SeenCommitment> =
+            serde_json::from_value(value).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mint = match mint {
+            Some(mint) => mint,
+            None => {
+                let mints: BTreeSet<&str> = seen.iter().map(|e| e.mint.as_str()).collect();
+                if mints.len() > 1 {
+                    return Err(format!(
+                        "{}: seen output covers several mints; pass --mint",
+                        path.display()
+                    ));
+                }
+                mints.into_iter().next().unwrap_or_default()
+            }
+        };
+        return Ok(ObserverView::from_seen(observer, mint, &seen));
+    }
+    let (report, signed) = read_report(path)?;
+    let signers = signed.map(|s| s.signed_by()).unwrap_or_default();
+    Ok(ObserverView::from_report(observer, &report, &signers))
+}
+
+/// Reads proofs as printed by `prove` or `prove-unburned`, either the whole
+/// verdict or just its details, holding one proof or a list.
+fn load_proofs<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, Box<dyn Error>> {
+    let mut value: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if let Some(details) = value.get_mut("details") {
+        value = details.take();
+    }
+    if !value.is_array() {
+        value = Value::Array(vec![value]);
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Writes `cashu-pol.1` plus one `cashu-pol-<subcommand>.1` page per
+/// subcommand.
+fn write_man_pages(out_dir: &Path) -> Result<(), Box<dyn Error>> {
+    std::fs::create_dir_all(out_dir)?;
+    let command = Cli::command();
+    let name = command.get_name().to_string();
+
+    let mut pages = vec![(name.clone(), command.clone())];
+    for subcommand in command.get_subcommands() {
+        let page = format!("{}-{}", name, subcommand.get_name());
+        pages.push((page.clone(), subcommand.clone().display_name(page)));
+    }
+
+    for (page, command) in pages {
+        let path = out_dir.join(format!("{}.1", page));
+        let mut file = std::fs::File::create(&path)?;
+        clap_mangen::Man::new(command).render(&mut file)?;
+        info!(path = %path.display(), "Man page written");
+    }
+    Ok(())
+}
+
+async fn cosign_report(
+    report_path: &Path,
+    key_path: &Path,
+    output: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut report: SignedReport = serde_json::from_str(&std::fs::read_to_string(report_path)?)?;
+    let signer = LocalSigner::from_file(key_path)?;
+
+    cosign(&mut report, &signer).await?;
+    std::fs::write(report_path, serde_json::to_string_pretty(&report)?)?;
+
+    info!(
+        valid_signers = report.valid_signers(),
+        threshold = report.policy.threshold,
+        "Report co-signed"
+    );
+    output::print(
+        output,
+        &serde_json::json!({
+            "valid_signers": report.valid_signers(),
+            "threshold": report.policy.threshold,
+        }),
+    )
+}
@@ -0,0 +1,313 @@
+use crate::types::MilliSats;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use serde::{Deserialize, Serialize};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Domain-separated leaf hash, as in RFC 6962.
+pub fn leaf_hash(data: &[u8]) -> sha256::Hash {
+    let mut engine = sha256::Hash::engine();
+    engine.input(&[LEAF_PREFIX]);
+    engine.input(data);
+    sha256::Hash::from_engine(engine)
+}
+
+pub(crate) fn node_hash(left: &sha256::Hash, right: &sha256::Hash) -> sha256::Hash {
+    let mut engine = sha256::Hash::engine();
+    engine.input(&[NODE_PREFIX]);
+    engine.input(left.as_byte_array());
+    engine.input(right.as_byte_array());
+    sha256::Hash::from_engine(engine)
+}
+
+/// A tree node that can be combined with a sibling into its parent.
+pub trait Node: Copy + PartialEq {
+    fn parent(left: &Self, right: &Self) -> Self;
+    /// Root of a tree with no leaves
+    fn empty() -> Self;
+}
+
+impl Node for sha256::Hash {
+    fn parent(left: &Self, right: &Self) -> Self {
+        node_hash(left, right)
+    }
+
+    fn empty() -> Self {
+        sha256::Hash::hash(&[])
+    }
+}
+
+/// Node of a Merkle-sum tree: a hash together with the sum of the amounts
+/// of every leaf beneath it. A parent hashes both children's sums, not just
+/// their total, so no amount can be moved between subtrees or left out of
+/// the root sum without changing the root hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SumNode {
+    pub hash: sha256::Hash,
+    pub sum: MilliSats,
+}
+
+impl Node for SumNode {
+    fn parent(left: &Self, right: &Self) -> Self {
+        let mut engine = sha256::Hash::engine();
+        engine.input(&[NODE_PREFIX]);
+        engine.input(left.hash.as_byte_array());
+        engine.input(&left.sum.to_msat().to_be_bytes());
+        engine.input(right.hash.as_byte_array());
+        engine.input(&right.sum.to_msat().to_be_bytes());
+        Self {
+            hash: sha256::Hash::from_engine(engine),
+            sum: left.sum.saturating_add(right.sum),
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            hash: sha256::Hash::hash(&[]),
+            sum: MilliSats::ZERO,
+        }
+    }
+}
+
+/// Largest power of two strictly smaller than `n` (for `n >= 2`).
+pub(crate) fn split_point(n: usize) -> usize {
+    1 << (usize::BITS - (n - 1).leading_zeros() - 1)
+}
+
+/// Merkle tree hash over already-hashed leaves, using the RFC 6962 split so
+/// trees of any size have a unique shape.
+pub fn merkle_root<N: Node>(leaves: &[N]) -> N {
+    match leaves.len() {
+        0 => N::empty(),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n);
+            N::parent(&merkle_root(&leaves[..k]), &merkle_root(&leaves[k..]))
+        }
+    }
+}
+
+/// RFC 6962 audit path for the leaf at `index`: the sibling nodes from the
+/// leaf up to the root.
+pub fn inclusion_path<N: Node>(leaves: &[N], index: usize) -> Vec<N> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split_point(n);
+    if index < k {
+        let mut path = inclusion_path(&leaves[..k], index);
+        path.push(merkle_root(&leaves[k..]));
+        path
+    } else {
+        let mut path = inclusion_path(&leaves[k..], index - k);
+        path.push(merkle_root(&leaves[..k]));
+        path
+    }
+}
+
+/// Recomputes the root of a `tree_size`-leaf tree from `leaf` at `index` and
+/// its audit path. `None` if the path does not fit the tree shape.
+pub fn root_from_path<N: Node>(leaf: N, index: usize, tree_size: usize, path: &[N]) -> Option<N> {
+    if index >= tree_size {
+        return None;
+    }
+    if tree_size == 1 {
+        return path.is_empty().then_some(leaf);
+    }
+    let (sibling, rest) = path.split_last()?;
+    let k = split_point(tree_size);
+    if index < k {
+        let left = root_from_path(leaf, index, k, rest)?;
+        Some(N::parent(&left, sibling))
+    } else {
+        let right = root_from_path(leaf, index - k, tree_size - k, rest)?;
+        Some(N::parent(sibling, &right))
+    }
+}
+
+/// RFC 6962 consistency proof that the first `old_size` leaves form a
+/// prefix of `leaves`, i.e. the tree only grew by appending.
+pub fn consistency_path(leaves: &[sha256::Hash], old_size: usize) -> Vec<sha256::Hash> {
+    fn subproof(leaves: &[sha256::Hash], m: usize, complete: bool) -> Vec<sha256::Hash> {
+        let n = leaves.len();
+        if m == n {
+            return if complete {
+                Vec::new()
+            } else {
+                vec![merkle_root(leaves)]
+            };
+        }
+        let k = split_point(n);
+        if m <= k {
+            let mut path = subproof(&leaves[..k], m, complete);
+            path.push(merkle_root(&leaves[k..]));
+            path
+        } else {
+            let mut path = subproof(&leaves[k..], m - k, false);
+            path.push(merkle_root(&leaves[..k]));
+            path
+        }
+    }
+
+    if old_size == 0 || old_size > leaves.len() {
+        return Vec::new();
+    }
+    subproof(leaves, old_size, true)
+}
+
+/// Checks a consistency proof between two tree heads, following RFC 9162
+/// section 2.1.4.2. An empty old tree is consistent with any new one.
+pub fn verify_consistency(
+    old_size: u64,
+    new_size: u64,
+    old_root: &sha256::Hash,
+    new_root: &sha256::Hash,
+    path: &[sha256::Hash],
+) -> bool {
+    if old_size > new_size {
+        return false;
+    }
+    if old_size == 0 {
+        return path.is_empty();
+    }
+    if old_size == new_size {
+        return path.is_empty() && old_root == new_root;
+    }
+
+    let mut path = path.to_vec();
+    if old_size.is_power_of_two() {
+        path.insert(0, *old_root);
+    }
+    let Some((first, rest)) = path.split_first() else {
+        return false;
+    };
+
+    let mut fn_ = old_size - 1;
+    let mut sn = new_size - 1;
+    while fn_ & 1 == 1 {
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    let (mut fr, mut sr) = (*first, *first);
+    for c in rest {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    sn == 0 && fr == *old_root && sr == *new_root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_point() {
+        assert_eq!(split_point(2), 1);
+        assert_eq!(split_point(3), 2);
+        assert_eq!(split_point(4), 2);
+        assert_eq!(split_point(5), 4);
+        assert_eq!(split_point(9), 8);
+    }
+
+    #[test]
+    fn test_merkle_root_shape() {
+        let leaves: Vec<_> = (0u8..3).map(|i| leaf_hash(&[i])).collect();
+
+        assert_eq!(merkle_root(&leaves[..1]), leaves[0]);
+        assert_eq!(
+            merkle_root(&leaves),
+            node_hash(&node_hash(&leaves[0], &leaves[1]), &leaves[2])
+        );
+    }
+
+    #[test]
+    fn test_inclusion_paths_verify() {
+        for n in 1..=9u8 {
+            let leaves: Vec<_> = (0..n).map(|i| leaf_hash(&[i])).collect();
+            let root = merkle_root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let path = inclusion_path(&leaves, index);
+                assert_eq!(
+                    root_from_path(*leaf, index, leaves.len(), &path),
+                    Some(root)
+                );
+                assert_ne!(
+                    root_from_path(leaf_hash(b"other"), index, leaves.len(), &path),
+                    Some(root)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_consistency_proofs_verify() {
+        let leaves: Vec<_> = (0u8..9).map(|i| leaf_hash(&[i])).collect();
+        for n in 1..=leaves.len() {
+            let new_root = merkle_root(&leaves[..n]);
+            for m in 1..=n {
+                let old_root = merkle_root(&leaves[..m]);
+                let path = consistency_path(&leaves[..n], m);
+                assert!(verify_consistency(
+                    m as u64, n as u64, &old_root, &new_root, &path
+                ));
+                if m < n {
+                    // A rewritten old leaf gives a different old root
+                    let mut rewritten = leaves[..m].to_vec();
+                    rewritten[0] = leaf_hash(b"rewritten");
+                    assert!(!verify_consistency(
+                        m as u64,
+                        n as u64,
+                        &merkle_root(&rewritten),
+                        &new_root,
+                        &path
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sum_tree_binds_amounts() {
+        let leaves: Vec<_> = (1u8..=5)
+            .map(|i| SumNode {
+                hash: leaf_hash(&[i]),
+                sum: MilliSats::from_msat(i as u64 * 1000),
+            })
+            .collect();
+        let root = merkle_root(&leaves);
+        assert_eq!(root.sum, MilliSats::from_sat(15));
+
+        let path = inclusion_path(&leaves, 2);
+        assert_eq!(
+            root_from_path(leaves[2], 2, leaves.len(), &path),
+            Some(root)
+        );
+
+        // Shifting value from a sibling into the leaf keeps the total but not
+        // the root hash
+        let mut shifted = path.clone();
+        shifted[0].sum = MilliSats::from_sat(3);
+        let inflated = SumNode {
+            sum: MilliSats::from_sat(4),
+            ..leaves[2]
+        };
+        let forged = root_from_path(inflated, 2, leaves.len(), &shifted).unwrap();
+        assert_eq!(forged.sum, root.sum);
+        assert_ne!(forged.hash, root.hash);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::output::{self, OutputFormat};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::XOnlyPublicKey;
+use cashu_pol::{fetch_versions, parse_report, Archive, PolError, PolService, SignaturePolicy};
+use serde::Deserialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration as StdDuration;
+use tracing::{info, warn};
+
+/// Mints to mirror and where to keep the archive, read from one JSON file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorConfig {
+    pub archive: PathBuf,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    pub mints: Vec<MirroredMint>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MirroredMint {
+    /// Label for the mint, usually its URL
+    pub mint: String,
+    /// The mint's x-only key; versions it did not sign are archived but
+    /// never handed to the monitor
+    pub pubkey: XOnlyPublicKey,
+    /// URLs or Nostr relays (ws:// or wss://) the mint's report is
+    /// published at
+    pub urls: Vec<String>,
+}
+
+fn default_interval_secs() -> u64 {
+    3600
+}
+
+impl MirrorConfig {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Crawls every configured source on each tick until ctrl-c, handing each
+/// new report version to the monitor and then archiving it. Equivocations
+/// are printed as they are found; a failing mint or source is logged and
+/// retried on the next tick.
+pub async fn run(
+    service: &PolService,
+    config: MirrorConfig,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut archive = Archive::open(&config.archive).await?;
+    let mut interval = tokio::time::interval(StdDuration::from_secs(config.interval_secs.max(1)));
+    loop {
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => return Ok(result?),
+            _ = interval.tick() => {}
+        }
+        for mint in &config.mints {
+            let trusted = SignaturePolicy::single(mint.pubkey);
+            for source in &mint.urls {
+                let versions = match fetch_versions(source).await {
+                    Ok(versions) => versions,
+                    Err(e) => {
+                        warn!(source = %source, error = %e, "Fetch failed");
+                        continue;
+                    }
+                };
+                for data in versions {
+                    if archive.contains(&mint.mint, source, &sha256::Hash::hash(&data)) {
+                        continue;
+                    }
+
+                    // Observe before indexing, so a version is only marked
+                    // seen once the monitor has it
+                    let observed = match parse_report(&data) {
+                        Ok(signed) => {
+                            service
+                                .observe_report(&mint.mint, source, &signed, &trusted)
+                                .await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    match observed {
+                        Ok(equivocations) => {
+                            for equivocation in &equivocations {
+                                output::print(format, equivocation)?;
+                            }
+                        }
+                        // Kept as served: a malformed or unsigned version
+                        // is evidence too
+                        Err(e @ (PolError::MalformedReport(_) | PolError::InvalidSignature(_))) => {
+                            warn!(source = %source, error = %e, "Archiving version the monitor rejected");
+                        }
+                        Err(e) => {
+                            warn!(source = %source, error = %e, "Observe failed; retrying next tick");
+                            continue;
+                        }
+                    }
+
+                    match archive.store(&mint.mint, source, &data).await {
+                        Ok(Some(entry)) => {
+                            info!(mint = %mint.mint, source = %source, hash = %entry.hash, "Archived new report version");
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!(source = %source, error = %e, "Archive failed"),
+                    }
+                }
+            }
+        }
+    }
+}
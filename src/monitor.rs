@@ -0,0 +1,224 @@
+use crate::signer::verify_signature;
+use crate::sink::{nostr_event_id, NOSTR_REPORT_KIND, NOSTR_REPORT_TAG, NOSTR_TIMEOUT_SECS};
+use crate::types::{PolError, SignedReport};
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::{schnorr::Signature, XOnlyPublicKey};
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration as StdDuration;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+// Far above any real report; a source serving more is refused rather
+// than buffered
+const MAX_REPORT_BYTES: usize = 16 * 1024 * 1024;
+// Replaceable events keep one per author, so this only bounds a flood of
+// authors copying the tag
+const NOSTR_MAX_EVENTS: usize = 100;
+const NOSTR_SUBSCRIPTION: &str = "cashu-pol-monitor";
+
+/// A commitment a monitor has seen a mint publish for a closed epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeenCommitment {
+    pub mint: String,
+    pub epoch_id: u64,
+    pub commitment: sha256::Hash,
+    /// Where the commitment was published
+    pub sources: Vec<String>,
+    /// Keys with a valid signature over a report carrying the commitment
+    pub signers: Vec<XOnlyPublicKey>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl SeenCommitment {
+    pub(crate) fn key(&self) -> String {
+        format!("{}|{:020}|{}", self.mint, self.epoch_id, self.commitment)
+    }
+}
+
+/// Two or more different commitments published by one mint for the same
+/// closed epoch: evidence that different readers are shown different views.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Equivocation {
+    pub mint: String,
+    pub epoch_id: u64,
+    pub commitments: Vec<SeenCommitment>,
+}
+
+/// Whether `source` is a Nostr relay rather than an HTTP URL.
+pub fn is_nostr_relay(source: &str) -> bool {
+    source.starts_with("wss://") || source.starts_with("ws://")
+}
+
+/// Fetches the exact bytes served at `url`, up to `MAX_REPORT_BYTES`.
+pub async fn fetch_published(url: &str) -> Result<Vec<u8>, PolError> {
+    let mut response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| PolError::MintUnreachable(e.to_string()))?;
+    let too_large =
+        || PolError::MalformedReport(format!("{} serves over {} bytes", url, MAX_REPORT_BYTES));
+    if response
+        .content_length()
+        .map_or(false, |len| len > MAX_REPORT_BYTES as u64)
+    {
+        return Err(too_large());
+    }
+
+    let mut data = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| PolError::MintUnreachable(e.to_string()))?
+    {
+        if data.len() + chunk.len() > MAX_REPORT_BYTES {
+            return Err(too_large());
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// Every report a source currently serves: the body at an HTTP URL, or
+/// the content of each correctly signed report event held by a relay.
+pub async fn fetch_versions(source: &str) -> Result<Vec<Vec<u8>>, PolError> {
+    if !is_nostr_relay(source) {
+        return Ok(vec![fetch_published(source).await?]);
+    }
+    tokio::time::timeout(
+        StdDuration::from_secs(NOSTR_TIMEOUT_SECS),
+        query_relay(source),
+    )
+    .await
+    .map_err(|_| PolError::MintUnreachable(format!("{} timed out", source)))?
+}
+
+async fn query_relay(relay: &str) -> Result<Vec<Vec<u8>>, PolError> {
+    let config = WebSocketConfig {
+        max_message_size: Some(MAX_REPORT_BYTES),
+        max_frame_size: Some(MAX_REPORT_BYTES),
+        ..Default::default()
+    };
+    let (mut socket, _) = tokio_tungstenite::connect_async_with_config(relay, Some(config), false)
+        .await
+        .map_err(|e| PolError::MintUnreachable(e.to_string()))?;
+    let filter = json!({
+        "kinds": [NOSTR_REPORT_KIND],
+        "#d": [NOSTR_REPORT_TAG],
+        "limit": NOSTR_MAX_EVENTS,
+    });
+    socket
+        .send(WsMessage::Text(
+            json!(["REQ", NOSTR_SUBSCRIPTION, filter]).to_string(),
+        ))
+        .await
+        .map_err(|e| PolError::MintUnreachable(e.to_string()))?;
+
+    // The relay sends stored events, then ["EOSE", <subscription>]
+    let mut versions = Vec::new();
+    while let Some(message) = socket.next().await {
+        let message = message.map_err(|e| PolError::MintUnreachable(e.to_string()))?;
+        let WsMessage::Text(text) = message else {
+            continue;
+        };
+        let Ok(reply) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        if reply[1] != NOSTR_SUBSCRIPTION {
+            continue;
+        }
+        match reply[0].as_str() {
+            Some("EVENT") if versions.len() < NOSTR_MAX_EVENTS => {
+                versions.extend(verified_content(&reply[2]));
+            }
+            Some("EOSE") => break,
+            Some("CLOSED") => {
+                return Err(PolError::MintUnreachable(format!(
+                    "Relay closed the subscription: {}",
+                    reply[2].as_str().unwrap_or_default()
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    let _ = socket.close(None).await;
+    Ok(versions)
+}
+
+#[derive(Deserialize)]
+struct NostrEvent {
+    id: sha256::Hash,
+    pubkey: XOnlyPublicKey,
+    created_at: i64,
+    kind: u16,
+    tags: Value,
+    content: String,
+    sig: Signature,
+}
+
+/// The content of a report event whose id and signature check out. The
+/// event key only shows who relayed the report; the report's own
+/// signatures are checked against the mint's pinned key later.
+fn verified_content(event: &Value) -> Option<Vec<u8>> {
+    let event = NostrEvent::deserialize(event).ok()?;
+    let id = nostr_event_id(
+        &event.pubkey.to_string(),
+        event.created_at,
+        &event.tags,
+        &event.content,
+    );
+    if event.kind != NOSTR_REPORT_KIND || id != event.id {
+        return None;
+    }
+    verify_signature(&id, &event.sig, &event.pubkey).ok()?;
+    Some(event.content.into_bytes())
+}
+
+/// Parses a published report. Only signed reports are accepted, since an
+/// unsigned one could come from anyone on the path to the reader.
+pub fn parse_report(data: &[u8]) -> Result<SignedReport, PolError> {
+    serde_json::from_slice(data)
+        .map_err(|e| PolError::MalformedReport(format!("Expected a signed report: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::NostrSink;
+    use crate::{LocalSigner, PolService, Signer};
+    use bitcoin::secp256k1::SecretKey;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_relay_events_are_checked_before_use() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let signer = Arc::new(LocalSigner::generate());
+        service.set_signer(signer.clone()).await;
+        let report = service.generate_signed_report().await.unwrap();
+
+        let sink = NostrSink::new(
+            "wss://relay.example",
+            SecretKey::from_slice(&[7; 32]).unwrap(),
+        );
+        let event = sink.event(&report, 1_700_000_000).unwrap();
+        let content = verified_content(&event).unwrap();
+        let parsed = parse_report(&content).unwrap();
+        assert_eq!(parsed.signed_by(), vec![signer.public_key()]);
+
+        let mut tampered = event.clone();
+        tampered["content"] = json!("{}");
+        assert!(verified_content(&tampered).is_none());
+
+        assert!(matches!(
+            parse_report(b"{\"epoch_reports\": []}"),
+            Err(PolError::MalformedReport(_))
+        ));
+    }
+}
@@ -0,0 +1,238 @@
+use crate::monitor::SeenCommitment;
+use crate::types::PolReport;
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Keys with a valid signature over a report carrying each commitment.
+pub type Sightings = BTreeMap<sha256::Hash, BTreeSet<XOnlyPublicKey>>;
+
+/// What one independent observer saw a single mint publish: a commitment
+/// per closed epoch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObserverView {
+    pub observer: String,
+    /// Commitments by epoch; an observer may have seen several for one
+    /// epoch if it caught the mint equivocating itself
+    pub commitments: BTreeMap<u64, Sightings>,
+}
+
+impl ObserverView {
+    /// Takes the closed epochs of a report the observer fetched, signed by
+    /// `signers`.
+    pub fn from_report(observer: &str, report: &PolReport, signers: &[XOnlyPublicKey]) -> Self {
+        let mut commitments: BTreeMap<u64, Sightings> = BTreeMap::new();
+        for epoch in report.epoch_reports.iter().filter(|e| e.end_time.is_some()) {
+            commitments
+                .entry(epoch.epoch_id)
+                .or_default()
+                .entry(epoch.commitment)
+                .or_default()
+                .extend(signers);
+        }
+        Self {
+            observer: observer.to_string(),
+            commitments,
+        }
+    }
+
+    /// Takes what the observer's monitor has recorded for `mint`. `seen`
+    /// may cover other mints, whose epochs would otherwise look like split
+    /// views of this one.
+    pub fn from_seen(observer: &str, mint: &str, seen: &[SeenCommitment]) -> Self {
+        let mut commitments: BTreeMap<u64, Sightings> = BTreeMap::new();
+        for entry in seen.iter().filter(|entry| entry.mint == mint) {
+            commitments
+                .entry(entry.epoch_id)
+                .or_default()
+                .entry(entry.commitment)
+                .or_default()
+                .extend(&entry.signers);
+        }
+        Self {
+            observer: observer.to_string(),
+            commitments,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trust {
+    /// Every observer saw the same single commitment
+    Consistent,
+    /// One commitment, but some observers never saw the epoch
+    Partial,
+    /// Observers saw different commitments: the mint showed a split view
+    Conflicting,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitmentSighting {
+    pub commitment: sha256::Hash,
+    pub observers: Vec<String>,
+    /// Keys any observer saw sign a report carrying the commitment
+    pub signers: Vec<XOnlyPublicKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochAssessment {
+    pub epoch_id: u64,
+    pub trust: Trust,
+    /// Set when one key signed two of the differing commitments, so the
+    /// split view is provably the signer's and not a forged copy
+    pub attributable: bool,
+    pub sightings: Vec<CommitmentSighting>,
+    /// Observers with no commitment for the epoch
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObserverComparison {
+    pub observers: Vec<String>,
+    pub epochs: Vec<EpochAssessment>,
+}
+
+impl ObserverComparison {
+    pub fn conflicting(&self) -> impl Iterator<Item = &EpochAssessment> {
+        self.epochs.iter().filter(|e| e.trust == Trust::Conflicting)
+    }
+}
+
+/// Diffs several observers' views of one mint epoch by epoch.
+pub fn compare(views: &[ObserverView]) -> ObserverComparison {
+    let epoch_ids: BTreeSet<u64> = views
+        .iter()
+        .flat_map(|v| v.commitments.keys().copied())
+        .collect();
+
+    let epochs = epoch_ids
+        .into_iter()
+        .map(|epoch_id| {
+            let mut sightings: BTreeMap<sha256::Hash, (Vec<String>, BTreeSet<XOnlyPublicKey>)> =
+                BTreeMap::new();
+            let mut missing = Vec::new();
+            for view in views {
+                match view.commitments.get(&epoch_id) {
+                    Some(commitments) => {
+                        for (commitment, signers) in commitments {
+                            let sighting = sightings.entry(*commitment).or_default();
+                            sighting.0.push(view.observer.clone());
+                            sighting.1.extend(signers);
+                        }
+                    }
+                    None => missing.push(view.observer.clone()),
+                }
+            }
+            let trust = match (sightings.len(), missing.is_empty()) {
+                (1, true) => Trust::Consistent,
+                (1, false) => Trust::Partial,
+                _ => Trust::Conflicting,
+            };
+            let mut signed_once = BTreeSet::new();
+            let attributable = sightings
+                .values()
+                .flat_map(|(_, signers)| signers)
+                .any(|signer| !signed_once.insert(signer));
+            EpochAssessment {
+                epoch_id,
+                trust,
+                attributable,
+                sightings: sightings
+                    .into_iter()
+                    .map(|(commitment, (observers, signers))| CommitmentSighting {
+                        commitment,
+                        observers,
+                        signers: signers.into_iter().collect(),
+                    })
+                    .collect(),
+                missing,
+            }
+        })
+        .collect();
+
+    ObserverComparison {
+        observers: views.iter().map(|v| v.observer.clone()).collect(),
+        epochs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LocalSigner, Signer};
+    use bitcoin::hashes::Hash;
+
+    fn view(observer: &str, commitments: &[(u64, &[u8])]) -> ObserverView {
+        let mut view = ObserverView {
+            observer: observer.to_string(),
+            commitments: BTreeMap::new(),
+        };
+        for (epoch_id, data) in commitments {
+            view.commitments
+                .entry(*epoch_id)
+                .or_default()
+                .insert(sha256::Hash::hash(data), BTreeSet::new());
+        }
+        view
+    }
+
+    #[test]
+    fn test_compare_flags_split_views() {
+        let views = vec![
+            view("alice", &[(0, b"a"), (1, b"b"), (2, b"c")]),
+            view("bob", &[(0, b"a"), (1, b"forked")]),
+            view("carol", &[(0, b"a"), (1, b"b")]),
+        ];
+
+        let comparison = compare(&views);
+        let trust: Vec<Trust> = comparison.epochs.iter().map(|e| e.trust).collect();
+        assert_eq!(
+            trust,
+            vec![Trust::Consistent, Trust::Conflicting, Trust::Partial]
+        );
+
+        let forked = comparison.conflicting().next().unwrap();
+        assert_eq!(forked.epoch_id, 1);
+        assert_eq!(forked.sightings.len(), 2);
+        assert_eq!(comparison.epochs[2].missing, vec!["bob", "carol"]);
+    }
+
+    #[test]
+    fn test_seen_views_keep_mints_and_signers_apart() {
+        let key = LocalSigner::generate().public_key();
+        let seen = |mint: &str, data: &[u8], signers: Vec<XOnlyPublicKey>| SeenCommitment {
+            mint: mint.to_string(),
+            epoch_id: 0,
+            commitment: sha256::Hash::hash(data),
+            sources: vec!["https://a".to_string()],
+            signers,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+        };
+        let alice = [
+            seen("mint-a", b"a", vec![key]),
+            seen("mint-b", b"b", Vec::new()),
+        ];
+        let bob = [seen("mint-a", b"forked", vec![key])];
+
+        // Another mint's epoch 0 is not a second view of this one
+        let views = vec![
+            ObserverView::from_seen("alice", "mint-a", &alice),
+            ObserverView::from_seen("bob", "mint-a", &alice),
+        ];
+        assert_eq!(compare(&views).epochs[0].trust, Trust::Consistent);
+
+        // The same key signed both commitments: the mint split the view
+        let views = vec![
+            ObserverView::from_seen("alice", "mint-a", &alice),
+            ObserverView::from_seen("bob", "mint-a", &bob),
+        ];
+        let comparison = compare(&views);
+        let epoch = &comparison.epochs[0];
+        assert_eq!(epoch.trust, Trust::Conflicting);
+        assert!(epoch.attributable);
+        assert!(epoch.sightings.iter().all(|s| s.signers == vec![key]));
+    }
+}
@@ -0,0 +1,79 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Pretty-printed JSON
+    #[default]
+    Json,
+    /// Indented `key: value` lines for people
+    Text,
+}
+
+/// Writes a command's result to stdout. Logs go to stderr, so stdout only
+/// ever carries data.
+pub fn print(format: OutputFormat, value: &impl Serialize) -> Result<(), Box<dyn Error>> {
+    println!("{}", render(format, value)?);
+    Ok(())
+}
+
+pub fn render(format: OutputFormat, value: &impl Serialize) -> Result<String, Box<dyn Error>> {
+    Ok(match format {
+        OutputFormat::Json => serde_json::to_string_pretty(value)?,
+        OutputFormat::Text => {
+            let mut text = String::new();
+            render_text(&mut text, &serde_json::to_value(value)?, 0)?;
+            text.trim_end().to_string()
+        }
+    })
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some("-".to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        Value::Array(items) if items.is_empty() => Some("[]".to_string()),
+        Value::Object(fields) if fields.is_empty() => Some("{}".to_string()),
+        _ => None,
+    }
+}
+
+fn render_text(out: &mut String, value: &Value, depth: usize) -> std::fmt::Result {
+    let indent = "  ".repeat(depth);
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                match scalar(field) {
+                    Some(s) => writeln!(out, "{}{}: {}", indent, key, s)?,
+                    None => {
+                        writeln!(out, "{}{}:", indent, key)?;
+                        render_text(out, field, depth + 1)?;
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                match scalar(item) {
+                    Some(s) => writeln!(out, "{}- {}", indent, s)?,
+                    None => {
+                        writeln!(out, "{}-", indent)?;
+                        render_text(out, item, depth + 1)?;
+                    }
+                }
+            }
+        }
+        scalar_value => writeln!(
+            out,
+            "{}{}",
+            indent,
+            scalar(scalar_value).unwrap_or_default()
+        )?,
+    }
+    Ok(())
+}
@@ -0,0 +1,434 @@
+use crate::types::{
+    AmountCommitment, BitProof, ConfidentialEpoch, MilliSats, PolError, RangeProof,
+};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{All, PublicKey, Scalar, Secp256k1, SecretKey};
+use rand::RngCore;
+use std::sync::OnceLock;
+
+const GENERATOR_TAG: &[u8] = b"cashu-pol/pedersen/H";
+const BLINDING_TAG: &[u8] = b"cashu-pol/pedersen/blinding";
+const RANGE_TAG: &[u8] = b"cashu-pol/pedersen/range";
+const CARRIED_TAG: &[u8] = b"cashu-pol/pedersen/carried";
+
+/// Range proofs over totals show a committed value lies in
+/// `[0, 2^RANGE_BITS)`.
+pub const RANGE_BITS: u32 = 64;
+
+/// Range of a single proof's amount, about 2.8k BTC. Far more proofs than
+/// any epoch holds could be summed before this wraps the group order, so
+/// a mint cannot hide liabilities behind a "negative" proof.
+pub const PROOF_RANGE_BITS: u32 = 48;
+
+fn secp() -> &'static Secp256k1<All> {
+    static SECP: OnceLock<Secp256k1<All>> = OnceLock::new();
+    SECP.get_or_init(Secp256k1::new)
+}
+
+fn tagged_hash(tag: &[u8], data: &[u8], counter: u32) -> [u8; 32] {
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag);
+    engine.input(data);
+    engine.input(&counter.to_be_bytes());
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Second generator H, found by try-and-increment so nobody knows its
+/// discrete log relative to G.
+pub fn generator_h() -> PublicKey {
+    static H: OnceLock<PublicKey> = OnceLock::new();
+    *H.get_or_init(|| {
+        (0u32..)
+            .find_map(|counter| {
+                let mut bytes = [0x02; 33];
+                bytes[1..].copy_from_slice(&tagged_hash(GENERATOR_TAG, &[], counter));
+                PublicKey::from_slice(&bytes).ok()
+            })
+            .expect("about half of all x coordinates are on the curve")
+    })
+}
+
+/// Blinding factor for a proof, derived from its secret so the holder can
+/// open the commitment to their own amount and nobody else can.
+pub fn blinding_factor(secret: &[u8]) -> SecretKey {
+    (0u32..)
+        .find_map(|counter| SecretKey::from_slice(&tagged_hash(BLINDING_TAG, secret, counter)).ok())
+        .expect("almost every hash is a valid scalar")
+}
+
+/// Blinding factor for the balance carried into `epoch_id` from pruned
+/// epochs, derived from a secret only the mint holds.
+pub(crate) fn carried_blinding(key: &[u8; 32], epoch_id: u64) -> SecretKey {
+    let mut data = key.to_vec();
+    data.extend_from_slice(&epoch_id.to_be_bytes());
+    (0u32..)
+        .find_map(|counter| SecretKey::from_slice(&tagged_hash(CARRIED_TAG, &data, counter)).ok())
+        .expect("almost every hash is a valid scalar")
+}
+
+/// `amount * G + blinding * H`.
+pub fn commit(amount: MilliSats, blinding: &SecretKey) -> Result<PublicKey, PolError> {
+    let blinded = generator_h()
+        .mul_tweak(secp(), &Scalar::from(*blinding))
+        .map_err(|e| PolError::ReportGenerationFailed(e.to_string()))?;
+    if amount == MilliSats::ZERO {
+        return Ok(blinded);
+    }
+
+    let mut value = [0u8; 32];
+    value[24..].copy_from_slice(&amount.to_msat().to_be_bytes());
+    let value = SecretKey::from_slice(&value)
+        .map_err(|e| PolError::ReportGenerationFailed(e.to_string()))?;
+    blinded
+        .combine(&PublicKey::from_secret_key(secp(), &value))
+        .map_err(|e| PolError::ReportGenerationFailed(e.to_string()))
+}
+
+/// Sum of commitments; `None` for an empty set.
+pub fn sum_commitments(commitments: &[PublicKey]) -> Option<PublicKey> {
+    let refs: Vec<&PublicKey> = commitments.iter().collect();
+    PublicKey::combine_keys(&refs).ok()
+}
+
+fn sum_blindings<'a>(mut blindings: impl Iterator<Item = &'a SecretKey>) -> Option<SecretKey> {
+    let first = *blindings.next()?;
+    blindings.try_fold(first, |sum, blinding| {
+        sum.add_tweak(&Scalar::from(*blinding)).ok()
+    })
+}
+
+/// Openings of one side of an epoch, known only to the mint. What gets
+/// published is derived from them.
+pub(crate) struct CommittedSide {
+    /// Y, amount and blinding of each proof, in Y order
+    openings: Vec<(cdk::nuts::nut01::PublicKey, MilliSats, SecretKey)>,
+    pub amount: MilliSats,
+    pub blinding: Option<SecretKey>,
+}
+
+impl CommittedSide {
+    /// Blinds each `(Y, amount, secret)` entry with a factor derived from
+    /// its secret.
+    pub fn new<'a>(
+        entries: impl IntoIterator<
+            Item = Result<(cdk::nuts::nut01::PublicKey, MilliSats, &'a [u8]), PolError>,
+        >,
+    ) -> Result<Self, PolError> {
+        let mut openings = entries
+            .into_iter()
+            .map(|entry| {
+                let (y, amount, secret) = entry?;
+                Ok((y, amount, blinding_factor(secret)))
+            })
+            .collect::<Result<Vec<_>, PolError>>()?;
+        openings.sort_unstable_by_key(|(y, _, _)| y.to_bytes());
+
+        Ok(Self {
+            amount: MilliSats::try_sum(openings.iter().map(|(_, amount, _)| *amount))?,
+            blinding: sum_blindings(openings.iter().map(|(_, _, blinding)| blinding)),
+            openings,
+        })
+    }
+
+    /// Sum of the per-proof commitments; `None` for an empty side.
+    pub fn total(&self) -> Result<Option<PublicKey>, PolError> {
+        self.blinding
+            .as_ref()
+            .map(|blinding| commit(self.amount, blinding))
+            .transpose()
+    }
+
+    /// Per-proof commitments, each with a proof that its amount is in range.
+    pub fn commitments(&self) -> Result<Vec<AmountCommitment>, PolError> {
+        self.openings
+            .iter()
+            .map(|(y, amount, blinding)| {
+                Ok(AmountCommitment {
+                    y: *y,
+                    commitment: commit(*amount, blinding)?,
+                    range: prove_range(*amount, blinding, PROOF_RANGE_BITS)?,
+                })
+            })
+            .collect()
+    }
+}
+
+fn range_error(e: impl ToString) -> PolError {
+    PolError::ReportGenerationFailed(format!("Range proof failed: {}", e.to_string()))
+}
+
+fn random_scalar() -> SecretKey {
+    let mut bytes = [0u8; 32];
+    loop {
+        rand::thread_rng().fill_bytes(&mut bytes);
+        if let Ok(key) = SecretKey::from_slice(&bytes) {
+            return key;
+        }
+    }
+}
+
+fn sub_scalar(a: &SecretKey, b: &SecretKey) -> Result<SecretKey, PolError> {
+    a.add_tweak(&Scalar::from(b.negate())).map_err(range_error)
+}
+
+fn mul_scalar(a: &SecretKey, b: &SecretKey) -> Result<SecretKey, PolError> {
+    a.mul_tweak(&Scalar::from(*b)).map_err(range_error)
+}
+
+/// `weight * G` for a nonzero weight.
+fn value_point(weight: u64) -> PublicKey {
+    let mut bytes = [0u8; 32];
+    bytes[24..].copy_from_slice(&weight.to_be_bytes());
+    let key = SecretKey::from_slice(&bytes).expect("weight is nonzero and below the order");
+    PublicKey::from_secret_key(secp(), &key)
+}
+
+/// `s * H + e * point`, the Schnorr commitment a verifier recomputes.
+fn schnorr_nonce(s: &SecretKey, e: &SecretKey, point: &PublicKey) -> Result<PublicKey, PolError> {
+    let sh = generator_h()
+        .mul_tweak(secp(), &Scalar::from(*s))
+        .map_err(range_error)?;
+    let ep = point
+        .mul_tweak(secp(), &Scalar::from(*e))
+        .map_err(range_error)?;
+    sh.combine(&ep).map_err(range_error)
+}
+
+fn bit_challenge(
+    bit: u32,
+    commitment: &PublicKey,
+    r0: &PublicKey,
+    r1: &PublicKey,
+) -> Result<SecretKey, PolError> {
+    let mut data = bit.to_be_bytes().to_vec();
+    for point in [commitment, r0, r1] {
+        data.extend_from_slice(&point.serialize());
+    }
+    SecretKey::from_slice(&tagged_hash(RANGE_TAG, &data, 0)).map_err(range_error)
+}
+
+/// Proves `commitment = value * 2^bit * G + blinding * H` for a `value` of
+/// 0 or 1, as a CDS OR-proof of knowing the H-discrete log of either
+/// `commitment` or `commitment - 2^bit * G`.
+fn prove_bit(bit: u32, set: bool, blinding: &SecretKey) -> Result<BitProof, PolError> {
+    let weight = value_point(1 << bit);
+    let commitment = commit_bit(bit, set, blinding)?;
+    let statements = [
+        commitment,
+        commitment
+            .combine(&weight.negate(secp()))
+            .map_err(range_error)?,
+    ];
+    let (real, fake) = if set { (1, 0) } else { (0, 1) };
+
+    let fake_e = random_scalar();
+    let fake_s = random_scalar();
+    let fake_r = schnorr_nonce(&fake_s, &fake_e, &statements[fake])?;
+    let k = random_scalar();
+    let real_r = generator_h()
+        .mul_tweak(secp(), &Scalar::from(k))
+        .map_err(range_error)?;
+
+    let mut nonces = [real_r; 2];
+    nonces[fake] = fake_r;
+    let e = bit_challenge(bit, &commitment, &nonces[0], &nonces[1])?;
+    let real_e = sub_scalar(&e, &fake_e)?;
+    let real_s = sub_scalar(&k, &mul_scalar(&real_e, blinding)?)?;
+
+    let mut es = [real_e; 2];
+    es[fake] = fake_e;
+    let mut ss = [real_s; 2];
+    ss[fake] = fake_s;
+    Ok(BitProof {
+        commitment,
+        e0: es[0],
+        e1: es[1],
+        s0: ss[0],
+        s1: ss[1],
+    })
+}
+
+fn commit_bit(bit: u32, set: bool, blinding: &SecretKey) -> Result<PublicKey, PolError> {
+    let blinded = generator_h()
+        .mul_tweak(secp(), &Scalar::from(*blinding))
+        .map_err(range_error)?;
+    if set {
+        blinded.combine(&value_point(1 << bit)).map_err(range_error)
+    } else {
+        Ok(blinded)
+    }
+}
+
+fn verify_bit(bit: u32, proof: &BitProof) -> bool {
+    let check = || -> Result<bool, PolError> {
+        let shifted = proof
+            .commitment
+            .combine(&value_point(1 << bit).negate(secp()))
+            .map_err(range_error)?;
+        let r0 = schnorr_nonce(&proof.s0, &proof.e0, &proof.commitment)?;
+        let r1 = schnorr_nonce(&proof.s1, &proof.e1, &shifted)?;
+        let e = bit_challenge(bit, &proof.commitment, &r0, &r1)?;
+        let sum = proof
+            .e0
+            .add_tweak(&Scalar::from(proof.e1))
+            .map_err(range_error)?;
+        Ok(sum == e)
+    };
+    check().unwrap_or(false)
+}
+
+/// Proves that `commit(value, blinding)` hides a value in `[0, 2^bits)`.
+/// Proof size is linear in the number of bits.
+pub fn prove_range(
+    value: MilliSats,
+    blinding: &SecretKey,
+    bits: u32,
+) -> Result<RangeProof, PolError> {
+    let value = value.to_msat();
+    if bits < u64::BITS && value >> bits != 0 {
+        return Err(PolError::InvalidAmount(format!(
+            "{} msat does not fit in a {}-bit range proof",
+            value, bits
+        )));
+    }
+    let mut blindings: Vec<SecretKey> = (1..bits).map(|_| random_scalar()).collect();
+    // The bit blindings must add up to the commitment's blinding
+    let mut last = *blinding;
+    for bit_blinding in &blindings {
+        last = sub_scalar(&last, bit_blinding)?;
+    }
+    blindings.push(last);
+
+    let bits = blindings
+        .iter()
+        .enumerate()
+        .map(|(bit, bit_blinding)| prove_bit(bit as u32, value >> bit & 1 == 1, bit_blinding))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(RangeProof { bits })
+}
+
+pub fn verify_range(commitment: &PublicKey, proof: &RangeProof, bits: u32) -> bool {
+    if bits == 0 || bits > u64::BITS || proof.bits.len() != bits as usize {
+        return false;
+    }
+    let points: Vec<PublicKey> = proof.bits.iter().map(|b| b.commitment).collect();
+    sum_commitments(&points) == Some(*commitment)
+        && proof
+            .bits
+            .iter()
+            .enumerate()
+            .all(|(bit, bit_proof)| verify_bit(bit as u32, bit_proof))
+}
+
+/// Commitment to `upper_bound - value`, given the commitment to `value`.
+pub fn complement(upper_bound: MilliSats, commitment: &PublicKey) -> Option<PublicKey> {
+    let negated = commitment.negate(secp());
+    if upper_bound == MilliSats::ZERO {
+        return Some(negated);
+    }
+    value_point(upper_bound.to_msat()).combine(&negated).ok()
+}
+
+/// Net commitment to carried balances plus total minted, less total
+/// burned, across `epochs`.
+pub fn net_commitment(epochs: &[ConfidentialEpoch]) -> Option<PublicKey> {
+    let mut points = Vec::new();
+    for epoch in epochs {
+        points.extend(epoch.carried.as_ref().map(|c| c.commitment));
+        points.extend(epoch.minted);
+        points.extend(epoch.burned.map(|c| c.negate(secp())));
+    }
+    sum_commitments(&points)
+}
+
+/// Opening of `net_commitment`: the net amount and its blinding. `carried`
+/// is the opening of the balance carried over from pruned epochs, if any.
+pub(crate) fn net_opening(
+    epochs: &[(CommittedSide, CommittedSide)],
+    carried: Option<(MilliSats, SecretKey)>,
+) -> Result<(MilliSats, SecretKey), PolError> {
+    let minted = MilliSats::try_sum(
+        carried
+            .iter()
+            .map(|(amount, _)| *amount)
+            .chain(epochs.iter().map(|(minted, _)| minted.amount)),
+    )?;
+    let burned = MilliSats::try_sum(epochs.iter().map(|(_, burned)| burned.amount))?;
+    let net = minted.checked_sub(burned).ok_or_else(|| {
+        PolError::InvalidAmount("More was burned than minted in the retained epochs".to_string())
+    })?;
+
+    let added = sum_blindings(
+        carried.iter().map(|(_, blinding)| blinding).chain(
+            epochs
+                .iter()
+                .filter_map(|(minted, _)| minted.blinding.as_ref()),
+        ),
+    );
+    let removed = sum_blindings(
+        epochs
+            .iter()
+            .filter_map(|(_, burned)| burned.blinding.as_ref()),
+    );
+    let blinding = match (added, removed) {
+        (Some(added), Some(removed)) => sub_scalar(&added, &removed)?,
+        (Some(added), None) => added,
+        (None, Some(removed)) => removed.negate(),
+        (None, None) => {
+            return Err(PolError::InvalidAmount(
+                "No liabilities to bound".to_string(),
+            ))
+        }
+    };
+
+    Ok((net, blinding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commitments_are_homomorphic() {
+        let amounts = [1_000u64, 0, 250_500];
+        let blindings: Vec<SecretKey> = (0u8..3).map(|i| blinding_factor(&[i])).collect();
+        let commitments: Vec<PublicKey> = amounts
+            .iter()
+            .zip(&blindings)
+            .map(|(amount, blinding)| commit(MilliSats::from_msat(*amount), blinding).unwrap())
+            .collect();
+
+        let total = MilliSats::from_msat(amounts.iter().sum());
+        let blinding = sum_blindings(blindings.iter()).unwrap();
+        assert_eq!(
+            sum_commitments(&commitments).unwrap(),
+            commit(total, &blinding).unwrap()
+        );
+        assert_ne!(
+            sum_commitments(&commitments).unwrap(),
+            commit(MilliSats::from_msat(251_499), &blinding).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_range_proofs() {
+        let blinding = blinding_factor(b"range");
+        let value = MilliSats::from_msat(1_234_567);
+        let commitment = commit(value, &blinding).unwrap();
+
+        let proof = prove_range(value, &blinding, RANGE_BITS).unwrap();
+        assert!(verify_range(&commitment, &proof, RANGE_BITS));
+        assert!(!verify_range(&commitment, &proof, PROOF_RANGE_BITS));
+
+        let other = commit(MilliSats::from_msat(1_234_568), &blinding).unwrap();
+        assert!(!verify_range(&other, &proof, RANGE_BITS));
+
+        let mut tampered = proof.clone();
+        tampered.bits.swap(0, 1);
+        assert!(!verify_range(&commitment, &tampered, RANGE_BITS));
+
+        let short = prove_range(value, &blinding, PROOF_RANGE_BITS).unwrap();
+        assert!(verify_range(&commitment, &short, PROOF_RANGE_BITS));
+        assert!(prove_range(MilliSats::from_msat(1 << 48), &blinding, PROOF_RANGE_BITS).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration as StdDuration, Instant};
+
+// Buckets are dropped once refilled; this bounds memory under a scan
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// How many requests one client may make: a sustained rate plus a burst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimit {
+    pub requests_per_minute: u32,
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 120,
+            burst: 30,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket per client address, so one client hammering a public
+/// endpoint cannot starve the database for everyone else.
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token for `client`, or returns how long until one is free.
+    pub fn check(&self, client: IpAddr) -> Result<(), StdDuration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), StdDuration> {
+        let burst = f64::from(self.limit.burst.max(1));
+        let per_sec = f64::from(self.limit.requests_per_minute) / 60.0;
+        let refill = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated);
+            (bucket.tokens + elapsed.as_secs_f64() * per_sec).min(burst)
+        };
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            buckets.retain(|_, bucket| refill(bucket) < burst);
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        if per_sec == 0.0 {
+            return Err(StdDuration::MAX);
+        }
+        Err(StdDuration::from_secs_f64((1.0 - bucket.tokens) / per_sec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_refills_per_client() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_minute: 60,
+            burst: 2,
+        });
+        let (alice, bob): (IpAddr, IpAddr) =
+            ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+        let start = Instant::now();
+
+        assert!(limiter.check_at(alice, start).is_ok());
+        assert!(limiter.check_at(alice, start).is_ok());
+        let wait = limiter.check_at(alice, start).unwrap_err();
+        assert_eq!(wait, StdDuration::from_secs(1));
+
+        // Other clients keep their own budget
+        assert!(limiter.check_at(bob, start).is_ok());
+
+        // One request per second refills
+        let later = start + StdDuration::from_secs(1);
+        assert!(limiter.check_at(alice, later).is_ok());
+        assert!(limiter.check_at(alice, later).is_err());
+    }
+}
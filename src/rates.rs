@@ -0,0 +1,129 @@
+use crate::types::{FiatAnnotation, PolError};
+use async_trait::async_trait;
+use bitcoin::Amount;
+use chrono::Utc;
+use tracing::warn;
+
+const FIAT_DISCLAIMER: &str =
+    "Informational only: approximate value at report time, not part of the attested liabilities";
+
+/// Supplies a BTC exchange rate used to annotate reports with a fiat value.
+#[async_trait]
+pub trait RateSource: Send + Sync {
+    /// Short name recorded alongside the rate, e.g. "static".
+    fn name(&self) -> &str;
+
+    /// ISO 4217 code of the quoted currency.
+    fn currency(&self) -> &str;
+
+    /// Digits after the decimal point in `currency`, e.g. 2 for cents.
+    fn decimals(&self) -> u8;
+
+    /// Price of one bitcoin in minor units of `currency`.
+    async fn btc_price(&self) -> Result<u64, PolError>;
+}
+
+/// A fixed, operator-supplied rate.
+pub struct StaticRate {
+    currency: String,
+    decimals: u8,
+    btc_price: u64,
+}
+
+impl StaticRate {
+    pub fn new(currency: impl Into<String>, decimals: u8, btc_price: u64) -> Self {
+        Self {
+            currency: currency.into(),
+            decimals,
+            btc_price,
+        }
+    }
+}
+
+#[async_trait]
+impl RateSource for StaticRate {
+    fn name(&self) -> &str {
+        "static"
+    }
+
+    fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    async fn btc_price(&self) -> Result<u64, PolError> {
+        Ok(self.btc_price)
+    }
+}
+
+/// Values `total` at the source's current rate. A failing source only drops
+/// the annotation; it never fails the report.
+pub(crate) async fn annotate(source: &dyn RateSource, total: Amount) -> Option<FiatAnnotation> {
+    let btc_price = match source.btc_price().await {
+        Ok(price) => price,
+        Err(e) => {
+            warn!(source = source.name(), error = %e, "Exchange rate unavailable");
+            return None;
+        }
+    };
+    let value =
+        u128::from(total.to_sat()) * u128::from(btc_price) / u128::from(Amount::ONE_BTC.to_sat());
+    let Ok(total_outstanding_value) = u64::try_from(value) else {
+        warn!(
+            source = source.name(),
+            btc_price, "Ignoring exchange rate that overflows the report total"
+        );
+        return None;
+    };
+
+    Some(FiatAnnotation {
+        currency: source.currency().to_string(),
+        decimals: source.decimals(),
+        btc_price,
+        total_outstanding_value,
+        source: source.name().to_string(),
+        captured_at: Utc::now(),
+        disclaimer: FIAT_DISCLAIMER.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_sample_proof, PolService};
+    use cdk::{nuts::nut02::Id, Amount as CashuAmount};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_report_carries_fiat_annotation() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        assert!(report.fiat_annotation.is_none());
+
+        service
+            .set_rate_source(Arc::new(StaticRate::new("USD", 2, 5_000_000)))
+            .await;
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let proof = create_sample_proof(keyset_id, CashuAmount::from(1u64));
+        service
+            .record_mint_proof(proof, Amount::from_sat(200_000))
+            .await
+            .unwrap();
+
+        let annotation = service
+            .generate_report()
+            .await
+            .unwrap()
+            .fiat_annotation
+            .unwrap();
+        assert_eq!(annotation.currency, "USD");
+        assert_eq!(annotation.total_outstanding_value, 10_000);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::types::{secret_to_y, EpochState, MilliSats, PolError};
+use cdk::nuts::nut01::PublicKey;
+use cdk::nuts::nut02::Id;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A proof the mint marked as spent. Either the secret or Y identifies it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpentEntry {
+    #[serde(default, rename = "Y")]
+    pub y: Option<PublicKey>,
+    #[serde(default)]
+    pub secret: Option<String>,
+    pub amount: u64,
+}
+
+/// A blind signature the mint issued. Blind signatures cannot be linked to
+/// the proofs they became, so these are compared in aggregate per keyset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedEntry {
+    pub keyset_id: Id,
+    pub amount: u64,
+}
+
+/// Dump of the mint's own records, with amounts in sats.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MintLedger {
+    #[serde(default)]
+    pub spent: Vec<SpentEntry>,
+    #[serde(default)]
+    pub issued: Vec<IssuedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Discrepancy {
+    /// Spent at the mint but never recorded as burned
+    MissingBurn { y: PublicKey, amount: MilliSats },
+    /// Recorded as burned but the mint never saw it spent
+    UnknownBurn { y: PublicKey, amount: MilliSats },
+    BurnAmountMismatch {
+        y: PublicKey,
+        recorded: MilliSats,
+        mint: MilliSats,
+    },
+    /// Issued and recorded mints disagree for a keyset
+    IssuedMismatch {
+        keyset_id: Id,
+        recorded_count: usize,
+        mint_count: usize,
+        recorded: MilliSats,
+        mint: MilliSats,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub checked_burns: usize,
+    pub checked_keysets: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl ReconciliationReport {
+    pub fn is_clean(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+impl MintLedger {
+    /// Reads a ledger dump from a file path or an http(s) URL.
+    pub async fn load(source: &str) -> Result<Self, PolError> {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            return reqwest::get(source)
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| PolError::MintUnreachable(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| PolError::DatabaseDeserializationError(e.into()));
+        }
+
+        let data = tokio::fs::read_to_string(source).await?;
+        serde_json::from_str(&data).map_err(|e| PolError::DatabaseDeserializationError(e.into()))
+    }
+}
+
+/// Compares recorded epochs with the mint's ledger. Only retained epochs
+/// are covered, so the ledger should span the same period.
+pub(crate) fn reconcile(
+    epochs: &[EpochState],
+    ledger: &MintLedger,
+) -> Result<ReconciliationReport, PolError> {
+    let mut discrepancies = Vec::new();
+
+    let mut recorded_burns: BTreeMap<Vec<u8>, (PublicKey, MilliSats)> = BTreeMap::new();
+    for proof in epochs.iter().flat_map(|e| &e.burn_proofs) {
+        let y = proof.y()?;
+        let entry = recorded_burns
+            .entry(y.to_bytes().to_vec())
+            .or_insert((y, MilliSats::ZERO));
+        entry.1 = entry.1.try_add(proof.amount)?;
+    }
+
+    let mut mint_burns: BTreeMap<Vec<u8>, (PublicKey, MilliSats)> = BTreeMap::new();
+    for spent in &ledger.spent {
+        let y = match (&spent.y, &spent.secret) {
+            (Some(y), _) => *y,
+            (None, Some(secret)) => secret_to_y(secret.as_bytes())?,
+            (None, None) => {
+                return Err(PolError::InvalidProof(
+                    "Spent entry has neither Y nor secret".to_string(),
+                ))
+            }
+        };
+        let entry = mint_burns
+            .entry(y.to_bytes().to_vec())
+            .or_insert((y, MilliSats::ZERO));
+        entry.1 = entry.1.try_add(MilliSats::from_sat(spent.amount))?;
+    }
+
+    for (key, (y, mint)) in &mint_burns {
+        match recorded_burns.get(key) {
+            None => discrepancies.push(Discrepancy::MissingBurn {
+                y: *y,
+                amount: *mint,
+            }),
+            Some((_, recorded)) if recorded != mint => {
+                discrepancies.push(Discrepancy::BurnAmountMismatch {
+                    y: *y,
+                    recorded: *recorded,
+                    mint: *mint,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, (y, recorded)) in &recorded_burns {
+        if !mint_burns.contains_key(key) {
+            discrepancies.push(Discrepancy::UnknownBurn {
+                y: *y,
+                amount: *recorded,
+            });
+        }
+    }
+
+    let mut issued: HashMap<Id, ((usize, MilliSats), (usize, MilliSats))> = HashMap::new();
+    for proof in epochs.iter().flat_map(|e| &e.mint_proofs) {
+        let (recorded, _) = issued.entry(proof.proof.keyset_id).or_default();
+        recorded.0 += 1;
+        recorded.1 = recorded.1.try_add(proof.amount)?;
+    }
+    for entry in &ledger.issued {
+        let (_, mint) = issued.entry(entry.keyset_id).or_default();
+        mint.0 += 1;
+        mint.1 = mint.1.try_add(MilliSats::from_sat(entry.amount))?;
+    }
+    let mut keysets: Vec<_> = issued.into_iter().collect();
+    keysets.sort_by_key(|(id, _)| id.to_string());
+    let checked_keysets = keysets.len();
+    for (keyset_id, (recorded, mint)) in keysets {
+        if recorded != mint {
+            discrepancies.push(Discrepancy::IssuedMismatch {
+                keyset_id,
+                recorded_count: recorded.0,
+                mint_count: mint.0,
+                recorded: recorded.1,
+                mint: mint.1,
+            });
+        }
+    }
+
+    Ok(ReconciliationReport {
+        checked_burns: mint_burns
+            .keys()
+            .chain(recorded_burns.keys())
+            .collect::<BTreeSet<_>>()
+            .len(),
+        checked_keysets,
+        discrepancies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_sample_mint_proof;
+    use crate::types::BurnProof;
+    use cdk::Amount as CashuAmount;
+    use chrono::Utc;
+
+    #[test]
+    fn test_reconcile_reports_discrepancies() {
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let burn = |secret: &str, sat| BurnProof {
+            secret: secret.to_string(),
+            amount: MilliSats::from_sat(sat),
+            timestamp: Utc::now(),
+            melt: None,
+        };
+        let epoch = EpochState {
+            epoch_id: 0,
+            start_time: Utc::now(),
+            mint_proofs: [create_sample_mint_proof(keyset_id, CashuAmount::from(4u64))]
+                .into_iter()
+                .collect(),
+            burn_proofs: [
+                burn("agreed", 2),
+                burn("wrong_amount", 3),
+                burn("curated", 1),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let spent = |secret: &str, amount| SpentEntry {
+            y: None,
+            secret: Some(secret.to_string()),
+            amount,
+        };
+        let ledger = MintLedger {
+            spent: vec![
+                spent("agreed", 2),
+                spent("wrong_amount", 4),
+                spent("hidden", 5),
+            ],
+            issued: vec![
+                IssuedEntry {
+                    keyset_id,
+                    amount: 4,
+                },
+                IssuedEntry {
+                    keyset_id,
+                    amount: 8,
+                },
+            ],
+        };
+
+        let report = reconcile(&[epoch], &ledger).unwrap();
+        let y = |secret: &str| secret_to_y(secret.as_bytes()).unwrap();
+        assert_eq!(report.discrepancies.len(), 4);
+        assert!(report.discrepancies.contains(&Discrepancy::MissingBurn {
+            y: y("hidden"),
+            amount: MilliSats::from_sat(5),
+        }));
+        assert!(report.discrepancies.contains(&Discrepancy::UnknownBurn {
+            y: y("curated"),
+            amount: MilliSats::from_sat(1),
+        }));
+        assert!(report
+            .discrepancies
+            .contains(&Discrepancy::BurnAmountMismatch {
+                y: y("wrong_amount"),
+                recorded: MilliSats::from_sat(3),
+                mint: MilliSats::from_sat(4),
+            }));
+        assert!(matches!(
+            report.discrepancies.last(),
+            Some(Discrepancy::IssuedMismatch {
+                recorded_count: 1,
+                mint_count: 2,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ledger_load_errors_name_their_cause() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let missing = temp_dir.path().join("missing.json");
+        assert!(matches!(
+            MintLedger::load(missing.to_str().unwrap()).await,
+            Err(PolError::Io(_))
+        ));
+
+        let malformed = temp_dir.path().join("ledger.json");
+        std::fs::write(&malformed, "{\"spent\": 1}").unwrap();
+        assert!(matches!(
+            MintLedger::load(malformed.to_str().unwrap()).await,
+            Err(PolError::DatabaseDeserializationError(_))
+        ));
+    }
+}
@@ -0,0 +1,673 @@
+use crate::grafana;
+use axum::extract::ConnectInfo;
+use axum::{Extension, Router};
+use bitcoin::secp256k1::SecretKey;
+use cashu_pol::{
+    write_json_lines, FileSink, HttpSink, IpfsSink, NostrSink, PolService, RateLimit, RateLimiter,
+};
+use chrono::Utc;
+use cron::Schedule;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use serde::Deserialize;
+use std::error::Error;
+use std::fs::{DirBuilder, Permissions};
+use std::net::SocketAddr;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::task::JoinSet;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+use tracing::{error, info, warn};
+
+pub const DEFAULT_EVENT_FEED: &str = "127.0.0.1:3339";
+
+/// Prefix of listen addresses that name a Unix domain socket path rather
+/// than a TCP address, e.g. "unix:/run/cashu-pol/events.sock".
+pub const UNIX_PREFIX: &str = "unix:";
+
+/// Owner and group may connect; nobody else
+const SOCKET_MODE: u32 = 0o660;
+
+/// Components run by `serve`, read from one JSON file. A component missing
+/// from the file is disabled.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServeConfig {
+    pub rotation: RotationConfig,
+    pub publication: PublicationConfig,
+    pub keysets: KeysetSyncConfig,
+    pub events: EventFeedConfig,
+    pub grafana: GrafanaConfig,
+    /// Per-client limit shared by the event feed and the Grafana datasource
+    pub rate_limit: RateLimit,
+}
+
+/// Rotates the epoch as soon as its duration has elapsed.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RotationConfig {
+    pub enabled: bool,
+}
+
+/// Periodically signs a report and publishes it to the configured sinks.
+/// Requires --signing-key.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PublicationConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Cron expression in UTC, e.g. "0 0 * * MON"; replaces `interval_secs`
+    /// when set. A leading seconds field is optional.
+    pub report_schedule: Option<String>,
+    /// Seconds between retries of failed deliveries
+    pub retry_secs: u64,
+    pub file_sinks: Vec<PathBuf>,
+    pub http_sinks: Vec<String>,
+    /// Relay URLs, e.g. "wss://relay.example.com"
+    pub nostr_sinks: Vec<String>,
+    /// Hex secret key the Nostr events are signed with; required with
+    /// `nostr_sinks`
+    pub nostr_key: Option<PathBuf>,
+    /// IPFS node RPC endpoints, e.g. "http://127.0.0.1:5001"
+    pub ipfs_sinks: Vec<String>,
+}
+
+impl Default for PublicationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 86400,
+            report_schedule: None,
+            retry_secs: 60,
+            file_sinks: Vec::new(),
+            http_sinks: Vec::new(),
+            nostr_sinks: Vec::new(),
+            nostr_key: None,
+            ipfs_sinks: Vec::new(),
+        }
+    }
+}
+
+/// Keeps the keyset registry in step with the mint's active keysets.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysetSyncConfig {
+    pub enabled: bool,
+    pub mint_url: Option<String>,
+    pub interval_secs: u64,
+}
+
+impl Default for KeysetSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mint_url: None,
+            interval_secs: 3600,
+        }
+    }
+}
+
+/// Streams every service event as JSON lines to each client connecting to
+/// `listen`, for `tail` to attach to. `listen` is a TCP address or
+/// `unix:<path>`.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventFeedConfig {
+    pub enabled: bool,
+    pub listen: String,
+}
+
+impl Default for EventFeedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: DEFAULT_EVENT_FEED.to_string(),
+        }
+    }
+}
+
+/// Serves outstanding balance, issuance and redemption as a Grafana JSON
+/// datasource on `listen`, under `/v1`. `listen` is a TCP address or
+/// `unix:<path>`.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrafanaConfig {
+    pub enabled: bool,
+    pub listen: String,
+    /// Serves HTTPS instead of plain HTTP; TCP addresses only
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for GrafanaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:3340".to_string(),
+            tls: None,
+        }
+    }
+}
+
+/// PEM files holding the certificate chain, leaf first, and its private key.
+/// They are read once at startup.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl ServeConfig {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let config: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if config.keysets.enabled && config.keysets.mint_url.is_none() {
+            return Err("keysets.mint_url is required when keyset sync is enabled".into());
+        }
+        if !config.publication.nostr_sinks.is_empty() && config.publication.nostr_key.is_none() {
+            return Err("publication.nostr_key is required with nostr_sinks".into());
+        }
+        if let Some(expression) = &config.publication.report_schedule {
+            parse_schedule(expression)?;
+        }
+        if config.grafana.tls.is_some() && config.grafana.listen.starts_with(UNIX_PREFIX) {
+            return Err("grafana.tls requires a TCP listen address".into());
+        }
+        Ok(config)
+    }
+}
+
+/// Runs every enabled component against the one service until ctrl-c.
+/// Components share the database through the service, so they never open
+/// it twice.
+pub async fn run(service: Arc<PolService>, config: ServeConfig) -> Result<(), Box<dyn Error>> {
+    let mut tasks = JoinSet::new();
+
+    if config.rotation.enabled {
+        tasks.spawn(rotate(service.clone()));
+    }
+
+    if config.publication.enabled {
+        for dir in &config.publication.file_sinks {
+            service.add_report_sink(Arc::new(FileSink::new(dir))).await;
+        }
+        for url in &config.publication.http_sinks {
+            service.add_report_sink(Arc::new(HttpSink::new(url))).await;
+        }
+        if let Some(path) = &config.publication.nostr_key {
+            let secret_key = read_secret_key(path)?;
+            for relay in &config.publication.nostr_sinks {
+                service
+                    .add_report_sink(Arc::new(NostrSink::new(relay, secret_key)))
+                    .await;
+            }
+        }
+        for url in &config.publication.ipfs_sinks {
+            service.add_report_sink(Arc::new(IpfsSink::new(url))).await;
+        }
+        match &config.publication.report_schedule {
+            Some(expression) => {
+                tasks.spawn(publish_on_schedule(
+                    service.clone(),
+                    parse_schedule(expression)?,
+                ));
+            }
+            None => {
+                tasks.spawn(publish(
+                    service.clone(),
+                    StdDuration::from_secs(config.publication.interval_secs.max(1)),
+                ));
+            }
+        }
+        tasks.spawn(retry_publications(
+            service.clone(),
+            StdDuration::from_secs(config.publication.retry_secs.max(1)),
+        ));
+    }
+
+    if config.keysets.enabled {
+        if let Some(mint_url) = config.keysets.mint_url {
+            tasks.spawn(sync_keysets(
+                service.clone(),
+                mint_url,
+                StdDuration::from_secs(config.keysets.interval_secs.max(1)),
+            ));
+        }
+    }
+
+    let limiter = Arc::new(RateLimiter::new(config.rate_limit));
+
+    if config.events.enabled {
+        let listener = bind(&config.events.listen).await?;
+        info!(listen = %config.events.listen, "Event feed listening");
+        tasks.spawn(serve_events(service.clone(), listener, limiter.clone()));
+    }
+
+    if config.grafana.enabled {
+        let tls = config.grafana.tls.as_ref().map(tls_acceptor).transpose()?;
+        let listener = bind(&config.grafana.listen).await?;
+        info!(listen = %config.grafana.listen, tls = tls.is_some(), "Grafana datasource listening");
+        let router = grafana::router(service.clone(), limiter.clone());
+        tasks.spawn(serve_http(listener, router, tls));
+    }
+
+    if tasks.is_empty() {
+        warn!("No components enabled");
+        return Ok(());
+    }
+
+    info!(components = tasks.len(), "Serving");
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        Some(result) = tasks.join_next() => result?,
+    }
+    info!("Shutting down");
+    tasks.shutdown().await;
+    Ok(())
+}
+
+fn read_secret_key(path: &Path) -> Result<SecretKey, Box<dyn Error>> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    SecretKey::from_str(contents.trim())
+        .map_err(|e| format!("{}: invalid secret key: {}", path.display(), e).into())
+}
+
+async fn rotate(service: Arc<PolService>) {
+    loop {
+        let due = match service.current_epoch_start().await {
+            Ok(start) => start + service.epoch_duration(),
+            Err(e) => {
+                error!(error = %e, "Failed to read current epoch");
+                tokio::time::sleep(StdDuration::from_secs(60)).await;
+                continue;
+            }
+        };
+        if let Ok(wait) = (due - Utc::now()).to_std() {
+            tokio::time::sleep(wait).await;
+        }
+        match service.rotate_epoch().await {
+            Ok(epoch_id) => info!(epoch_id, "Epoch rotated"),
+            Err(e) => {
+                error!(error = %e, "Epoch rotation failed");
+                tokio::time::sleep(StdDuration::from_secs(60)).await;
+            }
+        }
+    }
+}
+
+async fn publish(service: Arc<PolService>, every: StdDuration) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        match service.generate_signed_report().await {
+            Ok(report) => info!(commitment = %report.commitment, "Report published"),
+            Err(e) => error!(error = %e, "Report publication failed"),
+        }
+    }
+}
+
+/// Accepts standard five-field expressions as well as the cron crate's
+/// own, which start with a seconds field and number weekdays differently.
+fn parse_schedule(expression: &str) -> Result<Schedule, Box<dyn Error>> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    let expression = match fields[..] {
+        [minute, hour, day, month, weekday] => format!(
+            "0 {} {} {} {} {}",
+            minute,
+            hour,
+            day,
+            month,
+            cron_weekdays(weekday)?
+        ),
+        _ => expression.to_string(),
+    };
+    Schedule::from_str(&expression)
+        .map_err(|e| format!("Invalid report_schedule {:?}: {}", expression, e).into())
+}
+
+/// Renumbers a standard day-of-week field (0 to 7, Sunday being 0 or 7)
+/// for the cron crate, which counts 1 to 7 from Sunday. Numeric ranges and
+/// steps are expanded into lists; names and `*` pass through.
+fn cron_weekdays(field: &str) -> Result<String, Box<dyn Error>> {
+    let invalid = || format!("Invalid day of week {:?}", field);
+    let mut days = Vec::new();
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<usize>().map_err(|_| invalid())?)),
+            None => (item, None),
+        };
+        let day = |s: &str| s.parse::<u8>().ok();
+        let bounds = match range.split_once('-') {
+            _ if range == "*" => step.map(|_| (0, 6)),
+            Some((from, to)) => day(from).zip(day(to)),
+            None => day(range).map(|from| (from, if step.is_some() { 6 } else { from })),
+        };
+        let Some((from, to)) = bounds else {
+            days.push(item.to_string());
+            continue;
+        };
+        if to > 7 || from > to || step == Some(0) {
+            return Err(invalid().into());
+        }
+        for day in (from..=to).step_by(step.unwrap_or(1)) {
+            days.push((day % 7 + 1).to_string());
+        }
+    }
+    Ok(days.join(","))
+}
+
+async fn publish_on_schedule(service: Arc<PolService>, schedule: Schedule) {
+    let mut after = Utc::now();
+    loop {
+        let Some(due) = schedule.after(&after).next() else {
+            // Returning would end the daemon along with every other component
+            warn!("Report schedule has no further runs");
+            return std::future::pending().await;
+        };
+        info!(%due, "Next scheduled report");
+        if let Ok(wait) = (due - Utc::now()).to_std() {
+            tokio::time::sleep(wait).await;
+        }
+        match service.generate_signed_report().await {
+            Ok(report) => info!(commitment = %report.commitment, "Report published"),
+            Err(e) => error!(error = %e, "Report publication failed"),
+        }
+        // Runs missed while this one was building are skipped
+        after = due.max(Utc::now());
+    }
+}
+
+async fn retry_publications(service: Arc<PolService>, every: StdDuration) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        match service.retry_pending_publications().await {
+            Ok(0) => {}
+            Ok(retried) => info!(retried, "Retried pending publications"),
+            Err(e) => error!(error = %e, "Publication retry failed"),
+        }
+    }
+}
+
+async fn sync_keysets(service: Arc<PolService>, mint_url: String, every: StdDuration) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        match service.sync_keysets(&mint_url).await {
+            Ok(registered) => info!(registered = registered.len(), "Keysets synced"),
+            Err(e) => error!(error = %e, "Keyset sync failed"),
+        }
+    }
+}
+
+/// A bound TCP or Unix domain socket. Unix socket clients are local and
+/// admitted by the socket's file permissions, so they are not rate limited.
+enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+async fn bind(listen: &str) -> Result<Listener, Box<dyn Error>> {
+    let Some(path) = listen.strip_prefix(UNIX_PREFIX) else {
+        return Ok(Listener::Tcp(TcpListener::bind(listen).await?));
+    };
+
+    // A socket left behind by an earlier run would make the bind fail
+    if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = bind_unix(Path::new(path)).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(Listener::Unix(listener))
+}
+
+/// Binds inside a directory only the owner can enter and sets the socket's
+/// mode there, then moves it into place, so it is never reachable with the
+/// umask's looser permissions.
+fn bind_unix(path: &Path) -> std::io::Result<UnixListener> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let private = parent.join(format!(".cashu-pol-bind.{}", std::process::id()));
+    DirBuilder::new().mode(0o700).create(&private)?;
+
+    let staged = private.join("socket");
+    let result = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, Permissions::from_mode(SOCKET_MODE))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    std::fs::remove_dir(&private)?;
+    result
+}
+
+async fn serve_events(service: Arc<PolService>, listener: Listener, limiter: Arc<RateLimiter>) {
+    match listener {
+        Listener::Tcp(listener) => loop {
+            let (socket, peer) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    error!(error = %e, "Event feed accept failed");
+                    continue;
+                }
+            };
+            if limiter.check(peer.ip()).is_err() {
+                warn!(%peer, "Event feed client rate limited");
+                continue;
+            }
+            attach_events(&service, socket, peer.to_string());
+        },
+        Listener::Unix(listener) => loop {
+            match listener.accept().await {
+                Ok((socket, _)) => attach_events(&service, socket, "unix".to_string()),
+                Err(e) => error!(error = %e, "Event feed accept failed"),
+            }
+        },
+    }
+}
+
+fn attach_events<S>(service: &PolService, mut socket: S, peer: String)
+where
+    S: AsyncWrite + Unpin + Send + 'static,
+{
+    info!(%peer, "Event feed client attached");
+    let events = service.subscribe_events();
+    tokio::spawn(async move {
+        // Ends with an error once the client goes away
+        let result = write_json_lines(events, &mut socket).await;
+        info!(%peer, ?result, "Event feed client detached");
+    });
+}
+
+fn tls_acceptor(config: &TlsConfig) -> Result<TlsAcceptor, Box<dyn Error>> {
+    let read = |path: &Path| std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e));
+    let certs = rustls_pemfile::certs(&mut read(&config.cert_path)?.as_slice())
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        return Err(format!("{}: no certificates", config.cert_path.display()).into());
+    }
+    let key = rustls_pemfile::private_key(&mut read(&config.key_path)?.as_slice())?
+        .ok_or_else(|| format!("{}: no private key", config.key_path.display()))?;
+    let mut server = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    server.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(server)))
+}
+
+async fn serve_http(listener: Listener, router: Router, tls: Option<TlsAcceptor>) {
+    match (listener, tls) {
+        (Listener::Tcp(listener), None) => {
+            let app = router.into_make_service_with_connect_info::<SocketAddr>();
+            if let Err(e) = axum::serve(listener, app).await {
+                error!(error = %e, "Grafana datasource stopped");
+            }
+        }
+        // axum::serve only takes plain TCP listeners, so TLS and Unix
+        // connections are driven through hyper directly
+        (Listener::Tcp(listener), Some(tls)) => loop {
+            let (socket, peer) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    error!(error = %e, "Grafana datasource accept failed");
+                    continue;
+                }
+            };
+            // The rate limiter keys on the peer address
+            let router = router.clone().layer(Extension(ConnectInfo(peer)));
+            let tls = tls.clone();
+            tokio::spawn(async move {
+                match tls.accept(socket).await {
+                    Ok(stream) => serve_connection(stream, router).await,
+                    Err(e) => warn!(%peer, error = %e, "Grafana datasource TLS handshake failed"),
+                }
+            });
+        },
+        (Listener::Unix(listener), _) => loop {
+            match listener.accept().await {
+                Ok((socket, _)) => {
+                    tokio::spawn(serve_connection(socket, router.clone()));
+                }
+                Err(e) => error!(error = %e, "Grafana datasource accept failed"),
+            }
+        },
+    }
+}
+
+async fn serve_connection<S>(socket: S, router: Router)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = TowerToHyperService::new(router);
+    if let Err(e) = auto::Builder::new(TokioExecutor::new())
+        .serve_connection(TokioIo::new(socket), service)
+        .await
+    {
+        warn!(error = %e, "Grafana datasource connection failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, TimeZone, Weekday};
+
+    fn next_weekdays(expression: &str, count: usize) -> Vec<Weekday> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        parse_schedule(expression)
+            .unwrap()
+            .after(&start)
+            .take(count)
+            .map(|due| due.weekday())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_schedule_uses_standard_weekdays() {
+        assert_eq!(next_weekdays("0 0 * * 1", 1), [Weekday::Mon]);
+        assert_eq!(next_weekdays("0 0 * * 0", 1), [Weekday::Sun]);
+        assert_eq!(next_weekdays("0 0 * * 7", 1), [Weekday::Sun]);
+        assert_eq!(next_weekdays("0 0 * * MON", 1), [Weekday::Mon]);
+        assert_eq!(
+            next_weekdays("0 0 * * 5-7", 3),
+            [Weekday::Fri, Weekday::Sat, Weekday::Sun]
+        );
+        assert_eq!(
+            next_weekdays("0 0 * * */3", 3),
+            [Weekday::Wed, Weekday::Sat, Weekday::Sun]
+        );
+
+        // Six- and seven-field expressions keep the cron crate's numbering
+        assert_eq!(next_weekdays("0 0 0 * * 1", 1), [Weekday::Sun]);
+
+        assert!(parse_schedule("0 0 * * 8").is_err());
+        assert!(parse_schedule("0 0 * * 3-1").is_err());
+        assert!(parse_schedule("0 0 * * */0").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_schedule_keeps_its_task_running() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let service =
+            Arc::new(PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap());
+        let schedule = parse_schedule("0 0 0 1 1 * 2020").unwrap();
+        assert!(schedule.upcoming(Utc).next().is_none());
+
+        let task = publish_on_schedule(service, schedule);
+        assert!(tokio::time::timeout(StdDuration::from_millis(50), task)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket_is_bound_with_socket_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.sock");
+        let listen = format!("{}{}", UNIX_PREFIX, path.display());
+
+        let Listener::Unix(_listener) = bind(&listen).await.unwrap() else {
+            panic!("bound a TCP listener for {}", listen);
+        };
+        let meta = std::fs::symlink_metadata(&path).unwrap();
+        assert!(meta.file_type().is_socket());
+        assert_eq!(meta.permissions().mode() & 0o777, SOCKET_MODE);
+        // Only the socket is left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_load_requires_a_nostr_key_for_relays() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("serve.json");
+
+        std::fs::write(
+            &path,
+            r#"{"publication": {"nostr_sinks": ["wss://relay.example.com"]}}"#,
+        )
+        .unwrap();
+        assert!(ServeConfig::load(&path).is_err());
+
+        std::fs::write(
+            &path,
+            r#"{"publication": {"nostr_sinks": ["wss://relay.example.com"], "nostr_key": "nostr.key", "ipfs_sinks": ["http://127.0.0.1:5001"]}}"#,
+        )
+        .unwrap();
+        let config = ServeConfig::load(&path).unwrap();
+        assert_eq!(config.publication.ipfs_sinks, ["http://127.0.0.1:5001"]);
+    }
+
+    #[test]
+    fn test_load_rejects_tls_on_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("serve.json");
+        let tls = r#"{"cert_path": "cert.pem", "key_path": "key.pem"}"#;
+
+        let config = format!(
+            r#"{{"grafana": {{"listen": "unix:/tmp/g.sock", "tls": {}}}}}"#,
+            tls
+        );
+        std::fs::write(&path, config).unwrap();
+        assert!(ServeConfig::load(&path).is_err());
+
+        let config = format!(
+            r#"{{"grafana": {{"listen": "127.0.0.1:3340", "tls": {}}}}}"#,
+            tls
+        );
+        std::fs::write(&path, config).unwrap();
+        let config = ServeConfig::load(&path).unwrap();
+        assert_eq!(
+            config.grafana.tls.unwrap().cert_path,
+            PathBuf::from("cert.pem")
+        );
+    }
+}
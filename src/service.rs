@@ -1,30 +1,162 @@
-use crate::storage::Storage;
-use crate::types::{BurnProof, EpochReport, EpochState, MintProof, PolError, PolReport};
+use crate::bundle::{ProofParameters, ReportBundle};
+use crate::codec::CodecKind;
+use crate::events::{
+    run_hooks, GeneratedReport, HookFuture, Hooks, PolEvent, EVENT_CHANNEL_CAPACITY,
+    REPORT_CHANNEL_CAPACITY,
+};
+use crate::keysets;
+use crate::merkle::{self, SumNode};
+use crate::monitor::{self, Equivocation, SeenCommitment};
+use crate::pedersen::{self, CommittedSide};
+use crate::rates::{self, RateSource};
+use crate::reconcile::{self, MintLedger, ReconciliationReport};
+use crate::signer::{self, Signer};
+use crate::sink::{self, ReportSink, SinkState};
+use crate::spec::{SpecReport, SPEC_VERSION};
+use crate::sql;
+use crate::storage::{Appended, Compression, RetryPolicy, Storage};
+use crate::types::{
+    secret_to_y, sort_by_time_and_y, AuditEntry, AuditMismatch, AuditOperation, BurnIndexProof,
+    BurnProof, CarriedCommitment, ConfidentialEpoch, ConsistencyProof, CumulativeBalance,
+    EpochAggregates, EpochAttestation, EpochDetails, EpochFootprint, EpochIdMode, EpochListing,
+    EpochRecord, EpochReport, EpochState, EpochStatus, EpochSummary, ExternalObservation,
+    FinalizedEpoch, HistoryEntry, HistoryHead, HistoryProof, InclusionProof, KeysetRecord,
+    LeafKind, LiabilityBound, MeltQuoteInfo, MilliSats, MintProof, MintQuoteInfo, Page, PolError,
+    PolReport, ProofLookup, ProofRecord, PruneRule, PublicationStatus, Receipt, ReportSignature,
+    SelfAuditReport, SeriesPoint, ServiceStatus, SignaturePolicy, SignedReport, StorageStats,
+    TokenDirection,
+};
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::{schnorr::Signature, SecretKey, XOnlyPublicKey};
 use bitcoin::Amount;
-use cdk::nuts::nut00::Proof;
-use chrono::{Duration, Utc};
+use cdk::mint_url::MintUrl;
+use cdk::nuts::nut00::{Proof, Token};
+use cdk::nuts::nut01::PublicKey;
+use cdk::nuts::nut02::Id;
+use cdk::nuts::CurrencyUnit;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinSet;
+use tracing::{error, warn};
+
+/// Most buckets one liability series is split into.
+const MAX_SERIES_POINTS: usize = 10_000;
+
+/// Settings shared by every epoch of one report.
+struct ReportContext {
+    current_epoch: u64,
+    keysets: Vec<KeysetRecord>,
+    confidential: bool,
+    aggregates_only: bool,
+    /// Oldest retained epoch, when pruned liabilities were carried into it
+    carried_into: Option<u64>,
+}
+
+/// What a report needs from an epoch's proofs, computed off the async
+/// runtime.
+struct EpochDigest {
+    commitment: Result<sha256::Hash, PolError>,
+    burn_index: Result<sha256::Hash, PolError>,
+    confidential: Option<Result<ConfidentialEpoch, PolError>>,
+    /// Published in aggregates-only reports in place of the proofs
+    sum_roots: Option<Result<(SumNode, SumNode), PolError>>,
+}
+
+impl EpochDigest {
+    fn of(
+        epoch: &EpochState,
+        seal: Option<&FinalizedEpoch>,
+        confidential: bool,
+        aggregates_only: bool,
+    ) -> Self {
+        Self {
+            commitment: match seal {
+                Some(seal) => Ok(seal.commitment),
+                None => epoch.commitment(),
+            },
+            burn_index: epoch.burn_index_root(),
+            confidential: confidential.then(|| PolService::confidential_epoch(epoch)),
+            sum_roots: (aggregates_only && !confidential).then(|| epoch.sum_roots()),
+        }
+    }
+}
+
+/// Epoch reports produced one at a time by [`PolService::report_stream`].
+pub struct EpochReportStream<'a> {
+    service: &'a PolService,
+    context: ReportContext,
+    epoch_ids: std::vec::IntoIter<u64>,
+    opening: MilliSats,
+}
+
+impl EpochReportStream<'_> {
+    /// The next epoch's report, or `None` once every epoch was produced.
+    pub async fn next_epoch(&mut self) -> Option<Result<EpochReport, PolError>> {
+        let epoch_id = self.epoch_ids.next()?;
+        Some(self.report(epoch_id).await)
+    }
+
+    /// Running balance after the epochs produced so far, starting from the
+    /// liabilities carried over from pruned epochs.
+    pub fn total_outstanding(&self) -> MilliSats {
+        self.opening
+    }
+
+    async fn report(&mut self, epoch_id: u64) -> Result<EpochReport, PolError> {
+        // Totals come from the loaded epoch, so the balance chain matches
+        // the proofs and commitment even if proofs land mid-stream
+        let storage = &self.service.storage;
+        let epoch = storage
+            .get_epoch(epoch_id)?
+            .ok_or_else(|| PolError::InvalidEpoch(format!("Epoch {} not found", epoch_id)))?;
+        let seal = storage.get_finalized(epoch_id)?;
+        let opening = self.opening;
+        let closing = opening
+            .try_add(epoch.minted()?)?
+            .saturating_sub(epoch.burned()?);
+
+        let (confidential, aggregates_only) =
+            (self.context.confidential, self.context.aggregates_only);
+        let (epoch, seal, digest) = tokio::task::spawn_blocking(move || {
+            let digest = EpochDigest::of(&epoch, seal.as_ref(), confidential, aggregates_only);
+            (epoch, seal, digest)
+        })
+        .await
+        .map_err(|e| PolError::ReportGenerationFailed(e.to_string()))?;
+        self.opening = closing;
+
+        self.service
+            .epoch_report(&epoch, seal, digest, (opening, closing), &self.context)
+    }
+}
 
 pub struct PolService {
     storage: Storage,
     current_epoch: Arc<RwLock<u64>>,
     epoch_duration: Duration,
     max_epoch_history: usize,
+    events: broadcast::Sender<PolEvent>,
+    reports: broadcast::Sender<GeneratedReport>,
+    hooks: RwLock<Hooks>,
+    sinks: RwLock<Vec<Arc<dyn ReportSink>>>,
+    signer: RwLock<Option<Arc<dyn Signer>>>,
+    signature_policy: RwLock<Option<SignaturePolicy>>,
+    epoch_id_mode: RwLock<EpochIdMode>,
+    rate_source: RwLock<Option<Arc<dyn RateSource>>>,
+    confidential_reports: RwLock<bool>,
+    aggregate_reports: RwLock<bool>,
+    mint_url: RwLock<Option<MintUrl>>,
 }
 
 impl PolService {
     pub fn new(epoch_duration_days: i64, max_epoch_history: usize) -> Result<Self, PolError> {
         let db_path = PathBuf::from("cashu-pol.db");
-        let storage = Storage::new(db_path)?;
-
-        Ok(Self {
-            storage,
-            current_epoch: Arc::new(RwLock::new(0)),
-            epoch_duration: Duration::days(epoch_duration_days),
-            max_epoch_history,
-        })
+        Self::with_path(epoch_duration_days, max_epoch_history, db_path)
     }
 
     pub fn with_path<P: AsRef<Path>>(
@@ -33,257 +165,4621 @@ impl PolService {
         db_path: P,
     ) -> Result<Self, PolError> {
         let storage = Storage::new(db_path)?;
+        Ok(Self::with_storage(
+            epoch_duration_days,
+            max_epoch_history,
+            storage,
+        ))
+    }
+
+    /// Like [`PolService::with_path`], but waits out another process briefly
+    /// holding the database lock instead of failing right away.
+    pub async fn open<P: AsRef<Path>>(
+        epoch_duration_days: i64,
+        max_epoch_history: usize,
+        db_path: P,
+    ) -> Result<Self, PolError> {
+        let storage = RetryPolicy::default()
+            .run(|| Storage::new(db_path.as_ref()))
+            .await?;
+        Ok(Self::with_storage(
+            epoch_duration_days,
+            max_epoch_history,
+            storage,
+        ))
+    }
+
+    fn with_storage(epoch_duration_days: i64, max_epoch_history: usize, storage: Storage) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let (reports, _) = broadcast::channel(REPORT_CHANNEL_CAPACITY);
 
-        Ok(Self {
+        Self {
             storage,
             current_epoch: Arc::new(RwLock::new(0)),
             epoch_duration: Duration::days(epoch_duration_days),
             max_epoch_history,
-        })
+            events,
+            reports,
+            hooks: RwLock::new(Hooks::default()),
+            sinks: RwLock::new(Vec::new()),
+            signer: RwLock::new(None),
+            signature_policy: RwLock::new(None),
+            epoch_id_mode: RwLock::new(EpochIdMode::default()),
+            rate_source: RwLock::new(None),
+            confidential_reports: RwLock::new(false),
+            aggregate_reports: RwLock::new(false),
+            mint_url: RwLock::new(None),
+        }
     }
 
     pub async fn initialize(&self) -> Result<(), PolError> {
         let mut current_epoch = self.current_epoch.write().await;
+        self.load_epoch_id_mode().await?;
 
         // Try to load current epoch from storage
         if let Some(epoch_id) = self.storage.get_current_epoch()? {
             *current_epoch = epoch_id;
         } else {
-            // Initialize with epoch 0
-            let epoch_id = 0;
+            // Initialize with epoch 0, or the epoch covering now
+            let (epoch_id, start_time) = match *self.epoch_id_mode.read().await {
+                EpochIdMode::Sequential => (0, Utc::now()),
+                EpochIdMode::TimeDerived => self.time_derived_epoch(Utc::now())?,
+            };
             *current_epoch = epoch_id;
 
             let epoch_state = EpochState {
                 epoch_id,
-                start_time: Utc::now(),
+                start_time,
                 mint_proofs: Default::default(),
                 burn_proofs: Default::default(),
             };
 
-            self.storage.save_epoch(&epoch_state)?;
-            self.storage.save_current_epoch(epoch_id)?;
+            self.write_storage(|storage| storage.save_epoch(&epoch_state))
+                .await?;
+            self.write_storage(|storage| storage.save_current_epoch(epoch_id))
+                .await?;
         }
 
         Ok(())
     }
 
-    pub async fn record_mint_proof(&self, proof: Proof, amount: Amount) -> Result<(), PolError> {
-        let current_epoch = *self.current_epoch.read().await;
+    /// Initializes a fresh database whose history starts at `start_time`,
+    /// laying out consecutive epochs up to now so that past activity can be
+    /// backfilled into them. Behaves like `initialize` on an existing one.
+    pub async fn initialize_from(&self, start_time: DateTime<Utc>) -> Result<(), PolError> {
+        let mut current_epoch = self.current_epoch.write().await;
+        self.load_epoch_id_mode().await?;
 
-        let mut epoch_state = self
-            .storage
-            .get_epoch(current_epoch)?
-            .ok_or_else(|| PolError::InvalidEpoch(format!("Epoch {} not found", current_epoch)))?;
+        if let Some(epoch_id) = self.storage.get_current_epoch()? {
+            *current_epoch = epoch_id;
+            return Ok(());
+        }
 
-        let mint_proof = MintProof {
-            proof,
-            amount,
-            timestamp: Utc::now(),
+        let now = Utc::now();
+        let (mut epoch_id, mut epoch_start) = match *self.epoch_id_mode.read().await {
+            EpochIdMode::Sequential => (0, start_time),
+            EpochIdMode::TimeDerived => self.time_derived_epoch(start_time)?,
         };
+        loop {
+            let epoch_state = EpochState {
+                epoch_id,
+                start_time: epoch_start,
+                mint_proofs: Default::default(),
+                burn_proofs: Default::default(),
+            };
+            self.write_storage(|storage| storage.save_epoch(&epoch_state))
+                .await?;
+
+            let next_start = epoch_start + self.epoch_duration;
+            if next_start > now {
+                break;
+            }
+            epoch_id += 1;
+            epoch_start = next_start;
+        }
 
-        epoch_state.mint_proofs.insert(mint_proof);
-        self.storage.save_epoch(&epoch_state)?;
+        *current_epoch = epoch_id;
+        self.write_storage(|storage| storage.save_current_epoch(epoch_id))
+            .await?;
 
         Ok(())
     }
 
-    pub async fn record_burn_proof(&self, secret: String, amount: Amount) -> Result<(), PolError> {
-        let current_epoch = *self.current_epoch.read().await;
+    /// Chooses how new epoch ids are assigned. Set this before `initialize`,
+    /// which records it in the database; a database keeps the mode it was
+    /// created with, so an existing one is opened in its stored mode and
+    /// asking for another one fails.
+    pub async fn set_epoch_id_mode(&self, mode: EpochIdMode) {
+        *self.epoch_id_mode.write().await = mode;
+    }
 
-        let mut epoch_state = self
-            .storage
-            .get_epoch(current_epoch)?
-            .ok_or_else(|| PolError::InvalidEpoch(format!("Epoch {} not found", current_epoch)))?;
+    async fn load_epoch_id_mode(&self) -> Result<(), PolError> {
+        let mut mode = self.epoch_id_mode.write().await;
+        match self.storage.get_epoch_id_mode()? {
+            Some(stored) if *mode != stored && *mode != EpochIdMode::default() => {
+                Err(PolError::InvalidEpoch(format!(
+                    "Database assigns {} epoch ids, not {}",
+                    stored, *mode
+                )))
+            }
+            Some(stored) => {
+                *mode = stored;
+                Ok(())
+            }
+            // New databases, and ones created before the mode was stored,
+            // keep whatever mode they are opened with from now on
+            None => self.storage.save_epoch_id_mode(*mode),
+        }
+    }
 
-        let burn_proof = BurnProof {
-            secret,
-            amount,
-            timestamp: Utc::now(),
-        };
+    /// Id and aligned start time of the time-derived epoch containing `time`.
+    fn time_derived_epoch(&self, time: DateTime<Utc>) -> Result<(u64, DateTime<Utc>), PolError> {
+        let duration_secs = self.epoch_duration.num_seconds();
+        if duration_secs <= 0 {
+            return Err(PolError::InvalidEpoch(
+                "Epoch duration must be positive".to_string(),
+            ));
+        }
 
-        epoch_state.burn_proofs.insert(burn_proof);
-        self.storage.save_epoch(&epoch_state)?;
+        let epoch_id = u64::try_from(time.timestamp().div_euclid(duration_secs))
+            .map_err(|_| PolError::InvalidEpoch(format!("{} predates the unix epoch", time)))?;
+        let start_time = DateTime::from_timestamp(epoch_id as i64 * duration_secs, 0)
+            .ok_or_else(|| PolError::InvalidEpoch(format!("{} is out of range", time)))?;
+        Ok((epoch_id, start_time))
+    }
 
-        Ok(())
+    pub fn subscribe_events(&self) -> broadcast::Receiver<PolEvent> {
+        self.events.subscribe()
     }
 
-    pub async fn rotate_epoch(&self) -> Result<u64, PolError> {
-        let mut current_epoch = self.current_epoch.write().await;
+    /// Every report published from now on, for dashboards that would
+    /// otherwise poll `generate_report` on their own timer.
+    pub fn subscribe_reports(&self) -> broadcast::Receiver<GeneratedReport> {
+        self.reports.subscribe()
+    }
 
-        let new_epoch_id = *current_epoch + 1;
-        *current_epoch = new_epoch_id;
+    fn emit(&self, event: PolEvent) {
+        // Sending only fails when nobody is subscribed, which is fine
+        let _ = self.events.send(event);
+    }
 
-        let epoch_state = EpochState {
-            epoch_id: new_epoch_id,
-            start_time: Utc::now(),
-            mint_proofs: Default::default(),
-            burn_proofs: Default::default(),
-        };
+    pub async fn on_mint_recorded<F, Fut>(&self, hook: F)
+    where
+        F: Fn(u64, MintProof) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.write().await.mint_recorded.push(Arc::new(
+            move |(epoch_id, proof): (u64, MintProof)| -> HookFuture {
+                Box::pin(hook(epoch_id, proof))
+            },
+        ));
+    }
 
-        self.storage.save_epoch(&epoch_state)?;
-        self.storage.save_current_epoch(new_epoch_id)?;
+    pub async fn on_burn_recorded<F, Fut>(&self, hook: F)
+    where
+        F: Fn(u64, BurnProof) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.write().await.burn_recorded.push(Arc::new(
+            move |(epoch_id, proof): (u64, BurnProof)| -> HookFuture {
+                Box::pin(hook(epoch_id, proof))
+            },
+        ));
+    }
 
-        // Cleanup old epochs beyond max history
-        let epochs = self.storage.list_epochs()?;
-        if epochs.len() > self.max_epoch_history {
-            let mut epoch_ids: Vec<_> = epochs.iter().map(|e| e.epoch_id).collect();
-            epoch_ids.sort_unstable();
+    pub async fn on_epoch_rotated<F, Fut>(&self, hook: F)
+    where
+        F: Fn(u64, u64) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.write().await.epoch_rotated.push(Arc::new(
+            move |(previous_epoch_id, new_epoch_id): (u64, u64)| -> HookFuture {
+                Box::pin(hook(previous_epoch_id, new_epoch_id))
+            },
+        ));
+    }
 
-            while epoch_ids.len() > self.max_epoch_history {
-                if let Some(oldest_epoch) = epoch_ids.first() {
-                    self.storage.delete_epoch(*oldest_epoch)?;
-                }
-                epoch_ids.remove(0);
-            }
-        }
+    pub async fn on_report_generated<F, Fut>(&self, hook: F)
+    where
+        F: Fn(PolReport) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.write().await.report_generated.push(Arc::new(
+            move |report: PolReport| -> HookFuture { Box::pin(hook(report)) },
+        ));
+    }
 
-        Ok(new_epoch_id)
+    pub async fn set_signer(&self, signer: Arc<dyn Signer>) {
+        *self.signer.write().await = Some(signer);
     }
 
-    pub async fn generate_report(&self) -> Result<PolReport, PolError> {
-        let epochs = self.storage.list_epochs()?;
-        let current_epoch = *self.current_epoch.read().await;
-        let mut epoch_reports = Vec::new();
-        let mut total_outstanding = Amount::from_sat(0);
+    /// Policy recorded in signed reports. Without one, reports are signed
+    /// under a single-signer policy for the configured signer.
+    pub async fn set_signature_policy(&self, policy: SignaturePolicy) {
+        *self.signature_policy.write().await = Some(policy);
+    }
 
-        for epoch_state in epochs {
-            let mint_total: u64 = epoch_state
-                .mint_proofs
-                .iter()
-                .map(|p| p.amount.to_sat())
-                .sum();
+    /// Exchange rate used to annotate reports with an approximate fiat value.
+    pub async fn set_rate_source(&self, source: Arc<dyn RateSource>) {
+        *self.rate_source.write().await = Some(source);
+    }
 
-            let burn_total: u64 = epoch_state
-                .burn_proofs
-                .iter()
-                .map(|p| p.amount.to_sat())
-                .sum();
-
-            let outstanding_balance = Amount::from_sat(mint_total.saturating_sub(burn_total));
-            total_outstanding =
-                Amount::from_sat(total_outstanding.to_sat() + outstanding_balance.to_sat());
-
-            let report = EpochReport {
-                epoch_id: epoch_state.epoch_id,
-                start_time: epoch_state.start_time,
-                end_time: if epoch_state.epoch_id < current_epoch {
-                    Some(epoch_state.start_time + self.epoch_duration)
-                } else {
-                    None
-                },
-                mint_proofs: epoch_state.mint_proofs.iter().cloned().collect(),
-                burn_proofs: epoch_state.burn_proofs.iter().cloned().collect(),
-                outstanding_balance,
-            };
+    /// How transient storage failures (lock contention, interrupted I/O)
+    /// are retried before a record is reported as lost.
+    pub fn set_storage_retry_policy(&self, policy: RetryPolicy) {
+        self.storage.set_retry_policy(policy);
+    }
 
-            epoch_reports.push(report);
-        }
+    /// Runs a storage write under the configured retry policy, backing off
+    /// without blocking the runtime.
+    async fn write_storage<T>(
+        &self,
+        mut op: impl FnMut(&Storage) -> Result<T, PolError>,
+    ) -> Result<T, PolError> {
+        self.storage.retry_policy().run(|| op(&self.storage)).await
+    }
 
-        Ok(PolReport {
-            epoch_reports,
-            total_outstanding_balance: total_outstanding,
-            timestamp: Utc::now(),
-        })
+    /// Compress records stored in this database. Existing records are
+    /// re-encoded, and the setting sticks to the database for later runs.
+    pub fn set_storage_compression(&self, compression: Compression) -> Result<(), PolError> {
+        self.storage.set_compression(compression)
     }
 
-    pub async fn verify_mint_proof(&self, epoch_id: u64, proof: &Proof) -> Result<bool, PolError> {
-        if let Some(epoch_state) = self.storage.get_epoch(epoch_id)? {
-            Ok(epoch_state.mint_proofs.iter().any(|p| p.proof == *proof))
-        } else {
-            Err(PolError::InvalidEpoch(format!(
-                "Epoch {} not found",
-                epoch_id
-            )))
-        }
+    /// Serialization format of records stored in this database, kept like
+    /// the compression setting.
+    pub fn set_storage_codec(&self, codec: CodecKind) -> Result<(), PolError> {
+        self.storage.set_codec(codec)
     }
 
-    pub async fn verify_burn_proof(&self, epoch_id: u64, secret: &str) -> Result<bool, PolError> {
-        if let Some(epoch_state) = self.storage.get_epoch(epoch_id)? {
-            Ok(epoch_state.burn_proofs.iter().any(|p| p.secret == secret))
-        } else {
-            Err(PolError::InvalidEpoch(format!(
-                "Epoch {} not found",
-                epoch_id
-            )))
-        }
+    /// Deletes the pre-chunking copies of migrated epochs once the chunked
+    /// epochs are confirmed to hold their proofs.
+    pub fn drop_legacy_epochs(&self) -> Result<u64, PolError> {
+        self.storage.drop_legacy_epochs()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use bitcoin::Amount;
-    use tempfile::tempdir;
+    /// Publish per-epoch amounts only as Pedersen commitments. Proof lists
+    /// are left out of reports; balances remain in the clear.
+    pub async fn set_confidential_reports(&self, enabled: bool) {
+        *self.confidential_reports.write().await = enabled;
+    }
 
-    #[tokio::test]
-    async fn test_pol_service_lifecycle() {
-        let temp_dir = tempdir().unwrap();
-        let db_path = temp_dir.path().join("test.db");
-        let service = PolService::with_path(30, 24, db_path).unwrap();
-        service.initialize().await.unwrap();
+    /// Publish per-epoch proof counts and totals instead of proof lists.
+    /// Reports are then built one epoch at a time, so memory stays bounded
+    /// by the largest epoch rather than the whole history.
+    pub async fn set_aggregate_reports(&self, enabled: bool) {
+        *self.aggregate_reports.write().await = enabled;
+    }
 
-        // Test initial state
-        let report = service.generate_report().await.unwrap();
-        assert_eq!(report.epoch_reports.len(), 1);
-        assert_eq!(report.total_outstanding_balance, Amount::from_sat(0));
+    /// The mint this database tracks. Tokens issued by any other mint are
+    /// refused rather than recorded as its liabilities.
+    pub async fn set_mint_url(&self, mint_url: MintUrl) {
+        *self.mint_url.write().await = Some(mint_url);
+    }
 
-        // Test recording burn proof
-        let amount = Amount::from_sat(1000);
-        let secret = "test_secret".to_string();
-        service
-            .record_burn_proof(secret.clone(), amount)
-            .await
-            .unwrap();
+    pub async fn add_report_sink(&self, sink: Arc<dyn ReportSink>) {
+        self.sinks.write().await.push(sink);
+    }
 
-        let report = service.generate_report().await.unwrap();
-        assert_eq!(report.total_outstanding_balance, Amount::from_sat(0));
+    pub fn sink_state(&self, sink: &str) -> Result<Option<SinkState>, PolError> {
+        self.storage.get_sink_state(sink)
+    }
 
-        // Test epoch rotation
-        let new_epoch_id = service.rotate_epoch().await.unwrap();
-        assert_eq!(new_epoch_id, 1);
+    pub async fn retry_pending_publications(&self) -> Result<usize, PolError> {
+        let sinks = self.sinks.read().await.clone();
+        sink::retry_pending(&self.storage, &sinks).await
+    }
 
-        let report = service.generate_report().await.unwrap();
-        assert_eq!(report.epoch_reports.len(), 2);
+    #[cfg(test)]
+    pub(crate) fn storage(&self) -> &Storage {
+        &self.storage
+    }
 
-        // Test verification
-        assert!(service.verify_burn_proof(0, &secret).await.unwrap());
+    pub fn epoch_duration(&self) -> Duration {
+        self.epoch_duration
     }
 
-    #[tokio::test]
-    async fn test_epoch_cleanup() {
-        let max_history = 3;
-        let temp_dir = tempdir().unwrap();
-        let db_path = temp_dir.path().join("test.db");
-        let service = PolService::with_path(30, max_history, db_path).unwrap();
-        service.initialize().await.unwrap();
+    pub async fn current_epoch(&self) -> u64 {
+        *self.current_epoch.read().await
+    }
 
-        // Create more epochs than max_history
-        for _ in 0..5 {
-            service.rotate_epoch().await.unwrap();
+    /// When the open epoch started; it is due to rotate one epoch duration later.
+    pub async fn current_epoch_start(&self) -> Result<DateTime<Utc>, PolError> {
+        let epoch_id = *self.current_epoch.read().await;
+        self.storage
+            .get_epoch(epoch_id)?
+            .map(|epoch| epoch.start_time)
+            .ok_or_else(|| PolError::InvalidEpoch(format!("Epoch {} not found", epoch_id)))
+    }
+
+    pub async fn record_mint_proof(
+        &self,
+        proof: Proof,
+        amount: impl Into<MilliSats>,
+    ) -> Result<(), PolError> {
+        self.record_mint_proof_with_quote(proof, amount, None).await
+    }
+
+    /// Records every proof in a serialized token, V3 (`cashuA...`) or V4
+    /// (`cashuB...`), into the current epoch with the quote or melt given by
+    /// `direction`, and returns how many were new. The proofs are written in
+    /// one transaction, so either all or none are recorded.
+    pub async fn record_from_token(
+        &self,
+        token: &str,
+        direction: TokenDirection,
+    ) -> Result<usize, PolError> {
+        let token = Token::from_str(token.trim())
+            .map_err(|e| PolError::InvalidProof(format!("Invalid token: {}", e)))?;
+        if let Some(expected) = self.mint_url.read().await.as_ref() {
+            let mint_url = token
+                .mint_url()
+                .map_err(|e| PolError::InvalidProof(format!("Invalid token: {}", e)))?;
+            if mint_url != *expected {
+                return Err(PolError::InvalidProof(format!(
+                    "Token is from {}, not {}",
+                    mint_url, expected
+                )));
+            }
         }
+        let to_millisats: fn(u64) -> Result<MilliSats, PolError> = match token.unit() {
+            None | Some(CurrencyUnit::Sat) => MilliSats::try_from_sat,
+            Some(CurrencyUnit::Msat) => |msat| Ok(MilliSats::from_msat(msat)),
+            Some(unit) => {
+                return Err(PolError::InvalidProof(format!(
+                    "Unsupported token unit: {}",
+                    unit
+                )))
+            }
+        };
 
-        let report = service.generate_report().await.unwrap();
-        assert_eq!(report.epoch_reports.len(), max_history);
+        // V4 tokens group proofs by keyset; each proof keeps its keyset id
+        let proofs = token.proofs();
+        let timestamp = Utc::now();
+        let appended = match direction {
+            TokenDirection::Mint(quote) => {
+                let mints = proofs
+                    .into_iter()
+                    .map(|proof| {
+                        Ok(MintProof {
+                            amount: to_millisats(u64::from(proof.amount))?,
+                            proof,
+                            timestamp,
+                            quote: quote.clone(),
+                        })
+                    })
+                    .collect::<Result<_, PolError>>()?;
+                self.insert_proofs(None, mints, Vec::new()).await?
+            }
+            TokenDirection::Burn(melt) => {
+                let burns = proofs
+                    .into_iter()
+                    .map(|proof| {
+                        Ok(BurnProof {
+                            amount: to_millisats(u64::from(proof.amount))?,
+                            secret: proof.secret.to_string(),
+                            timestamp,
+                            melt: melt.clone(),
+                        })
+                    })
+                    .collect::<Result<_, PolError>>()?;
+                self.insert_proofs(None, Vec::new(), burns).await?
+            }
+        };
+
+        Ok(appended.count())
     }
 
-    #[tokio::test]
-    async fn test_outstanding_balance_calculation() {
-        let temp_dir = tempdir().unwrap();
-        let db_path = temp_dir.path().join("test.db");
-        let service = PolService::with_path(30, 24, db_path).unwrap();
-        service.initialize().await.unwrap();
+    /// Records a mint into the current epoch and returns a receipt, signed
+    /// by the service's signer, for the mint to hand back to the wallet.
+    pub async fn record_mint_proof_with_receipt(
+        &self,
+        proof: Proof,
+        amount: impl Into<MilliSats>,
+    ) -> Result<Receipt, PolError> {
+        let signer = self.signer().await?;
 
-        // Record multiple burn proofs
-        let amounts = vec![1000, 2000, 3000];
-        for (i, amount) in amounts.iter().enumerate() {
-            let secret = format!("test_secret_{}", i);
-            service
-                .record_burn_proof(secret, Amount::from_sat(*amount))
-                .await
-                .unwrap();
+        let mint_proof = MintProof {
+            proof,
+            amount: amount.into(),
+            timestamp: Utc::now(),
+            quote: None,
+        };
+        let (y, amount, timestamp) = (mint_proof.y()?, mint_proof.amount, mint_proof.timestamp);
+
+        let epoch_id = self.insert_mint_proof(None, mint_proof).await?;
+        Self::sign_receipt(
+            signer.as_ref(),
+            LeafKind::Mint,
+            epoch_id,
+            y,
+            amount,
+            timestamp,
+        )
+        .await
+    }
+
+    /// Records a mint into the current epoch along with the quote it was
+    /// issued against, when known.
+    pub async fn record_mint_proof_with_quote(
+        &self,
+        proof: Proof,
+        amount: impl Into<MilliSats>,
+        quote: Option<MintQuoteInfo>,
+    ) -> Result<(), PolError> {
+        let mint_proof = MintProof {
+            proof,
+            amount: amount.into(),
+            timestamp: Utc::now(),
+            quote,
+        };
+
+        self.insert_mint_proof(None, mint_proof).await?;
+        Ok(())
+    }
+
+    pub async fn record_burn_proof(
+        &self,
+        secret: String,
+        amount: impl Into<MilliSats>,
+    ) -> Result<(), PolError> {
+        self.record_burn_proof_with_melt(secret, amount, None).await
+    }
+
+    /// Records a mint that happened at `timestamp` into the epoch that was
+    /// active then, creating earlier epochs if it predates all of them.
+    /// Closed epochs that carry attestations are refused; others are logged
+    /// again with their new commitment.
+    pub async fn record_mint_proof_at(
+        &self,
+        proof: Proof,
+        amount: impl Into<MilliSats>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), PolError> {
+        let epoch_id = self.epoch_for_backfill(timestamp).await?;
+
+        let mint_proof = MintProof {
+            proof,
+            amount: amount.into(),
+            timestamp,
+            quote: None,
+        };
+
+        self.insert_mint_proof(Some(epoch_id), mint_proof).await?;
+        let current_epoch = self.current_epoch().await;
+        if epoch_id < current_epoch {
+            self.relog_closed_epochs(current_epoch).await?;
         }
+        Ok(())
+    }
 
-        let report = service.generate_report().await.unwrap();
-        assert_eq!(report.total_outstanding_balance, Amount::from_sat(0));
+    /// Records a burn into the current epoch and returns a signed receipt,
+    /// so the wallet can later prove the redemption was reported.
+    pub async fn record_burn_proof_with_receipt(
+        &self,
+        secret: String,
+        amount: impl Into<MilliSats>,
+    ) -> Result<Receipt, PolError> {
+        let signer = self.signer().await?;
+
+        let burn_proof = BurnProof {
+            secret,
+            amount: amount.into(),
+            timestamp: Utc::now(),
+            melt: None,
+        };
+        let (y, amount, timestamp) = (burn_proof.y()?, burn_proof.amount, burn_proof.timestamp);
+
+        let epoch_id = self.insert_burn_proof(None, burn_proof).await?;
+        Self::sign_receipt(
+            signer.as_ref(),
+            LeafKind::Burn,
+            epoch_id,
+            y,
+            amount,
+            timestamp,
+        )
+        .await
+    }
+
+    /// Records a burn into the current epoch along with the melt it was
+    /// redeemed in, when known.
+    pub async fn record_burn_proof_with_melt(
+        &self,
+        secret: String,
+        amount: impl Into<MilliSats>,
+        melt: Option<MeltQuoteInfo>,
+    ) -> Result<(), PolError> {
+        let burn_proof = BurnProof {
+            secret,
+            amount: amount.into(),
+            timestamp: Utc::now(),
+            melt,
+        };
+
+        self.insert_burn_proof(None, burn_proof).await?;
+        Ok(())
+    }
+
+    /// Records a burn that happened at `timestamp` into the epoch that was
+    /// active then, creating earlier epochs if it predates all of them.
+    /// Closed epochs that carry attestations are refused; others are logged
+    /// again with their new commitment.
+    pub async fn record_burn_proof_at(
+        &self,
+        secret: String,
+        amount: impl Into<MilliSats>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), PolError> {
+        let epoch_id = self.epoch_for_backfill(timestamp).await?;
+
+        let burn_proof = BurnProof {
+            secret,
+            amount: amount.into(),
+            timestamp,
+            melt: None,
+        };
+
+        self.insert_burn_proof(Some(epoch_id), burn_proof).await?;
+        let current_epoch = self.current_epoch().await;
+        if epoch_id < current_epoch {
+            self.relog_closed_epochs(current_epoch).await?;
+        }
+        Ok(())
+    }
+
+    /// Appends history entries for closed epochs a backfill or rewrite
+    /// changed, so the log keeps matching what reports publish.
+    async fn relog_closed_epochs(&self, current_epoch: u64) -> Result<(), PolError> {
+        let closed: Vec<_> = self
+            .storage
+            .list_epochs()?
+            .into_iter()
+            .filter(|epoch| epoch.epoch_id < current_epoch)
+            .collect();
+        let entries = self.history_updates(&closed)?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+        self.write_storage(|storage| storage.append_history(&entries))
+            .await
+    }
+
+    /// History entries for `closed` epochs, oldest first, that differ from
+    /// what the log last holds for them.
+    fn history_updates(&self, closed: &[EpochState]) -> Result<Vec<HistoryEntry>, PolError> {
+        let logged: HashMap<u64, HistoryEntry> = self
+            .storage
+            .list_history()?
+            .into_iter()
+            .map(|entry| (entry.epoch_id, entry))
+            .collect();
+        let balances = self.epoch_balances(closed)?;
+
+        let mut entries = Vec::new();
+        for (epoch, (_, closing)) in closed.iter().zip(balances) {
+            let entry = HistoryEntry {
+                epoch_id: epoch.epoch_id,
+                commitment: match self.storage.get_finalized(epoch.epoch_id)? {
+                    Some(seal) => seal.commitment,
+                    None => epoch.commitment()?,
+                },
+                closing_balance: closing,
+            };
+            if logged.get(&epoch.epoch_id) != Some(&entry) {
+                entries.push(entry);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn epoch_for_backfill(&self, timestamp: DateTime<Utc>) -> Result<u64, PolError> {
+        if let Some(epoch_id) = self.epoch_for_time(timestamp).await? {
+            // Attestations sign a closed epoch as it stood; changing it
+            // would leave them stale and fail every later self-audit
+            if epoch_id < *self.current_epoch.read().await
+                && !self.storage.get_attestations(epoch_id)?.is_empty()
+            {
+                return Err(PolError::InvalidEpoch(format!(
+                    "Epoch {} is attested and cannot be backfilled",
+                    epoch_id
+                )));
+            }
+            return Ok(epoch_id);
+        }
+
+        let earliest = self
+            .storage
+            .list_epochs()?
+            .into_iter()
+            .min_by_key(|epoch| epoch.epoch_id)
+            .ok_or_else(|| PolError::InvalidEpoch("No epochs initialized".to_string()))?;
+
+        // Work out the whole gap first so a failure leaves storage untouched
+        let mut gap = Vec::new();
+        let mut start_time = earliest.start_time;
+        let mut epoch_id = earliest.epoch_id;
+        while start_time > timestamp {
+            epoch_id = epoch_id.checked_sub(1).ok_or_else(|| {
+                PolError::InvalidEpoch(format!(
+                    "{} predates epoch 0; initialize the database from an earlier start",
+                    timestamp
+                ))
+            })?;
+            start_time -= self.epoch_duration;
+            gap.push(EpochState {
+                epoch_id,
+                start_time,
+                mint_proofs: Default::default(),
+                burn_proofs: Default::default(),
+            });
+        }
+
+        for epoch_state in &gap {
+            self.write_storage(|storage| storage.save_epoch(epoch_state))
+                .await?;
+        }
+
+        Ok(epoch_id)
+    }
+
+    async fn insert_mint_proof(
+        &self,
+        epoch_id: Option<u64>,
+        mint_proof: MintProof,
+    ) -> Result<u64, PolError> {
+        let appended = self
+            .insert_proofs(epoch_id, vec![mint_proof], Vec::new())
+            .await?;
+        Ok(appended.summary.epoch_id)
+    }
+
+    async fn insert_burn_proof(
+        &self,
+        epoch_id: Option<u64>,
+        burn_proof: BurnProof,
+    ) -> Result<u64, PolError> {
+        let appended = self
+            .insert_proofs(epoch_id, Vec::new(), vec![burn_proof])
+            .await?;
+        Ok(appended.summary.epoch_id)
+    }
+
+    /// Appends mints and burns to an epoch, the current one when `epoch_id`
+    /// is `None`, in one transaction, then announces only the ones it did
+    /// not already hold.
+    async fn insert_proofs(
+        &self,
+        epoch_id: Option<u64>,
+        mint_proofs: Vec<MintProof>,
+        burn_proofs: Vec<BurnProof>,
+    ) -> Result<Appended, PolError> {
+        let (epoch_id, appended) = {
+            // Rotations, merges and resegmentation move epochs under the
+            // write lock, so the current epoch is read under the same guard
+            // that covers the append
+            let current_epoch = self.current_epoch.read().await;
+            let epoch_id = epoch_id.unwrap_or(*current_epoch);
+            let appended = self
+                .write_storage(|storage| {
+                    storage.append_proofs(epoch_id, &mint_proofs, &burn_proofs)
+                })
+                .await?
+                .ok_or_else(|| PolError::InvalidEpoch(format!("Epoch {} not found", epoch_id)))?;
+            (epoch_id, appended)
+        };
+
+        let (mint_hooks, burn_hooks) = {
+            let hooks = self.hooks.read().await;
+            (hooks.mint_recorded.clone(), hooks.burn_recorded.clone())
+        };
+        for mint_proof in &appended.mints {
+            self.emit(PolEvent::MintRecorded {
+                epoch_id,
+                proof: mint_proof.listing()?,
+            });
+            run_hooks(&mint_hooks, (epoch_id, mint_proof.clone())).await;
+        }
+        for burn_proof in &appended.burns {
+            self.emit(PolEvent::BurnRecorded {
+                epoch_id,
+                proof: burn_proof.listing()?,
+            });
+            run_hooks(&burn_hooks, (epoch_id, burn_proof.clone())).await;
+        }
+        if !appended.burns.is_empty() {
+            self.alert_if_overdrawn(&appended.summary);
+        }
+
+        Ok(appended)
+    }
+
+    fn alert_if_overdrawn(&self, summary: &EpochSummary) {
+        let (minted, burned) = (summary.minted, summary.burned);
+        if burned > minted {
+            self.emit(PolEvent::Alert {
+                epoch_id: summary.epoch_id,
+                message: format!(
+                    "Epoch {} has redeemed {} more than it issued",
+                    summary.epoch_id,
+                    burned.saturating_sub(minted)
+                ),
+                timestamp: Utc::now(),
+            });
+        }
+    }
+
+    /// Records a batch of proofs into the current epoch in one storage
+    /// transaction. Mint amounts are the proofs' own, in sats. Returns how
+    /// many were stored; a record repeated within the batch is stored once.
+    pub async fn record_batch(&self, records: Vec<ProofRecord>) -> Result<usize, PolError> {
+        let timestamp = Utc::now();
+        let mut mints = Vec::new();
+        let mut burns = Vec::new();
+        for record in records {
+            match record {
+                ProofRecord::Mint { proof, quote } => mints.push(MintProof {
+                    amount: MilliSats::try_from_sat(u64::from(proof.amount))?,
+                    proof,
+                    timestamp,
+                    quote,
+                }),
+                ProofRecord::Burn {
+                    secret,
+                    amount,
+                    melt,
+                } => burns.push(BurnProof {
+                    secret,
+                    amount: MilliSats::try_from_sat(amount)?,
+                    timestamp,
+                    melt,
+                }),
+            }
+        }
+
+        let appended = self.insert_proofs(None, mints, burns).await?;
+        Ok(appended.count())
+    }
+
+    pub async fn rotate_epoch(&self) -> Result<u64, PolError> {
+        let mut current_epoch = self.current_epoch.write().await;
+
+        let previous_epoch_id = *current_epoch;
+        let (new_epoch_id, start_time) = match *self.epoch_id_mode.read().await {
+            EpochIdMode::Sequential => (*current_epoch + 1, Utc::now()),
+            EpochIdMode::TimeDerived => {
+                let (epoch_id, start_time) = self.time_derived_epoch(Utc::now())?;
+                if epoch_id <= previous_epoch_id {
+                    return Err(PolError::InvalidEpoch(format!(
+                        "Epoch {} has not ended yet",
+                        previous_epoch_id
+                    )));
+                }
+                (epoch_id, start_time)
+            }
+        };
+        let mut epochs = self.storage.list_epochs()?;
+
+        // The new epoch opens with what the previous one closed at
+        let carried = self
+            .epoch_balances(&epochs)?
+            .last()
+            .map_or(MilliSats::ZERO, |(_, closing)| *closing);
+
+        // The closing epoch goes into the history log as last committed,
+        // along with any closed epoch not logged as it now stands
+        let history = self.history_updates(&epochs)?;
+
+        let epoch_state = EpochState {
+            epoch_id: new_epoch_id,
+            start_time,
+            mint_proofs: Default::default(),
+            burn_proofs: Default::default(),
+        };
+        epochs.push(epoch_state.clone());
+
+        // Cleanup old epochs beyond max history
+        let mut pruned_epoch_ids = Vec::new();
+        let mut retained_opening = None;
+        // Sealed epochs covered by a published report are only pruned by
+        // force, so rotation stops short of the first one
+        let mut keep_from = epochs.len().saturating_sub(self.max_epoch_history);
+        for (index, epoch) in epochs[..keep_from].iter().enumerate() {
+            if self.is_published_seal(epoch.epoch_id)? {
+                warn!(
+                    epoch_id = epoch.epoch_id,
+                    "Keeping a published sealed epoch past max history"
+                );
+                keep_from = index;
+                break;
+            }
+        }
+        if keep_from > 0 {
+            let balances = self.epoch_balances(&epochs)?;
+            pruned_epoch_ids = epochs[..keep_from].iter().map(|e| e.epoch_id).collect();
+
+            // Pruned liabilities live on in the oldest retained epoch
+            retained_opening = epochs
+                .get(keep_from)
+                .map(|oldest| (oldest.epoch_id, balances[keep_from].0));
+        }
+
+        let audit = retained_opening.map(|(_, carried_balance)| AuditEntry {
+            timestamp: Utc::now(),
+            operation: AuditOperation::Prune {
+                epoch_ids: pruned_epoch_ids.clone(),
+                carried_balance,
+                forced: false,
+            },
+        });
+
+        self.write_storage(|storage| {
+            storage.rotate_epoch(
+                &epoch_state,
+                &history,
+                carried,
+                &pruned_epoch_ids,
+                retained_opening,
+                audit.as_ref(),
+            )
+        })
+        .await?;
+        *current_epoch = new_epoch_id;
+
+        self.emit(PolEvent::EpochRotated {
+            previous_epoch_id,
+            new_epoch_id,
+            pruned_epoch_ids,
+            timestamp: Utc::now(),
+        });
+
+        // Release the epoch lock so hooks can call back into the service
+        drop(current_epoch);
+        let hooks = self.hooks.read().await.epoch_rotated.clone();
+        run_hooks(&hooks, (previous_epoch_id, new_epoch_id)).await;
+
+        Ok(new_epoch_id)
+    }
+
+    fn committed_sides(epoch: &EpochState) -> Result<(CommittedSide, CommittedSide), PolError> {
+        let minted = CommittedSide::new(
+            epoch
+                .mint_proofs
+                .iter()
+                .map(|p| Ok((p.y()?, p.amount, p.proof.secret.as_bytes()))),
+        )?;
+        let burned = CommittedSide::new(
+            epoch
+                .burn_proofs
+                .iter()
+                .map(|p| Ok((p.y()?, p.amount, p.secret.as_bytes()))),
+        )?;
+        Ok((minted, burned))
+    }
+
+    fn confidential_epoch(epoch: &EpochState) -> Result<ConfidentialEpoch, PolError> {
+        let (minted, burned) = Self::committed_sides(epoch)?;
+        Ok(ConfidentialEpoch {
+            mint_commitments: minted.commitments()?,
+            burn_commitments: burned.commitments()?,
+            minted: minted.total()?,
+            burned: burned.total()?,
+            carried: None,
+        })
+    }
+
+    /// Range-proves that net liabilities across retained epochs are at most
+    /// `upper_bound`, against the commitments of a confidential report.
+    pub async fn prove_liability_bound(
+        &self,
+        upper_bound: impl Into<MilliSats>,
+    ) -> Result<LiabilityBound, PolError> {
+        let upper_bound = upper_bound.into();
+        let stored = self.storage.list_epochs()?;
+        let carried = match stored.first() {
+            Some(first) => match self.storage.get_opening_balance(first.epoch_id)? {
+                Some(amount) => Some(self.carried_opening(first.epoch_id, amount)?),
+                None => None,
+            },
+            None => None,
+        };
+        let sides = stored
+            .iter()
+            .map(Self::committed_sides)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Commitments add up, so this equals the sum a report publishes
+        let (net, blinding) = pedersen::net_opening(&sides, carried)?;
+        let commitment = pedersen::commit(net, &blinding)?;
+        let headroom = upper_bound.checked_sub(net).ok_or_else(|| {
+            PolError::InvalidAmount(format!(
+                "Liabilities exceed the claimed bound of {}",
+                upper_bound
+            ))
+        })?;
+
+        tokio::task::spawn_blocking(move || {
+            Ok(LiabilityBound {
+                commitment,
+                upper_bound,
+                lower: pedersen::prove_range(net, &blinding, pedersen::RANGE_BITS)?,
+                upper: pedersen::prove_range(headroom, &blinding.negate(), pedersen::RANGE_BITS)?,
+            })
+        })
+        .await
+        .map_err(|e| PolError::ReportGenerationFailed(e.to_string()))?
+    }
+
+    /// Opening and closing balance of each of `epochs`, in id order. The
+    /// oldest epoch opens with the balance carried into it when earlier
+    /// history was pruned.
+    fn epoch_balances(
+        &self,
+        epochs: &[EpochState],
+    ) -> Result<Vec<(MilliSats, MilliSats)>, PolError> {
+        let Some(first) = epochs.first() else {
+            return Ok(Vec::new());
+        };
+
+        let mut opening = self
+            .storage
+            .get_opening_balance(first.epoch_id)?
+            .unwrap_or(MilliSats::ZERO);
+        let mut balances = Vec::with_capacity(epochs.len());
+        for epoch in epochs {
+            let closing = opening
+                .try_add(epoch.minted()?)?
+                .saturating_sub(epoch.burned()?);
+            balances.push((opening, closing));
+            opening = closing;
+        }
+
+        Ok(balances)
+    }
+
+    pub async fn generate_report(&self) -> Result<PolReport, PolError> {
+        if *self.aggregate_reports.read().await {
+            let mut stream = self.report_stream().await?;
+            let mut epoch_reports = Vec::new();
+            while let Some(report) = stream.next_epoch().await {
+                epoch_reports.push(report?);
+            }
+            return self
+                .finish_report(epoch_reports, stream.total_outstanding())
+                .await;
+        }
+
+        let epochs = self.storage.list_epochs()?;
+        let context = self
+            .report_context(epochs.first().map(|e| e.epoch_id))
+            .await?;
+        let mut epoch_reports = Vec::new();
+
+        // The total is the running balance, so an epoch redeeming more than
+        // it issued pays down earlier epochs instead of counting as zero
+        let balances = self.epoch_balances(&epochs)?;
+        let total_outstanding = balances
+            .last()
+            .map_or(MilliSats::ZERO, |(_, closing)| *closing);
+        let seals = epochs
+            .iter()
+            .map(|epoch| self.storage.get_finalized(epoch.epoch_id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Epochs are independent, so aggregate and hash them in parallel on
+        // the blocking pool; Merkle roots dominate report cost
+        let (confidential, aggregates_only) = (context.confidential, context.aggregates_only);
+        let mut tasks = JoinSet::new();
+        for (index, (epoch, seal)) in epochs.into_iter().zip(seals).enumerate() {
+            tasks.spawn_blocking(move || {
+                let digest = EpochDigest::of(&epoch, seal.as_ref(), confidential, aggregates_only);
+                (index, epoch, seal, digest)
+            });
+        }
+        let mut aggregates = Vec::with_capacity(balances.len());
+        while let Some(result) = tasks.join_next().await {
+            aggregates.push(result.map_err(|e| PolError::ReportGenerationFailed(e.to_string()))?);
+        }
+        aggregates.sort_unstable_by_key(|(index, ..)| *index);
+
+        for ((_, epoch_state, seal, digest), balance) in aggregates.into_iter().zip(balances) {
+            epoch_reports.push(self.epoch_report(&epoch_state, seal, digest, balance, &context)?);
+        }
+
+        self.finish_report(epoch_reports, total_outstanding).await
+    }
+
+    /// Streams the report's epochs oldest first, loading one epoch's proofs
+    /// at a time. Balances are chained from each epoch as it is loaded.
+    pub async fn report_stream(&self) -> Result<EpochReportStream<'_>, PolError> {
+        let summaries = self.storage.list_epoch_summaries()?;
+        let opening = match summaries.first() {
+            Some(first) => self
+                .storage
+                .get_opening_balance(first.epoch_id)?
+                .unwrap_or(MilliSats::ZERO),
+            None => MilliSats::ZERO,
+        };
+
+        Ok(EpochReportStream {
+            service: self,
+            context: self
+                .report_context(summaries.first().map(|s| s.epoch_id))
+                .await?,
+            epoch_ids: summaries
+                .iter()
+                .map(|s| s.epoch_id)
+                .collect::<Vec<_>>()
+                .into_iter(),
+            opening,
+        })
+    }
+
+    async fn report_context(&self, first_epoch: Option<u64>) -> Result<ReportContext, PolError> {
+        let carried_into = match first_epoch {
+            Some(epoch_id) => self
+                .storage
+                .get_opening_balance(epoch_id)?
+                .map(|_| epoch_id),
+            None => None,
+        };
+        Ok(ReportContext {
+            current_epoch: *self.current_epoch.read().await,
+            keysets: self.storage.list_keysets()?,
+            confidential: *self.confidential_reports.read().await,
+            aggregates_only: *self.aggregate_reports.read().await,
+            carried_into,
+        })
+    }
+
+    /// Opening of the commitment to liabilities carried into `epoch_id`.
+    fn carried_opening(
+        &self,
+        epoch_id: u64,
+        amount: MilliSats,
+    ) -> Result<(MilliSats, SecretKey), PolError> {
+        let key = self.storage.confidential_key()?;
+        Ok((amount, pedersen::carried_blinding(&key, epoch_id)))
+    }
+
+    fn carried_commitment(
+        &self,
+        epoch_id: u64,
+        amount: MilliSats,
+    ) -> Result<CarriedCommitment, PolError> {
+        let (amount, blinding) = self.carried_opening(epoch_id, amount)?;
+        Ok(CarriedCommitment {
+            commitment: pedersen::commit(amount, &blinding)?,
+            range: pedersen::prove_range(amount, &blinding, pedersen::RANGE_BITS)?,
+        })
+    }
+
+    fn epoch_report(
+        &self,
+        epoch_state: &EpochState,
+        seal: Option<FinalizedEpoch>,
+        digest: EpochDigest,
+        (opening_balance, closing_balance): (MilliSats, MilliSats),
+        context: &ReportContext,
+    ) -> Result<EpochReport, PolError> {
+        let summary = epoch_state.summary()?;
+        let end_time = if epoch_state.epoch_id < context.current_epoch {
+            Some(epoch_state.start_time + self.epoch_duration)
+        } else {
+            None
+        };
+        let mut confidential = digest.confidential.transpose()?;
+        if let Some(confidential) = confidential.as_mut() {
+            if context.carried_into == Some(epoch_state.epoch_id) {
+                confidential.carried =
+                    Some(self.carried_commitment(epoch_state.epoch_id, opening_balance)?);
+            }
+        }
+        let aggregates = match digest.sum_roots {
+            Some(roots) if confidential.is_none() => {
+                let (mint_root, burn_root) = roots?;
+                Some(EpochAggregates {
+                    mint_root: Some(mint_root),
+                    burn_root: Some(burn_root),
+                    ..summary.aggregates()
+                })
+            }
+            _ => None,
+        };
+        let list_proofs = confidential.is_none() && aggregates.is_none();
+        // Confidential balances would reveal the committed totals
+        let balance = |amount: MilliSats| {
+            if confidential.is_some() {
+                Amount::ZERO
+            } else {
+                amount.to_amount()
+            }
+        };
+
+        Ok(EpochReport {
+            keysets: context
+                .keysets
+                .iter()
+                .filter(|k| k.active_during(epoch_state.start_time, end_time))
+                .map(|k| k.id)
+                .collect(),
+            commitment: digest.commitment?,
+            finalized_at: seal.map(|seal| seal.finalized_at),
+            attestations: self.storage.get_attestations(epoch_state.epoch_id)?,
+            epoch_id: epoch_state.epoch_id,
+            start_time: epoch_state.start_time,
+            end_time,
+            mint_proofs: if list_proofs {
+                epoch_state.mint_proofs.iter().cloned().collect()
+            } else {
+                Vec::new()
+            },
+            burn_proofs: if list_proofs {
+                epoch_state.burn_proofs.iter().cloned().collect()
+            } else {
+                Vec::new()
+            },
+            outstanding_balance: balance(summary.outstanding()),
+            opening_balance: balance(opening_balance),
+            closing_balance: balance(closing_balance),
+            confidential,
+            burn_index: Some(digest.burn_index?),
+            aggregates,
+        })
+    }
+
+    /// Wraps epoch reports into the full report.
+    async fn finish_report(
+        &self,
+        epoch_reports: Vec<EpochReport>,
+        total_outstanding: MilliSats,
+    ) -> Result<PolReport, PolError> {
+        let pruned_balance = epoch_reports
+            .first()
+            .map_or(Amount::ZERO, |e| e.opening_balance);
+        let cumulative_balances = epoch_reports
+            .iter()
+            .map(|e| CumulativeBalance {
+                epoch_id: e.epoch_id,
+                balance: e.closing_balance,
+            })
+            .collect();
+
+        let confidential = epoch_reports.iter().any(|e| e.confidential.is_some());
+        let total_outstanding_balance = if confidential {
+            Amount::ZERO
+        } else {
+            total_outstanding.to_amount()
+        };
+        let rate_source = self.rate_source.read().await.clone();
+        let fiat_annotation = match rate_source {
+            Some(source) if !confidential => {
+                rates::annotate(source.as_ref(), total_outstanding_balance).await
+            }
+            _ => None,
+        };
+
+        let history = self.storage.list_history()?;
+        let history_proofs = Self::history_proofs(&history, &epoch_reports);
+        let report = PolReport {
+            epoch_reports,
+            total_outstanding_balance,
+            cumulative_balances,
+            pruned_balance,
+            fiat_annotation,
+            timestamp: Utc::now(),
+            external_observations: self.storage.list_observations()?,
+            history: Some(HistoryHead::of(&history)),
+            history_proofs,
+        };
+
+        Ok(report)
+    }
+
+    /// Proof of the latest log entry of each reported epoch. Confidential
+    /// epochs are left out, since an entry reveals the closing balance.
+    fn history_proofs(
+        history: &[HistoryEntry],
+        epoch_reports: &[EpochReport],
+    ) -> Vec<HistoryProof> {
+        let leaves: Vec<_> = history.iter().map(HistoryEntry::leaf).collect();
+        epoch_reports
+            .iter()
+            .filter(|epoch| epoch.confidential.is_none())
+            .filter_map(|epoch| {
+                let index = history.iter().rposition(|e| e.epoch_id == epoch.epoch_id)?;
+                Some(HistoryProof {
+                    entry: history[index].clone(),
+                    index: index as u64,
+                    path: merkle::inclusion_path(&leaves, index),
+                })
+            })
+            .collect()
+    }
+
+    /// Announces a report that is about to be published to subscribers and
+    /// hooks. Reports generated only for inspection are never announced.
+    async fn announce(&self, report: &PolReport) {
+        self.emit(PolEvent::ReportGenerated {
+            epoch_count: report.epoch_reports.len(),
+            total_outstanding_balance: report.total_outstanding_balance,
+            timestamp: report.timestamp,
+        });
+
+        let _ = self.reports.send(GeneratedReport::Unsigned(report.clone()));
+
+        let hooks = self.hooks.read().await.report_generated.clone();
+        run_hooks(&hooks, report.clone()).await;
+    }
+
+    async fn signer(&self) -> Result<Arc<dyn Signer>, PolError> {
+        self.signer
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| PolError::SigningFailed("No signer configured".to_string()))
+    }
+
+    async fn sign_receipt(
+        signer: &dyn Signer,
+        kind: LeafKind,
+        epoch_id: u64,
+        y: PublicKey,
+        amount: MilliSats,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Receipt, PolError> {
+        let digest = Receipt::digest(kind, epoch_id, &y, amount, timestamp);
+        Ok(Receipt {
+            kind,
+            epoch_id,
+            y,
+            amount,
+            timestamp,
+            signature: ReportSignature {
+                public_key: signer.public_key(),
+                signature: signer.sign(&digest).await?,
+            },
+        })
+    }
+
+    pub async fn generate_signed_report(&self) -> Result<SignedReport, PolError> {
+        let signer = self.signer().await?;
+        let signed = self.sign_current_report(signer.as_ref()).await?;
+
+        let epoch_ids: Vec<u64> = signed
+            .report
+            .epoch_reports
+            .iter()
+            .map(|e| e.epoch_id)
+            .collect();
+        self.write_storage(|storage| storage.record_publication(&epoch_ids, &signed.commitment))
+            .await?;
+        // Only reports that were recorded are announced
+        self.announce(&signed.report).await;
+
+        let _ = self.reports.send(GeneratedReport::Signed(signed.clone()));
+
+        let sinks = self.sinks.read().await.clone();
+        sink::fan_out(&self.storage, &sinks, &signed).await?;
+
+        Ok(signed)
+    }
+
+    /// Signs a report over the current state without announcing, recording
+    /// or publishing it.
+    async fn sign_current_report(&self, signer: &dyn Signer) -> Result<SignedReport, PolError> {
+        let policy = self
+            .signature_policy
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(|| SignaturePolicy::single(signer.public_key()));
+
+        // Never sign over a database whose persisted artifacts have drifted
+        let audit = self.self_audit().await?;
+        if !audit.is_clean() {
+            for mismatch in &audit.mismatches {
+                error!(
+                    epoch_id = mismatch.epoch_id,
+                    artifact = %mismatch.artifact,
+                    detail = %mismatch.detail,
+                    "Self-audit mismatch"
+                );
+            }
+            return Err(PolError::SelfAuditFailed(audit.mismatches.len()));
+        }
+
+        let report = self.generate_report().await?;
+        signer::sign_report(report, policy, signer).await
+    }
+
+    /// Signs and publishes a new report, then signs its spec layout as
+    /// well, since the native signature does not cover that document.
+    pub async fn generate_signed_spec_report(&self) -> Result<SpecReport, PolError> {
+        let signed = self.generate_signed_report().await?;
+        let signer = self.signer().await?;
+        SpecReport::from_report(&signed.report)?
+            .sign(signer.as_ref())
+            .await
+    }
+
+    /// Signs a new report without publishing it, then packs it into a
+    /// bundle with the seals over its epochs, the keysets proofs were signed
+    /// with and the given reserve attestations. The same signer signs the
+    /// bundle manifest.
+    pub async fn report_bundle(
+        &self,
+        reserves: Vec<(String, Vec<u8>)>,
+    ) -> Result<ReportBundle, PolError> {
+        let signer = self.signer().await?;
+        let signed = self.sign_current_report(signer.as_ref()).await?;
+        let mut seals = Vec::new();
+        for epoch in &signed.report.epoch_reports {
+            if let Some(seal) = self.storage.get_finalized(epoch.epoch_id)? {
+                seals.push(seal);
+            }
+        }
+        let parameters = ProofParameters {
+            spec_version: SPEC_VERSION.to_string(),
+            history: signed.report.history,
+            keysets: self.storage.list_keysets()?,
+        };
+
+        ReportBundle::create(&signed, &seals, &parameters, reserves, signer.as_ref()).await
+    }
+
+    /// Recomputes every derived artifact from the stored proofs and checks
+    /// it against what was persisted: seal commitments and signatures, and
+    /// attestation commitments and signatures. Attestations on the open
+    /// epoch are only checked for a valid signature, since its commitment
+    /// still moves with every record.
+    pub async fn self_audit(&self) -> Result<SelfAuditReport, PolError> {
+        let current_epoch = *self.current_epoch.read().await;
+        let mut report = SelfAuditReport::default();
+        let mismatch = |epoch_id, artifact: &str, detail: String| AuditMismatch {
+            epoch_id,
+            artifact: artifact.to_string(),
+            detail,
+        };
+
+        for epoch in self.storage.list_epochs()? {
+            report.checked_epochs += 1;
+            let commitment = epoch.commitment()?;
+
+            if let Some(seal) = self.storage.get_finalized(epoch.epoch_id)? {
+                report.checked_seals += 1;
+                if seal.commitment != commitment {
+                    // Sealed before sum trees, and still over the same proofs
+                    if seal.commitment == epoch.legacy_commitment()? {
+                        report.legacy_seals.push(epoch.epoch_id);
+                    } else {
+                        report.mismatches.push(mismatch(
+                            epoch.epoch_id,
+                            "seal",
+                            format!("sealed {}, recomputed {}", seal.commitment, commitment),
+                        ));
+                    }
+                }
+                if let Some(signature) = &seal.signature {
+                    if let Err(e) = signer::verify_signature(
+                        &seal.commitment,
+                        &signature.signature,
+                        &signature.public_key,
+                    ) {
+                        report.mismatches.push(mismatch(
+                            epoch.epoch_id,
+                            "seal_signature",
+                            e.to_string(),
+                        ));
+                    }
+                }
+            }
+
+            for attestation in self.storage.get_attestations(epoch.epoch_id)? {
+                report.checked_attestations += 1;
+                if let Err(e) = attestation.verify() {
+                    report.mismatches.push(mismatch(
+                        epoch.epoch_id,
+                        "attestation_signature",
+                        e.to_string(),
+                    ));
+                }
+                if epoch.epoch_id < current_epoch && attestation.commitment != commitment {
+                    report.mismatches.push(mismatch(
+                        epoch.epoch_id,
+                        "attestation",
+                        format!(
+                            "{} attested {}, recomputed {}",
+                            attestation.public_key, attestation.commitment, commitment
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Folds consecutive epochs into the first of them. Refuses epochs that
+    /// were covered by a signed report unless `force` is set, since merging
+    /// changes their commitments.
+    pub async fn merge_epochs(&self, epoch_ids: &[u64], force: bool) -> Result<u64, PolError> {
+        let mut current_epoch = self.current_epoch.write().await;
+
+        let mut epoch_ids = epoch_ids.to_vec();
+        epoch_ids.sort_unstable();
+        epoch_ids.dedup();
+        if epoch_ids.len() < 2 {
+            return Err(PolError::InvalidEpoch(
+                "At least two epochs are needed for a merge".to_string(),
+            ));
+        }
+
+        let stored: Vec<u64> = self
+            .storage
+            .list_epochs()?
+            .iter()
+            .map(|e| e.epoch_id)
+            .collect();
+        let first = stored
+            .iter()
+            .position(|id| *id == epoch_ids[0])
+            .ok_or(PolError::EpochNotFound(epoch_ids[0]))?;
+        if stored.get(first..first + epoch_ids.len()) != Some(epoch_ids.as_slice()) {
+            return Err(PolError::InvalidEpoch(format!(
+                "Epochs {:?} are not adjacent",
+                epoch_ids
+            )));
+        }
+
+        if !force {
+            for epoch_id in &epoch_ids {
+                if !self.storage.get_publications(*epoch_id)?.is_empty() {
+                    return Err(PolError::InvalidEpoch(format!(
+                        "Epoch {} is covered by a published signed report",
+                        epoch_id
+                    )));
+                }
+            }
+        }
+
+        let mut merged = self
+            .storage
+            .get_epoch(epoch_ids[0])?
+            .ok_or(PolError::EpochNotFound(epoch_ids[0]))?;
+        for epoch_id in &epoch_ids[1..] {
+            let epoch = self
+                .storage
+                .get_epoch(*epoch_id)?
+                .ok_or(PolError::EpochNotFound(*epoch_id))?;
+            merged.mint_proofs.extend(epoch.mint_proofs);
+            merged.burn_proofs.extend(epoch.burn_proofs);
+        }
+
+        let audit = AuditEntry {
+            timestamp: Utc::now(),
+            operation: AuditOperation::Merge {
+                epoch_ids: epoch_ids.clone(),
+                merged_into: merged.epoch_id,
+                forced: force,
+            },
+        };
+        let moved_current = epoch_ids
+            .contains(&*current_epoch)
+            .then_some(merged.epoch_id);
+        self.write_storage(|storage| {
+            storage.merge_epochs(&merged, &epoch_ids[1..], moved_current, &audit)
+        })
+        .await?;
+        if let Some(epoch_id) = moved_current {
+            *current_epoch = epoch_id;
+        }
+        self.relog_closed_epochs(*current_epoch).await?;
+
+        Ok(merged.epoch_id)
+    }
+
+    /// Rebuilds epoch boundaries under the configured epoch duration, e.g.
+    /// after switching from 30-day to 7-day epochs. Epochs are laid out from
+    /// the earliest retained start time (aligned down in time-derived mode)
+    /// and every stored proof is reassigned by its timestamp. The operation
+    /// is recorded in the audit log.
+    pub async fn resegment_epochs(&self, force: bool) -> Result<Vec<u64>, PolError> {
+        let mut current_epoch = self.current_epoch.write().await;
+
+        let duration_secs = self.epoch_duration.num_seconds();
+        if duration_secs <= 0 {
+            return Err(PolError::InvalidEpoch(
+                "Epoch duration must be positive".to_string(),
+            ));
+        }
+
+        let epochs = self.storage.list_epochs()?;
+        let first = epochs
+            .iter()
+            .min_by_key(|e| e.epoch_id)
+            .ok_or_else(|| PolError::InvalidEpoch("No epochs initialized".to_string()))?;
+        let earliest = epochs
+            .iter()
+            .map(|e| e.start_time)
+            .min()
+            .unwrap_or(first.start_time);
+        let (base_id, anchor) = match *self.epoch_id_mode.read().await {
+            EpochIdMode::Sequential => (first.epoch_id, earliest),
+            EpochIdMode::TimeDerived => self.time_derived_epoch(earliest)?,
+        };
+        let carried = self.storage.get_opening_balance(first.epoch_id)?;
+
+        if !force {
+            for epoch in &epochs {
+                if !self.storage.get_publications(epoch.epoch_id)?.is_empty() {
+                    return Err(PolError::InvalidEpoch(format!(
+                        "Epoch {} is covered by a published signed report",
+                        epoch.epoch_id
+                    )));
+                }
+            }
+        }
+
+        let count = ((Utc::now() - anchor).num_seconds() / duration_secs).max(0) + 1;
+        let mut new_epochs: Vec<EpochState> = (0..count)
+            .map(|i| EpochState {
+                epoch_id: base_id + i as u64,
+                start_time: anchor + Duration::seconds(i * duration_secs),
+                mint_proofs: Default::default(),
+                burn_proofs: Default::default(),
+            })
+            .collect();
+        let slot = |timestamp: DateTime<Utc>| -> usize {
+            ((timestamp - anchor).num_seconds() / duration_secs).clamp(0, count - 1) as usize
+        };
+
+        let previous_epochs = epochs.iter().map(|e| (e.epoch_id, e.start_time)).collect();
+        for epoch in epochs {
+            for proof in epoch.mint_proofs {
+                new_epochs[slot(proof.timestamp)].mint_proofs.insert(proof);
+            }
+            for proof in epoch.burn_proofs {
+                new_epochs[slot(proof.timestamp)].burn_proofs.insert(proof);
+            }
+        }
+
+        let new_epoch_ids: Vec<u64> = new_epochs.iter().map(|e| e.epoch_id).collect();
+        let new_current = base_id + (count - 1) as u64;
+        let audit = AuditEntry {
+            timestamp: Utc::now(),
+            operation: AuditOperation::Resegment {
+                previous_epochs,
+                new_epoch_ids: new_epoch_ids.clone(),
+                epoch_duration_secs: duration_secs,
+                forced: force,
+            },
+        };
+
+        let opening = carried.map(|balance| (base_id, balance));
+        self.write_storage(|storage| {
+            storage.replace_epochs(&new_epochs, new_current, opening, &audit)
+        })
+        .await?;
+        *current_epoch = new_current;
+        self.relog_closed_epochs(new_current).await?;
+
+        Ok(new_epoch_ids)
+    }
+
+    /// Database size, per-epoch footprint and growth, projected forward to
+    /// when retention starts pruning and, given `disk_limit_bytes`, when
+    /// the database file would reach it.
+    pub fn storage_stats(&self, disk_limit_bytes: Option<u64>) -> Result<StorageStats, PolError> {
+        let now = Utc::now();
+        let db_bytes = self.storage.file_size()?;
+        let sizes: HashMap<u64, u64> = self.storage.epoch_sizes()?.into_iter().collect();
+        let epochs: Vec<EpochFootprint> = self
+            .storage
+            .list_epochs()?
+            .into_iter()
+            .map(|epoch| EpochFootprint {
+                epoch_id: epoch.epoch_id,
+                start_time: epoch.start_time,
+                mint_proofs: epoch.mint_proofs.len(),
+                burn_proofs: epoch.burn_proofs.len(),
+                bytes: sizes.get(&epoch.epoch_id).copied().unwrap_or(0),
+            })
+            .collect();
+
+        let epoch_bytes: u64 = epochs.iter().map(|e| e.bytes).sum();
+        // At least an hour, so a fresh database does not project wild rates
+        let elapsed_secs = epochs
+            .first()
+            .map_or(0, |first| (now - first.start_time).num_seconds())
+            .max(3600);
+        let bytes_per_day = epoch_bytes as f64 * 86400.0 / elapsed_secs as f64;
+
+        let retention_full_at = match epochs.last() {
+            // Left unset when too far out to represent
+            Some(last) if epochs.len() <= self.max_epoch_history => {
+                i32::try_from(self.max_epoch_history - epochs.len() + 1)
+                    .ok()
+                    .and_then(|rotations| self.epoch_duration.checked_mul(rotations))
+                    .and_then(|wait| last.start_time.checked_add_signed(wait))
+            }
+            _ => None,
+        };
+        let epoch_days = self.epoch_duration.num_seconds() as f64 / 86400.0;
+        let bytes_at_retention =
+            (bytes_per_day * epoch_days * self.max_epoch_history as f64) as u64;
+
+        // The file stops growing once retention prunes as fast as it fills
+        let capped_bytes = db_bytes
+            .saturating_sub(epoch_bytes)
+            .saturating_add(bytes_at_retention);
+        let disk_limit_at = disk_limit_bytes.and_then(|limit| {
+            if db_bytes >= limit {
+                Some(now)
+            } else if capped_bytes < limit || bytes_per_day <= 0.0 {
+                None
+            } else {
+                let days = (limit - db_bytes) as f64 / bytes_per_day;
+                Some(now + Duration::seconds((days * 86400.0) as i64))
+            }
+        });
+
+        Ok(StorageStats {
+            db_bytes,
+            mint_proofs: epochs.iter().map(|e| e.mint_proofs).sum(),
+            burn_proofs: epochs.iter().map(|e| e.burn_proofs).sum(),
+            epochs,
+            bytes_per_day,
+            max_epoch_history: self.max_epoch_history,
+            retention_full_at,
+            bytes_at_retention,
+            disk_limit_bytes,
+            disk_limit_at,
+        })
+    }
+
+    /// Every retained epoch, oldest first, read from epoch headers.
+    pub async fn epoch_listing(&self) -> Result<Vec<EpochListing>, PolError> {
+        let current_epoch = self.current_epoch().await;
+        self.storage
+            .list_epoch_summaries()?
+            .into_iter()
+            .map(|summary| self.listing_entry(&summary, current_epoch))
+            .collect()
+    }
+
+    fn listing_entry(
+        &self,
+        summary: &EpochSummary,
+        current_epoch: u64,
+    ) -> Result<EpochListing, PolError> {
+        let status = self.epoch_status(summary.epoch_id, current_epoch)?;
+        Ok(EpochListing {
+            epoch_id: summary.epoch_id,
+            start_time: summary.start_time,
+            end_time: (status != EpochStatus::Open)
+                .then(|| summary.start_time + self.epoch_duration),
+            mint_count: summary.mint_count,
+            burn_count: summary.burn_count,
+            minted: summary.minted,
+            burned: summary.burned,
+            outstanding: summary.outstanding(),
+            status,
+        })
+    }
+
+    /// Removes the oldest closed epochs `rule` selects and returns them.
+    /// Their liabilities carry over into the oldest retained epoch, as when
+    /// rotation prunes. Sealed epochs covered by a published signed report
+    /// are only pruned with `force`; with `dry_run` nothing is removed.
+    pub async fn prune_epochs(
+        &self,
+        rule: PruneRule,
+        force: bool,
+        dry_run: bool,
+    ) -> Result<Vec<EpochListing>, PolError> {
+        let current_epoch = self.current_epoch.write().await;
+        let summaries = self.storage.list_epoch_summaries()?;
+        let closed = summaries
+            .iter()
+            .take_while(|s| s.epoch_id < *current_epoch)
+            .count();
+        let cutoff = match rule {
+            PruneRule::OlderThan(age) => {
+                let before = Utc::now() - age;
+                summaries[..closed]
+                    .iter()
+                    .take_while(|s| s.start_time + self.epoch_duration <= before)
+                    .count()
+            }
+            PruneRule::KeepLatest(keep) => summaries.len().saturating_sub(keep).min(closed),
+        };
+
+        let mut pruned = Vec::with_capacity(cutoff);
+        for summary in &summaries[..cutoff] {
+            if !force && self.is_published_seal(summary.epoch_id)? {
+                return Err(PolError::EpochFinalized(summary.epoch_id));
+            }
+            pruned.push(self.listing_entry(summary, *current_epoch)?);
+        }
+        let Some(oldest_retained) = summaries.get(cutoff) else {
+            return Ok(pruned);
+        };
+        if pruned.is_empty() || dry_run {
+            return Ok(pruned);
+        }
+
+        let carried = self.running_balance(&summaries[..cutoff])?;
+
+        let epoch_ids: Vec<u64> = pruned.iter().map(|e| e.epoch_id).collect();
+        let audit = AuditEntry {
+            timestamp: Utc::now(),
+            operation: AuditOperation::Prune {
+                epoch_ids: epoch_ids.clone(),
+                carried_balance: carried,
+                forced: force,
+            },
+        };
+        let retained_opening = (oldest_retained.epoch_id, carried);
+        self.write_storage(|storage| storage.prune_epochs(&epoch_ids, retained_opening, &audit))
+            .await?;
+
+        Ok(pruned)
+    }
+
+    /// One epoch's commitments, seal, attestations and totals, with a page
+    /// of each kind of proof when `proofs` gives an offset and limit.
+    pub async fn epoch_details(
+        &self,
+        epoch_id: u64,
+        proofs: Option<(usize, usize)>,
+    ) -> Result<EpochDetails, PolError> {
+        let current_epoch = self.current_epoch().await;
+        let epoch = self
+            .storage
+            .get_epoch(epoch_id)?
+            .ok_or(PolError::EpochNotFound(epoch_id))?;
+        let status = self.epoch_status(epoch_id, current_epoch)?;
+        let seal = self.storage.get_finalized(epoch_id)?;
+        let summary = epoch.summary()?;
+
+        let (mint_proofs, burn_proofs) = match proofs {
+            Some((offset, limit)) => {
+                let mints = epoch
+                    .mint_proofs
+                    .iter()
+                    .map(MintProof::listing)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mints = sort_by_time_and_y(mints, |p| Ok((p.timestamp, p.y)))?;
+                let burns = epoch
+                    .burn_proofs
+                    .iter()
+                    .map(BurnProof::listing)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let burns = sort_by_time_and_y(burns, |p| Ok((p.timestamp, p.y)))?;
+                (
+                    Some(Page::from_sorted(mints, offset, limit)),
+                    Some(Page::from_sorted(burns, offset, limit)),
+                )
+            }
+            None => (None, None),
+        };
+
+        Ok(EpochDetails {
+            epoch_id,
+            start_time: epoch.start_time,
+            end_time: (status != EpochStatus::Open).then(|| epoch.start_time + self.epoch_duration),
+            status,
+            aggregates: summary.aggregates(),
+            outstanding: summary.outstanding(),
+            commitment: match &seal {
+                Some(seal) => seal.commitment,
+                None => epoch.commitment()?,
+            },
+            burn_index_root: epoch.burn_index_root()?,
+            seal,
+            attestations: self.storage.get_attestations(epoch_id)?,
+            mint_proofs,
+            burn_proofs,
+        })
+    }
+
+    /// Sealed epochs a published report covers, which pruning leaves alone
+    /// unless forced.
+    fn is_published_seal(&self, epoch_id: u64) -> Result<bool, PolError> {
+        Ok(self.storage.get_finalized(epoch_id)?.is_some()
+            && !self.storage.get_publications(epoch_id)?.is_empty())
+    }
+
+    fn epoch_status(&self, epoch_id: u64, current_epoch: u64) -> Result<EpochStatus, PolError> {
+        Ok(if epoch_id >= current_epoch {
+            EpochStatus::Open
+        } else if self.storage.get_finalized(epoch_id)?.is_some() {
+            EpochStatus::Sealed
+        } else {
+            EpochStatus::Closed
+        })
+    }
+
+    /// Issuance, redemption and outstanding balance in `step` buckets over
+    /// `[from, to)`, from proof timestamps. Only epochs overlapping the range
+    /// are loaded; earlier ones count through their headers. The step is
+    /// widened if the range would need more than `MAX_SERIES_POINTS`.
+    pub fn liability_series(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        step: Duration,
+    ) -> Result<Vec<SeriesPoint>, PolError> {
+        if to <= from || step <= Duration::zero() {
+            return Err(PolError::InvalidRange(format!(
+                "{} to {} by {}s",
+                from,
+                to,
+                step.num_seconds()
+            )));
+        }
+        let span_ms = (to - from).num_milliseconds();
+        // A step past the whole range makes a single bucket
+        let step_ms = step
+            .num_milliseconds()
+            .max(span_ms / MAX_SERIES_POINTS as i64)
+            .min(span_ms)
+            .max(1);
+        let bucket_count = (span_ms / step_ms + i64::from(span_ms % step_ms != 0)) as usize;
+        let mut points: Vec<SeriesPoint> = (0..bucket_count)
+            .map(|i| SeriesPoint {
+                time: from + Duration::milliseconds(step_ms * i as i64),
+                issued: MilliSats::ZERO,
+                redeemed: MilliSats::ZERO,
+                outstanding: MilliSats::ZERO,
+            })
+            .collect();
+        let bucket = |time: DateTime<Utc>| ((time - from).num_milliseconds() / step_ms) as usize;
+
+        // Outstanding follows reports: each epoch closes at its opening plus
+        // what it minted, less what it burned, floored at zero. Within an
+        // epoch the same holds for the proofs recorded so far.
+        let summaries = self.storage.list_epoch_summaries()?;
+        let mut balance = match summaries.first() {
+            Some(first) => self
+                .storage
+                .get_opening_balance(first.epoch_id)?
+                .unwrap_or(MilliSats::ZERO),
+            None => MilliSats::ZERO,
+        };
+        for point in &mut points {
+            point.outstanding = balance;
+        }
+        for (i, summary) in summaries.iter().enumerate() {
+            let ends_before = summaries
+                .get(i + 1)
+                .is_some_and(|next| next.start_time <= from);
+            if ends_before {
+                balance = balance
+                    .try_add(summary.minted)?
+                    .saturating_sub(summary.burned);
+                continue;
+            }
+            if summary.start_time >= to {
+                break;
+            }
+
+            let epoch = self
+                .storage
+                .get_epoch(summary.epoch_id)?
+                .ok_or(PolError::EpochNotFound(summary.epoch_id))?;
+            let (mut minted, mut burned) = (MilliSats::ZERO, MilliSats::ZERO);
+            let mut net = vec![(MilliSats::ZERO, MilliSats::ZERO); bucket_count];
+            for proof in &epoch.mint_proofs {
+                match proof.timestamp {
+                    t if t < from => minted = minted.try_add(proof.amount)?,
+                    t if t < to => {
+                        let b = bucket(t);
+                        points[b].issued = points[b].issued.try_add(proof.amount)?;
+                        net[b].0 = net[b].0.try_add(proof.amount)?;
+                    }
+                    _ => {}
+                }
+            }
+            for proof in &epoch.burn_proofs {
+                match proof.timestamp {
+                    t if t < from => burned = burned.try_add(proof.amount)?,
+                    t if t < to => {
+                        let b = bucket(t);
+                        points[b].redeemed = points[b].redeemed.try_add(proof.amount)?;
+                        net[b].1 = net[b].1.try_add(proof.amount)?;
+                    }
+                    _ => {}
+                }
+            }
+
+            // Buckets ending after the epoch starts see it partly recorded,
+            // until a later epoch takes over
+            let first_bucket = match summary.start_time {
+                start if start <= from => 0,
+                start => bucket(start),
+            };
+            for (point, (bucket_minted, bucket_burned)) in
+                points.iter_mut().zip(net).skip(first_bucket)
+            {
+                minted = minted.try_add(bucket_minted)?;
+                burned = burned.try_add(bucket_burned)?;
+                point.outstanding = balance.try_add(minted)?.saturating_sub(burned);
+            }
+            balance = balance
+                .try_add(summary.minted)?
+                .saturating_sub(summary.burned);
+        }
+
+        Ok(points)
+    }
+
+    /// Balance left after `summaries`, a leading run of retained epochs,
+    /// starting from whatever pruning carried into the first of them.
+    fn running_balance(&self, summaries: &[EpochSummary]) -> Result<MilliSats, PolError> {
+        let Some(first) = summaries.first() else {
+            return Ok(MilliSats::ZERO);
+        };
+        let mut balance = self
+            .storage
+            .get_opening_balance(first.epoch_id)?
+            .unwrap_or(MilliSats::ZERO);
+        for summary in summaries {
+            balance = balance
+                .try_add(summary.minted)?
+                .saturating_sub(summary.burned);
+        }
+        Ok(balance)
+    }
+
+    /// Summary of the open epoch, liabilities, storage and publication,
+    /// read from epoch headers without loading any proofs.
+    pub async fn status(&self) -> Result<ServiceStatus, PolError> {
+        let current_epoch = self.current_epoch().await;
+        let epoch_started_at = self
+            .storage
+            .get_epoch_summary(current_epoch)?
+            .ok_or(PolError::EpochNotFound(current_epoch))?
+            .start_time;
+        let rotation_due_at = epoch_started_at + self.epoch_duration;
+
+        let summaries = self.storage.list_epoch_summaries()?;
+        let outstanding = self.running_balance(&summaries)?;
+        let publications = self
+            .storage
+            .list_sink_states()?
+            .into_iter()
+            .map(|(sink, state)| PublicationStatus {
+                sink,
+                last_attempt: state.last_attempt,
+                last_success: state.last_success,
+                last_error: state.last_error,
+                pending: state.pending_report.is_some(),
+            })
+            .collect();
+
+        Ok(ServiceStatus {
+            current_epoch,
+            epoch_started_at,
+            rotation_due_at,
+            seconds_until_rotation: (rotation_due_at - Utc::now()).num_seconds(),
+            outstanding,
+            epochs: summaries.len(),
+            mint_proofs: summaries.iter().map(|s| s.mint_count).sum(),
+            burn_proofs: summaries.iter().map(|s| s.burn_count).sum(),
+            db_bytes: self.storage.file_size()?,
+            publications,
+        })
+    }
+
+    pub fn audit_log(&self) -> Result<Vec<AuditEntry>, PolError> {
+        self.storage.list_audit_entries()
+    }
+
+    /// Writes every epoch, commitment and proof as portable SQL statements.
+    pub fn export_sql<W: std::io::Write>(&self, out: &mut W) -> Result<(), PolError> {
+        sql::write_sql_dump(&self.storage, self.epoch_duration, out)
+    }
+
+    /// Seals a closed epoch: its commitment is fixed (and signed, when a
+    /// signer is set) and storage rejects any further change to it.
+    pub async fn finalize_epoch(&self, epoch_id: u64) -> Result<FinalizedEpoch, PolError> {
+        let current_epoch = self.current_epoch.read().await;
+        if epoch_id >= *current_epoch {
+            return Err(PolError::InvalidEpoch(format!(
+                "Epoch {} is still open",
+                epoch_id
+            )));
+        }
+
+        let epoch_state = self
+            .storage
+            .get_epoch(epoch_id)?
+            .ok_or(PolError::EpochNotFound(epoch_id))?;
+        let commitment = epoch_state.commitment()?;
+
+        let signer = self.signer.read().await.clone();
+        let signature = match signer {
+            Some(signer) => Some(ReportSignature {
+                public_key: signer.public_key(),
+                signature: signer.sign(&commitment).await?,
+            }),
+            None => None,
+        };
+
+        let seal = FinalizedEpoch {
+            epoch_id,
+            commitment,
+            signature,
+            finalized_at: Utc::now(),
+        };
+        self.write_storage(|storage| storage.finalize_epoch(&seal))
+            .await?;
+
+        Ok(seal)
+    }
+
+    /// Replaces seals made under the pre-sum-tree commitment with seals over
+    /// the current one. Each old seal must still match the stored proofs
+    /// under the old scheme, and its signature must verify, so a seal that
+    /// drifted is never laundered into a valid one. Returns the resealed
+    /// epoch ids.
+    pub async fn reseal_epochs(&self) -> Result<Vec<u64>, PolError> {
+        let _current_epoch = self.current_epoch.write().await;
+        let signer = self.signer.read().await.clone();
+
+        let mut seals = Vec::new();
+        let mut previous_seals = Vec::new();
+        for epoch in self.storage.list_epochs()? {
+            let Some(seal) = self.storage.get_finalized(epoch.epoch_id)? else {
+                continue;
+            };
+            let commitment = epoch.commitment()?;
+            if seal.commitment == commitment {
+                continue;
+            }
+            if seal.commitment != epoch.legacy_commitment()? {
+                return Err(PolError::InvalidEpoch(format!(
+                    "Seal of epoch {} matches neither commitment scheme",
+                    epoch.epoch_id
+                )));
+            }
+            if let Some(signature) = &seal.signature {
+                signer::verify_signature(
+                    &seal.commitment,
+                    &signature.signature,
+                    &signature.public_key,
+                )?;
+            }
+
+            let signature = match &signer {
+                Some(signer) => Some(ReportSignature {
+                    public_key: signer.public_key(),
+                    signature: signer.sign(&commitment).await?,
+                }),
+                None => None,
+            };
+            previous_seals.push((seal.epoch_id, seal.commitment));
+            seals.push(FinalizedEpoch {
+                epoch_id: seal.epoch_id,
+                commitment,
+                signature,
+                finalized_at: seal.finalized_at,
+            });
+        }
+
+        if seals.is_empty() {
+            return Ok(Vec::new());
+        }
+        let audit = AuditEntry {
+            timestamp: Utc::now(),
+            operation: AuditOperation::Reseal {
+                previous_seals: previous_seals.clone(),
+            },
+        };
+        self.storage.replace_seals(&seals, &audit)?;
+
+        Ok(previous_seals.into_iter().map(|(id, _)| id).collect())
+    }
+
+    pub fn finalized_epoch(&self, epoch_id: u64) -> Result<Option<FinalizedEpoch>, PolError> {
+        self.storage.get_finalized(epoch_id)
+    }
+
+    pub fn epoch_commitment(&self, epoch_id: u64) -> Result<sha256::Hash, PolError> {
+        self.storage
+            .get_epoch(epoch_id)?
+            .ok_or(PolError::EpochNotFound(epoch_id))?
+            .commitment()
+    }
+
+    /// Stores an external party's signature over the epoch's current
+    /// commitment; it is included in every subsequent report.
+    pub async fn submit_attestation(
+        &self,
+        epoch_id: u64,
+        public_key: XOnlyPublicKey,
+        signature: Signature,
+        statement: Option<String>,
+    ) -> Result<EpochAttestation, PolError> {
+        let attestation = EpochAttestation {
+            epoch_id,
+            commitment: self.epoch_commitment(epoch_id)?,
+            public_key,
+            signature,
+            statement,
+            submitted_at: Utc::now(),
+        };
+
+        attestation.verify()?;
+        self.storage.add_attestation(&attestation)?;
+
+        Ok(attestation)
+    }
+
+    /// The epoch that was active at `time`, i.e. the latest epoch that had
+    /// already started. `None` if `time` predates every retained epoch.
+    pub async fn epoch_for_time(&self, time: DateTime<Utc>) -> Result<Option<u64>, PolError> {
+        Ok(self
+            .storage
+            .list_epochs()?
+            .into_iter()
+            .filter(|epoch| epoch.start_time <= time)
+            .max_by_key(|epoch| epoch.start_time)
+            .map(|epoch| epoch.epoch_id))
+    }
+
+    /// Mint proofs recorded in `[from, to)`, oldest first.
+    pub async fn get_mint_proofs_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Page<EpochRecord<MintProof>>, PolError> {
+        let mut records: Vec<_> = self
+            .storage
+            .list_epochs()?
+            .into_iter()
+            .filter(|epoch| epoch.start_time < to)
+            .flat_map(|epoch| {
+                let epoch_id = epoch.epoch_id;
+                epoch
+                    .mint_proofs
+                    .into_iter()
+                    .filter(|p| p.timestamp >= from && p.timestamp < to)
+                    .map(move |record| EpochRecord { epoch_id, record })
+            })
+            .collect();
+
+        let records = sort_by_time_and_y(records, |r| Ok((r.record.timestamp, r.record.y()?)))?;
+        Ok(Page::from_sorted(records, offset, limit))
+    }
+
+    /// Burn proofs recorded in `[from, to)`, oldest first.
+    pub async fn get_burn_proofs_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Page<EpochRecord<BurnProof>>, PolError> {
+        let mut records: Vec<_> = self
+            .storage
+            .list_epochs()?
+            .into_iter()
+            .filter(|epoch| epoch.start_time < to)
+            .flat_map(|epoch| {
+                let epoch_id = epoch.epoch_id;
+                epoch
+                    .burn_proofs
+                    .into_iter()
+                    .filter(|p| p.timestamp >= from && p.timestamp < to)
+                    .map(move |record| EpochRecord { epoch_id, record })
+            })
+            .collect();
+
+        let records = sort_by_time_and_y(records, |r| Ok((r.record.timestamp, r.record.y()?)))?;
+        Ok(Page::from_sorted(records, offset, limit))
+    }
+
+    /// Finds where a proof was minted and burned. Accepts either the proof
+    /// secret or its hex-encoded Y = hash_to_curve(secret).
+    pub async fn lookup(&self, secret_or_y: &str) -> Result<ProofLookup, PolError> {
+        let y = match PublicKey::from_hex(secret_or_y) {
+            Ok(y) => y,
+            Err(_) => secret_to_y(secret_or_y.as_bytes())?,
+        };
+
+        let mut lookup = ProofLookup {
+            y,
+            minted: Vec::new(),
+            burned: Vec::new(),
+        };
+
+        for epoch in self.storage.list_epochs()? {
+            for proof in epoch.mint_proofs {
+                if proof.y()? == y {
+                    lookup.minted.push(EpochRecord {
+                        epoch_id: epoch.epoch_id,
+                        record: proof,
+                    });
+                }
+            }
+            for proof in epoch.burn_proofs {
+                if proof.y()? == y {
+                    lookup.burned.push(EpochRecord {
+                        epoch_id: epoch.epoch_id,
+                        record: proof,
+                    });
+                }
+            }
+        }
+
+        Ok(lookup)
+    }
+
+    /// Inclusion proofs for a Y = hash_to_curve(secret) across all retained
+    /// epochs. Wallets only ever reveal Y, never the secret.
+    pub async fn inclusion_proofs(&self, y: &PublicKey) -> Result<Vec<InclusionProof>, PolError> {
+        let mut proofs = Vec::new();
+        for epoch in self.storage.list_epochs()? {
+            proofs.extend(epoch.inclusion_proofs(y)?);
+        }
+
+        Ok(proofs)
+    }
+
+    /// Proof that the history log only grew since it held `old_size`
+    /// entries, to be checked between two published reports.
+    pub async fn consistency_proof(&self, old_size: u64) -> Result<ConsistencyProof, PolError> {
+        let entries = self.storage.list_history()?;
+        let old_size = usize::try_from(old_size)
+            .ok()
+            .filter(|size| *size <= entries.len())
+            .ok_or_else(|| {
+                PolError::ReportGenerationFailed(format!(
+                    "History has {} entries, fewer than {}",
+                    entries.len(),
+                    old_size
+                ))
+            })?;
+        let leaves: Vec<_> = entries.iter().map(HistoryEntry::leaf).collect();
+
+        Ok(ConsistencyProof {
+            old: HistoryHead::of(&entries[..old_size]),
+            new: HistoryHead::of(&entries),
+            path: merkle::consistency_path(&leaves, old_size),
+        })
+    }
+
+    /// Sparse Merkle proof that a Y was or was not burned in an epoch.
+    pub async fn burn_index_proof(
+        &self,
+        epoch_id: u64,
+        y: &PublicKey,
+    ) -> Result<BurnIndexProof, PolError> {
+        self.storage
+            .get_epoch(epoch_id)?
+            .ok_or(PolError::EpochNotFound(epoch_id))?
+            .burn_index_proof(y)
+    }
+
+    pub fn register_keyset(&self, keyset: &KeysetRecord) -> Result<(), PolError> {
+        self.storage.save_keyset(keyset)
+    }
+
+    /// Marks a keyset as no longer used for new proofs from `at` on.
+    pub fn retire_keyset(&self, keyset_id: &Id, at: DateTime<Utc>) -> Result<(), PolError> {
+        let mut keyset = self
+            .storage
+            .list_keysets()?
+            .into_iter()
+            .find(|k| k.id == *keyset_id)
+            .ok_or_else(|| PolError::KeysetNotFound(keyset_id.to_string()))?;
+        keyset.active_until = Some(at);
+        self.storage.save_keyset(&keyset)
+    }
+
+    pub fn keysets(&self) -> Result<Vec<KeysetRecord>, PolError> {
+        self.storage.list_keysets()
+    }
+
+    /// Registers keysets the mint lists, fills in keys for keysets known
+    /// only by id, and retires those it has deactivated or no longer lists.
+    /// Returns the ids of newly registered keysets.
+    pub async fn sync_keysets(&self, mint_url: &str) -> Result<Vec<Id>, PolError> {
+        let listed = keysets::fetch(mint_url).await?;
+        let known = self.storage.list_keysets()?;
+        let now = Utc::now();
+
+        // The mint does not say when a keyset came into use. One seen on
+        // the first sync may have signed anything recorded so far; later
+        // ones appeared since the previous sync, unless recorded proofs
+        // show they signed earlier.
+        let mut first_signed: HashMap<Id, DateTime<Utc>> = HashMap::new();
+        let mut genesis = now;
+        if listed.iter().any(|l| !known.iter().any(|k| k.id == l.id)) {
+            for epoch in self.storage.list_epochs()? {
+                genesis = genesis.min(epoch.start_time);
+                for proof in &epoch.mint_proofs {
+                    let signed = first_signed
+                        .entry(proof.proof.keyset_id)
+                        .or_insert(proof.timestamp);
+                    *signed = (*signed).min(proof.timestamp);
+                }
+            }
+        }
+
+        let mut registered = Vec::new();
+        for keyset in &listed {
+            let known_record = known.iter().find(|k| k.id == keyset.id);
+            let mut record = known_record.cloned().unwrap_or_else(|| {
+                let seen = if known.is_empty() { genesis } else { now };
+                KeysetRecord {
+                    id: keyset.id,
+                    unit: keyset.unit.clone(),
+                    keys: BTreeMap::new(),
+                    active_from: first_signed
+                        .get(&keyset.id)
+                        .map_or(seen, |signed| (*signed).min(seen)),
+                    active_until: None,
+                }
+            });
+            if record.keys.is_empty() {
+                record.keys = keyset.keys.clone();
+            }
+            if !keyset.active && record.active_until.is_none() {
+                record.active_until = Some(now);
+            }
+
+            if known_record != Some(&record) {
+                self.storage.save_keyset(&record)?;
+            }
+            if known_record.is_none() {
+                registered.push(keyset.id);
+            }
+        }
+        for keyset in known {
+            if keyset.active_until.is_none() && !listed.iter().any(|k| k.id == keyset.id) {
+                self.retire_keyset(&keyset.id, now)?;
+            }
+        }
+
+        Ok(registered)
+    }
+
+    /// Polls a mint's public API once: syncs its keysets, then asks which
+    /// recorded but unburned proofs it now reports spent and records those
+    /// as burns, timestamped when observed. Only proofs this database
+    /// already knows about can be checked, so the result is partial and
+    /// reports carry the observation to say so.
+    pub async fn follow_mint(&self, mint_url: &str) -> Result<ExternalObservation, PolError> {
+        self.sync_keysets(mint_url).await?;
+
+        let epochs = self.storage.list_epochs()?;
+        let mut burned = HashSet::new();
+        for proof in epochs.iter().flat_map(|e| &e.burn_proofs) {
+            burned.insert(proof.y()?.to_bytes());
+        }
+        let mut unburned = HashMap::new();
+        for proof in epochs.iter().flat_map(|e| &e.mint_proofs) {
+            let y = proof.y()?;
+            if !burned.contains(&y.to_bytes()) {
+                unburned.insert(
+                    y.to_bytes(),
+                    (y, proof.proof.secret.to_string(), proof.amount),
+                );
+            }
+        }
+
+        let ys: Vec<PublicKey> = unburned.values().map(|(y, _, _)| *y).collect();
+        let spent = keysets::spent(mint_url, &ys).await?;
+        for y in &spent {
+            if let Some((_, secret, amount)) = unburned.remove(&y.to_bytes()) {
+                self.record_burn_proof(secret, amount).await?;
+            }
+        }
+
+        let previous = self
+            .storage
+            .list_observations()?
+            .into_iter()
+            .find(|o| o.mint_url == mint_url);
+        let observation = ExternalObservation {
+            mint_url: mint_url.to_string(),
+            polls: previous.as_ref().map_or(0, |o| o.polls) + 1,
+            last_polled: Utc::now(),
+            proofs_checked: ys.len(),
+            spent_observed: previous.as_ref().map_or(0, |o| o.spent_observed) + spent.len() as u64,
+        };
+        self.storage.save_observation(&observation)?;
+
+        Ok(observation)
+    }
+
+    /// Stores the closed-epoch commitments of a report published by `mint`
+    /// and returns the equivocations it reveals: epochs for which the mint
+    /// has now been seen publishing more than one commitment. Open epochs
+    /// are skipped since their commitment changes with every record. The
+    /// report must satisfy `trusted`, and only commitments signed by a
+    /// trusted key are compared, so a forged copy cannot raise an alert.
+    pub async fn observe_report(
+        &self,
+        mint: &str,
+        source: &str,
+        signed: &SignedReport,
+        trusted: &SignaturePolicy,
+    ) -> Result<Vec<Equivocation>, PolError> {
+        signed.verify(trusted)?;
+        let report = &signed.report;
+        let signers = signed.signed_by();
+        let now = Utc::now();
+        let mut seen = self.storage.list_seen(Some(mint))?;
+        let mut changed = Vec::new();
+        let mut equivocations = Vec::new();
+
+        for epoch in report.epoch_reports.iter().filter(|e| e.end_time.is_some()) {
+            let existing = seen
+                .iter_mut()
+                .find(|s| s.epoch_id == epoch.epoch_id && s.commitment == epoch.commitment);
+            if let Some(existing) = existing {
+                existing.last_seen = now;
+                if !existing.sources.iter().any(|s| s == source) {
+                    existing.sources.push(source.to_string());
+                }
+                for signer in &signers {
+                    if !existing.signers.contains(signer) {
+                        existing.signers.push(*signer);
+                    }
+                }
+                changed.push(existing.clone());
+                continue;
+            }
+
+            let entry = SeenCommitment {
+                mint: mint.to_string(),
+                epoch_id: epoch.epoch_id,
+                commitment: epoch.commitment,
+                sources: vec![source.to_string()],
+                signers: signers.clone(),
+                first_seen: now,
+                last_seen: now,
+            };
+            let mut commitments: Vec<SeenCommitment> = seen
+                .iter()
+                .filter(|s| {
+                    s.epoch_id == epoch.epoch_id
+                        && s.signers.iter().any(|k| trusted.signers.contains(k))
+                })
+                .cloned()
+                .collect();
+            seen.push(entry.clone());
+            changed.push(entry.clone());
+            if !commitments.is_empty() {
+                commitments.push(entry);
+                error!(
+                    mint,
+                    epoch_id = epoch.epoch_id,
+                    commitments = commitments.len(),
+                    "Equivocation detected"
+                );
+                self.emit(PolEvent::Alert {
+                    epoch_id: epoch.epoch_id,
+                    message: format!(
+                        "{} published {} different commitments for epoch {}",
+                        mint,
+                        commitments.len(),
+                        epoch.epoch_id
+                    ),
+                    timestamp: now,
+                });
+                equivocations.push(Equivocation {
+                    mint: mint.to_string(),
+                    epoch_id: epoch.epoch_id,
+                    commitments,
+                });
+            }
+        }
+
+        self.storage.save_seen(&changed)?;
+        Ok(equivocations)
+    }
+
+    /// Fetches `mint`'s reports from every source, HTTP URL or Nostr
+    /// relay, once and observes each. A source that cannot be fetched, or a
+    /// report that does not satisfy `trusted`, is logged and skipped, so
+    /// one dead or lying mirror does not blind the monitor.
+    pub async fn monitor_mint(
+        &self,
+        mint: &str,
+        trusted: &SignaturePolicy,
+        sources: &[String],
+    ) -> Result<Vec<Equivocation>, PolError> {
+        let mut equivocations = Vec::new();
+        for source in sources {
+            let versions = match monitor::fetch_versions(source).await {
+                Ok(versions) => versions,
+                Err(e) => {
+                    error!(source = %source, error = %e, "Failed to fetch report");
+                    continue;
+                }
+            };
+            for data in versions {
+                let signed = match monitor::parse_report(&data)
+                    .and_then(|signed| signed.verify(trusted).map(|()| signed))
+                {
+                    Ok(signed) => signed,
+                    Err(e) => {
+                        error!(source = %source, error = %e, "Ignoring report");
+                        continue;
+                    }
+                };
+                equivocations.extend(self.observe_report(mint, source, &signed, trusted).await?);
+            }
+        }
+        Ok(equivocations)
+    }
+
+    /// Commitments the monitor has seen, optionally for one mint only.
+    pub fn seen_commitments(&self, mint: Option<&str>) -> Result<Vec<SeenCommitment>, PolError> {
+        self.storage.list_seen(mint)
+    }
+
+    /// Compares the recorded ledger with the mint's own spent and issued
+    /// records, so a curated PoL database cannot go unnoticed.
+    pub async fn reconcile(&self, ledger: &MintLedger) -> Result<ReconciliationReport, PolError> {
+        reconcile::reconcile(&self.storage.list_epochs()?, ledger)
+    }
+
+    /// Mint proofs issued against a quote, matched by quote id or payment
+    /// hash.
+    pub async fn mint_proofs_for_quote(
+        &self,
+        quote_id_or_payment_hash: &str,
+    ) -> Result<Vec<EpochRecord<MintProof>>, PolError> {
+        let mut records = self.records_where(
+            |epoch| epoch.mint_proofs,
+            |proof| {
+                proof
+                    .quote
+                    .as_ref()
+                    .is_some_and(|quote| quote.matches(quote_id_or_payment_hash))
+            },
+        )?;
+        sort_by_time_and_y(records, |r| Ok((r.record.timestamp, r.record.y()?)))
+    }
+
+    /// Burn proofs redeemed in a melt, matched by quote id or payment hash.
+    pub async fn burn_proofs_for_melt(
+        &self,
+        quote_id_or_payment_hash: &str,
+    ) -> Result<Vec<EpochRecord<BurnProof>>, PolError> {
+        let mut records = self.records_where(
+            |epoch| epoch.burn_proofs,
+            |proof| {
+                proof
+                    .melt
+                    .as_ref()
+                    .is_some_and(|melt| melt.matches(quote_id_or_payment_hash))
+            },
+        )?;
+        sort_by_time_and_y(records, |r| Ok((r.record.timestamp, r.record.y()?)))
+    }
+
+    /// Every stored proof of one kind that satisfies `filter`, with its
+    /// epoch.
+    fn records_where<P>(
+        &self,
+        proofs: impl Fn(EpochState) -> HashSet<P>,
+        filter: impl Fn(&P) -> bool,
+    ) -> Result<Vec<EpochRecord<P>>, PolError> {
+        let mut records = Vec::new();
+        for epoch in self.storage.list_epochs()? {
+            let epoch_id = epoch.epoch_id;
+            records.extend(
+                proofs(epoch)
+                    .into_iter()
+                    .filter(|proof| filter(proof))
+                    .map(|record| EpochRecord { epoch_id, record }),
+            );
+        }
+        Ok(records)
+    }
+
+    pub async fn verify_mint_proof(&self, epoch_id: u64, proof: &Proof) -> Result<bool, PolError> {
+        if let Some(epoch_state) = self.storage.get_epoch(epoch_id)? {
+            Ok(epoch_state.mint_proofs.iter().any(|p| p.proof == *proof))
+        } else {
+            Err(PolError::InvalidEpoch(format!(
+                "Epoch {} not found",
+                epoch_id
+            )))
+        }
+    }
+
+    pub async fn verify_burn_proof(&self, epoch_id: u64, secret: &str) -> Result<bool, PolError> {
+        if let Some(epoch_state) = self.storage.get_epoch(epoch_id)? {
+            Ok(epoch_state.burn_proofs.iter().any(|p| p.secret == secret))
+        } else {
+            Err(PolError::InvalidEpoch(format!(
+                "Epoch {} not found",
+                epoch_id
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_sample_proof;
+    use bitcoin::hashes::Hash;
+    use bitcoin::Amount;
+    use cdk::{nuts::nut02::Id, Amount as CashuAmount};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_pol_service_lifecycle() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+
+        // Test initial state
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.epoch_reports.len(), 1);
+        assert_eq!(report.total_outstanding_balance, Amount::from_sat(0));
+
+        // Test recording burn proof
+        let amount = Amount::from_sat(1000);
+        let secret = "test_secret".to_string();
+        service
+            .record_burn_proof(secret.clone(), amount)
+            .await
+            .unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.total_outstanding_balance, Amount::from_sat(0));
+
+        // Test epoch rotation
+        let new_epoch_id = service.rotate_epoch().await.unwrap();
+        assert_eq!(new_epoch_id, 1);
+
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.epoch_reports.len(), 2);
+
+        // Test verification
+        assert!(service.verify_burn_proof(0, &secret).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_epoch_cleanup() {
+        let max_history = 3;
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, max_history, db_path).unwrap();
+        service.initialize().await.unwrap();
+
+        // Create more epochs than max_history
+        for _ in 0..5 {
+            service.rotate_epoch().await.unwrap();
+        }
+
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.epoch_reports.len(), max_history);
+    }
+
+    #[tokio::test]
+    async fn test_outstanding_balance_calculation() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+
+        // Record multiple burn proofs
+        let amounts = vec![1000, 2000, 3000];
+        for (i, amount) in amounts.iter().enumerate() {
+            let secret = format!("test_secret_{}", i);
+            service
+                .record_burn_proof(secret, Amount::from_sat(*amount))
+                .await
+                .unwrap();
+        }
+
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.total_outstanding_balance, Amount::from_sat(0));
+    }
+
+    #[tokio::test]
+    async fn test_total_outstanding_is_the_running_balance() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let proof = create_sample_proof(keyset_id, CashuAmount::from(10u64));
+        service
+            .record_mint_proof(proof, MilliSats::from_sat(10))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+        service
+            .record_burn_proof("redeemed".to_string(), Amount::from_sat(4))
+            .await
+            .unwrap();
+
+        // The second epoch only redeems, which pays down the first epoch's
+        // liabilities rather than counting as zero
+        for aggregates_only in [false, true] {
+            service.set_aggregate_reports(aggregates_only).await;
+            let report = service.generate_report().await.unwrap();
+            assert_eq!(report.epoch_reports[1].outstanding_balance, Amount::ZERO);
+            assert_eq!(report.total_outstanding_balance, Amount::from_sat(6));
+            assert!(report.check_consistency().unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_are_emitted() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+
+        let mut events = service.subscribe_events();
+        service
+            .record_burn_proof("event_secret".to_string(), Amount::from_sat(500))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+
+        match events.recv().await.unwrap() {
+            PolEvent::BurnRecorded { epoch_id, proof } => {
+                assert_eq!(epoch_id, 0);
+                assert_eq!(proof.y, secret_to_y(b"event_secret").unwrap());
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        }
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            PolEvent::Alert { epoch_id: 0, .. }
+        ));
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            PolEvent::EpochRotated {
+                previous_epoch_id: 0,
+                new_epoch_id: 1,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hooks_are_invoked() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+
+        let burned = Arc::new(RwLock::new(Vec::new()));
+        let rotations = Arc::new(RwLock::new(Vec::new()));
+
+        let burned_hook = burned.clone();
+        service
+            .on_burn_recorded(move |epoch_id, proof| {
+                let burned = burned_hook.clone();
+                async move { burned.write().await.push((epoch_id, proof.secret)) }
+            })
+            .await;
+        let rotations_hook = rotations.clone();
+        service
+            .on_epoch_rotated(move |previous, new| {
+                let rotations = rotations_hook.clone();
+                async move { rotations.write().await.push((previous, new)) }
+            })
+            .await;
+
+        service
+            .record_burn_proof("hook_secret".to_string(), Amount::from_sat(100))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+
+        assert_eq!(*burned.read().await, vec![(0, "hook_secret".to_string())]);
+        assert_eq!(*rotations.read().await, vec![(0, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_proofs_between_paginates_across_epochs() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+
+        let from = Utc::now();
+        for i in 0..3 {
+            service
+                .record_burn_proof(format!("range_{}", i), Amount::from_sat(100))
+                .await
+                .unwrap();
+            service.rotate_epoch().await.unwrap();
+        }
+        let to = Utc::now() + Duration::seconds(1);
+
+        let page = service
+            .get_burn_proofs_between(from, to, 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].epoch_id, 1);
+        assert_eq!(page.items[0].record.secret, "range_1");
+
+        let empty = service
+            .get_mint_proofs_between(from, to, 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(empty.total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_epoch_for_time() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+
+        let before_start = Utc::now() - Duration::days(1);
+        service.rotate_epoch().await.unwrap();
+        let during_second = Utc::now();
+
+        assert_eq!(service.epoch_for_time(before_start).await.unwrap(), None);
+        assert_eq!(
+            service.epoch_for_time(during_second).await.unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            service
+                .epoch_for_time(Utc::now() + Duration::days(365))
+                .await
+                .unwrap(),
+            Some(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backfill_routes_to_historical_epochs() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(7, 24, db_path).unwrap();
+        service
+            .initialize_from(Utc::now() - Duration::days(20))
+            .await
+            .unwrap();
+        assert_eq!(service.current_epoch().await, 2);
+
+        service
+            .record_burn_proof_at(
+                "old".to_string(),
+                Amount::from_sat(10),
+                Utc::now() - Duration::days(18),
+            )
+            .await
+            .unwrap();
+        service
+            .record_burn_proof_at(
+                "recent".to_string(),
+                Amount::from_sat(20),
+                Utc::now() - Duration::days(9),
+            )
+            .await
+            .unwrap();
+
+        assert!(service.verify_burn_proof(0, "old").await.unwrap());
+        assert!(service.verify_burn_proof(1, "recent").await.unwrap());
+
+        // Nothing can be placed before epoch 0
+        assert!(service
+            .record_burn_proof_at(
+                "ancient".to_string(),
+                Amount::from_sat(30),
+                Utc::now() - Duration::days(60),
+            )
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_backfill_creates_gap_epochs() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(7, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+        for _ in 0..3 {
+            service.rotate_epoch().await.unwrap();
+        }
+        // Drop the oldest epochs as pruning would have
+        service.storage.delete_epoch(0).unwrap();
+        service.storage.delete_epoch(1).unwrap();
+
+        service
+            .record_burn_proof_at(
+                "gap".to_string(),
+                Amount::from_sat(10),
+                Utc::now() - Duration::days(10),
+            )
+            .await
+            .unwrap();
+
+        assert!(service.verify_burn_proof(0, "gap").await.unwrap());
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.epoch_reports.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_report_matches_full_report() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+
+        for i in 0..3u64 {
+            let proof = create_sample_proof(keyset_id, CashuAmount::from(1000u64));
+            service
+                .record_mint_proof(proof, Amount::from_sat(1000))
+                .await
+                .unwrap();
+            service
+                .record_burn_proof(format!("aggregate_{}", i), Amount::from_sat(100 * (i + 1)))
+                .await
+                .unwrap();
+            service.rotate_epoch().await.unwrap();
+        }
+        let full = service.generate_report().await.unwrap();
+
+        service.set_aggregate_reports(true).await;
+        let aggregated = service.generate_report().await.unwrap();
+        assert!(aggregated.check_consistency().unwrap().is_empty());
+        assert_eq!(
+            aggregated.total_outstanding_balance,
+            full.total_outstanding_balance
+        );
+        for (epoch, reference) in aggregated.epoch_reports.iter().zip(&full.epoch_reports) {
+            assert!(epoch.mint_proofs.is_empty() && epoch.burn_proofs.is_empty());
+            assert_eq!(epoch.commitment, reference.commitment);
+            assert_eq!(epoch.closing_balance, reference.closing_balance);
+            let aggregates = epoch.aggregates.as_ref().unwrap();
+            assert_eq!(aggregates.mint_count, reference.mint_proofs.len() as u64);
+        }
+
+        // Inflating a total along with its root sum breaks the commitment
+        let mut inflated = aggregated.clone();
+        let aggregates = inflated.epoch_reports[0].aggregates.as_mut().unwrap();
+        let mint_root = aggregates.mint_root.as_mut().unwrap();
+        mint_root.sum = mint_root.sum.try_add(MilliSats::from_sat(1)).unwrap();
+        aggregates.minted = mint_root.sum;
+        assert!(inflated
+            .check_consistency()
+            .unwrap()
+            .iter()
+            .any(|m| m.check == "aggregates"));
+    }
+
+    #[tokio::test]
+    async fn test_merge_epochs() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+
+        for i in 0..3 {
+            service
+                .record_burn_proof(format!("merge_{}", i), Amount::from_sat(100))
+                .await
+                .unwrap();
+            if i < 2 {
+                service.rotate_epoch().await.unwrap();
+            }
+        }
+
+        // Epochs 0 and 2 are not adjacent
+        assert!(service.merge_epochs(&[0, 2], false).await.is_err());
+
+        assert_eq!(service.merge_epochs(&[1, 2], false).await.unwrap(), 1);
+        assert_eq!(service.current_epoch().await, 1);
+        assert_eq!(service.storage().get_current_epoch().unwrap(), Some(1));
+        assert!(service.verify_burn_proof(1, "merge_2").await.unwrap());
+
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.epoch_reports.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_merge_refuses_published_epochs() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+        service.rotate_epoch().await.unwrap();
+
+        service
+            .set_signer(Arc::new(crate::LocalSigner::generate()))
+            .await;
+        service.generate_signed_report().await.unwrap();
+
+        assert!(service.merge_epochs(&[0, 1], false).await.is_err());
+        assert_eq!(service.merge_epochs(&[0, 1], true).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_resegment_epochs() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+
+        {
+            let service = PolService::with_path(30, 24, &db_path).unwrap();
+            service
+                .initialize_from(Utc::now() - Duration::days(65))
+                .await
+                .unwrap();
+            for (secret, days_ago) in [("a", 60), ("b", 40), ("c", 20), ("d", 3)] {
+                service
+                    .record_burn_proof_at(
+                        secret.to_string(),
+                        Amount::from_sat(10),
+                        Utc::now() - Duration::days(days_ago),
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let service = PolService::with_path(7, 24, &db_path).unwrap();
+        service.initialize().await.unwrap();
+        let new_ids = service.resegment_epochs(false).await.unwrap();
+
+        assert_eq!(new_ids, (0..10).collect::<Vec<u64>>());
+        assert_eq!(service.current_epoch().await, 9);
+        for (secret, epoch_id) in [("a", 0), ("b", 3), ("c", 6), ("d", 8)] {
+            assert!(service.verify_burn_proof(epoch_id, secret).await.unwrap());
+        }
+        // Openings saved at the old boundaries are gone
+        for epoch_id in 1..10 {
+            assert_eq!(
+                service.storage().get_opening_balance(epoch_id).unwrap(),
+                None
+            );
+        }
+
+        let audit = service.audit_log().unwrap();
+        assert_eq!(audit.len(), 1);
+        assert!(matches!(
+            audit[0].operation,
+            AuditOperation::Resegment { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_time_derived_epoch_ids() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 24, temp_dir.path().join("test.db")).unwrap();
+        service.set_epoch_id_mode(EpochIdMode::TimeDerived).await;
+        service
+            .initialize_from(Utc::now() - Duration::days(20))
+            .await
+            .unwrap();
+
+        let week = Duration::days(7).num_seconds();
+        let expected = (Utc::now().timestamp() / week) as u64;
+        assert_eq!(service.current_epoch().await, expected);
+
+        let epochs = service.storage().list_epochs().unwrap();
+        assert!(epochs.len() >= 3);
+        for epoch in &epochs {
+            assert_eq!(epoch.start_time.timestamp(), epoch.epoch_id as i64 * week);
+        }
+
+        // The current window has not elapsed, so there is nothing to rotate to
+        assert!(service.rotate_epoch().await.is_err());
+        drop(service);
+
+        // The mode is stored with the database and survives a reopen
+        let service = PolService::with_path(7, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        assert_eq!(
+            *service.epoch_id_mode.read().await,
+            EpochIdMode::TimeDerived
+        );
+
+        let sequential = temp_dir.path().join("sequential.db");
+        PolService::with_path(7, 24, &sequential)
+            .unwrap()
+            .initialize()
+            .await
+            .unwrap();
+        let service = PolService::with_path(7, 24, &sequential).unwrap();
+        service.set_epoch_id_mode(EpochIdMode::TimeDerived).await;
+        assert!(service.initialize().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_finalized_epoch_is_immutable() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service
+            .initialize_from(Utc::now() - Duration::days(40))
+            .await
+            .unwrap();
+        service
+            .record_burn_proof_at(
+                "sealed".to_string(),
+                Amount::from_sat(10),
+                Utc::now() - Duration::days(35),
+            )
+            .await
+            .unwrap();
+
+        // The current epoch cannot be sealed
+        assert!(service.finalize_epoch(1).await.is_err());
+
+        let seal = service.finalize_epoch(0).await.unwrap();
+        assert_eq!(seal.commitment, service.epoch_commitment(0).unwrap());
+        assert!(matches!(
+            service.finalize_epoch(0).await,
+            Err(PolError::EpochFinalized(0))
+        ));
+
+        let late = service
+            .record_burn_proof_at(
+                "late".to_string(),
+                Amount::from_sat(10),
+                Utc::now() - Duration::days(35),
+            )
+            .await;
+        assert!(matches!(late, Err(PolError::EpochFinalized(0))));
+        assert!(service.merge_epochs(&[0, 1], true).await.is_err());
+
+        let report = service.generate_report().await.unwrap();
+        let epoch = &report.epoch_reports[0];
+        assert_eq!(epoch.commitment, seal.commitment);
+        assert!(epoch.finalized_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_balances_carry_over_pruned_epochs() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 2, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let proof = create_sample_proof(keyset_id, CashuAmount::from(1000u64));
+        service
+            .record_mint_proof(proof, Amount::from_sat(1000))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+        service
+            .record_burn_proof("spent".to_string(), Amount::from_sat(300))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+
+        // Epoch 0 has been pruned, but its liabilities carry into epoch 1
+        let report = service.generate_report().await.unwrap();
+        let balances: Vec<_> = report
+            .epoch_reports
+            .iter()
+            .map(|e| {
+                (
+                    e.epoch_id,
+                    e.opening_balance.to_sat(),
+                    e.closing_balance.to_sat(),
+                )
+            })
+            .collect();
+        assert_eq!(balances, vec![(1, 1000, 700), (2, 700, 700)]);
+
+        assert_eq!(report.pruned_balance, Amount::from_sat(1000));
+        let series: Vec<_> = report
+            .cumulative_balances
+            .iter()
+            .map(|c| (c.epoch_id, c.balance.to_sat()))
+            .collect();
+        assert_eq!(series, vec![(1, 700), (2, 700)]);
+    }
+
+    #[tokio::test]
+    async fn test_msat_amounts_round_only_in_reports() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        for _ in 0..3 {
+            let proof = create_sample_proof(keyset_id, CashuAmount::from(1u64));
+            service
+                .record_mint_proof(proof, MilliSats::from_msat(1_400))
+                .await
+                .unwrap();
+        }
+
+        // Rounding each proof on ingest would report 3 sat
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.total_outstanding_balance, Amount::from_sat(4));
+        assert_eq!(
+            report.epoch_reports[0].mint_proofs[0].amount,
+            MilliSats::from_msat(1_400)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mint_proofs_for_quote() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let quote = MintQuoteInfo {
+            quote_id: Some("quote-1".to_string()),
+            payment_hash: Some("ab".repeat(32)),
+            method: Some("bolt11".to_string()),
+        };
+        for amount in [1u64, 4] {
+            let proof = create_sample_proof(keyset_id, CashuAmount::from(amount));
+            service
+                .record_mint_proof_with_quote(proof, Amount::from_sat(amount), Some(quote.clone()))
+                .await
+                .unwrap();
+        }
+        let proof = create_sample_proof(keyset_id, CashuAmount::from(2u64));
+        service
+            .record_mint_proof(proof, Amount::from_sat(2))
+            .await
+            .unwrap();
+
+        let by_id = service.mint_proofs_for_quote("quote-1").await.unwrap();
+        assert_eq!(by_id.len(), 2);
+        let by_hash = service
+            .mint_proofs_for_quote(&"ab".repeat(32))
+            .await
+            .unwrap();
+        assert_eq!(by_hash.len(), 2);
+        assert_eq!(by_hash[0].record.quote.as_ref(), Some(&quote));
+    }
+
+    #[tokio::test]
+    async fn test_burn_proofs_for_melt() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let melt = MeltQuoteInfo {
+            quote_id: Some("melt-1".to_string()),
+            payment_hash: Some("cd".repeat(32)),
+            preimage: Some("ef".repeat(32)),
+        };
+        service
+            .record_burn_proof_with_melt(
+                "paid".to_string(),
+                Amount::from_sat(8),
+                Some(melt.clone()),
+            )
+            .await
+            .unwrap();
+        service
+            .record_burn_proof("unrelated".to_string(), Amount::from_sat(2))
+            .await
+            .unwrap();
+
+        let records = service.burn_proofs_for_melt("melt-1").await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record.secret, "paid");
+        assert_eq!(records[0].record.melt.as_ref(), Some(&melt));
+        assert_eq!(
+            service
+                .burn_proofs_for_melt(&"cd".repeat(32))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_by_secret_or_y() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+
+        service.rotate_epoch().await.unwrap();
+        service
+            .record_burn_proof("disputed".to_string(), Amount::from_sat(250))
+            .await
+            .unwrap();
+
+        let by_secret = service.lookup("disputed").await.unwrap();
+        assert!(by_secret.minted.is_empty());
+        assert_eq!(by_secret.burned.len(), 1);
+        assert_eq!(by_secret.burned[0].epoch_id, 1);
+
+        let by_y = service.lookup(&by_secret.y.to_hex()).await.unwrap();
+        assert_eq!(by_y.burned.len(), 1);
+
+        let unknown = service.lookup("never_seen").await.unwrap();
+        assert!(unknown.burned.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_attestations_are_verified_and_reported() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let service = PolService::with_path(30, 24, db_path).unwrap();
+        service.initialize().await.unwrap();
+        service
+            .record_burn_proof("attested".to_string(), Amount::from_sat(100))
+            .await
+            .unwrap();
+
+        let auditor = crate::LocalSigner::generate();
+        let commitment = service.epoch_commitment(0).unwrap();
+        let signature = auditor.sign(&commitment).await.unwrap();
+
+        // A signature over a different epoch's commitment is rejected
+        service.rotate_epoch().await.unwrap();
+        assert!(service
+            .submit_attestation(1, auditor.public_key(), signature, None)
+            .await
+            .is_err());
+
+        service
+            .submit_attestation(0, auditor.public_key(), signature, Some("ok".to_string()))
+            .await
+            .unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        let epoch = &report.epoch_reports[0];
+        assert_eq!(epoch.commitment, commitment);
+        assert_eq!(epoch.attestations.len(), 1);
+        assert_eq!(epoch.attestations[0].public_key, auditor.public_key());
+        assert!(report.epoch_reports[1].attestations.is_empty());
+
+        // Backfilling would invalidate the attestation, so it is refused
+        let backfilled = service
+            .record_burn_proof_at(
+                "late".to_string(),
+                Amount::from_sat(1),
+                epoch.start_time + Duration::seconds(1),
+            )
+            .await;
+        assert!(matches!(backfilled, Err(PolError::InvalidEpoch(_))));
+        assert!(service.self_audit().await.unwrap().is_clean());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_records_are_never_lost() {
+        let temp_dir = tempdir().unwrap();
+        let service =
+            Arc::new(PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap());
+        service.initialize().await.unwrap();
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+
+        let mut tasks = JoinSet::new();
+        for i in 0..64u64 {
+            let service = service.clone();
+            tasks.spawn(async move {
+                let proof = create_sample_proof(keyset_id, CashuAmount::from(1u64));
+                service
+                    .record_mint_proof(proof, Amount::from_sat(10))
+                    .await
+                    .unwrap();
+                service
+                    .record_burn_proof(format!("burn_{}", i), Amount::from_sat(1))
+                    .await
+                    .unwrap();
+            });
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.unwrap();
+        }
+
+        let epoch = service.storage.get_epoch(0).unwrap().unwrap();
+        assert_eq!(epoch.mint_proofs.len(), 64);
+        assert_eq!(epoch.burn_proofs.len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_reports() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service
+            .set_signer(Arc::new(crate::LocalSigner::generate()))
+            .await;
+        let mut reports = service.subscribe_reports();
+
+        service.generate_signed_report().await.unwrap();
+
+        let unsigned = reports.recv().await.unwrap();
+        assert!(matches!(unsigned, GeneratedReport::Unsigned(_)));
+        let GeneratedReport::Signed(signed) = reports.recv().await.unwrap() else {
+            panic!("expected the signed report");
+        };
+        assert_eq!(signed.report.timestamp, unsigned.report().timestamp);
+    }
+
+    #[tokio::test]
+    async fn test_inclusion_proofs_verify_against_report() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+
+        let proof = create_sample_proof(keyset_id, CashuAmount::from(8u64));
+        let y = secret_to_y(proof.secret.as_bytes()).unwrap();
+        service
+            .record_mint_proof(proof, Amount::from_sat(8))
+            .await
+            .unwrap();
+        for amount in 1..5 {
+            let other = create_sample_proof(keyset_id, CashuAmount::from(amount));
+            service
+                .record_mint_proof(other, Amount::from_sat(amount))
+                .await
+                .unwrap();
+        }
+
+        let report = service.generate_report().await.unwrap();
+        let commitment = report.epoch_reports[0].commitment;
+        let proofs = service.inclusion_proofs(&y).await.unwrap();
+        assert_eq!(proofs.len(), 1);
+        assert_eq!(proofs[0].kind, crate::types::LeafKind::Mint);
+        assert_eq!(proofs[0].tree_size, 5);
+        assert!(proofs[0].verify(&y, &commitment));
+        assert_eq!(proofs[0].mint_root.sum, MilliSats::from_sat(18));
+        assert!(proofs[0].matches_balance(&report.epoch_reports[0]));
+
+        let mut forged = proofs[0].clone();
+        forged.amount = MilliSats::from_sat(9);
+        assert!(!forged.verify(&y, &commitment));
+
+        // Understating the minted total breaks the commitment
+        let mut forged = proofs[0].clone();
+        forged.mint_root.sum = MilliSats::from_sat(10);
+        assert!(!forged.verify(&y, &commitment));
+    }
+
+    #[tokio::test]
+    async fn test_history_only_grows_between_reports() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 2, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let mut reports = Vec::new();
+        for amount in [3, 5, 8, 13] {
+            service
+                .record_burn_proof(format!("burn_{}", amount), Amount::from_sat(amount))
+                .await
+                .unwrap();
+            service.rotate_epoch().await.unwrap();
+            reports.push(service.generate_report().await.unwrap());
+        }
+        // The log keeps every closed epoch even though reports retain two
+        let last = reports.last().unwrap();
+        assert_eq!(last.epoch_reports.len(), 2);
+        assert_eq!(last.history.unwrap().size, 4);
+
+        for old in &reports {
+            let proof = service
+                .consistency_proof(old.history.unwrap().size)
+                .await
+                .unwrap();
+            assert!(proof.verify_reports(old, last));
+        }
+
+        let proof = service.consistency_proof(1).await.unwrap();
+        assert!(!proof.verify_reports(&reports[1], last));
+        assert!(service.consistency_proof(5).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_backfills_are_logged_again() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service
+            .initialize_from(Utc::now() - Duration::days(40))
+            .await
+            .unwrap();
+        service
+            .record_burn_proof_at(
+                "early".to_string(),
+                Amount::from_sat(3),
+                Utc::now() - Duration::days(38),
+            )
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+
+        let before = service.generate_report().await.unwrap();
+        assert_eq!(before.history_proofs.len(), 1);
+        assert!(before.check_consistency().unwrap().is_empty());
+
+        service
+            .record_burn_proof_at(
+                "late".to_string(),
+                Amount::from_sat(5),
+                Utc::now() - Duration::days(35),
+            )
+            .await
+            .unwrap();
+        let after = service.generate_report().await.unwrap();
+        assert_eq!(after.history.unwrap().size, 2);
+        assert_eq!(after.history_proofs[0].index, 1);
+        assert!(after.check_consistency().unwrap().is_empty());
+
+        // Showing the entry logged before the backfill does not hide it
+        let leaves: Vec<_> = service
+            .storage
+            .list_history()
+            .unwrap()
+            .iter()
+            .map(HistoryEntry::leaf)
+            .collect();
+        let mut stale = after.clone();
+        stale.history_proofs[0] = HistoryProof {
+            entry: before.history_proofs[0].entry.clone(),
+            index: 0,
+            path: merkle::inclusion_path(&leaves, 0),
+        };
+        assert!(stale.history_proofs[0].verify(&after.history.unwrap()));
+        let mismatches = stale.check_consistency().unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].check, "history");
+    }
+
+    #[tokio::test]
+    async fn test_monitor_detects_equivocation() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service
+            .record_burn_proof("burn".to_string(), Amount::from_sat(3))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+        let signer = crate::LocalSigner::generate();
+        let trusted = SignaturePolicy::single(signer.public_key());
+        let report = service.generate_report().await.unwrap();
+        let signed = crate::sign_report(report.clone(), trusted.clone(), &signer)
+            .await
+            .unwrap();
+
+        let equivocations = service
+            .observe_report("mint", "https://a", &signed, &trusted)
+            .await
+            .unwrap();
+        assert!(equivocations.is_empty());
+        // Seeing the same view again, from another source, is fine
+        let equivocations = service
+            .observe_report("mint", "https://b", &signed, &trusted)
+            .await
+            .unwrap();
+        assert!(equivocations.is_empty());
+
+        let mut split = report.clone();
+        split.epoch_reports[0].commitment = sha256::Hash::hash(b"other view");
+
+        // A split view nobody trusted signed is refused, not alerted on
+        let impostor = crate::LocalSigner::generate();
+        let forged = crate::sign_report(
+            split.clone(),
+            SignaturePolicy::single(impostor.public_key()),
+            &impostor,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            service
+                .observe_report("mint", "https://b", &forged, &trusted)
+                .await,
+            Err(PolError::InvalidSignature(_))
+        ));
+
+        let split = crate::sign_report(split, trusted.clone(), &signer)
+            .await
+            .unwrap();
+        let equivocations = service
+            .observe_report("mint", "https://b", &split, &trusted)
+            .await
+            .unwrap();
+        assert_eq!(equivocations.len(), 1);
+        assert_eq!(equivocations[0].epoch_id, 0);
+        assert_eq!(equivocations[0].commitments.len(), 2);
+        assert_eq!(equivocations[0].commitments[0].sources.len(), 2);
+
+        // The open epoch is never recorded
+        let seen = service.seen_commitments(Some("mint")).unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(seen.iter().all(|s| s.epoch_id == 0));
+    }
+
+    #[tokio::test]
+    async fn test_burn_index_proves_non_inclusion() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        for (secret, amount) in [("a", 3), ("b", 5), ("c", 8)] {
+            service
+                .record_burn_proof(secret.to_string(), Amount::from_sat(amount))
+                .await
+                .unwrap();
+        }
+
+        service.rotate_epoch().await.unwrap();
+        let seal = service.finalize_epoch(0).await.unwrap();
+        let report = service.generate_report().await.unwrap();
+        let burn_index = report.epoch_reports[0].burn_index.unwrap();
+
+        let burned = secret_to_y(b"b").unwrap();
+        let proof = service.burn_index_proof(0, &burned).await.unwrap();
+        assert_eq!(proof.amount, Some(MilliSats::from_sat(5)));
+        assert_eq!(proof.burn_index, burn_index);
+        assert!(proof.verify(&seal.commitment));
+
+        let unburned = secret_to_y(b"never").unwrap();
+        let proof = service.burn_index_proof(0, &unburned).await.unwrap();
+        assert_eq!(proof.amount, None);
+        assert!(proof.verify(&seal.commitment));
+
+        // Claiming the unburned Y was burned does not verify
+        let mut forged = proof.clone();
+        forged.amount = Some(MilliSats::from_sat(5));
+        assert!(!forged.verify(&seal.commitment));
+
+        // Nor does absence from an index other than the sealed one
+        let mut forged = proof.clone();
+        forged.burn_index = crate::smt::root(&[]);
+        forged.siblings.clear();
+        assert!(!forged.verify(&seal.commitment));
+    }
+
+    #[tokio::test]
+    async fn test_confidential_reports_hide_amounts() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service.set_confidential_reports(true).await;
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+
+        let proof = create_sample_proof(keyset_id, CashuAmount::from(5u64));
+        let secret = proof.secret.to_string();
+        service
+            .record_mint_proof(proof, Amount::from_sat(5))
+            .await
+            .unwrap();
+        service
+            .record_mint_proof(
+                create_sample_proof(keyset_id, CashuAmount::from(3u64)),
+                Amount::from_sat(3),
+            )
+            .await
+            .unwrap();
+        service
+            .record_burn_proof("spent".to_string(), Amount::from_sat(2))
+            .await
+            .unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        let epoch = &report.epoch_reports[0];
+        assert!(epoch.mint_proofs.is_empty());
+        assert!(epoch.burn_proofs.is_empty());
+
+        assert_eq!(epoch.outstanding_balance, Amount::ZERO);
+        assert_eq!(epoch.closing_balance, Amount::ZERO);
+        assert_eq!(report.total_outstanding_balance, Amount::ZERO);
+        assert!(report.check_consistency().unwrap().is_empty());
+
+        let confidential = epoch.confidential.as_ref().unwrap();
+        assert!(confidential.verify());
+
+        let y = secret_to_y(secret.as_bytes()).unwrap();
+        let own = confidential
+            .mint_commitments
+            .iter()
+            .find(|c| c.y == y)
+            .unwrap();
+        assert!(own.opens_to(secret.as_bytes(), MilliSats::from_sat(5)));
+        assert!(!own.opens_to(secret.as_bytes(), MilliSats::from_sat(6)));
+
+        // A commitment without a valid range proof could hide a negative amount
+        let mut tampered = confidential.clone();
+        tampered.mint_commitments[0].range = tampered.mint_commitments[1].range.clone();
+        assert!(!tampered.verify());
+    }
+
+    #[tokio::test]
+    async fn test_liability_bound() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service.set_confidential_reports(true).await;
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        service
+            .record_mint_proof(
+                create_sample_proof(keyset_id, CashuAmount::from(8u64)),
+                Amount::from_sat(8),
+            )
+            .await
+            .unwrap();
+        service
+            .record_burn_proof("spent".to_string(), Amount::from_sat(3))
+            .await
+            .unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        let bound = service
+            .prove_liability_bound(Amount::from_sat(10))
+            .await
+            .unwrap();
+        assert!(bound.verify_against(&report));
+
+        let mut understated = bound.clone();
+        understated.upper_bound = MilliSats::from_sat(4);
+        assert!(!understated.verify());
+
+        assert!(service
+            .prove_liability_bound(Amount::from_sat(4))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_liability_bound_covers_pruned_liabilities() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 1, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service.set_confidential_reports(true).await;
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        service
+            .record_mint_proof(
+                create_sample_proof(keyset_id, CashuAmount::from(8u64)),
+                Amount::from_sat(8),
+            )
+            .await
+            .unwrap();
+
+        // Epoch 0 is pruned and the retained epoch only burns
+        service.rotate_epoch().await.unwrap();
+        service
+            .record_burn_proof("spent".to_string(), Amount::from_sat(3))
+            .await
+            .unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.epoch_reports.len(), 1);
+        let confidential = report.epoch_reports[0].confidential.as_ref().unwrap();
+        assert!(confidential.carried.is_some());
+        assert!(confidential.verify());
+
+        let bound = service
+            .prove_liability_bound(Amount::from_sat(5))
+            .await
+            .unwrap();
+        assert!(bound.verify_against(&report));
+        assert!(service
+            .prove_liability_bound(Amount::from_sat(4))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reports_bind_epochs_to_keysets() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let start = Utc::now() - Duration::days(1);
+        let active = Id::from_bytes(&[0; 8]).unwrap();
+        let retired = Id::from_bytes(&[0, 1, 1, 1, 1, 1, 1, 1]).unwrap();
+        for (id, active_until) in [(active, None), (retired, Some(start))] {
+            service
+                .register_keyset(&KeysetRecord {
+                    id,
+                    unit: Some("sat".to_string()),
+                    keys: Default::default(),
+                    active_from: start - Duration::days(60),
+                    active_until,
+                })
+                .unwrap();
+        }
+
+        for keyset_id in [active, retired] {
+            let proof = create_sample_proof(keyset_id, CashuAmount::from(1u64));
+            service
+                .record_mint_proof(proof, Amount::from_sat(1))
+                .await
+                .unwrap();
+        }
+
+        let report = service.generate_report().await.unwrap();
+        let epoch = &report.epoch_reports[0];
+        assert_eq!(epoch.keysets, vec![active]);
+        let outside = epoch.mints_outside_keysets();
+        assert_eq!(outside.len(), 1);
+        assert_eq!(outside[0].proof.keyset_id, retired);
+    }
+
+    #[tokio::test]
+    async fn test_synced_keysets_cover_recorded_history() {
+        use crate::test_utils::mock_mint::MockMint;
+
+        let mint = MockMint::start(1).await.unwrap();
+        let keyset_id = mint.keyset_ids()[0];
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        for proof in mint.mint(keyset_id, "quote", 5).await.unwrap() {
+            let amount = Amount::from_sat(proof.amount.into());
+            service.record_mint_proof(proof, amount).await.unwrap();
+        }
+
+        assert_eq!(
+            service.sync_keysets(mint.url()).await.unwrap(),
+            vec![keyset_id]
+        );
+        assert!(service.sync_keysets(mint.url()).await.unwrap().is_empty());
+        let keysets = service.keysets().unwrap();
+        assert_eq!(keysets[0].keys.len(), 16);
+
+        let report = service.generate_report().await.unwrap();
+        let epoch = &report.epoch_reports[0];
+        assert!(keysets[0].active_from <= epoch.start_time);
+        assert_eq!(epoch.keysets, vec![keyset_id]);
+        assert!(epoch.mints_outside_keysets().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_self_audit_blocks_publishing_on_drift() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service
+            .set_signer(Arc::new(crate::LocalSigner::generate()))
+            .await;
+        service
+            .record_burn_proof("sealed".to_string(), Amount::from_sat(5))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+        service.finalize_epoch(0).await.unwrap();
+
+        let audit = service.self_audit().await.unwrap();
+        assert!(audit.is_clean());
+        assert_eq!(audit.checked_seals, 1);
+        service.generate_signed_report().await.unwrap();
+
+        // A seal whose commitment does not match the stored proofs
+        service
+            .storage
+            .finalize_epoch(&FinalizedEpoch {
+                epoch_id: 1,
+                commitment: service.epoch_commitment(0).unwrap(),
+                signature: None,
+                finalized_at: Utc::now(),
+            })
+            .unwrap();
+
+        let audit = service.self_audit().await.unwrap();
+        assert_eq!(audit.mismatches.len(), 1);
+        assert_eq!(audit.mismatches[0].artifact, "seal");
+        assert_eq!(
+            service.generate_signed_report().await.unwrap_err().code(),
+            "self_audit_failed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_legacy_seals_pass_audit_and_reseal() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let signer = Arc::new(crate::LocalSigner::generate());
+        service.set_signer(signer.clone()).await;
+        service
+            .record_burn_proof("sealed".to_string(), Amount::from_sat(5))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+
+        // A seal written before the commitment covered sums
+        let epoch = service.storage.get_epoch(0).unwrap().unwrap();
+        let legacy = epoch.legacy_commitment().unwrap();
+        assert_ne!(legacy, epoch.commitment().unwrap());
+        service
+            .storage
+            .finalize_epoch(&FinalizedEpoch {
+                epoch_id: 0,
+                commitment: legacy,
+                signature: Some(ReportSignature {
+                    public_key: signer.public_key(),
+                    signature: signer.sign(&legacy).await.unwrap(),
+                }),
+                finalized_at: Utc::now(),
+            })
+            .unwrap();
+
+        let audit = service.self_audit().await.unwrap();
+        assert!(audit.is_clean());
+        assert_eq!(audit.legacy_seals, vec![0]);
+        service.generate_signed_report().await.unwrap();
+
+        assert_eq!(service.reseal_epochs().await.unwrap(), vec![0]);
+        let seal = service.finalized_epoch(0).unwrap().unwrap();
+        assert_eq!(seal.commitment, service.epoch_commitment(0).unwrap());
+        let audit = service.self_audit().await.unwrap();
+        assert!(audit.is_clean() && audit.legacy_seals.is_empty());
+        assert!(matches!(
+            service.audit_log().unwrap().last().unwrap().operation,
+            AuditOperation::Reseal { .. }
+        ));
+        assert!(service.reseal_epochs().await.unwrap().is_empty());
+
+        // A seal matching neither scheme is left for an operator to look at
+        service
+            .storage
+            .finalize_epoch(&FinalizedEpoch {
+                epoch_id: 1,
+                commitment: legacy,
+                signature: None,
+                finalized_at: Utc::now(),
+            })
+            .unwrap();
+        assert!(matches!(
+            service.reseal_epochs().await,
+            Err(PolError::InvalidEpoch(_))
+        ));
+    }
+
+    // Token from the NUT-00 examples: 2 + 8 sat from keyset 009a1f293253e41e
+    const V3_TOKEN: &str = "cashuAeyJ0b2tlbiI6W3sibWludCI6Imh0dHBzOi8vODMzMy5zcGFjZTozMzM4IiwicHJvb2ZzIjpbeyJhbW91bnQiOjIsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6IjQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzciLCJDIjoiMDJiYzkwOTc5OTdkODFhZmIyY2M3MzQ2YjVlNDM0NWE5MzQ2YmQyYTUwNmViNzk1ODU5OGE3MmYwY2Y4NTE2M2VhIn0seyJhbW91bnQiOjgsImlkIjoiMDA5YTFmMjkzMjUzZTQxZSIsInNlY3JldCI6ImZlMTUxMDkzMTRlNjFkNzc1NmIwZjhlZTBmMjNhNjI0YWNhYTNmNGUwNDJmNjE0MzNjNzI4YzcwNTdiOTMxYmUiLCJDIjoiMDI5ZThlNTA1MGI4OTBhN2Q2YzA5NjhkYjE2YmMxZDVkNWZhMDQwZWExZGUyODRmNmVjNjlkNjEyOTlmNjcxMDU5In1dfV0sInVuaXQiOiJzYXQiLCJtZW1vIjoiVGhhbmsgeW91LiJ9";
+
+    #[tokio::test]
+    async fn test_record_from_token() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let recorded = service
+            .record_from_token(V3_TOKEN, TokenDirection::Mint(None))
+            .await
+            .unwrap();
+        assert_eq!(recorded, 2);
+
+        let report = service.generate_report().await.unwrap();
+        let epoch = &report.epoch_reports[0];
+        assert_eq!(epoch.outstanding_balance, Amount::from_sat(10));
+        assert!(epoch
+            .mint_proofs
+            .iter()
+            .all(|p| p.proof.keyset_id.to_string() == "009a1f293253e41e"));
+
+        let melt = MeltQuoteInfo {
+            quote_id: Some("melt-1".to_string()),
+            ..Default::default()
+        };
+        service
+            .record_from_token(V3_TOKEN, TokenDirection::Burn(Some(melt)))
+            .await
+            .unwrap();
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.total_outstanding_balance, Amount::ZERO);
+        assert_eq!(
+            service.burn_proofs_for_melt("melt-1").await.unwrap().len(),
+            2
+        );
+
+        assert!(service
+            .record_from_token("cashuBnotatoken", TokenDirection::Mint(None))
+            .await
+            .is_err());
+    }
+
+    // V4 token with 1 sat from keyset 00ad268c4d1f5826 and 2 + 8 sat from 009a1f293253e41e
+    const V4_TOKEN: &str = "cashuBo2F0gqJhaUgArSaMTR9YJmFwgaNhYQFhc3hAOWE2ZGJiODQ3YmQyMzJiYTc2ZGIwZGYxOTcyMTZiMjlkM2I4Y2MxNDU1M2NkMjc4MjdmYzFjYzk0MmZlZGI0ZWFjWCEDhhhUP_trhpXfStS6vN6So0qWvc2X3O4NfM-Y1HISZ5KiYWlIAJofKTJT5B5hcIKjYWECYXN4QDQwNzkxNWJjMjEyYmU2MWE3N2UzZTZkMmFlYjRjNzI3OTgwYmRhNTFjZDA2YTZhZmMyOWUyODYxNzY4YTc4MzdhY1ghAryQl5l9ga-yzHNGteQ0WpNGvSpQbreVhZinLwz4UWPqo2FhCGFzeEBmZTE1MTA5MzE0ZTYxZDc3NTZiMGY4ZWUwZjIzYTYyNGFjYWEzZjRlMDQyZjYxNDMzYzcyOGM3MDU3YjkzMWJlYWNYIQKejlBQuJCn1sCWjbFrwdXV-gQOod4oT27GnWEpn2cQWWFtdWh0dHA6Ly9sb2NhbGhvc3Q6MzMzOGF1Y3NhdA";
+
+    #[tokio::test]
+    async fn test_record_from_v4_token_with_several_keysets() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let recorded = service
+            .record_from_token(V4_TOKEN, TokenDirection::Mint(None))
+            .await
+            .unwrap();
+        assert_eq!(recorded, 3);
+
+        let report = service.generate_report().await.unwrap();
+        let epoch = &report.epoch_reports[0];
+        assert_eq!(epoch.outstanding_balance, Amount::from_sat(11));
+        let mut keysets: Vec<String> = epoch
+            .mint_proofs
+            .iter()
+            .map(|p| p.proof.keyset_id.to_string())
+            .collect();
+        keysets.sort();
+        assert_eq!(
+            keysets,
+            ["009a1f293253e41e", "009a1f293253e41e", "00ad268c4d1f5826"]
+        );
+
+        // The V3 and V4 tokens share two proofs, so burning the V3 one
+        // leaves only the 1 sat proof outstanding
+        service
+            .record_from_token(V3_TOKEN, TokenDirection::Burn(None))
+            .await
+            .unwrap();
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.total_outstanding_balance, Amount::from_sat(1));
+    }
+
+    #[tokio::test]
+    async fn test_record_from_token_checks_the_mint() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service
+            .set_mint_url(MintUrl::from_str("https://mint.example").unwrap())
+            .await;
+
+        assert!(matches!(
+            service
+                .record_from_token(V3_TOKEN, TokenDirection::Mint(None))
+                .await,
+            Err(PolError::InvalidProof(_))
+        ));
+        let report = service.generate_report().await.unwrap();
+        assert!(report.epoch_reports[0].mint_proofs.is_empty());
+
+        service
+            .set_mint_url(MintUrl::from_str("https://8333.space:3338").unwrap())
+            .await;
+        let recorded = service
+            .record_from_token(V3_TOKEN, TokenDirection::Mint(None))
+            .await
+            .unwrap();
+        assert_eq!(recorded, 2);
+    }
+
+    #[tokio::test]
+    async fn test_record_from_v4_token_checks_the_mint() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        // Refused as a whole, across all of its keysets
+        service
+            .set_mint_url(MintUrl::from_str("https://8333.space:3338").unwrap())
+            .await;
+        assert!(matches!(
+            service
+                .record_from_token(V4_TOKEN, TokenDirection::Mint(None))
+                .await,
+            Err(PolError::InvalidProof(_))
+        ));
+        let report = service.generate_report().await.unwrap();
+        assert!(report.epoch_reports[0].mint_proofs.is_empty());
+
+        service
+            .set_mint_url(MintUrl::from_str("http://localhost:3338").unwrap())
+            .await;
+        let recorded = service
+            .record_from_token(V4_TOKEN, TokenDirection::Mint(None))
+            .await
+            .unwrap();
+        assert_eq!(recorded, 3);
+    }
+
+    #[tokio::test]
+    async fn test_reports_carry_external_observations() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        assert!(service
+            .generate_report()
+            .await
+            .unwrap()
+            .external_observations
+            .is_empty());
+
+        let observation = ExternalObservation {
+            mint_url: "https://mint.example".to_string(),
+            polls: 3,
+            last_polled: Utc::now(),
+            proofs_checked: 10,
+            spent_observed: 2,
+        };
+        service.storage.save_observation(&observation).unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.external_observations, vec![observation]);
+    }
+
+    #[tokio::test]
+    async fn test_follow_mint_records_spends_across_polls() {
+        use crate::test_utils::mock_mint::MockMint;
+
+        let mint = MockMint::start(1).await.unwrap();
+        let keyset_id = mint.keyset_ids()[0];
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let proofs = mint.mint(keyset_id, "quote", 7).await.unwrap();
+        for proof in &proofs {
+            let amount = Amount::from_sat(proof.amount.into());
+            service
+                .record_mint_proof(proof.clone(), amount)
+                .await
+                .unwrap();
+        }
+
+        let first = service.follow_mint(mint.url()).await.unwrap();
+        assert_eq!((first.polls, first.proofs_checked), (1, 3));
+        assert_eq!(first.spent_observed, 0);
+        assert_eq!(service.keysets().unwrap()[0].id, keyset_id);
+
+        mint.melt("melt", &proofs[..2]).await.unwrap();
+        let second = service.follow_mint(mint.url()).await.unwrap();
+        assert_eq!((second.polls, second.proofs_checked), (2, 3));
+        assert_eq!(second.spent_observed, 2);
+
+        // Spends already recorded are not checked or counted again
+        let third = service.follow_mint(mint.url()).await.unwrap();
+        assert_eq!((third.polls, third.proofs_checked), (3, 1));
+        assert_eq!(third.spent_observed, 2);
+
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.epoch_reports[0].burn_proofs.len(), 2);
+        assert_eq!(report.external_observations, vec![third]);
+    }
+
+    #[tokio::test]
+    async fn test_issuance_receipt_checks_against_commitment() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let proof = create_sample_proof(keyset_id, CashuAmount::from(64u64));
+
+        assert!(service
+            .record_mint_proof_with_receipt(proof.clone(), Amount::from_sat(64))
+            .await
+            .is_err());
+
+        service
+            .set_signer(Arc::new(crate::LocalSigner::generate()))
+            .await;
+        let receipt = service
+            .record_mint_proof_with_receipt(proof, Amount::from_sat(64))
+            .await
+            .unwrap();
+        receipt.verify().unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        let commitment = report.epoch_reports[0].commitment;
+        let inclusion = service.inclusion_proofs(&receipt.y).await.unwrap();
+        assert!(receipt.verify_inclusion(&inclusion[0], &commitment));
+
+        let mut forged = receipt.clone();
+        forged.amount = MilliSats::from_sat(65);
+        assert!(forged.verify().is_err());
+        assert!(!forged.verify_inclusion(&inclusion[0], &commitment));
+    }
+
+    #[tokio::test]
+    async fn test_redemption_receipt_checks_against_report() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service
+            .set_signer(Arc::new(crate::LocalSigner::generate()))
+            .await;
+
+        let receipt = service
+            .record_burn_proof_with_receipt("redeemed".to_string(), Amount::from_sat(21))
+            .await
+            .unwrap();
+        assert_eq!(receipt.kind, LeafKind::Burn);
+        receipt.verify().unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        assert!(receipt.verify_in_report(&report, &[]));
+
+        // A report that drops the burn no longer honours the receipt
+        let mut curated = report.clone();
+        curated.epoch_reports[0].burn_proofs.clear();
+        assert!(!receipt.verify_in_report(&curated, &[]));
+
+        // With proof lists withheld, an inclusion proof stands in for them
+        service.set_confidential_reports(true).await;
+        let confidential = service.generate_report().await.unwrap();
+        assert!(!receipt.verify_in_report(&confidential, &[]));
+        let proofs = service.inclusion_proofs(&receipt.y).await.unwrap();
+        assert!(receipt.verify_in_report(&confidential, &proofs));
+    }
+
+    #[tokio::test]
+    async fn test_storage_stats_projects_retention() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 3, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        for i in 0..10 {
+            service
+                .record_burn_proof(format!("burn_{}", i), Amount::from_sat(1))
+                .await
+                .unwrap();
+        }
+
+        let stats = service.storage_stats(Some(1)).unwrap();
+        assert_eq!(stats.epochs.len(), 1);
+        assert_eq!(stats.burn_proofs, 10);
+        assert!(stats.epochs[0].bytes > 0);
+        assert!(stats.db_bytes >= stats.epochs[0].bytes);
+        assert_eq!(
+            stats.retention_full_at,
+            Some(stats.epochs[0].start_time + Duration::days(90))
+        );
+        // Already over a one byte limit
+        assert!(stats.disk_limit_at.is_some_and(|at| at <= Utc::now()));
+        assert!(service
+            .storage_stats(Some(u64::MAX))
+            .unwrap()
+            .disk_limit_at
+            .is_none());
+
+        // Retention too far out to represent has no projection
+        let service =
+            PolService::with_path(30, usize::MAX, temp_dir.path().join("huge.db")).unwrap();
+        service.initialize().await.unwrap();
+        assert_eq!(service.storage_stats(None).unwrap().retention_full_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_status_summarizes_epochs_and_publication() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let proof = create_sample_proof(keyset_id, CashuAmount::from(1000u64));
+        service
+            .record_mint_proof(proof, Amount::from_sat(1000))
+            .await
+            .unwrap();
+        service.rotate_epoch().await.unwrap();
+        service
+            .record_burn_proof("spent".to_string(), Amount::from_sat(300))
+            .await
+            .unwrap();
+        service
+            .storage()
+            .save_sink_state(
+                "file:out",
+                &SinkState {
+                    attempts: 1,
+                    last_attempt: Some(Utc::now()),
+                    last_error: Some("disk full".to_string()),
+                    pending_report: Some("{}".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let status = service.status().await.unwrap();
+        assert_eq!(status.current_epoch, 1);
+        assert_eq!(
+            (status.epochs, status.mint_proofs, status.burn_proofs),
+            (2, 1, 1)
+        );
+        // The burn settles part of what the earlier epoch minted
+        assert_eq!(status.outstanding, MilliSats::from_sat(700));
+        assert!(status.seconds_until_rotation > 0);
+        assert_eq!(status.publications.len(), 1);
+        assert!(status.publications[0].pending);
+    }
+
+    #[tokio::test]
+    async fn test_epoch_listing_reports_status() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        for _ in 0..2 {
+            service
+                .record_burn_proof("spent".to_string(), Amount::from_sat(5))
+                .await
+                .unwrap();
+            service.rotate_epoch().await.unwrap();
+        }
+        service.finalize_epoch(0).await.unwrap();
+
+        let listing = service.epoch_listing().await.unwrap();
+        let statuses: Vec<EpochStatus> = listing.iter().map(|e| e.status).collect();
+        assert_eq!(
+            statuses,
+            [EpochStatus::Sealed, EpochStatus::Closed, EpochStatus::Open]
+        );
+        assert_eq!(listing[1].burn_count, 1);
+        assert!(listing[2].end_time.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_epoch_details_pages_proofs() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        for i in 0..3 {
+            service
+                .record_burn_proof(format!("burn_{}", i), Amount::from_sat(5))
+                .await
+                .unwrap();
+        }
+        service.rotate_epoch().await.unwrap();
+        let seal = service.finalize_epoch(0).await.unwrap();
+
+        let details = service.epoch_details(0, Some((1, 1))).await.unwrap();
+        assert_eq!(details.status, EpochStatus::Sealed);
+        assert_eq!(details.commitment, seal.commitment);
+        assert_eq!(details.aggregates.burn_count, 3);
+        let burns = details.burn_proofs.unwrap();
+        assert_eq!((burns.total, burns.items.len()), (3, 1));
+        assert!(!serde_json::to_string(&burns).unwrap().contains("burn_"));
+
+        // Every proof lands on exactly one page, ties included
+        let mut seen = HashSet::new();
+        for offset in 0..3 {
+            let page = service.epoch_details(0, Some((offset, 1))).await.unwrap();
+            seen.insert(page.burn_proofs.unwrap().items[0].y.to_hex());
+        }
+        assert_eq!(seen.len(), 3);
+        assert!(service
+            .epoch_details(0, None)
+            .await
+            .unwrap()
+            .mint_proofs
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rotation_keeps_published_seals() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 2, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service.rotate_epoch().await.unwrap();
+        service.finalize_epoch(0).await.unwrap();
+        service
+            .storage()
+            .record_publication(&[0], &sha256::Hash::hash(b"report"))
+            .unwrap();
+
+        service.rotate_epoch().await.unwrap();
+        service.rotate_epoch().await.unwrap();
+        let ids: Vec<u64> = service
+            .epoch_listing()
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.epoch_id)
+            .collect();
+        assert_eq!(ids, [0, 1, 2, 3]);
+        assert!(service.finalized_epoch(0).unwrap().is_some());
+        assert!(service.audit_log().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_published_seals_without_force() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 10, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        for _ in 0..3 {
+            let proof = create_sample_proof(keyset_id, CashuAmount::from(1000u64));
+            service
+                .record_mint_proof(proof, Amount::from_sat(1000))
+                .await
+                .unwrap();
+            service.rotate_epoch().await.unwrap();
+        }
+        service.finalize_epoch(0).await.unwrap();
+        service
+            .storage()
+            .record_publication(&[0], &sha256::Hash::hash(b"report"))
+            .unwrap();
+
+        assert!(matches!(
+            service
+                .prune_epochs(PruneRule::KeepLatest(2), false, false)
+                .await,
+            Err(PolError::EpochFinalized(0))
+        ));
+        let planned = service
+            .prune_epochs(PruneRule::KeepLatest(2), true, true)
+            .await
+            .unwrap();
+        assert_eq!(service.epoch_listing().await.unwrap().len(), 4);
+
+        let pruned = service
+            .prune_epochs(PruneRule::KeepLatest(2), true, false)
+            .await
+            .unwrap();
+        let pruned_ids: Vec<u64> = pruned.iter().map(|e| e.epoch_id).collect();
+        assert_eq!(pruned_ids, [0, 1]);
+        assert_eq!(planned.len(), pruned.len());
+        // Pruned liabilities open the oldest retained epoch
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(report.epoch_reports.len(), 2);
+        assert_eq!(
+            report.epoch_reports[0].opening_balance,
+            Amount::from_sat(2000)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_batch_parses_json_lines() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let proof = create_sample_proof(keyset_id, CashuAmount::from(64u64));
+        let lines = [
+            serde_json::json!({ "type": "mint", "proof": proof }).to_string(),
+            r#"{"type":"burn","secret":"spent","amount":16}"#.to_string(),
+        ];
+        let records: Vec<ProofRecord> = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        // A repeated line is stored, counted and announced once
+        let mut events = service.subscribe_events();
+        let mut repeated = records.clone();
+        repeated.push(records[0].clone());
+        assert_eq!(service.record_batch(repeated).await.unwrap(), 2);
+        assert!(events.try_recv().is_ok());
+        assert!(events.try_recv().is_ok());
+        assert!(events.try_recv().is_err());
+
+        let summary = service.storage().get_epoch_summary(0).unwrap().unwrap();
+        assert_eq!(summary.minted, MilliSats::from_sat(64));
+        assert_eq!(summary.burned, MilliSats::from_sat(16));
+        assert_eq!((summary.mint_count, summary.burn_count), (1, 1));
+
+        // An amount that overflows in msat is refused, not saturated
+        let huge: ProofRecord = serde_json::from_str(&format!(
+            r#"{{"type":"burn","secret":"huge","amount":{}}}"#,
+            u64::MAX
+        ))
+        .unwrap();
+        assert!(matches!(
+            service.record_batch(vec![huge]).await,
+            Err(PolError::InvalidAmount(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_rotation_while_recording_keeps_closed_epochs() {
+        let temp_dir = tempdir().unwrap();
+        let service =
+            Arc::new(PolService::with_path(7, 24, temp_dir.path().join("test.db")).unwrap());
+        service.initialize().await.unwrap();
+
+        let writers: Vec<_> = (0..32)
+            .map(|i| {
+                let service = service.clone();
+                tokio::spawn(async move {
+                    service
+                        .record_burn_proof(format!("racing_{}", i), Amount::from_sat(1))
+                        .await
+                })
+            })
+            .collect();
+        for _ in 0..4 {
+            service.rotate_epoch().await.unwrap();
+        }
+        for writer in writers {
+            writer.await.unwrap().unwrap();
+        }
+
+        // Nothing landed in an epoch after it closed, so the history logged
+        // at each rotation still matches
+        let current_epoch = service.current_epoch().await;
+        let epochs = service.storage().list_epochs().unwrap();
+        let burned: usize = epochs.iter().map(|e| e.burn_proofs.len()).sum();
+        assert_eq!(burned, 32);
+        let closed: Vec<_> = epochs
+            .into_iter()
+            .filter(|e| e.epoch_id < current_epoch)
+            .collect();
+        assert!(service.history_updates(&closed).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_liability_series_buckets_proofs() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap();
+        let start = Utc::now() - Duration::days(3);
+        service.initialize_from(start).await.unwrap();
+
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        for (day, amount) in [(0, 1000u64), (1, 500)] {
+            let proof = create_sample_proof(keyset_id, CashuAmount::from(amount));
+            service
+                .record_mint_proof_at(
+                    proof,
+                    Amount::from_sat(amount),
+                    start + Duration::days(day) + Duration::hours(1),
+                )
+                .await
+                .unwrap();
+        }
+        service
+            .record_burn_proof_at(
+                "spent".to_string(),
+                Amount::from_sat(300),
+                start + Duration::days(1) + Duration::hours(2),
+            )
+            .await
+            .unwrap();
+
+        let series = service
+            .liability_series(
+                start + Duration::days(1),
+                start + Duration::days(3),
+                Duration::days(1),
+            )
+            .unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].issued, MilliSats::from_sat(500));
+        assert_eq!(series[0].redeemed, MilliSats::from_sat(300));
+        // The day before the range carries in as the opening balance
+        assert_eq!(series[0].outstanding, MilliSats::from_sat(1200));
+        assert_eq!(series[1].outstanding, MilliSats::from_sat(1200));
+    }
+
+    #[tokio::test]
+    async fn test_liability_series_matches_status() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(7, 4, temp_dir.path().join("test.db")).unwrap();
+        let start = Utc::now() - Duration::days(2);
+        service.initialize_from(start).await.unwrap();
+
+        // A redemption recorded ahead of its issuance still nets against it
+        service
+            .record_burn_proof_at(
+                "early".to_string(),
+                Amount::from_sat(300),
+                start + Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        let proof =
+            create_sample_proof(Id::from_bytes(&[0; 8]).unwrap(), CashuAmount::from(1000u64));
+        service
+            .record_mint_proof_at(proof, Amount::from_sat(1000), start + Duration::hours(2))
+            .await
+            .unwrap();
+
+        let series = service
+            .liability_series(start, start + Duration::hours(3), Duration::hours(1))
+            .unwrap();
+        let status = service.status().await.unwrap();
+        assert_eq!(status.outstanding, MilliSats::from_sat(700));
+        assert_eq!(series[1].outstanding, MilliSats::ZERO);
+        assert_eq!(series[2].outstanding, status.outstanding);
+
+        assert!(matches!(
+            service.liability_series(start, start, Duration::days(1)),
+            Err(PolError::InvalidRange(_))
+        ));
     }
 }
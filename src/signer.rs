@@ -0,0 +1,255 @@
+use crate::types::{PolError, PolReport, ReportSignature, SignaturePolicy, SignedReport};
+use async_trait::async_trait;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::{
+    schnorr::Signature, All, Keypair, Message, Secp256k1, SecretKey, XOnlyPublicKey,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Produces Schnorr signatures over 32-byte commitments.
+#[async_trait]
+pub trait Signer: Send + Sync {
+    fn public_key(&self) -> XOnlyPublicKey;
+
+    async fn sign(&self, commitment: &sha256::Hash) -> Result<Signature, PolError>;
+}
+
+/// Signs with a secret key held in process memory.
+pub struct LocalSigner {
+    secp: Secp256k1<All>,
+    keypair: Keypair,
+}
+
+impl LocalSigner {
+    pub fn new(secret_key: SecretKey) -> Self {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &secret_key);
+        Self { secp, keypair }
+    }
+
+    pub fn generate() -> Self {
+        loop {
+            // Out of range scalars are astronomically unlikely, but retry anyway
+            if let Ok(secret_key) = SecretKey::from_slice(&rand::random::<[u8; 32]>()) {
+                return Self::new(secret_key);
+            }
+        }
+    }
+
+    pub fn from_hex(secret_key: &str) -> Result<Self, PolError> {
+        let secret_key = SecretKey::from_str(secret_key.trim())
+            .map_err(|e| PolError::SigningFailed(format!("Invalid secret key: {}", e)))?;
+        Ok(Self::new(secret_key))
+    }
+
+    /// Loads a hex-encoded secret key from a file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, PolError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| PolError::SigningFailed(format!("Cannot read key file: {}", e)))?;
+        Self::from_hex(&contents)
+    }
+}
+
+#[async_trait]
+impl Signer for LocalSigner {
+    fn public_key(&self) -> XOnlyPublicKey {
+        self.keypair.x_only_public_key().0
+    }
+
+    async fn sign(&self, commitment: &sha256::Hash) -> Result<Signature, PolError> {
+        let message = Message::from_digest(commitment.to_byte_array());
+        Ok(self.secp.sign_schnorr_no_aux_rand(&message, &self.keypair))
+    }
+}
+
+#[derive(Serialize)]
+struct RemoteSignRequest {
+    commitment: String,
+}
+
+#[derive(Deserialize)]
+struct RemoteSignResponse {
+    signature: Signature,
+}
+
+/// Delegates signing to a remote service (e.g. in front of an HSM).
+///
+/// The remote endpoint receives `{"commitment": "<hex>"}` and must answer
+/// with `{"signature": "<hex>"}`; the returned signature is checked against
+/// the configured public key before use.
+pub struct RemoteSigner {
+    url: String,
+    public_key: XOnlyPublicKey,
+    client: reqwest::Client,
+}
+
+impl RemoteSigner {
+    pub fn new(url: impl Into<String>, public_key: XOnlyPublicKey) -> Self {
+        Self {
+            url: url.into(),
+            public_key,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> XOnlyPublicKey {
+        self.public_key
+    }
+
+    async fn sign(&self, commitment: &sha256::Hash) -> Result<Signature, PolError> {
+        let response: RemoteSignResponse = self
+            .client
+            .post(&self.url)
+            .json(&RemoteSignRequest {
+                commitment: commitment.to_string(),
+            })
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| PolError::SigningFailed(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| PolError::SigningFailed(e.to_string()))?;
+
+        verify_signature(commitment, &response.signature, &self.public_key)?;
+        Ok(response.signature)
+    }
+}
+
+pub fn verify_signature(
+    commitment: &sha256::Hash,
+    signature: &Signature,
+    public_key: &XOnlyPublicKey,
+) -> Result<(), PolError> {
+    let message = Message::from_digest(commitment.to_byte_array());
+    Secp256k1::verification_only()
+        .verify_schnorr(signature, &message, public_key)
+        .map_err(|e| PolError::InvalidSignature(e.to_string()))
+}
+
+pub async fn sign_report(
+    report: PolReport,
+    policy: SignaturePolicy,
+    signer: &dyn Signer,
+) -> Result<SignedReport, PolError> {
+    let commitment = SignedReport::commitment_for(&report, &policy)?;
+    let mut signed = SignedReport {
+        report,
+        policy,
+        commitment,
+        signatures: Vec::new(),
+    };
+
+    cosign(&mut signed, signer).await?;
+    Ok(signed)
+}
+
+/// Adds `signer`'s signature to an existing report, e.g. an auditor
+/// attesting to a report the mint already signed.
+pub async fn cosign(signed: &mut SignedReport, signer: &dyn Signer) -> Result<(), PolError> {
+    let signature = signer.sign(&signed.commitment).await?;
+    signed.add_signature(ReportSignature {
+        public_key: signer.public_key(),
+        signature,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Amount;
+    use chrono::Utc;
+
+    fn empty_report() -> PolReport {
+        PolReport {
+            epoch_reports: vec![],
+            total_outstanding_balance: Amount::from_sat(0),
+            cumulative_balances: vec![],
+            pruned_balance: Amount::from_sat(0),
+            fiat_annotation: None,
+            timestamp: Utc::now(),
+            external_observations: vec![],
+            history: None,
+            history_proofs: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signed_report_verifies() {
+        let signer = LocalSigner::generate();
+        let policy = SignaturePolicy::single(signer.public_key());
+        let signed = sign_report(empty_report(), policy.clone(), &signer)
+            .await
+            .unwrap();
+
+        signed.verify(&policy).unwrap();
+        assert_eq!(signed.signatures[0].public_key, signer.public_key());
+        assert_eq!(signed.signed_by(), vec![signer.public_key()]);
+    }
+
+    #[tokio::test]
+    async fn test_embedded_policy_is_not_trusted() {
+        let mint = LocalSigner::generate();
+        let impostor = LocalSigner::generate();
+        let signed = sign_report(
+            empty_report(),
+            SignaturePolicy::single(impostor.public_key()),
+            &impostor,
+        )
+        .await
+        .unwrap();
+
+        assert!(signed
+            .verify(&SignaturePolicy::single(mint.public_key()))
+            .is_err());
+        signed
+            .verify(&SignaturePolicy::single(impostor.public_key()))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tampered_report_fails_verification() {
+        let signer = LocalSigner::generate();
+        let policy = SignaturePolicy::single(signer.public_key());
+        let mut signed = sign_report(empty_report(), policy.clone(), &signer)
+            .await
+            .unwrap();
+
+        signed.report.total_outstanding_balance = Amount::from_sat(1);
+        assert!(signed.verify(&policy).is_err());
+        assert!(signed.signed_by().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_threshold_cosigning() {
+        let mint = LocalSigner::generate();
+        let auditor = LocalSigner::generate();
+        let outsider = LocalSigner::generate();
+        let policy = SignaturePolicy::new(
+            2,
+            vec![
+                mint.public_key(),
+                auditor.public_key(),
+                LocalSigner::generate().public_key(),
+            ],
+        )
+        .unwrap();
+
+        let mut signed = sign_report(empty_report(), policy.clone(), &mint)
+            .await
+            .unwrap();
+        assert!(signed.verify(&policy).is_err());
+
+        assert!(cosign(&mut signed, &outsider).await.is_err());
+        assert!(signed.verify(&policy).is_err());
+
+        cosign(&mut signed, &auditor).await.unwrap();
+        signed.verify(&policy).unwrap();
+        assert_eq!(signed.valid_signers(), 2);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::bench::synthetic_proof;
+use bitcoin::Amount;
+use cashu_pol::PolService;
+use cdk::nuts::nut02::Id;
+use clap::ValueEnum;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::error::Error;
+use std::time::Duration as StdDuration;
+use tokio::time::Instant;
+use tracing::info;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum AmountDistribution {
+    /// Any amount between 1 and the maximum
+    Uniform,
+    /// Powers of two up to the maximum, like ecash denominations
+    PowersOfTwo,
+}
+
+pub struct SimConfig {
+    pub duration: StdDuration,
+    /// Mean mints per second
+    pub mint_rate: f64,
+    /// Mean burns per second
+    pub burn_rate: f64,
+    pub rotate_every: Option<StdDuration>,
+    pub distribution: AmountDistribution,
+    pub max_amount: u64,
+}
+
+#[derive(Default)]
+struct SimStats {
+    mints: u64,
+    burns: u64,
+    rotations: u64,
+}
+
+/// Time until the next arrival of a Poisson process with the given rate.
+fn next_arrival(rng: &mut StdRng, rate: f64) -> Option<StdDuration> {
+    if rate <= 0.0 {
+        return None;
+    }
+    let u: f64 = rng.gen();
+    Some(StdDuration::from_secs_f64(-(1.0 - u).ln() / rate))
+}
+
+fn sample_amount(rng: &mut StdRng, distribution: AmountDistribution, max_amount: u64) -> u64 {
+    let max_amount = max_amount.max(1);
+    match distribution {
+        AmountDistribution::Uniform => rng.gen_range(1..=max_amount),
+        AmountDistribution::PowersOfTwo => 1u64 << rng.gen_range(0..=max_amount.ilog2()),
+    }
+}
+
+/// Drives the service with random mints and burns for `config.duration`,
+/// rotating epochs on a fixed schedule. Burns always redeem a previously
+/// minted proof, so the workload never goes net negative.
+pub async fn run(service: &PolService, config: &SimConfig) -> Result<(), Box<dyn Error>> {
+    let mut rng = StdRng::from_entropy();
+    let keyset_id = Id::from_bytes(&[0; 8])?;
+    let mut outstanding: Vec<(String, u64)> = Vec::new();
+    let mut stats = SimStats::default();
+
+    let now = Instant::now();
+    let deadline = now + config.duration;
+    let schedule = |rng: &mut StdRng, from: Instant, rate: f64| {
+        next_arrival(rng, rate).map_or(deadline, |delay| from + delay)
+    };
+    let mut next_mint = schedule(&mut rng, now, config.mint_rate);
+    let mut next_burn = schedule(&mut rng, now, config.burn_rate);
+    let mut next_rotation = config.rotate_every.map_or(deadline, |every| now + every);
+
+    info!(duration = ?config.duration, "Starting simulation");
+    loop {
+        let next = next_mint.min(next_burn).min(next_rotation);
+        if next >= deadline {
+            break;
+        }
+        tokio::time::sleep_until(next).await;
+
+        if next == next_rotation {
+            service.rotate_epoch().await?;
+            stats.rotations += 1;
+            next_rotation = config.rotate_every.map_or(deadline, |every| next + every);
+        } else if next == next_mint {
+            let amount = sample_amount(&mut rng, config.distribution, config.max_amount);
+            let proof = synthetic_proof(keyset_id, amount)?;
+            outstanding.push((proof.secret.to_string(), amount));
+            service
+                .record_mint_proof(proof, Amount::from_sat(amount))
+                .await?;
+            stats.mints += 1;
+            next_mint = schedule(&mut rng, next, config.mint_rate);
+        } else {
+            if !outstanding.is_empty() {
+                let (secret, amount) = outstanding.swap_remove(rng.gen_range(0..outstanding.len()));
+                service
+                    .record_burn_proof(secret, Amount::from_sat(amount))
+                    .await?;
+                stats.burns += 1;
+            }
+            next_burn = schedule(&mut rng, next, config.burn_rate);
+        }
+    }
+
+    let outstanding_sat: u64 = outstanding.iter().map(|(_, amount)| amount).sum();
+    info!(
+        mints = stats.mints,
+        burns = stats.burns,
+        rotations = stats.rotations,
+        outstanding_sat,
+        "Simulation finished"
+    );
+    Ok(())
+}
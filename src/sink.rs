@@ -0,0 +1,483 @@
+use crate::storage::Storage;
+use crate::types::{PolError, SignedReport};
+use async_trait::async_trait;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::{All, Keypair, Message, Secp256k1, SecretKey};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tracing::{info, warn};
+
+const RETRY_BASE_SECS: i64 = 30;
+const RETRY_MAX_SECS: i64 = 3600;
+// NIP-78 application-specific data, replaced by each newer report
+pub(crate) const NOSTR_REPORT_KIND: u16 = 30078;
+pub(crate) const NOSTR_REPORT_TAG: &str = "cashu-pol";
+pub(crate) const NOSTR_TIMEOUT_SECS: u64 = 30;
+
+/// A publication target that receives every signed report.
+#[async_trait]
+pub trait ReportSink: Send + Sync {
+    /// Stable name used to key the sink's retry state in storage.
+    fn name(&self) -> &str;
+
+    async fn publish(&self, report: &SignedReport) -> Result<(), PolError>;
+}
+
+/// Delivery bookkeeping for one sink, persisted across restarts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SinkState {
+    pub attempts: u32,
+    pub last_attempt: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// JSON of the most recent report that has not been delivered yet
+    pub pending_report: Option<String>,
+}
+
+impl SinkState {
+    pub fn next_retry_at(&self) -> Option<DateTime<Utc>> {
+        let last_attempt = self.last_attempt?;
+        self.pending_report.as_ref()?;
+
+        let exponent = self.attempts.saturating_sub(1).min(16);
+        let delay = (RETRY_BASE_SECS << exponent).min(RETRY_MAX_SECS);
+        Some(last_attempt + Duration::seconds(delay))
+    }
+}
+
+/// Writes each report as a JSON file into a directory.
+pub struct FileSink {
+    name: String,
+    dir: PathBuf,
+}
+
+impl FileSink {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        let dir = dir.into();
+        Self {
+            name: format!("file:{}", dir.display()),
+            dir,
+        }
+    }
+}
+
+#[async_trait]
+impl ReportSink for FileSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn publish(&self, report: &SignedReport) -> Result<(), PolError> {
+        let json = serde_json::to_vec_pretty(report)
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+        let path = self.dir.join(format!(
+            "pol-report-{}.json",
+            report.report.timestamp.format("%Y%m%dT%H%M%S%.3fZ")
+        ));
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+        tokio::fs::write(&path, json)
+            .await
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// POSTs each report as JSON to an HTTPS endpoint.
+pub struct HttpSink {
+    name: String,
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        let url = url.into();
+        Self {
+            name: format!("http:{}", url),
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ReportSink for HttpSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn publish(&self, report: &SignedReport) -> Result<(), PolError> {
+        self.client
+            .post(&self.url)
+            .json(report)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// NIP-01: the id is the hash of the compact serialized event.
+pub(crate) fn nostr_event_id(
+    pubkey: &str,
+    created_at: i64,
+    tags: &serde_json::Value,
+    content: &str,
+) -> sha256::Hash {
+    let serialized = json!([0, pubkey, created_at, NOSTR_REPORT_KIND, tags, content]);
+    sha256::Hash::hash(serialized.to_string().as_bytes())
+}
+
+/// Publishes each report as a signed Nostr event to a relay.
+pub struct NostrSink {
+    name: String,
+    relay: String,
+    secp: Secp256k1<All>,
+    keypair: Keypair,
+}
+
+impl NostrSink {
+    pub fn new(relay: impl Into<String>, secret_key: SecretKey) -> Self {
+        let relay = relay.into();
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &secret_key);
+        Self {
+            name: format!("nostr:{}", relay),
+            relay,
+            secp,
+            keypair,
+        }
+    }
+
+    pub(crate) fn event(
+        &self,
+        report: &SignedReport,
+        created_at: i64,
+    ) -> Result<serde_json::Value, PolError> {
+        let content = serde_json::to_string(report)
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+        let pubkey = self.keypair.x_only_public_key().0.to_string();
+        let tags = json!([["d", NOSTR_REPORT_TAG]]);
+        let id = nostr_event_id(&pubkey, created_at, &tags, &content);
+        let signature = self
+            .secp
+            .sign_schnorr_no_aux_rand(&Message::from_digest(id.to_byte_array()), &self.keypair);
+
+        Ok(json!({
+            "id": id.to_string(),
+            "pubkey": pubkey,
+            "created_at": created_at,
+            "kind": NOSTR_REPORT_KIND,
+            "tags": tags,
+            "content": content,
+            "sig": signature.to_string(),
+        }))
+    }
+
+    async fn send(&self, event: serde_json::Value) -> Result<(), PolError> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(self.relay.as_str())
+            .await
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+        let id = event["id"].clone();
+        socket
+            .send(WsMessage::Text(json!(["EVENT", event]).to_string()))
+            .await
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+
+        // The relay acknowledges with ["OK", <id>, <accepted>, <message>]
+        while let Some(message) = socket.next().await {
+            let message = message.map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+            let WsMessage::Text(text) = message else {
+                continue;
+            };
+            let Ok(reply) = serde_json::from_str::<serde_json::Value>(&text) else {
+                continue;
+            };
+            if reply[0] != "OK" || reply[1] != id {
+                continue;
+            }
+
+            let _ = socket.close(None).await;
+            return match reply[2].as_bool() {
+                Some(true) => Ok(()),
+                _ => Err(PolError::PublicationFailed(format!(
+                    "Relay rejected the event: {}",
+                    reply[3].as_str().unwrap_or_default()
+                ))),
+            };
+        }
+
+        Err(PolError::PublicationFailed(
+            "Relay closed the connection before acknowledging".to_string(),
+        ))
+    }
+}
+
+#[async_trait]
+impl ReportSink for NostrSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn publish(&self, report: &SignedReport) -> Result<(), PolError> {
+        let event = self.event(report, Utc::now().timestamp())?;
+        tokio::time::timeout(
+            std::time::Duration::from_secs(NOSTR_TIMEOUT_SECS),
+            self.send(event),
+        )
+        .await
+        .map_err(|_| PolError::PublicationFailed("Relay timed out".to_string()))?
+    }
+}
+
+#[derive(Deserialize)]
+struct IpfsAddResponse {
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+/// Adds and pins each report through an IPFS node's HTTP RPC API.
+pub struct IpfsSink {
+    name: String,
+    api_url: String,
+    client: reqwest::Client,
+}
+
+impl IpfsSink {
+    /// `api_url` is the node's RPC endpoint, e.g. `http://127.0.0.1:5001`.
+    pub fn new(api_url: impl Into<String>) -> Self {
+        let api_url = api_url.into();
+        Self {
+            name: format!("ipfs:{}", api_url),
+            api_url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ReportSink for IpfsSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn publish(&self, report: &SignedReport) -> Result<(), PolError> {
+        let json = serde_json::to_vec_pretty(report)
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+        let file = reqwest::multipart::Part::bytes(json)
+            .file_name("pol-report.json")
+            .mime_str("application/json")
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+
+        let added: IpfsAddResponse = self
+            .client
+            .post(format!(
+                "{}/api/v0/add?pin=true",
+                self.api_url.trim_end_matches('/')
+            ))
+            .multipart(reqwest::multipart::Form::new().part("file", file))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| PolError::PublicationFailed(e.to_string()))?;
+
+        info!(sink = %self.name, cid = %added.hash, "Report pinned");
+        Ok(())
+    }
+}
+
+async fn deliver(
+    storage: &Storage,
+    sink: &dyn ReportSink,
+    report: &SignedReport,
+    mut state: SinkState,
+) -> Result<(), PolError> {
+    state.last_attempt = Some(Utc::now());
+
+    match sink.publish(report).await {
+        Ok(()) => {
+            info!(sink = sink.name(), "Report published");
+            state.attempts = 0;
+            state.last_success = state.last_attempt;
+            state.last_error = None;
+            state.pending_report = None;
+        }
+        Err(e) => {
+            warn!(sink = sink.name(), error = %e, "Report publication failed");
+            state.attempts += 1;
+            state.last_error = Some(e.to_string());
+            state.pending_report = Some(
+                serde_json::to_string(report)
+                    .map_err(|e| PolError::DatabaseSerializationError(e.into()))?,
+            );
+        }
+    }
+
+    storage.save_sink_state(sink.name(), &state)
+}
+
+/// Publishes a freshly signed report to every sink. A newer report
+/// supersedes whatever was still pending for a sink.
+pub(crate) async fn fan_out(
+    storage: &Storage,
+    sinks: &[Arc<dyn ReportSink>],
+    report: &SignedReport,
+) -> Result<(), PolError> {
+    for sink in sinks {
+        let state = storage.get_sink_state(sink.name())?.unwrap_or_default();
+        deliver(storage, sink.as_ref(), report, state).await?;
+    }
+
+    Ok(())
+}
+
+/// Retries sinks whose last delivery failed and whose backoff has elapsed.
+pub(crate) async fn retry_pending(
+    storage: &Storage,
+    sinks: &[Arc<dyn ReportSink>],
+) -> Result<usize, PolError> {
+    let now = Utc::now();
+    let mut retried = 0;
+
+    for sink in sinks {
+        let Some(state) = storage.get_sink_state(sink.name())? else {
+            continue;
+        };
+        let (Some(retry_at), Some(pending)) = (state.next_retry_at(), &state.pending_report) else {
+            continue;
+        };
+        if retry_at > now {
+            continue;
+        }
+
+        let report: SignedReport = serde_json::from_str(pending)
+            .map_err(|e| PolError::DatabaseDeserializationError(e.into()))?;
+        deliver(storage, sink.as_ref(), &report, state).await?;
+        retried += 1;
+    }
+
+    Ok(retried)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LocalSigner, PolService};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::tempdir;
+
+    struct FlakySink {
+        fail: AtomicBool,
+    }
+
+    #[async_trait]
+    impl ReportSink for FlakySink {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn publish(&self, _report: &SignedReport) -> Result<(), PolError> {
+            if self.fail.load(Ordering::SeqCst) {
+                Err(PolError::PublicationFailed("unreachable".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reports_fan_out_to_sinks() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let reports_dir = temp_dir.path().join("reports");
+        service.set_signer(Arc::new(LocalSigner::generate())).await;
+        service
+            .add_report_sink(Arc::new(FileSink::new(&reports_dir)))
+            .await;
+        service.generate_signed_report().await.unwrap();
+
+        let written = std::fs::read_dir(&reports_dir).unwrap().count();
+        assert_eq!(written, 1);
+    }
+
+    #[tokio::test]
+    async fn test_nostr_event_is_signed_over_its_id() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service.set_signer(Arc::new(LocalSigner::generate())).await;
+        let report = service.generate_signed_report().await.unwrap();
+
+        let secret_key = SecretKey::from_slice(&[7; 32]).unwrap();
+        let sink = NostrSink::new("wss://relay.example", secret_key);
+        let event = sink.event(&report, 1_700_000_000).unwrap();
+
+        let serialized = json!([
+            0,
+            event["pubkey"],
+            event["created_at"],
+            event["kind"],
+            event["tags"],
+            event["content"]
+        ]);
+        let id = sha256::Hash::hash(serialized.to_string().as_bytes());
+        assert_eq!(event["id"], id.to_string());
+
+        let signature: bitcoin::secp256k1::schnorr::Signature =
+            event["sig"].as_str().unwrap().parse().unwrap();
+        let public_key = event["pubkey"].as_str().unwrap().parse().unwrap();
+        crate::verify_signature(&id, &signature, &public_key).unwrap();
+
+        let content: SignedReport =
+            serde_json::from_str(event["content"].as_str().unwrap()).unwrap();
+        assert_eq!(content.commitment, report.commitment);
+    }
+
+    #[tokio::test]
+    async fn test_failed_publication_is_retried() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        let sink = Arc::new(FlakySink {
+            fail: AtomicBool::new(true),
+        });
+        service.set_signer(Arc::new(LocalSigner::generate())).await;
+        service.add_report_sink(sink.clone()).await;
+        service.generate_signed_report().await.unwrap();
+
+        let state = service.sink_state("flaky").unwrap().unwrap();
+        assert_eq!(state.attempts, 1);
+        assert!(state.pending_report.is_some());
+
+        // Not due yet, so nothing is retried
+        assert_eq!(service.retry_pending_publications().await.unwrap(), 0);
+
+        sink.fail.store(false, Ordering::SeqCst);
+        let mut due = state.clone();
+        due.last_attempt = Some(Utc::now() - Duration::seconds(RETRY_MAX_SECS));
+        service.storage().save_sink_state("flaky", &due).unwrap();
+
+        assert_eq!(service.retry_pending_publications().await.unwrap(), 1);
+        let state = service.sink_state("flaky").unwrap().unwrap();
+        assert_eq!(state.attempts, 0);
+        assert!(state.pending_report.is_none());
+        assert!(state.last_success.is_some());
+    }
+}
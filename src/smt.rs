@@ -0,0 +1,137 @@
+use crate::merkle::node_hash;
+use bitcoin::hashes::{sha256, Hash};
+
+/// Keys are 256-bit, so every key has its own leaf and a missing key reads
+/// as the empty leaf.
+pub const DEPTH: usize = 256;
+
+/// Hash of an empty subtree rooted at each depth, from the root (0) down to
+/// a single leaf (`DEPTH`).
+fn empty_hashes() -> Vec<sha256::Hash> {
+    let mut hashes = vec![sha256::Hash::all_zeros(); DEPTH + 1];
+    for depth in (0..DEPTH).rev() {
+        hashes[depth] = node_hash(&hashes[depth + 1], &hashes[depth + 1]);
+    }
+    hashes
+}
+
+/// Bit of `key` that picks the child below `depth`; 1 goes right.
+fn bit(key: &[u8; 32], depth: usize) -> bool {
+    key[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+/// Root of the subtree at `depth` holding `entries`, which must be sorted by
+/// key and share the path down to it.
+fn subtree(
+    entries: &[([u8; 32], sha256::Hash)],
+    depth: usize,
+    empty: &[sha256::Hash],
+) -> sha256::Hash {
+    match entries {
+        [] => empty[depth],
+        // Keys are unique, so only one entry can reach a leaf
+        [(_, leaf), ..] if depth == DEPTH => *leaf,
+        _ => {
+            let split = entries.partition_point(|(key, _)| !bit(key, depth));
+            node_hash(
+                &subtree(&entries[..split], depth + 1, empty),
+                &subtree(&entries[split..], depth + 1, empty),
+            )
+        }
+    }
+}
+
+/// Root over `entries`, sorted by key with no duplicates.
+pub fn root(entries: &[([u8; 32], sha256::Hash)]) -> sha256::Hash {
+    subtree(entries, 0, &empty_hashes())
+}
+
+/// Siblings on the path to `key` that are not empty subtrees, each with the
+/// depth it sits at. Works whether or not `key` is present.
+pub fn proof(entries: &[([u8; 32], sha256::Hash)], key: &[u8; 32]) -> Vec<(u16, sha256::Hash)> {
+    let empty = empty_hashes();
+    let mut siblings = Vec::new();
+    let mut entries = entries;
+    for depth in 0..DEPTH {
+        if entries.is_empty() {
+            break;
+        }
+        let split = entries.partition_point(|(key, _)| !bit(key, depth));
+        let (left, right) = entries.split_at(split);
+        let (path, sibling) = if bit(key, depth) {
+            (right, left)
+        } else {
+            (left, right)
+        };
+        if !sibling.is_empty() {
+            siblings.push(((depth + 1) as u16, subtree(sibling, depth + 1, &empty)));
+        }
+        entries = path;
+    }
+    siblings
+}
+
+/// Recomputes the root from the leaf at `key`, `None` meaning the key is
+/// absent. `None` if the siblings are out of order or out of range.
+pub fn root_from_proof(
+    key: &[u8; 32],
+    leaf: Option<sha256::Hash>,
+    siblings: &[(u16, sha256::Hash)],
+) -> Option<sha256::Hash> {
+    if siblings.windows(2).any(|w| w[0].0 >= w[1].0)
+        || siblings
+            .iter()
+            .any(|(depth, _)| *depth == 0 || *depth as usize > DEPTH)
+    {
+        return None;
+    }
+
+    let empty = empty_hashes();
+    let mut node = leaf.unwrap_or(empty[DEPTH]);
+    let mut siblings = siblings.iter().rev().peekable();
+    for depth in (1..=DEPTH).rev() {
+        let sibling = match siblings.next_if(|(d, _)| *d as usize == depth) {
+            Some((_, hash)) => *hash,
+            None => empty[depth],
+        };
+        node = if bit(key, depth - 1) {
+            node_hash(&sibling, &node)
+        } else {
+            node_hash(&node, &sibling)
+        };
+    }
+    Some(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u8) -> [u8; 32] {
+        sha256::Hash::hash(&[i]).to_byte_array()
+    }
+
+    #[test]
+    fn test_inclusion_and_non_inclusion() {
+        let mut entries: Vec<_> = (0u8..6)
+            .map(|i| (key(i), sha256::Hash::hash(&[i, i])))
+            .collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        let tree_root = root(&entries);
+        assert_eq!(root(&[]), empty_hashes()[0]);
+
+        for (k, leaf) in &entries {
+            let siblings = proof(&entries, k);
+            assert_eq!(root_from_proof(k, Some(*leaf), &siblings), Some(tree_root));
+            assert_ne!(root_from_proof(k, None, &siblings), Some(tree_root));
+        }
+
+        let absent = key(42);
+        let siblings = proof(&entries, &absent);
+        assert_eq!(root_from_proof(&absent, None, &siblings), Some(tree_root));
+        assert_ne!(
+            root_from_proof(&absent, Some(sha256::Hash::hash(b"x")), &siblings),
+            Some(tree_root)
+        );
+    }
+}
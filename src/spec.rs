@@ -0,0 +1,274 @@
+use crate::signer::{verify_signature, Signer};
+use crate::types::{PolError, PolReport, ReportSignature, SignaturePolicy};
+use bitcoin::hashes::{sha256, Hash};
+use cdk::nuts::nut01::PublicKey;
+use cdk::nuts::nut02::Id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Version 1 commits epochs with Merkle-sum trees and the burn index;
+/// version 0 documents hashed the same leaves into plain Merkle trees.
+pub const SPEC_VERSION: &str = "cashu-pol/1";
+
+/// Unit of every amount in the document; epoch commitments cover msat
+/// leaves, so anything coarser could not be checked against them.
+pub const SPEC_UNIT: &str = "msat";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecMint {
+    pub amount: u64,
+    #[serde(rename = "Y")]
+    pub y: PublicKey,
+    #[serde(rename = "C")]
+    pub c: PublicKey,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecBurn {
+    pub amount: u64,
+    #[serde(rename = "Y")]
+    pub y: PublicKey,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecKeyset {
+    pub id: Id,
+    pub mint_proofs: Vec<SpecMint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecEpoch {
+    pub epoch: u64,
+    pub start: i64,
+    pub end: Option<i64>,
+    /// Mints grouped by the keyset that signed them
+    pub keysets: Vec<SpecKeyset>,
+    /// Burns carry no keyset, so they are listed per epoch
+    pub burn_proofs: Vec<SpecBurn>,
+    /// Mints and burns each form a Merkle-sum tree. A leaf is
+    /// `sha256(0x00 || "mint" or "burn" || Y || amount)` carrying `amount`,
+    /// with leaves sorted by hash; a node is
+    /// `sha256(0x01 || left.hash || left.sum || right.hash || right.sum)`
+    /// carrying `left.sum + right.sum`. Burns are also indexed in a sparse
+    /// Merkle tree of depth 256 keyed by `sha256(Y)`, whose leaf is the burn
+    /// leaf hash of Y's total burned amount and whose empty leaf is all
+    /// zeroes. The commitment is `sha256(epoch || start || mint_root.hash ||
+    /// mint_root.sum || burn_root.hash || burn_root.sum || burn_index)`,
+    /// integers as big-endian u64/i64 and amounts in msat, so it fixes the
+    /// epoch's totals and which Ys were never burned as well as its proof
+    /// sets.
+    pub commitment: sha256::Hash,
+}
+
+/// Report layout from the Cashu proof-of-liabilities scheme proposal:
+/// per-epoch mint and burn lists grouped by the keysets that signed them,
+/// plus the published commitments. Proofs are identified by Y, never by
+/// secret. When signed, `commitment` is the hash of the document itself
+/// with `commitment` and `signatures` left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecReport {
+    pub version: String,
+    pub unit: String,
+    pub timestamp: i64,
+    pub epochs: Vec<SpecEpoch>,
+    pub outstanding: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<sha256::Hash>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<ReportSignature>,
+}
+
+fn unix(time: DateTime<Utc>) -> i64 {
+    time.timestamp()
+}
+
+impl SpecReport {
+    pub fn from_report(report: &PolReport) -> Result<Self, PolError> {
+        let mut epochs = Vec::with_capacity(report.epoch_reports.len());
+        for epoch in &report.epoch_reports {
+            let mut keysets: BTreeMap<String, SpecKeyset> = BTreeMap::new();
+            for proof in &epoch.mint_proofs {
+                let keyset_id = proof.proof.keyset_id;
+                keysets
+                    .entry(keyset_id.to_string())
+                    .or_insert_with(|| SpecKeyset {
+                        id: keyset_id,
+                        mint_proofs: Vec::new(),
+                    })
+                    .mint_proofs
+                    .push(SpecMint {
+                        amount: proof.amount.to_msat(),
+                        y: proof.y()?,
+                        c: proof.proof.c,
+                    });
+            }
+            let mut keysets: Vec<SpecKeyset> = keysets.into_values().collect();
+            for keyset in &mut keysets {
+                keyset.mint_proofs.sort_by_key(|p| p.y.to_bytes());
+            }
+
+            let mut burn_proofs = epoch
+                .burn_proofs
+                .iter()
+                .map(|proof| {
+                    Ok(SpecBurn {
+                        amount: proof.amount.to_msat(),
+                        y: proof.y()?,
+                    })
+                })
+                .collect::<Result<Vec<_>, PolError>>()?;
+            burn_proofs.sort_by_key(|p| p.y.to_bytes());
+
+            epochs.push(SpecEpoch {
+                epoch: epoch.epoch_id,
+                start: unix(epoch.start_time),
+                end: epoch.end_time.map(unix),
+                keysets,
+                burn_proofs,
+                commitment: epoch.commitment,
+            });
+        }
+
+        Ok(Self {
+            version: SPEC_VERSION.to_string(),
+            unit: SPEC_UNIT.to_string(),
+            timestamp: unix(report.timestamp),
+            epochs,
+            outstanding: report.total_outstanding_balance.to_msat(),
+            commitment: None,
+            signatures: Vec::new(),
+        })
+    }
+
+    pub fn commitment_for(&self) -> Result<sha256::Hash, PolError> {
+        let unsigned = Self {
+            commitment: None,
+            signatures: Vec::new(),
+            ..self.clone()
+        };
+        let data = serde_json::to_vec(&unsigned)
+            .map_err(|e| PolError::ReportGenerationFailed(e.to_string()))?;
+        Ok(sha256::Hash::hash(&data))
+    }
+
+    /// Signs the document as published, so the signature covers exactly
+    /// the bytes a reader checks rather than the native report behind it.
+    pub async fn sign(mut self, signer: &dyn Signer) -> Result<Self, PolError> {
+        let commitment = self.commitment_for()?;
+        let signature = signer.sign(&commitment).await?;
+        self.commitment = Some(commitment);
+        self.signatures
+            .retain(|s| s.public_key != signer.public_key());
+        self.signatures.push(ReportSignature {
+            public_key: signer.public_key(),
+            signature,
+        });
+        Ok(self)
+    }
+
+    /// Checks the document against a policy the caller pinned out of band.
+    pub fn verify(&self, trusted: &SignaturePolicy) -> Result<(), PolError> {
+        let commitment = self.commitment_for()?;
+        if self.commitment != Some(commitment) {
+            return Err(PolError::InvalidSignature(
+                "Commitment does not match report contents".to_string(),
+            ));
+        }
+
+        let valid = self
+            .signatures
+            .iter()
+            .filter(|s| trusted.signers.contains(&s.public_key))
+            .filter(|s| verify_signature(&commitment, &s.signature, &s.public_key).is_ok())
+            .map(|s| s.public_key)
+            .collect::<HashSet<_>>()
+            .len();
+        if valid < trusted.threshold {
+            return Err(PolError::InvalidSignature(format!(
+                "{} of {} required signatures by trusted keys present",
+                valid, trusted.threshold
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::LocalSigner;
+    use crate::{create_sample_proof, PolService};
+    use bitcoin::Amount;
+    use cdk::Amount as CashuAmount;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_spec_report_groups_by_keyset() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+
+        for keyset in [[0; 8], [0, 1, 1, 1, 1, 1, 1, 1], [0; 8]] {
+            let keyset_id = Id::from_bytes(&keyset).unwrap();
+            let proof = create_sample_proof(keyset_id, CashuAmount::from(4u64));
+            service
+                .record_mint_proof(proof, Amount::from_sat(4))
+                .await
+                .unwrap();
+        }
+        service
+            .record_burn_proof("spent".to_string(), Amount::from_sat(4))
+            .await
+            .unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        let spec = SpecReport::from_report(&report).unwrap();
+        let epoch = &spec.epochs[0];
+        assert_eq!(epoch.keysets.len(), 2);
+        assert_eq!(
+            epoch
+                .keysets
+                .iter()
+                .map(|k| k.mint_proofs.len())
+                .sum::<usize>(),
+            3
+        );
+        assert_eq!(epoch.burn_proofs.len(), 1);
+        assert_eq!(spec.unit, "msat");
+        assert_eq!(spec.outstanding, 8_000);
+
+        let json = serde_json::to_value(&spec).unwrap();
+        assert!(json["epochs"][0]["burn_proofs"][0]["Y"].is_string());
+        assert!(json.get("signatures").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_signed_spec_report_covers_document() {
+        let temp_dir = tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        service
+            .record_burn_proof("spent".to_string(), Amount::from_sat(4))
+            .await
+            .unwrap();
+
+        let signer = LocalSigner::generate();
+        let policy = SignaturePolicy::single(signer.public_key());
+        let report = service.generate_report().await.unwrap();
+        let spec = SpecReport::from_report(&report)
+            .unwrap()
+            .sign(&signer)
+            .await
+            .unwrap();
+        spec.verify(&policy).unwrap();
+
+        let other = SignaturePolicy::single(LocalSigner::generate().public_key());
+        assert!(spec.verify(&other).is_err());
+
+        let mut tampered = spec.clone();
+        tampered.epochs[0].burn_proofs[0].amount += 1;
+        assert!(tampered.verify(&policy).is_err());
+    }
+}
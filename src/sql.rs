@@ -0,0 +1,241 @@
+use crate::storage::Storage;
+use crate::types::{sort_by_time_and_y, BurnProof, MilliSats, MintProof, PolError};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use std::io::Write;
+
+/// Column types are limited to ones every SQL engine accepts, and
+/// timestamps are RFC 3339 text so no engine's date parsing is assumed.
+/// Millisatoshi amounts span the whole u64 range, past what a signed
+/// BIGINT holds, so they are NUMERIC(20).
+const SCHEMA: &str = "\
+CREATE TABLE epochs (
+    epoch_id BIGINT PRIMARY KEY,
+    start_time VARCHAR(40) NOT NULL,
+    end_time VARCHAR(40),
+    opening_balance_msat NUMERIC(20) NOT NULL,
+    mint_count BIGINT NOT NULL,
+    burn_count BIGINT NOT NULL,
+    minted_msat NUMERIC(20) NOT NULL,
+    burned_msat NUMERIC(20) NOT NULL
+);
+CREATE TABLE commitments (
+    epoch_id BIGINT PRIMARY KEY,
+    commitment CHAR(64) NOT NULL,
+    sealed INTEGER NOT NULL,
+    signer_pubkey CHAR(64),
+    signature CHAR(128),
+    finalized_at VARCHAR(40)
+);
+CREATE TABLE mint_proofs (
+    epoch_id BIGINT NOT NULL,
+    y CHAR(66) NOT NULL,
+    keyset_id VARCHAR(66) NOT NULL,
+    amount_msat NUMERIC(20) NOT NULL,
+    recorded_at VARCHAR(40) NOT NULL,
+    quote_id TEXT,
+    payment_hash TEXT,
+    method TEXT
+);
+CREATE TABLE burn_proofs (
+    epoch_id BIGINT NOT NULL,
+    y CHAR(66) NOT NULL,
+    secret TEXT NOT NULL,
+    amount_msat NUMERIC(20) NOT NULL,
+    recorded_at VARCHAR(40) NOT NULL,
+    quote_id TEXT,
+    payment_hash TEXT,
+    preimage TEXT
+);
+";
+
+/// Writes the whole dataset as `CREATE TABLE` and `INSERT` statements in
+/// one transaction, loading one epoch at a time. Mint proofs are dumped by
+/// Y, as in reports, so the dump never carries spendable tokens. Closed
+/// epochs end `epoch_duration` after they start, as in reports.
+pub fn write_sql_dump<W: Write>(
+    storage: &Storage,
+    epoch_duration: Duration,
+    out: &mut W,
+) -> Result<(), PolError> {
+    write_sql(out, "BEGIN;\n")?;
+    write_sql(out, SCHEMA)?;
+
+    let current_epoch = storage.get_current_epoch()?;
+    // Running balance, as reports carry it from the oldest retained epoch
+    let mut opening = None;
+    for summary in storage.list_epoch_summaries()? {
+        let epoch = storage
+            .get_epoch(summary.epoch_id)?
+            .ok_or(PolError::EpochNotFound(summary.epoch_id))?;
+        let end_time = current_epoch
+            .filter(|current| epoch.epoch_id < *current)
+            .map(|_| epoch.start_time + epoch_duration);
+        let opening_balance = match opening {
+            Some(balance) => balance,
+            None => storage
+                .get_opening_balance(epoch.epoch_id)?
+                .unwrap_or(MilliSats::ZERO),
+        };
+        opening = Some(
+            opening_balance
+                .try_add(summary.minted)?
+                .saturating_sub(summary.burned),
+        );
+        write_sql(
+            out,
+            &format!(
+                "INSERT INTO epochs VALUES ({}, {}, {}, {}, {}, {}, {}, {});\n",
+                epoch.epoch_id,
+                time(epoch.start_time),
+                end_time.map_or("NULL".to_string(), time),
+                opening_balance.to_msat(),
+                summary.mint_count,
+                summary.burn_count,
+                summary.minted.to_msat(),
+                summary.burned.to_msat(),
+            ),
+        )?;
+
+        let seal = storage.get_finalized(epoch.epoch_id)?;
+        let commitment = match &seal {
+            Some(seal) => seal.commitment,
+            None => epoch.commitment()?,
+        };
+        let signature = seal.as_ref().and_then(|s| s.signature.as_ref());
+        write_sql(
+            out,
+            &format!(
+                "INSERT INTO commitments VALUES ({}, {}, {}, {}, {}, {});\n",
+                epoch.epoch_id,
+                text(&commitment.to_string()),
+                seal.is_some() as u8,
+                nullable(signature.map(|s| s.public_key.to_string()).as_deref()),
+                nullable(signature.map(|s| s.signature.to_string()).as_deref()),
+                seal.as_ref()
+                    .map_or("NULL".to_string(), |s| time(s.finalized_at)),
+            ),
+        )?;
+
+        let mint_proofs: Vec<&MintProof> = epoch.mint_proofs.iter().collect();
+        let mint_proofs = sort_by_time_and_y(mint_proofs, |p| Ok((p.timestamp, p.y()?)))?;
+        for proof in mint_proofs {
+            let quote = proof.quote.clone().unwrap_or_default();
+            write_sql(
+                out,
+                &format!(
+                    "INSERT INTO mint_proofs VALUES ({}, {}, {}, {}, {}, {}, {}, {});\n",
+                    epoch.epoch_id,
+                    text(&proof.y()?.to_hex()),
+                    text(&proof.proof.keyset_id.to_string()),
+                    proof.amount.to_msat(),
+                    time(proof.timestamp),
+                    nullable(quote.quote_id.as_deref()),
+                    nullable(quote.payment_hash.as_deref()),
+                    nullable(quote.method.as_deref()),
+                ),
+            )?;
+        }
+
+        let burn_proofs: Vec<&BurnProof> = epoch.burn_proofs.iter().collect();
+        let burn_proofs = sort_by_time_and_y(burn_proofs, |p| Ok((p.timestamp, p.y()?)))?;
+        for proof in burn_proofs {
+            let melt = proof.melt.clone().unwrap_or_default();
+            write_sql(
+                out,
+                &format!(
+                    "INSERT INTO burn_proofs VALUES ({}, {}, {}, {}, {}, {}, {}, {});\n",
+                    epoch.epoch_id,
+                    text(&proof.y()?.to_hex()),
+                    text(&proof.secret),
+                    proof.amount.to_msat(),
+                    time(proof.timestamp),
+                    nullable(melt.quote_id.as_deref()),
+                    nullable(melt.payment_hash.as_deref()),
+                    nullable(melt.preimage.as_deref()),
+                ),
+            )?;
+        }
+    }
+
+    write_sql(out, "COMMIT;\n")?;
+    out.flush()
+        .map_err(|e| PolError::ExportFailed(e.to_string()))
+}
+
+fn write_sql<W: Write>(out: &mut W, sql: &str) -> Result<(), PolError> {
+    out.write_all(sql.as_bytes())
+        .map_err(|e| PolError::ExportFailed(e.to_string()))
+}
+
+/// A string literal; doubling quotes is the only escape standard SQL has.
+fn text(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn nullable(value: Option<&str>) -> String {
+    value.map_or("NULL".to_string(), text)
+}
+
+fn time(value: DateTime<Utc>) -> String {
+    text(&value.to_rfc3339_opts(SecondsFormat::Micros, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_sample_mint_proof;
+    use crate::types::EpochState;
+    use cdk::{nuts::nut02::Id, Amount as CashuAmount};
+    use std::collections::HashSet;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_dump_escapes_and_covers_every_record() {
+        let temp_dir = tempdir().unwrap();
+        let storage = Storage::new(temp_dir.path().join("test.db")).unwrap();
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        for epoch_id in 0..2 {
+            let mut epoch = EpochState {
+                epoch_id,
+                start_time: Utc::now(),
+                mint_proofs: HashSet::new(),
+                burn_proofs: HashSet::new(),
+            };
+            epoch
+                .mint_proofs
+                .insert(create_sample_mint_proof(keyset_id, CashuAmount::from(64)));
+            epoch.burn_proofs.insert(BurnProof {
+                secret: format!("it's burn {}", epoch_id),
+                amount: MilliSats::from_sat(8),
+                timestamp: Utc::now(),
+                melt: None,
+            });
+            storage.save_epoch(&epoch).unwrap();
+        }
+
+        let mut out = Vec::new();
+        storage.save_current_epoch(1).unwrap();
+        write_sql_dump(&storage, Duration::hours(24), &mut out).unwrap();
+        let sql = String::from_utf8(out).unwrap();
+
+        for table in ["epochs", "commitments", "mint_proofs", "burn_proofs"] {
+            let inserts = format!("INSERT INTO {} VALUES", table);
+            assert_eq!(sql.matches(&inserts).count(), 2, "{}", table);
+        }
+        assert!(sql.contains("'it''s burn 1'"));
+        // Only the closed epoch has an end, a day after its start
+        let start = storage.get_epoch_summary(0).unwrap().unwrap().start_time;
+        assert!(sql.contains(&format!(
+            "INSERT INTO epochs VALUES (0, {}, {},",
+            time(start),
+            time(start + Duration::hours(24))
+        )));
+        let start = storage.get_epoch_summary(1).unwrap().unwrap().start_time;
+        // Epoch 1 opens with what epoch 0 closed at
+        assert!(sql.contains(&format!(
+            "INSERT INTO epochs VALUES (1, {}, NULL, 56000,",
+            time(start)
+        )));
+        assert!(sql.starts_with("BEGIN;") && sql.ends_with("COMMIT;\n"));
+    }
+}
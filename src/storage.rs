@@ -1,209 +1,2462 @@
-use crate::types::{EpochState, PolError};
-use bincode::{deserialize, serialize};
-use redb::{Database, ReadableTable, TableDefinition};
-use std::path::Path;
+use crate::codec::CodecKind;
+use crate::monitor::SeenCommitment;
+use crate::sink::SinkState;
+use crate::types::{
+    AuditEntry, BurnProof, EpochAttestation, EpochIdMode, EpochState, EpochSummary,
+    ExternalObservation, FinalizedEpoch, HistoryEntry, KeysetRecord, MilliSats, MintProof,
+    PolError,
+};
+use bincode::serialize;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Amount;
+use cdk::nuts::nut00::Proof;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use redb::{Database, ReadableTable, TableDefinition, WriteTransaction};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration as StdDuration;
 use tracing::{debug, info, instrument, warn};
 
-const EPOCHS_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("epochs");
+/// Whole-epoch blobs written before epochs were chunked. Kept after they
+/// are chunked, so a downgrade still finds its data, until dropped with
+/// [`Storage::drop_legacy_epochs`].
+const LEGACY_EPOCHS_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("epochs");
+const EPOCH_HEADERS_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("epoch_headers");
+/// (epoch id, proof kind, chunk index) to up to `CHUNK_SIZE` proofs
+const PROOF_CHUNKS_TABLE: TableDefinition<(u64, u8, u32), &[u8]> =
+    TableDefinition::new("proof_chunks");
+/// (epoch id, proof kind, proof digest) to the chunk holding the proof
+const PROOF_KEYS_TABLE: TableDefinition<(u64, u8, &[u8]), u32> = TableDefinition::new("proof_keys");
 const CURRENT_EPOCH_TABLE: TableDefinition<&str, u64> = TableDefinition::new("current_epoch");
+const SINK_STATE_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("sink_state");
+const ATTESTATIONS_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("attestations");
+const PUBLICATIONS_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("publications");
+const AUDIT_LOG_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("audit_log");
+const FINALIZED_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("finalized");
+const KEYSETS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("keysets");
+const OBSERVATIONS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("observations");
+const OPENING_BALANCES_TABLE: TableDefinition<u64, u64> = TableDefinition::new("opening_balances");
+const HISTORY_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("history");
+const SEEN_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("seen_commitments");
+const META_TABLE: TableDefinition<&str, &str> = TableDefinition::new("meta");
+
+const CODEC_KEY: &str = "epoch_codec";
+const COMPRESSION_KEY: &str = "epoch_compression";
+const EPOCH_ID_MODE_KEY: &str = "epoch_id_mode";
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+/// Schema version the retained legacy blobs were written under
+const LEGACY_LAYOUT_KEY: &str = "legacy_layout";
+const CONFIDENTIAL_KEY: &str = "confidential_key";
+
+/// Layout of the stored data. Databases from before it was recorded hold
+/// whole-epoch blobs with amounts in sats, which is version 1. Every change
+/// to a stored type gets a new version and a layout in `LegacyEpoch`, since
+/// the binary codecs cannot skip or default fields.
+const SCHEMA_VERSION: u32 = 6;
+/// First version whose legacy table only holds blobs already chunked.
+const CHUNKED_SCHEMA_VERSION: u32 = 5;
+const ZSTD_LEVEL: i32 = 3;
+
+/// Proofs per stored chunk. Recording a proof rewrites only the last chunk
+/// of its kind.
+const CHUNK_SIZE: usize = 1024;
+const MINT_CHUNK: u8 = 0;
+const BURN_CHUNK: u8 = 1;
+
+/// An epoch as stored under an older schema, generic over the proof
+/// layouts of its version.
+#[derive(Serialize, Deserialize)]
+struct LegacyEpoch<M, B> {
+    epoch_id: u64,
+    start_time: DateTime<Utc>,
+    mint_proofs: Vec<M>,
+    burn_proofs: Vec<B>,
+}
+
+/// A mint proof from before quotes were recorded.
+#[derive(Serialize, Deserialize)]
+struct UnquotedMint<A> {
+    proof: Proof,
+    amount: A,
+    timestamp: DateTime<Utc>,
+}
+
+/// A burn proof from before melts were recorded.
+#[derive(Serialize, Deserialize)]
+struct PlainBurn<A> {
+    secret: String,
+    amount: A,
+    timestamp: DateTime<Utc>,
+}
+
+/// Version 1: amounts in sats.
+type EpochV1 = LegacyEpoch<UnquotedMint<Amount>, PlainBurn<Amount>>;
+/// Version 2: amounts in millisatoshis, still without quotes.
+type EpochV2 = LegacyEpoch<UnquotedMint<MilliSats>, PlainBurn<MilliSats>>;
+/// Version 3: mint quotes, but no melts.
+type EpochV3 = LegacyEpoch<MintProof, PlainBurn<MilliSats>>;
+
+impl<A: Into<MilliSats>> From<UnquotedMint<A>> for MintProof {
+    fn from(p: UnquotedMint<A>) -> Self {
+        Self {
+            proof: p.proof,
+            amount: p.amount.into(),
+            timestamp: p.timestamp,
+            quote: None,
+        }
+    }
+}
+
+impl<A: Into<MilliSats>> From<PlainBurn<A>> for BurnProof {
+    fn from(p: PlainBurn<A>) -> Self {
+        Self {
+            secret: p.secret,
+            amount: p.amount.into(),
+            timestamp: p.timestamp,
+            melt: None,
+        }
+    }
+}
+
+impl<M: Into<MintProof>, B: Into<BurnProof>> From<LegacyEpoch<M, B>> for EpochState {
+    fn from(epoch: LegacyEpoch<M, B>) -> Self {
+        Self {
+            epoch_id: epoch.epoch_id,
+            start_time: epoch.start_time,
+            mint_proofs: epoch.mint_proofs.into_iter().map(Into::into).collect(),
+            burn_proofs: epoch.burn_proofs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A proof kind stored in chunks, with its share of the epoch summary.
+trait ChunkedProof: Clone + Eq + std::hash::Hash + Serialize + DeserializeOwned {
+    const KIND: u8;
+
+    fn count(summary: &EpochSummary) -> u64;
+
+    fn record(&self, summary: &mut EpochSummary) -> Result<(), PolError>;
+}
+
+impl ChunkedProof for MintProof {
+    const KIND: u8 = MINT_CHUNK;
+
+    fn count(summary: &EpochSummary) -> u64 {
+        summary.mint_count
+    }
+
+    fn record(&self, summary: &mut EpochSummary) -> Result<(), PolError> {
+        summary.mint_count = increment(summary.mint_count)?;
+        summary.minted = summary.minted.try_add(self.amount)?;
+        Ok(())
+    }
+}
+
+impl ChunkedProof for BurnProof {
+    const KIND: u8 = BURN_CHUNK;
+
+    fn count(summary: &EpochSummary) -> u64 {
+        summary.burn_count
+    }
+
+    fn record(&self, summary: &mut EpochSummary) -> Result<(), PolError> {
+        summary.burn_count = increment(summary.burn_count)?;
+        summary.burned = summary.burned.try_add(self.amount)?;
+        Ok(())
+    }
+}
+
+fn increment(count: u64) -> Result<u64, PolError> {
+    count
+        .checked_add(1)
+        .ok_or_else(|| PolError::InvalidAmount(format!("{} + 1 proofs overflows", count)))
+}
+
+/// Decodes a whole-epoch blob written under schema version `layout`.
+fn decode_legacy(encoding: Encoding, layout: u32, data: &[u8]) -> Result<EpochState, PolError> {
+    Ok(match layout {
+        1 => decode::<EpochV1>(encoding, data)?.into(),
+        2 => decode::<EpochV2>(encoding, data)?.into(),
+        3 => decode::<EpochV3>(encoding, data)?.into(),
+        _ => decode::<EpochState>(encoding, data)?,
+    })
+}
+
+/// How epoch records are compressed after encoding. Recorded in the
+/// database, so every epoch in one database uses the same setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// As written by databases created before compression
+    #[default]
+    None,
+    Zstd,
+}
+
+impl Compression {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "zstd" => Ok(Self::Zstd),
+            other => Err(format!("Unknown compression: {}", other)),
+        }
+    }
+}
+
+/// Codec then compression, as recorded for the database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Encoding {
+    codec: CodecKind,
+    compression: Compression,
+}
+
+/// How often and how patiently transient storage failures are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first one
+    pub max_attempts: u32,
+    pub base_delay: StdDuration,
+    pub max_delay: StdDuration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: StdDuration::from_millis(25),
+            max_delay: StdDuration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    fn delay(&self, attempt: u32) -> StdDuration {
+        self.base_delay
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay)
+    }
+
+    /// Runs `op`, retrying with exponential backoff while it fails with a
+    /// retryable error. Fatal errors are returned immediately.
+    pub(crate) async fn run<T>(
+        &self,
+        mut op: impl FnMut() -> Result<T, PolError>,
+    ) -> Result<T, PolError> {
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Err(e) if e.is_retryable() && attempt < self.max_attempts => {
+                    let delay = self.delay(attempt);
+                    warn!(attempt, error = %e, ?delay, "Retrying transient storage failure");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// What an append stored: the epoch's updated summary and the proofs that
+/// were not already in it, in the order given.
+#[derive(Debug, Clone)]
+pub struct Appended {
+    pub summary: EpochSummary,
+    pub mints: Vec<MintProof>,
+    pub burns: Vec<BurnProof>,
+}
+
+impl Appended {
+    pub fn count(&self) -> usize {
+        self.mints.len() + self.burns.len()
+    }
+}
 
 pub struct Storage {
     db: Database,
+    path: PathBuf,
+    retry_policy: RwLock<RetryPolicy>,
+    encoding: RwLock<Encoding>,
 }
 
-impl Storage {
-    #[instrument(skip(path), err)]
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, PolError> {
-        info!("Initializing storage");
-        let db = Database::create(path)
-            .map_err(|e| PolError::DatabaseInitializationError(e.to_string()))?;
+impl Storage {
+    #[instrument(skip(path), err)]
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, PolError> {
+        info!("Initializing storage");
+        let db = Database::create(path.as_ref())
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+
+        // Create tables if they don't exist
+        let write_txn = db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        debug!("Creating tables if they don't exist");
+        write_txn
+            .open_table(LEGACY_EPOCHS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(EPOCH_HEADERS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(PROOF_CHUNKS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(PROOF_KEYS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(CURRENT_EPOCH_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(SINK_STATE_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(KEYSETS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(OBSERVATIONS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(ATTESTATIONS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(PUBLICATIONS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(AUDIT_LOG_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(FINALIZED_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(OPENING_BALANCES_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(HISTORY_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        write_txn
+            .open_table(SEEN_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        let encoding = {
+            let meta = write_txn
+                .open_table(META_TABLE)
+                .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+            Encoding {
+                codec: read_meta(&meta, CODEC_KEY)?.unwrap_or_default(),
+                compression: read_meta(&meta, COMPRESSION_KEY)?.unwrap_or_default(),
+            }
+        };
+        let schema_version = Self::schema_version(&write_txn)?;
+        Self::migrate_legacy_epochs(&write_txn, encoding, schema_version)?;
+        Self::migrate_schema(&write_txn, schema_version, encoding)?;
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        info!(
+            codec = %encoding.codec,
+            compression = %encoding.compression,
+            "Storage initialized successfully"
+        );
+        Ok(Self {
+            db,
+            path: path.as_ref().to_path_buf(),
+            retry_policy: RwLock::new(RetryPolicy::default()),
+            encoding: RwLock::new(encoding),
+        })
+    }
+
+    pub fn set_retry_policy(&self, policy: RetryPolicy) {
+        *self
+            .retry_policy
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = policy;
+    }
+
+    fn encoding(&self) -> Encoding {
+        *self.encoding.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn codec(&self) -> CodecKind {
+        self.encoding().codec
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.encoding().compression
+    }
+
+    /// Switches the database to `codec`, re-encoding every stored record in
+    /// the same transaction that records the new setting.
+    pub fn set_codec(&self, codec: CodecKind) -> Result<(), PolError> {
+        self.set_encoding(|encoding| encoding.codec = codec)
+    }
+
+    /// Switches the database to `compression`, like [`Storage::set_codec`].
+    pub fn set_compression(&self, compression: Compression) -> Result<(), PolError> {
+        self.set_encoding(|encoding| encoding.compression = compression)
+    }
+
+    #[instrument(skip(self, change), err)]
+    fn set_encoding(&self, change: impl FnOnce(&mut Encoding)) -> Result<(), PolError> {
+        let mut current = self
+            .encoding
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut encoding = *current;
+        change(&mut encoding);
+        if *current == encoding {
+            return Ok(());
+        }
+
+        let from = *current;
+        // Retained blobs must stay readable by the build that wrote them
+        if self.legacy_epoch_count()? > 0 {
+            return Err(PolError::DatabaseSerializationError(
+                "Drop the retained legacy epochs before changing the encoding".into(),
+            ));
+        }
+        info!(
+            codec = %encoding.codec,
+            compression = %encoding.compression,
+            "Re-encoding stored records"
+        );
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        {
+            recode_by_id::<EpochSummary>(&write_txn, EPOCH_HEADERS_TABLE, from, encoding)?;
+            Self::recode_records(&write_txn, from, encoding)?;
+
+            // Chunks are recoded one at a time to keep memory flat
+            let mut chunks = write_txn
+                .open_table(PROOF_CHUNKS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut keys = Vec::new();
+            for result in chunks
+                .iter()
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+            {
+                let (key, _) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+                keys.push(key.value());
+            }
+            for key in keys {
+                let (_, kind, _) = key;
+                let data = match chunks
+                    .get(key)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?
+                {
+                    Some(data) if kind == MINT_CHUNK => {
+                        encode(encoding, &decode::<Vec<MintProof>>(from, data.value())?)?
+                    }
+                    Some(data) => encode(encoding, &decode::<Vec<BurnProof>>(from, data.value())?)?,
+                    None => continue,
+                };
+                chunks
+                    .insert(key, data.as_slice())
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+
+            let mut meta = write_txn
+                .open_table(META_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            meta.insert(CODEC_KEY, encoding.codec.as_str())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            meta.insert(COMPRESSION_KEY, encoding.compression.as_str())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        *current = encoding;
+        Ok(())
+    }
+
+    /// Re-encodes every record outside the epoch tables.
+    fn recode_records(
+        write_txn: &WriteTransaction,
+        from: Encoding,
+        to: Encoding,
+    ) -> Result<(), PolError> {
+        recode_by_id::<FinalizedEpoch>(write_txn, FINALIZED_TABLE, from, to)?;
+        recode_by_id::<HistoryEntry>(write_txn, HISTORY_TABLE, from, to)?;
+        recode_by_id::<AuditEntry>(write_txn, AUDIT_LOG_TABLE, from, to)?;
+        recode_by_id::<Vec<sha256::Hash>>(write_txn, PUBLICATIONS_TABLE, from, to)?;
+        recode_by_id::<Vec<EpochAttestation>>(write_txn, ATTESTATIONS_TABLE, from, to)?;
+        recode_by_name::<SinkState>(write_txn, SINK_STATE_TABLE, from, to)?;
+        recode_by_name::<KeysetRecord>(write_txn, KEYSETS_TABLE, from, to)?;
+        recode_by_name::<ExternalObservation>(write_txn, OBSERVATIONS_TABLE, from, to)?;
+        recode_by_name::<SeenCommitment>(write_txn, SEEN_TABLE, from, to)
+    }
+
+    /// The recorded schema version. Databases from before it was recorded
+    /// are version 1 if they hold unchunked data and current otherwise.
+    fn schema_version(write_txn: &WriteTransaction) -> Result<u32, PolError> {
+        let meta = write_txn
+            .open_table(META_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        if let Some(version) = meta
+            .get(SCHEMA_VERSION_KEY)
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            return version
+                .value()
+                .parse()
+                .map_err(|e: ParseIntError| PolError::DatabaseDeserializationError(e.into()));
+        }
+
+        let headers = write_txn
+            .open_table(EPOCH_HEADERS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        let legacy = write_txn
+            .open_table(LEGACY_EPOCHS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        let balances = write_txn
+            .open_table(OPENING_BALANCES_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        let chunked = !headers
+            .is_empty()
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let unchunked = !legacy
+            .is_empty()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+            || !balances
+                .is_empty()
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        Ok(if unchunked && !chunked {
+            1
+        } else {
+            SCHEMA_VERSION
+        })
+    }
+
+    /// Brings data outside the epoch tables up to the current schema and
+    /// records it. Version 1 kept opening balances in sats.
+    fn migrate_schema(
+        write_txn: &WriteTransaction,
+        from: u32,
+        encoding: Encoding,
+    ) -> Result<(), PolError> {
+        if from > SCHEMA_VERSION {
+            return Err(PolError::DatabaseDeserializationError(
+                format!("Schema version {} is newer than this build supports", from).into(),
+            ));
+        }
+        if from < SCHEMA_VERSION {
+            info!(from, to = SCHEMA_VERSION, "Migrating storage schema");
+        }
+        if from < 2 {
+            let mut balances = write_txn
+                .open_table(OPENING_BALANCES_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut scaled = Vec::new();
+            for result in balances
+                .iter()
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+            {
+                let (epoch_id, sat) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+                let msat = sat.value().checked_mul(1000).ok_or_else(|| {
+                    PolError::InvalidAmount(format!("{} sat overflows in msat", sat.value()))
+                })?;
+                scaled.push((epoch_id.value(), msat));
+            }
+            for (epoch_id, msat) in scaled {
+                balances
+                    .insert(epoch_id, msat)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+        }
+
+        // Only epochs followed the recorded encoding before version 6
+        if from < 6 && encoding != Encoding::default() {
+            Self::recode_records(write_txn, Encoding::default(), encoding)?;
+        }
+
+        let mut meta = write_txn
+            .open_table(META_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        meta.insert(SCHEMA_VERSION_KEY, SCHEMA_VERSION.to_string().as_str())
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        Ok(())
+    }
+
+    /// Copies whole-epoch blobs from databases created before chunking into
+    /// the chunked tables, converting them from `schema_version` on the
+    /// way. The blobs stay in place, and their layout is recorded for
+    /// [`Storage::drop_legacy_epochs`].
+    fn migrate_legacy_epochs(
+        write_txn: &WriteTransaction,
+        encoding: Encoding,
+        schema_version: u32,
+    ) -> Result<(), PolError> {
+        if schema_version >= CHUNKED_SCHEMA_VERSION {
+            return Ok(());
+        }
+        let legacy = write_txn
+            .open_table(LEGACY_EPOCHS_TABLE)
+            .map_err(|e| PolError::DatabaseInitializationError(e.into()))?;
+        if legacy
+            .is_empty()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            return Ok(());
+        }
+
+        info!(
+            epoch_count = legacy
+                .len()
+                .map_err(|e| PolError::DatabaseError(e.into()))?,
+            "Chunking stored epochs"
+        );
+        // One epoch at a time to keep memory flat
+        for result in legacy
+            .iter()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (_, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            let epoch_state = decode_legacy(encoding, schema_version, data.value())?;
+            Self::write_epoch(write_txn, encoding, &epoch_state)?;
+        }
+
+        let mut meta = write_txn
+            .open_table(META_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        meta.insert(LEGACY_LAYOUT_KEY, schema_version.to_string().as_str())
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        Ok(())
+    }
+
+    /// Blobs kept in the legacy table after chunking.
+    pub fn legacy_epoch_count(&self) -> Result<u64, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        let legacy = read_txn
+            .open_table(LEGACY_EPOCHS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        legacy.len().map_err(|e| PolError::DatabaseError(e.into()))
+    }
+
+    /// Deletes the blobs kept after chunking, once every proof in them is
+    /// confirmed to be in the chunked epoch of the same id. Returns how
+    /// many were dropped.
+    #[instrument(skip(self), err)]
+    pub fn drop_legacy_epochs(&self) -> Result<u64, PolError> {
+        let encoding = self.encoding();
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        let dropped = {
+            let mut meta = write_txn
+                .open_table(META_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let layout: u32 = match meta
+                .get(LEGACY_LAYOUT_KEY)
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+            {
+                Some(layout) => layout
+                    .value()
+                    .parse()
+                    .map_err(|e: ParseIntError| PolError::DatabaseDeserializationError(e.into()))?,
+                None => return Ok(0),
+            };
+
+            let mut legacy = write_txn
+                .open_table(LEGACY_EPOCHS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let headers = write_txn
+                .open_table(EPOCH_HEADERS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let chunks = write_txn
+                .open_table(PROOF_CHUNKS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut epoch_ids = Vec::new();
+            for result in legacy
+                .iter()
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+            {
+                let (epoch_id, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+                epoch_ids.push(epoch_id.value());
+                let migrated = decode_legacy(encoding, layout, data.value())?;
+                let chunked = Self::read_epoch(&headers, &chunks, encoding, epoch_id.value())?;
+                let confirmed = chunked.map_or(false, |chunked| {
+                    migrated.mint_proofs.is_subset(&chunked.mint_proofs)
+                        && migrated.burn_proofs.is_subset(&chunked.burn_proofs)
+                });
+                if !confirmed {
+                    return Err(PolError::InvalidEpoch(format!(
+                        "Epoch {} is missing proofs from its legacy copy",
+                        epoch_id.value()
+                    )));
+                }
+            }
+
+            for epoch_id in &epoch_ids {
+                legacy
+                    .remove(epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+            meta.remove(LEGACY_LAYOUT_KEY)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            epoch_ids.len() as u64
+        };
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        info!(dropped, "Dropped legacy epochs");
+        Ok(dropped)
+    }
+
+    /// Replaces whatever is stored under the epoch's id with its header and
+    /// proof chunks.
+    fn write_epoch(
+        write_txn: &WriteTransaction,
+        encoding: Encoding,
+        epoch_state: &EpochState,
+    ) -> Result<(), PolError> {
+        Self::remove_epoch(write_txn, epoch_state.epoch_id)?;
+
+        let mut headers = write_txn
+            .open_table(EPOCH_HEADERS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let data = encode(encoding, &epoch_state.summary()?)?;
+        headers
+            .insert(epoch_state.epoch_id, data.as_slice())
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        Self::write_chunks(
+            write_txn,
+            encoding,
+            epoch_state.epoch_id,
+            &epoch_state.mint_proofs,
+        )?;
+        Self::write_chunks(
+            write_txn,
+            encoding,
+            epoch_state.epoch_id,
+            &epoch_state.burn_proofs,
+        )
+    }
+
+    fn write_chunks<P: ChunkedProof>(
+        write_txn: &WriteTransaction,
+        encoding: Encoding,
+        epoch_id: u64,
+        proofs: &HashSet<P>,
+    ) -> Result<(), PolError> {
+        let mut chunks = write_txn
+            .open_table(PROOF_CHUNKS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let mut keys = write_txn
+            .open_table(PROOF_KEYS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let proofs: Vec<&P> = proofs.iter().collect();
+        for (index, chunk) in proofs.chunks(CHUNK_SIZE).enumerate() {
+            let index = index as u32;
+            let data = encode(encoding, chunk)?;
+            chunks
+                .insert((epoch_id, P::KIND, index), data.as_slice())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            for proof in chunk {
+                let digest = proof_digest(*proof)?;
+                keys.insert(
+                    (epoch_id, P::KIND, digest.as_byte_array().as_slice()),
+                    index,
+                )
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes an epoch's header, chunks and proof keys, if any.
+    fn remove_epoch(write_txn: &WriteTransaction, epoch_id: u64) -> Result<(), PolError> {
+        let mut headers = write_txn
+            .open_table(EPOCH_HEADERS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        headers
+            .remove(epoch_id)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let mut chunks = write_txn
+            .open_table(PROOF_CHUNKS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let mut chunk_keys = Vec::new();
+        for result in chunks
+            .range((epoch_id, 0, 0)..=(epoch_id, u8::MAX, u32::MAX))
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (key, _) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            chunk_keys.push(key.value());
+        }
+        for key in chunk_keys {
+            chunks
+                .remove(key)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+
+        let mut keys = write_txn
+            .open_table(PROOF_KEYS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let mut proof_keys = Vec::new();
+        for result in keys
+            .range((epoch_id, 0u8, &[0u8; 0][..])..)
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (key, _) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            let (key_epoch, kind, digest) = key.value();
+            if key_epoch != epoch_id {
+                break;
+            }
+            proof_keys.push((kind, digest.to_vec()));
+        }
+        for (kind, digest) in proof_keys {
+            keys.remove((epoch_id, kind, digest.as_slice()))
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+        Ok(())
+    }
+
+    fn read_summary(
+        headers: &impl ReadableTable<u64, &'static [u8]>,
+        encoding: Encoding,
+        epoch_id: u64,
+    ) -> Result<Option<EpochSummary>, PolError> {
+        headers
+            .get(epoch_id)
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+            .map(|data| decode(encoding, data.value()))
+            .transpose()
+    }
+
+    fn read_epoch(
+        headers: &impl ReadableTable<u64, &'static [u8]>,
+        chunks: &impl ReadableTable<(u64, u8, u32), &'static [u8]>,
+        encoding: Encoding,
+        epoch_id: u64,
+    ) -> Result<Option<EpochState>, PolError> {
+        let Some(summary) = Self::read_summary(headers, encoding, epoch_id)? else {
+            return Ok(None);
+        };
+        Ok(Some(EpochState {
+            epoch_id: summary.epoch_id,
+            start_time: summary.start_time,
+            mint_proofs: Self::read_chunks(chunks, encoding, epoch_id)?,
+            burn_proofs: Self::read_chunks(chunks, encoding, epoch_id)?,
+        }))
+    }
+
+    fn read_chunks<P: ChunkedProof>(
+        chunks: &impl ReadableTable<(u64, u8, u32), &'static [u8]>,
+        encoding: Encoding,
+        epoch_id: u64,
+    ) -> Result<HashSet<P>, PolError> {
+        let mut proofs = HashSet::new();
+        for result in chunks
+            .range((epoch_id, P::KIND, 0)..=(epoch_id, P::KIND, u32::MAX))
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (_, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            proofs.extend(decode::<Vec<P>>(encoding, data.value())?);
+        }
+        Ok(proofs)
+    }
+
+    pub(crate) fn retry_policy(&self) -> RetryPolicy {
+        *self
+            .retry_policy
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[instrument(skip(self, epoch_state), err)]
+    pub fn save_epoch(&self, epoch_state: &EpochState) -> Result<(), PolError> {
+        info!(epoch_id = epoch_state.epoch_id, "Saving epoch");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        Self::ensure_not_finalized(&write_txn, &[epoch_state.epoch_id])?;
+        Self::write_epoch(&write_txn, self.encoding(), epoch_state)?;
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        debug!(epoch_id = epoch_state.epoch_id, "Epoch saved successfully");
+        Ok(())
+    }
+
+    /// Applies `update` to the stored epoch and writes it back inside one
+    /// write transaction. redb serializes writers, so concurrent updates to
+    /// the same epoch never overwrite each other. Returns the updated state,
+    /// or `None` if the epoch does not exist.
+    #[instrument(skip(self, update), err)]
+    pub fn update_epoch(
+        &self,
+        epoch_id: u64,
+        mut update: impl FnMut(&mut EpochState),
+    ) -> Result<Option<EpochState>, PolError> {
+        debug!(epoch_id, "Updating epoch");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        Self::ensure_not_finalized(&write_txn, &[epoch_id])?;
+
+        let encoding = self.encoding();
+        let existing = {
+            let headers = write_txn
+                .open_table(EPOCH_HEADERS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let chunks = write_txn
+                .open_table(PROOF_CHUNKS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            Self::read_epoch(&headers, &chunks, encoding, epoch_id)?
+        };
+        let Some(mut updated) = existing else {
+            return Ok(None);
+        };
+
+        update(&mut updated);
+        Self::write_epoch(&write_txn, encoding, &updated)?;
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(Some(updated))
+    }
+
+    /// Adds a proof to a stored epoch, rewriting only the last mint chunk
+    /// and the epoch header. Returns the updated summary, or `None` if the
+    /// epoch does not exist.
+    #[instrument(skip(self, proof), err)]
+    pub fn append_mint_proof(
+        &self,
+        epoch_id: u64,
+        proof: &MintProof,
+    ) -> Result<Option<EpochSummary>, PolError> {
+        self.append_mint_proofs(epoch_id, std::slice::from_ref(proof))
+    }
+
+    /// Burn counterpart of [`Storage::append_mint_proof`].
+    #[instrument(skip(self, proof), err)]
+    pub fn append_burn_proof(
+        &self,
+        epoch_id: u64,
+        proof: &BurnProof,
+    ) -> Result<Option<EpochSummary>, PolError> {
+        self.append_burn_proofs(epoch_id, std::slice::from_ref(proof))
+    }
+
+    /// Adds many mint proofs to a stored epoch in one transaction.
+    #[instrument(skip(self, proofs), fields(count = proofs.len()), err)]
+    pub fn append_mint_proofs(
+        &self,
+        epoch_id: u64,
+        proofs: &[MintProof],
+    ) -> Result<Option<EpochSummary>, PolError> {
+        Ok(self
+            .append_proofs(epoch_id, proofs, &[])?
+            .map(|appended| appended.summary))
+    }
+
+    /// Burn counterpart of [`Storage::append_mint_proofs`].
+    #[instrument(skip(self, proofs), fields(count = proofs.len()), err)]
+    pub fn append_burn_proofs(
+        &self,
+        epoch_id: u64,
+        proofs: &[BurnProof],
+    ) -> Result<Option<EpochSummary>, PolError> {
+        Ok(self
+            .append_proofs(epoch_id, &[], proofs)?
+            .map(|appended| appended.summary))
+    }
+
+    /// Adds mints and burns to a stored epoch in one transaction, skipping
+    /// proofs it already holds. Returns `None` if the epoch does not exist.
+    #[instrument(skip(self, mints, burns), fields(mints = mints.len(), burns = burns.len()), err)]
+    pub fn append_proofs(
+        &self,
+        epoch_id: u64,
+        mints: &[MintProof],
+        burns: &[BurnProof],
+    ) -> Result<Option<Appended>, PolError> {
+        let encoding = self.encoding();
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        Self::ensure_not_finalized(&write_txn, &[epoch_id])?;
+
+        let appended = {
+            let mut headers = write_txn
+                .open_table(EPOCH_HEADERS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let Some(mut summary) = Self::read_summary(&headers, encoding, epoch_id)? else {
+                return Ok(None);
+            };
+            let mints = Self::append_chunked(&write_txn, encoding, &mut summary, mints)?;
+            let burns = Self::append_chunked(&write_txn, encoding, &mut summary, burns)?;
+            if !mints.is_empty() || !burns.is_empty() {
+                let data = encode(encoding, &summary)?;
+                headers
+                    .insert(epoch_id, data.as_slice())
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+            Appended {
+                summary,
+                mints,
+                burns,
+            }
+        };
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(Some(appended))
+    }
+
+    /// Writes the proofs of one kind that the epoch does not hold yet into
+    /// its last chunks, counting them into `summary`, and returns them.
+    fn append_chunked<P: ChunkedProof>(
+        write_txn: &WriteTransaction,
+        encoding: Encoding,
+        summary: &mut EpochSummary,
+        proofs: &[P],
+    ) -> Result<Vec<P>, PolError> {
+        if proofs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let epoch_id = summary.epoch_id;
+        let digests = proofs
+            .iter()
+            .map(proof_digest)
+            .collect::<Result<Vec<_>, _>>()?;
+        debug!(
+            epoch_id,
+            kind = P::KIND,
+            count = proofs.len(),
+            "Appending proofs"
+        );
+
+        let mut keys = write_txn
+            .open_table(PROOF_KEYS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let mut chunks = write_txn
+            .open_table(PROOF_CHUNKS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let mut index = (P::count(summary) / CHUNK_SIZE as u64) as u32;
+        let mut chunk: Vec<P> = chunks
+            .get((epoch_id, P::KIND, index))
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+            .map(|data| decode(encoding, data.value()))
+            .transpose()?
+            .unwrap_or_default();
+        let mut inserted = Vec::new();
+
+        for (proof, digest) in proofs.iter().zip(&digests) {
+            let key = (epoch_id, P::KIND, digest.as_byte_array().as_slice());
+            if keys
+                .get(key)
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+                .is_some()
+            {
+                continue;
+            }
+
+            if chunk.len() == CHUNK_SIZE {
+                let data = encode(encoding, &chunk)?;
+                chunks
+                    .insert((epoch_id, P::KIND, index), data.as_slice())
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+                index += 1;
+                chunk.clear();
+            }
+            chunk.push(proof.clone());
+            keys.insert(key, index)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            proof.record(summary)?;
+            inserted.push(proof.clone());
+        }
+
+        if !inserted.is_empty() {
+            let data = encode(encoding, &chunk)?;
+            chunks
+                .insert((epoch_id, P::KIND, index), data.as_slice())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+        Ok(inserted)
+    }
+
+    /// An epoch's counts and totals, read without loading its proofs.
+    #[instrument(skip(self), err)]
+    pub fn get_epoch_summary(&self, epoch_id: u64) -> Result<Option<EpochSummary>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        let headers = read_txn
+            .open_table(EPOCH_HEADERS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        Self::read_summary(&headers, self.encoding(), epoch_id)
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn list_epoch_summaries(&self) -> Result<Vec<EpochSummary>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        let headers = read_txn
+            .open_table(EPOCH_HEADERS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let encoding = self.encoding();
+        let mut summaries = Vec::new();
+        for result in headers
+            .iter()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (_, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            summaries.push(decode(encoding, data.value())?);
+        }
+        Ok(summaries)
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn get_epoch(&self, epoch_id: u64) -> Result<Option<EpochState>, PolError> {
+        debug!(epoch_id, "Getting epoch");
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let headers = read_txn
+            .open_table(EPOCH_HEADERS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let chunks = read_txn
+            .open_table(PROOF_CHUNKS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let result = Self::read_epoch(&headers, &chunks, self.encoding(), epoch_id)?;
+        if result.is_some() {
+            debug!(epoch_id, "Epoch found");
+        } else {
+            warn!(epoch_id, "Epoch not found");
+        }
+
+        Ok(result)
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn list_epochs(&self) -> Result<Vec<EpochState>, PolError> {
+        debug!("Listing all epochs");
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let headers = read_txn
+            .open_table(EPOCH_HEADERS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let chunks = read_txn
+            .open_table(PROOF_CHUNKS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let encoding = self.encoding();
+        let mut epochs = Vec::new();
+        for result in headers
+            .iter()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (epoch_id, _) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            if let Some(epoch_state) =
+                Self::read_epoch(&headers, &chunks, encoding, epoch_id.value())?
+            {
+                epochs.push(epoch_state);
+            }
+        }
+
+        debug!(epoch_count = epochs.len(), "Listed all epochs");
+        Ok(epochs)
+    }
+
+    /// Size of the database file on disk.
+    pub fn file_size(&self) -> Result<u64, PolError> {
+        Ok(std::fs::metadata(&self.path)?.len())
+    }
+
+    /// Serialized size of each stored epoch, header and chunks, by epoch id.
+    #[instrument(skip(self), err)]
+    pub fn epoch_sizes(&self) -> Result<Vec<(u64, u64)>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let headers = read_txn
+            .open_table(EPOCH_HEADERS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let chunks = read_txn
+            .open_table(PROOF_CHUNKS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let mut sizes = BTreeMap::new();
+        for result in headers
+            .iter()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (epoch_id, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            sizes.insert(epoch_id.value(), data.value().len() as u64);
+        }
+        for result in chunks
+            .iter()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (key, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            let (epoch_id, _, _) = key.value();
+            *sizes.entry(epoch_id).or_default() += data.value().len() as u64;
+        }
+
+        Ok(sizes.into_iter().collect())
+    }
+
+    /// Removes an epoch with its attestations. Sealed epochs are read-only
+    /// and only leave the database through pruning.
+    #[instrument(skip(self), err)]
+    pub fn delete_epoch(&self, epoch_id: u64) -> Result<(), PolError> {
+        info!(epoch_id, "Deleting epoch");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        Self::ensure_not_finalized(&write_txn, &[epoch_id])?;
+
+        {
+            Self::remove_epoch(&write_txn, epoch_id)?;
+
+            let mut attestations = write_txn
+                .open_table(ATTESTATIONS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            attestations
+                .remove(epoch_id)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        debug!(epoch_id, "Epoch deleted successfully");
+        Ok(())
+    }
+
+    /// Starts `new_epoch` with `opening`, moves the current epoch pointer to it
+    /// and prunes `pruned`, all in one transaction, so a crash mid-rotation
+    /// never leaves the pointer on a missing epoch. `retained_opening` carries
+    /// the pruned liabilities into the oldest remaining epoch, and `closed`
+    /// and any changed `history` entries are appended to the history log.
+    /// `audit` records the prune, if any.
+    #[instrument(skip(self, new_epoch, history, audit), err)]
+    pub fn rotate_epoch(
+        &self,
+        new_epoch: &EpochState,
+        history: &[HistoryEntry],
+        opening: MilliSats,
+        pruned: &[u64],
+        retained_opening: Option<(u64, MilliSats)>,
+        audit: Option<&AuditEntry>,
+    ) -> Result<(), PolError> {
+        info!(epoch_id = new_epoch.epoch_id, ?pruned, "Rotating epoch");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        {
+            let encoding = self.encoding();
+            Self::write_epoch(&write_txn, encoding, new_epoch)?;
+            for epoch_id in pruned {
+                Self::remove_epoch(&write_txn, *epoch_id)?;
+            }
+
+            let mut attestations = write_txn
+                .open_table(ATTESTATIONS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut finalized = write_txn
+                .open_table(FINALIZED_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut openings = write_txn
+                .open_table(OPENING_BALANCES_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut current = write_txn
+                .open_table(CURRENT_EPOCH_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            openings
+                .insert(new_epoch.epoch_id, opening.to_msat())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            current
+                .insert("current", new_epoch.epoch_id)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            for epoch_id in pruned {
+                attestations
+                    .remove(*epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+                finalized
+                    .remove(*epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+                openings
+                    .remove(*epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+
+            if let Some((epoch_id, balance)) = retained_opening {
+                openings
+                    .insert(epoch_id, balance.to_msat())
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+        }
+        self.append_history_entries(&write_txn, history)?;
+        if let Some(audit) = audit {
+            self.append_audit_entry(&write_txn, audit)?;
+        }
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        debug!(epoch_id = new_epoch.epoch_id, "Epoch rotated successfully");
+        Ok(())
+    }
+
+    /// Replaces `merged.epoch_id` with the merged state and removes the
+    /// epochs folded into it, all in one transaction that also moves the
+    /// current epoch pointer when given. Attestations on every affected
+    /// epoch are dropped since their commitments no longer hold.
+    #[instrument(skip(self, merged, audit), err)]
+    pub fn merge_epochs(
+        &self,
+        merged: &EpochState,
+        removed: &[u64],
+        current_epoch: Option<u64>,
+        audit: &AuditEntry,
+    ) -> Result<(), PolError> {
+        info!(epoch_id = merged.epoch_id, ?removed, "Merging epochs");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        Self::ensure_not_finalized(&write_txn, &[merged.epoch_id])?;
+        Self::ensure_not_finalized(&write_txn, removed)?;
+
+        {
+            Self::write_epoch(&write_txn, self.encoding(), merged)?;
+            let mut attestations = write_txn
+                .open_table(ATTESTATIONS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            attestations
+                .remove(merged.epoch_id)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            for epoch_id in removed {
+                Self::remove_epoch(&write_txn, *epoch_id)?;
+                attestations
+                    .remove(*epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+
+            if let Some(current_epoch) = current_epoch {
+                let mut current = write_txn
+                    .open_table(CURRENT_EPOCH_TABLE)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+                current
+                    .insert("current", current_epoch)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+        }
+        self.append_audit_entry(&write_txn, audit)?;
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    /// Removes `pruned` epochs with their seals and attestations and moves
+    /// their liabilities into the oldest remaining epoch, in one transaction.
+    #[instrument(skip(self, audit), err)]
+    pub fn prune_epochs(
+        &self,
+        pruned: &[u64],
+        retained_opening: (u64, MilliSats),
+        audit: &AuditEntry,
+    ) -> Result<(), PolError> {
+        info!(?pruned, "Pruning epochs");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        {
+            let mut attestations = write_txn
+                .open_table(ATTESTATIONS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut finalized = write_txn
+                .open_table(FINALIZED_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut openings = write_txn
+                .open_table(OPENING_BALANCES_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            for epoch_id in pruned {
+                Self::remove_epoch(&write_txn, *epoch_id)?;
+                attestations
+                    .remove(*epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+                finalized
+                    .remove(*epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+                openings
+                    .remove(*epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+
+            let (epoch_id, balance) = retained_opening;
+            openings
+                .insert(epoch_id, balance.to_msat())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+        self.append_audit_entry(&write_txn, audit)?;
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    /// Swaps the entire epoch set for `epochs` and moves the current epoch
+    /// pointer, in one transaction. All attestations and opening balances
+    /// are dropped, and `opening` is carried into the new layout.
+    #[instrument(skip(self, epochs, audit), err)]
+    pub fn replace_epochs(
+        &self,
+        epochs: &[EpochState],
+        current_epoch: u64,
+        opening: Option<(u64, MilliSats)>,
+        audit: &AuditEntry,
+    ) -> Result<(), PolError> {
+        info!(
+            epoch_count = epochs.len(),
+            current_epoch, "Replacing all epochs"
+        );
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        {
+            let finalized = write_txn
+                .open_table(FINALIZED_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            if let Some((epoch_id, _)) = finalized
+                .first()
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+            {
+                return Err(PolError::EpochFinalized(epoch_id.value()));
+            }
+        }
+
+        {
+            let mut stored = Vec::new();
+            {
+                let headers = write_txn
+                    .open_table(EPOCH_HEADERS_TABLE)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+                for result in headers
+                    .iter()
+                    .map_err(|e| PolError::DatabaseError(e.into()))?
+                {
+                    let (epoch_id, _) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+                    stored.push(epoch_id.value());
+                }
+            }
+            for epoch_id in stored {
+                Self::remove_epoch(&write_txn, epoch_id)?;
+            }
+
+            let mut attestations = write_txn
+                .open_table(ATTESTATIONS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut keys = Vec::new();
+            for result in attestations
+                .iter()
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+            {
+                let (key, _) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+                keys.push(key.value());
+            }
+            for key in keys {
+                attestations
+                    .remove(key)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+
+            // Openings were keyed by the old boundaries
+            let mut openings = write_txn
+                .open_table(OPENING_BALANCES_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let mut keys = Vec::new();
+            for result in openings
+                .iter()
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+            {
+                let (key, _) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+                keys.push(key.value());
+            }
+            for key in keys {
+                openings
+                    .remove(key)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+            if let Some((epoch_id, balance)) = opening {
+                openings
+                    .insert(epoch_id, balance.to_msat())
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+
+            let encoding = self.encoding();
+            for epoch_state in epochs {
+                Self::write_epoch(&write_txn, encoding, epoch_state)?;
+            }
+
+            let mut current = write_txn
+                .open_table(CURRENT_EPOCH_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            current
+                .insert("current", current_epoch)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+        self.append_audit_entry(&write_txn, audit)?;
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    fn ensure_not_finalized(
+        write_txn: &WriteTransaction,
+        epoch_ids: &[u64],
+    ) -> Result<(), PolError> {
+        let finalized = write_txn
+            .open_table(FINALIZED_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        for epoch_id in epoch_ids {
+            if finalized
+                .get(*epoch_id)
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+                .is_some()
+            {
+                return Err(PolError::EpochFinalized(*epoch_id));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Seals an epoch. Fails if it was already finalized.
+    #[instrument(skip(self, seal), err)]
+    pub fn finalize_epoch(&self, seal: &FinalizedEpoch) -> Result<(), PolError> {
+        info!(epoch_id = seal.epoch_id, "Finalizing epoch");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        Self::ensure_not_finalized(&write_txn, &[seal.epoch_id])?;
+
+        {
+            let mut table = write_txn
+                .open_table(FINALIZED_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            let data = encode(self.encoding(), seal)?;
+            table
+                .insert(seal.epoch_id, data.as_slice())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    /// Replaces existing seals, recording `entry` in the audit log in the
+    /// same transaction. Every epoch must already be sealed.
+    pub fn replace_seals(
+        &self,
+        seals: &[FinalizedEpoch],
+        entry: &AuditEntry,
+    ) -> Result<(), PolError> {
+        info!(count = seals.len(), "Replacing epoch seals");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        {
+            let mut table = write_txn
+                .open_table(FINALIZED_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            for seal in seals {
+                let sealed = table
+                    .get(seal.epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?
+                    .is_some();
+                if !sealed {
+                    return Err(PolError::InvalidEpoch(format!(
+                        "Epoch {} is not finalized",
+                        seal.epoch_id
+                    )));
+                }
+                let data = encode(self.encoding(), seal)?;
+                table
+                    .insert(seal.epoch_id, data.as_slice())
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+        }
+        self.append_audit_entry(&write_txn, entry)?;
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn get_finalized(&self, epoch_id: u64) -> Result<Option<FinalizedEpoch>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let table = read_txn
+            .open_table(FINALIZED_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let result = match table
+            .get(epoch_id)
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            Some(data) => Some(decode(self.encoding(), data.value())?),
+            None => None,
+        };
+
+        Ok(result)
+    }
+
+    fn append_audit_entry(
+        &self,
+        write_txn: &WriteTransaction,
+        entry: &AuditEntry,
+    ) -> Result<(), PolError> {
+        let mut table = write_txn
+            .open_table(AUDIT_LOG_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let next = table
+            .last()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+            .map(|(key, _)| key.value() + 1)
+            .unwrap_or(0);
+
+        let data = encode(self.encoding(), entry)?;
+        table
+            .insert(next, data.as_slice())
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn list_audit_entries(&self) -> Result<Vec<AuditEntry>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let table = read_txn
+            .open_table(AUDIT_LOG_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let mut entries = Vec::new();
+        for result in table
+            .iter()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (_, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            entries.push(decode(self.encoding(), data.value())?);
+        }
+
+        Ok(entries)
+    }
+
+    /// Remembers that a signed report covering `epoch_ids` was produced.
+    #[instrument(skip(self, epoch_ids), err)]
+    pub fn record_publication(
+        &self,
+        epoch_ids: &[u64],
+        report_commitment: &sha256::Hash,
+    ) -> Result<(), PolError> {
+        debug!(%report_commitment, "Recording publication");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        {
+            let mut table = write_txn
+                .open_table(PUBLICATIONS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            for epoch_id in epoch_ids {
+                let mut reports: Vec<sha256::Hash> = match table
+                    .get(*epoch_id)
+                    .map_err(|e| PolError::DatabaseError(e.into()))?
+                {
+                    Some(data) => decode(self.encoding(), data.value())?,
+                    None => Vec::new(),
+                };
+                reports.push(*report_commitment);
+
+                let data = encode(self.encoding(), &reports)?;
+                table
+                    .insert(*epoch_id, data.as_slice())
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
+        }
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    /// Commitments of signed reports that covered `epoch_id`.
+    #[instrument(skip(self), err)]
+    pub fn get_publications(&self, epoch_id: u64) -> Result<Vec<sha256::Hash>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let table = read_txn
+            .open_table(PUBLICATIONS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let result = match table
+            .get(epoch_id)
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            Some(data) => decode(self.encoding(), data.value())?,
+            None => Vec::new(),
+        };
+
+        Ok(result)
+    }
+
+    /// Records the balance an epoch opened with, carried over from the
+    /// closing balance of the epoch before it.
+    #[instrument(skip(self), err)]
+    pub fn save_opening_balance(&self, epoch_id: u64, balance: MilliSats) -> Result<(), PolError> {
+        debug!(epoch_id, "Saving opening balance");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        {
+            let mut table = write_txn
+                .open_table(OPENING_BALANCES_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            table
+                .insert(epoch_id, balance.to_msat())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn get_opening_balance(&self, epoch_id: u64) -> Result<Option<MilliSats>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let table = read_txn
+            .open_table(OPENING_BALANCES_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let result = table
+            .get(epoch_id)
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+            .map(|v| MilliSats::from_msat(v.value()));
+
+        Ok(result)
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn save_current_epoch(&self, epoch_id: u64) -> Result<(), PolError> {
+        info!(epoch_id, "Saving current epoch");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        {
+            let mut table = write_txn
+                .open_table(CURRENT_EPOCH_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            table
+                .insert("current", epoch_id)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        debug!(epoch_id, "Current epoch saved successfully");
+        Ok(())
+    }
+
+    /// How the database assigns epoch ids, recorded when it was initialized.
+    pub fn get_epoch_id_mode(&self) -> Result<Option<EpochIdMode>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        let meta = read_txn
+            .open_table(META_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        read_meta(&meta, EPOCH_ID_MODE_KEY)
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn save_epoch_id_mode(&self, mode: EpochIdMode) -> Result<(), PolError> {
+        info!(%mode, "Saving epoch id mode");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        {
+            let mut meta = write_txn
+                .open_table(META_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            meta.insert(EPOCH_ID_MODE_KEY, mode.as_str())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    /// Secret from which confidential reports derive the blinding of
+    /// balances carried over from pruned epochs. Created on first use and
+    /// never exported.
+    pub(crate) fn confidential_key(&self) -> Result<[u8; 32], PolError> {
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        let key = {
+            let mut meta = write_txn
+                .open_table(META_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            let stored = meta
+                .get(CONFIDENTIAL_KEY)
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+                .map(|value| value.value().to_string());
+            match stored {
+                Some(hex_key) => {
+                    let mut key = [0u8; 32];
+                    hex::decode_to_slice(&hex_key, &mut key)
+                        .map_err(|e| PolError::DatabaseDeserializationError(e.into()))?;
+                    key
+                }
+                None => {
+                    let mut key = [0u8; 32];
+                    rand::thread_rng().fill_bytes(&mut key);
+                    meta.insert(CONFIDENTIAL_KEY, hex::encode(key).as_str())
+                        .map_err(|e| PolError::DatabaseError(e.into()))?;
+                    key
+                }
+            }
+        };
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(key)
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn get_current_epoch(&self) -> Result<Option<u64>, PolError> {
+        debug!("Getting current epoch");
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let table = read_txn
+            .open_table(CURRENT_EPOCH_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let result = table
+            .get("current")
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+            .map(|v| v.value());
+
+        if let Some(epoch_id) = result {
+            debug!(epoch_id, "Current epoch found");
+        } else {
+            warn!("No current epoch found");
+        }
+
+        Ok(result)
+    }
+
+    #[instrument(skip(self, state), err)]
+    pub fn save_sink_state(&self, sink: &str, state: &SinkState) -> Result<(), PolError> {
+        debug!(sink, "Saving sink state");
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        {
+            let mut table = write_txn
+                .open_table(SINK_STATE_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            let data = encode(self.encoding(), state)?;
+            table
+                .insert(sink, data.as_slice())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
+
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self), err)]
+    pub fn get_sink_state(&self, sink: &str) -> Result<Option<SinkState>, PolError> {
+        debug!(sink, "Getting sink state");
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let table = read_txn
+            .open_table(SINK_STATE_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let result = match table
+            .get(sink)
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            Some(data) => Some(decode(self.encoding(), data.value())?),
+            None => None,
+        };
 
-        // Create tables if they don't exist
-        let write_txn = db
+        Ok(result)
+    }
+
+    /// Every sink's delivery state, by sink name.
+    #[instrument(skip(self), err)]
+    pub fn list_sink_states(&self) -> Result<Vec<(String, SinkState)>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let table = read_txn
+            .open_table(SINK_STATE_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let mut states = Vec::new();
+        for result in table
+            .iter()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (sink, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            let state: SinkState = decode(self.encoding(), data.value())?;
+            states.push((sink.value().to_string(), state));
+        }
+
+        Ok(states)
+    }
+
+    #[instrument(skip(self, keyset), err)]
+    pub fn save_keyset(&self, keyset: &KeysetRecord) -> Result<(), PolError> {
+        info!(keyset_id = %keyset.id, "Saving keyset");
+        let write_txn = self
+            .db
             .begin_write()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
-        debug!("Creating tables if they don't exist");
-        write_txn
-            .open_table(EPOCHS_TABLE)
-            .map_err(|e| PolError::DatabaseInitializationError(e.to_string()))?;
-        write_txn
-            .open_table(CURRENT_EPOCH_TABLE)
-            .map_err(|e| PolError::DatabaseInitializationError(e.to_string()))?;
+        {
+            let mut table = write_txn
+                .open_table(KEYSETS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            let data = encode(self.encoding(), keyset)?;
+            table
+                .insert(keyset.id.to_string().as_str(), data.as_slice())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+        }
 
         write_txn
             .commit()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
-        info!("Storage initialized successfully");
-        Ok(Self { db })
+        Ok(())
     }
 
-    #[instrument(skip(self, epoch_state), err)]
-    pub fn save_epoch(&self, epoch_state: &EpochState) -> Result<(), PolError> {
-        info!(epoch_id = epoch_state.epoch_id, "Saving epoch");
+    /// All registered keysets, oldest first.
+    #[instrument(skip(self), err)]
+    pub fn list_keysets(&self) -> Result<Vec<KeysetRecord>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let table = read_txn
+            .open_table(KEYSETS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let mut keysets = Vec::new();
+        for result in table
+            .iter()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            let (_, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            let keyset: KeysetRecord = decode(self.encoding(), data.value())?;
+            keysets.push(keyset);
+        }
+
+        keysets.sort_by_key(|k| k.active_from);
+        Ok(keysets)
+    }
+
+    #[instrument(skip(self, observation), err)]
+    pub fn save_observation(&self, observation: &ExternalObservation) -> Result<(), PolError> {
         let write_txn = self
             .db
             .begin_write()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
         {
             let mut table = write_txn
-                .open_table(EPOCHS_TABLE)
-                .map_err(|e| PolError::DatabaseError(e.to_string()))?;
+                .open_table(OBSERVATIONS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
 
-            let data = serialize(epoch_state)
-                .map_err(|e| PolError::DatabaseSerializationError(e.to_string()))?;
+            let data = encode(self.encoding(), observation)?;
             table
-                .insert(epoch_state.epoch_id, data.as_slice())
-                .map_err(|e| PolError::DatabaseError(e.to_string()))?;
+                .insert(observation.mint_url.as_str(), data.as_slice())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
         }
 
         write_txn
             .commit()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
-        debug!(epoch_id = epoch_state.epoch_id, "Epoch saved successfully");
         Ok(())
     }
 
+    /// Observations of every followed mint, by mint URL.
     #[instrument(skip(self), err)]
-    pub fn get_epoch(&self, epoch_id: u64) -> Result<Option<EpochState>, PolError> {
-        debug!(epoch_id, "Getting epoch");
+    pub fn list_observations(&self) -> Result<Vec<ExternalObservation>, PolError> {
         let read_txn = self
             .db
             .begin_read()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
         let table = read_txn
-            .open_table(EPOCHS_TABLE)
-            .map_err(|e| PolError::DatabaseError(e.to_string()))?;
+            .open_table(OBSERVATIONS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
 
-        let result = if let Some(data) = table
-            .get(epoch_id)
-            .map_err(|e| PolError::DatabaseError(e.to_string()))?
+        let mut observations = Vec::new();
+        for result in table
+            .iter()
+            .map_err(|e| PolError::DatabaseError(e.into()))?
         {
-            let epoch_state = deserialize(data.value())
-                .map_err(|e| PolError::DatabaseDeserializationError(e.to_string()))?;
-            debug!(epoch_id, "Epoch found");
-            Some(epoch_state)
-        } else {
-            warn!(epoch_id, "Epoch not found");
-            None
-        };
+            let (_, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            observations.push(decode(self.encoding(), data.value())?);
+        }
 
-        Ok(result)
+        Ok(observations)
+    }
+
+    /// Appends entries to the history log, for closed epochs whose
+    /// commitment or closing balance changed since they were last logged.
+    #[instrument(skip(self, entries), err)]
+    pub fn append_history(&self, entries: &[HistoryEntry]) -> Result<(), PolError> {
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+        self.append_history_entries(&write_txn, entries)?;
+        write_txn
+            .commit()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        Ok(())
+    }
+
+    fn append_history_entries(
+        &self,
+        write_txn: &WriteTransaction,
+        entries: &[HistoryEntry],
+    ) -> Result<(), PolError> {
+        let mut history = write_txn
+            .open_table(HISTORY_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        let mut index = history
+            .len()
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+        for entry in entries {
+            let data = encode(self.encoding(), entry)?;
+            history
+                .insert(index, data.as_slice())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+            index += 1;
+        }
+
+        Ok(())
     }
 
+    /// The history log of closed epochs, oldest first.
     #[instrument(skip(self), err)]
-    pub fn list_epochs(&self) -> Result<Vec<EpochState>, PolError> {
-        debug!("Listing all epochs");
+    pub fn list_history(&self) -> Result<Vec<HistoryEntry>, PolError> {
         let read_txn = self
             .db
             .begin_read()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
         let table = read_txn
-            .open_table(EPOCHS_TABLE)
-            .map_err(|e| PolError::DatabaseError(e.to_string()))?;
+            .open_table(HISTORY_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
 
-        let mut epochs = Vec::new();
+        let mut entries = Vec::new();
         for result in table
             .iter()
-            .map_err(|e| PolError::DatabaseError(e.to_string()))?
+            .map_err(|e| PolError::DatabaseError(e.into()))?
         {
-            let (_, data) = result.map_err(|e| PolError::DatabaseError(e.to_string()))?;
-            let epoch_state = deserialize(data.value())
-                .map_err(|e| PolError::DatabaseDeserializationError(e.to_string()))?;
-            epochs.push(epoch_state);
+            let (_, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            entries.push(decode(self.encoding(), data.value())?);
         }
 
-        debug!(epoch_count = epochs.len(), "Listed all epochs");
-        Ok(epochs)
+        Ok(entries)
     }
 
-    #[instrument(skip(self), err)]
-    pub fn delete_epoch(&self, epoch_id: u64) -> Result<(), PolError> {
-        info!(epoch_id, "Deleting epoch");
+    /// Inserts or updates commitments seen by the monitor, in one
+    /// transaction.
+    #[instrument(skip(self, seen), err)]
+    pub fn save_seen(&self, seen: &[SeenCommitment]) -> Result<(), PolError> {
         let write_txn = self
             .db
             .begin_write()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
         {
             let mut table = write_txn
-                .open_table(EPOCHS_TABLE)
-                .map_err(|e| PolError::DatabaseError(e.to_string()))?;
+                .open_table(SEEN_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
 
-            table
-                .remove(epoch_id)
-                .map_err(|e| PolError::DatabaseError(e.to_string()))?;
+            for entry in seen {
+                let data = encode(self.encoding(), entry)?;
+                table
+                    .insert(entry.key().as_str(), data.as_slice())
+                    .map_err(|e| PolError::DatabaseError(e.into()))?;
+            }
         }
 
         write_txn
             .commit()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
-        debug!(epoch_id, "Epoch deleted successfully");
         Ok(())
     }
 
+    /// Commitments the monitor has seen, by mint and epoch. Keys start
+    /// with the mint, so one mint's are read as a range.
     #[instrument(skip(self), err)]
-    pub fn save_current_epoch(&self, epoch_id: u64) -> Result<(), PolError> {
-        info!(epoch_id, "Saving current epoch");
+    pub fn list_seen(&self, mint: Option<&str>) -> Result<Vec<SeenCommitment>, PolError> {
+        let read_txn = self
+            .db
+            .begin_read()
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
+
+        let table = read_txn
+            .open_table(SEEN_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        // '}' sorts right after the '|' separating the mint from the epoch
+        let bounds = mint.map(|mint| (format!("{}|", mint), format!("{}}}", mint)));
+        let entries = match &bounds {
+            Some((start, end)) => table.range(start.as_str()..end.as_str()),
+            None => table.iter(),
+        }
+        .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+        let mut seen = Vec::new();
+        for result in entries {
+            let (_, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+            let entry: SeenCommitment = decode(self.encoding(), data.value())?;
+            // A mint label may itself contain '|'
+            if mint.map_or(true, |mint| entry.mint == mint) {
+                seen.push(entry);
+            }
+        }
+
+        Ok(seen)
+    }
+
+    #[instrument(skip(self, attestation), err)]
+    pub fn add_attestation(&self, attestation: &EpochAttestation) -> Result<(), PolError> {
+        info!(epoch_id = attestation.epoch_id, "Saving attestation");
         let write_txn = self
             .db
             .begin_write()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
         {
             let mut table = write_txn
-                .open_table(CURRENT_EPOCH_TABLE)
-                .map_err(|e| PolError::DatabaseError(e.to_string()))?;
+                .open_table(ATTESTATIONS_TABLE)
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
+
+            let mut attestations: Vec<EpochAttestation> = match table
+                .get(attestation.epoch_id)
+                .map_err(|e| PolError::DatabaseError(e.into()))?
+            {
+                Some(data) => decode(self.encoding(), data.value())?,
+                None => Vec::new(),
+            };
 
+            // A newer attestation from the same key replaces the older one
+            attestations.retain(|a| a.public_key != attestation.public_key);
+            attestations.push(attestation.clone());
+
+            let data = encode(self.encoding(), &attestations)?;
             table
-                .insert("current", epoch_id)
-                .map_err(|e| PolError::DatabaseError(e.to_string()))?;
+                .insert(attestation.epoch_id, data.as_slice())
+                .map_err(|e| PolError::DatabaseError(e.into()))?;
         }
 
         write_txn
             .commit()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
-        debug!(epoch_id, "Current epoch saved successfully");
         Ok(())
     }
 
     #[instrument(skip(self), err)]
-    pub fn get_current_epoch(&self) -> Result<Option<u64>, PolError> {
-        debug!("Getting current epoch");
+    pub fn get_attestations(&self, epoch_id: u64) -> Result<Vec<EpochAttestation>, PolError> {
+        debug!(epoch_id, "Getting attestations");
         let read_txn = self
             .db
             .begin_read()
-            .map_err(|e| PolError::DatabaseTransactionError(e.to_string()))?;
+            .map_err(|e| PolError::DatabaseTransactionError(e.into()))?;
 
         let table = read_txn
-            .open_table(CURRENT_EPOCH_TABLE)
-            .map_err(|e| PolError::DatabaseError(e.to_string()))?;
-
-        let result = table
-            .get("current")
-            .map_err(|e| PolError::DatabaseError(e.to_string()))?
-            .map(|v| v.value());
+            .open_table(ATTESTATIONS_TABLE)
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
 
-        if let Some(epoch_id) = result {
-            debug!(epoch_id, "Current epoch found");
-        } else {
-            warn!("No current epoch found");
-        }
+        let result = match table
+            .get(epoch_id)
+            .map_err(|e| PolError::DatabaseError(e.into()))?
+        {
+            Some(data) => decode(self.encoding(), data.value())?,
+            None => Vec::new(),
+        };
 
         Ok(result)
     }
 }
 
+fn compress(compression: Compression, data: Vec<u8>) -> Result<Vec<u8>, PolError> {
+    match compression {
+        Compression::None => Ok(data),
+        Compression::Zstd => zstd::encode_all(data.as_slice(), ZSTD_LEVEL)
+            .map_err(|e| PolError::DatabaseSerializationError(e.into())),
+    }
+}
+
+fn decompress(compression: Compression, data: &[u8]) -> Result<Cow<'_, [u8]>, PolError> {
+    match compression {
+        Compression::None => Ok(Cow::Borrowed(data)),
+        Compression::Zstd => zstd::decode_all(data)
+            .map(Cow::Owned)
+            .map_err(|e| PolError::DatabaseDeserializationError(e.into())),
+    }
+}
+
+fn encode<T: Serialize + ?Sized>(encoding: Encoding, value: &T) -> Result<Vec<u8>, PolError> {
+    let data = encoding
+        .codec
+        .encode(value)
+        .map_err(PolError::DatabaseSerializationError)?;
+    compress(encoding.compression, data)
+}
+
+fn decode<T: DeserializeOwned>(encoding: Encoding, data: &[u8]) -> Result<T, PolError> {
+    encoding
+        .codec
+        .decode(&decompress(encoding.compression, data)?)
+        .map_err(PolError::DatabaseDeserializationError)
+}
+
+/// Re-encodes every record of a table keyed by epoch id or sequence number.
+fn recode_by_id<T: Serialize + DeserializeOwned>(
+    write_txn: &WriteTransaction,
+    definition: TableDefinition<'_, u64, &'static [u8]>,
+    from: Encoding,
+    to: Encoding,
+) -> Result<(), PolError> {
+    let mut table = write_txn
+        .open_table(definition)
+        .map_err(|e| PolError::DatabaseError(e.into()))?;
+    let mut recoded = Vec::new();
+    for result in table
+        .iter()
+        .map_err(|e| PolError::DatabaseError(e.into()))?
+    {
+        let (key, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+        recoded.push((key.value(), encode(to, &decode::<T>(from, data.value())?)?));
+    }
+    for (key, data) in recoded {
+        table
+            .insert(key, data.as_slice())
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+    }
+    Ok(())
+}
+
+/// Re-encodes every record of a table keyed by name.
+fn recode_by_name<T: Serialize + DeserializeOwned>(
+    write_txn: &WriteTransaction,
+    definition: TableDefinition<'_, &'static str, &'static [u8]>,
+    from: Encoding,
+    to: Encoding,
+) -> Result<(), PolError> {
+    let mut table = write_txn
+        .open_table(definition)
+        .map_err(|e| PolError::DatabaseError(e.into()))?;
+    let mut recoded = Vec::new();
+    for result in table
+        .iter()
+        .map_err(|e| PolError::DatabaseError(e.into()))?
+    {
+        let (key, data) = result.map_err(|e| PolError::DatabaseError(e.into()))?;
+        recoded.push((
+            key.value().to_string(),
+            encode(to, &decode::<T>(from, data.value())?)?,
+        ));
+    }
+    for (key, data) in recoded {
+        table
+            .insert(key.as_str(), data.as_slice())
+            .map_err(|e| PolError::DatabaseError(e.into()))?;
+    }
+    Ok(())
+}
+
+fn read_meta<T: FromStr<Err = String>>(
+    meta: &impl ReadableTable<&'static str, &'static str>,
+    key: &str,
+) -> Result<Option<T>, PolError> {
+    meta.get(key)
+        .map_err(|e| PolError::DatabaseError(e.into()))?
+        .map(|value| value.value().parse())
+        .transpose()
+        .map_err(|e: String| PolError::DatabaseDeserializationError(e.into()))
+}
+
+/// Identifies a proof within its epoch for de-duplication. Always
+/// bincode, since the digests key stored proofs whatever the codec.
+fn proof_digest<P: Serialize>(proof: &P) -> Result<sha256::Hash, PolError> {
+    let data = serialize(proof).map_err(|e| PolError::DatabaseSerializationError(e.into()))?;
+    Ok(sha256::Hash::hash(&data))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::create_sample_mint_proof;
+    use crate::types::AuditOperation;
+    use bincode::deserialize;
+    use cdk::{nuts::nut02::Id, Amount as CashuAmount};
     use chrono::Utc;
     use std::collections::HashSet;
     use tempfile::tempdir;
@@ -239,5 +2492,400 @@ mod tests {
         // Test deleting epoch
         storage.delete_epoch(1).unwrap();
         assert!(storage.get_epoch(1).unwrap().is_none());
+
+        // Sealed epochs cannot be deleted
+        storage.save_epoch(&epoch_state).unwrap();
+        storage
+            .finalize_epoch(&FinalizedEpoch {
+                epoch_id: 1,
+                commitment: epoch_state.commitment().unwrap(),
+                signature: None,
+                finalized_at: Utc::now(),
+            })
+            .unwrap();
+        assert!(matches!(
+            storage.delete_epoch(1),
+            Err(PolError::EpochFinalized(1))
+        ));
+        assert!(storage.get_epoch(1).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_compression_is_kept_per_database() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mut epoch_state = EpochState {
+            epoch_id: 1,
+            start_time: Utc::now(),
+            mint_proofs: HashSet::new(),
+            burn_proofs: HashSet::new(),
+        };
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        for amount in [1u64, 2, 4, 8] {
+            epoch_state.mint_proofs.insert(create_sample_mint_proof(
+                keyset_id,
+                CashuAmount::from(amount),
+            ));
+        }
+
+        {
+            let storage = Storage::new(&db_path).unwrap();
+            storage.save_epoch(&epoch_state).unwrap();
+            let plain = storage.epoch_sizes().unwrap()[0].1;
+
+            // Switching re-encodes what is already stored
+            storage.set_compression(Compression::Zstd).unwrap();
+            assert_ne!(storage.epoch_sizes().unwrap()[0].1, plain);
+            let decoded = storage.get_epoch(1).unwrap().unwrap();
+            assert_eq!(decoded.mint_proofs, epoch_state.mint_proofs);
+        }
+
+        let reopened = Storage::new(&db_path).unwrap();
+        assert_eq!(reopened.compression(), Compression::Zstd);
+        let epochs = reopened.list_epochs().unwrap();
+        assert_eq!(epochs[0].mint_proofs, epoch_state.mint_proofs);
+    }
+
+    #[test]
+    fn test_codec_is_kept_per_database() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let mut epoch_state = EpochState {
+            epoch_id: 3,
+            start_time: Utc::now(),
+            mint_proofs: HashSet::new(),
+            burn_proofs: HashSet::new(),
+        };
+        epoch_state
+            .mint_proofs
+            .insert(create_sample_mint_proof(keyset_id, CashuAmount::from(8u64)));
+
+        {
+            let storage = Storage::new(&db_path).unwrap();
+            assert_eq!(storage.codec(), CodecKind::Bincode);
+            storage.save_epoch(&epoch_state).unwrap();
+            storage
+                .record_publication(&[3], &sha256::Hash::hash(b"report"))
+                .unwrap();
+            storage.set_codec(CodecKind::MessagePack).unwrap();
+            storage.set_compression(Compression::Zstd).unwrap();
+        }
+
+        // Records outside the epoch tables follow the codec too
+        let reopened = Storage::new(&db_path).unwrap();
+        assert_eq!(reopened.codec(), CodecKind::MessagePack);
+        assert_eq!(
+            reopened.get_publications(3).unwrap(),
+            vec![sha256::Hash::hash(b"report")]
+        );
+        let stored = reopened.get_epoch(3).unwrap().unwrap();
+        assert_eq!(stored.mint_proofs, epoch_state.mint_proofs);
+        assert_eq!(
+            reopened.get_epoch_summary(3).unwrap(),
+            Some(epoch_state.summary().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_epochs_are_stored_in_chunks() {
+        let temp_dir = tempdir().unwrap();
+        let storage = Storage::new(temp_dir.path().join("test.db")).unwrap();
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let mut epoch_state = EpochState {
+            epoch_id: 0,
+            start_time: Utc::now(),
+            mint_proofs: HashSet::new(),
+            burn_proofs: HashSet::new(),
+        };
+        for _ in 0..CHUNK_SIZE + 10 {
+            epoch_state
+                .mint_proofs
+                .insert(create_sample_mint_proof(keyset_id, CashuAmount::from(1u64)));
+        }
+        storage.save_epoch(&epoch_state).unwrap();
+
+        let extra = create_sample_mint_proof(keyset_id, CashuAmount::from(2u64));
+        let summary = storage.append_mint_proof(0, &extra).unwrap().unwrap();
+        assert_eq!(summary.mint_count, CHUNK_SIZE as u64 + 11);
+        assert_eq!(summary.minted, MilliSats::from_sat(CHUNK_SIZE as u64 + 12));
+        // Recording the same proof twice counts it once
+        assert_eq!(storage.append_mint_proof(0, &extra).unwrap(), Some(summary));
+        assert!(storage.append_mint_proof(1, &extra).unwrap().is_none());
+
+        epoch_state.mint_proofs.insert(extra);
+        let stored = storage.get_epoch(0).unwrap().unwrap();
+        assert_eq!(stored.mint_proofs, epoch_state.mint_proofs);
+        assert_eq!(
+            storage.get_epoch_summary(0).unwrap(),
+            Some(epoch_state.summary().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_sat_epochs_are_migrated_to_msat() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mint = create_sample_mint_proof(Id::from_bytes(&[0; 8]).unwrap(), 8u64.into());
+        let legacy: EpochV1 = LegacyEpoch {
+            epoch_id: 0,
+            start_time: Utc::now(),
+            mint_proofs: vec![UnquotedMint {
+                proof: mint.proof.clone(),
+                amount: Amount::from_sat(8),
+                timestamp: mint.timestamp,
+            }],
+            burn_proofs: vec![PlainBurn {
+                secret: "burned".to_string(),
+                amount: Amount::from_sat(3),
+                timestamp: mint.timestamp,
+            }],
+        };
+        {
+            let db = Database::create(&db_path).unwrap();
+            let write_txn = db.begin_write().unwrap();
+            {
+                let mut epochs = write_txn.open_table(LEGACY_EPOCHS_TABLE).unwrap();
+                epochs
+                    .insert(0, serialize(&legacy).unwrap().as_slice())
+                    .unwrap();
+                let mut balances = write_txn.open_table(OPENING_BALANCES_TABLE).unwrap();
+                balances.insert(0, 5).unwrap();
+            }
+            write_txn.commit().unwrap();
+        }
+
+        let storage = Storage::new(&db_path).unwrap();
+        let epoch = storage.get_epoch(0).unwrap().unwrap();
+        assert_eq!(epoch.mint_proofs, HashSet::from([mint]));
+        assert_eq!(epoch.burned().unwrap(), MilliSats::from_sat(3));
+        assert_eq!(
+            storage.get_opening_balance(0).unwrap(),
+            Some(MilliSats::from_sat(5))
+        );
+        drop(storage);
+
+        // The recorded version keeps balances from being scaled again
+        let reopened = Storage::new(&db_path).unwrap();
+        assert_eq!(
+            reopened.get_opening_balance(0).unwrap(),
+            Some(MilliSats::from_sat(5))
+        );
+    }
+
+    #[test]
+    fn test_unquoted_mint_proofs_are_read_by_schema_version() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mint = create_sample_mint_proof(Id::from_bytes(&[0; 8]).unwrap(), 8u64.into());
+        let legacy: EpochV2 = LegacyEpoch {
+            epoch_id: 0,
+            start_time: Utc::now(),
+            mint_proofs: vec![UnquotedMint {
+                proof: mint.proof.clone(),
+                amount: mint.amount,
+                timestamp: mint.timestamp,
+            }],
+            burn_proofs: Vec::new(),
+        };
+        let data = serialize(&legacy).unwrap();
+        // The current layout expects a quote that was never written
+        assert!(deserialize::<EpochState>(&data).is_err());
+        {
+            let db = Database::create(&db_path).unwrap();
+            let write_txn = db.begin_write().unwrap();
+            {
+                let mut epochs = write_txn.open_table(LEGACY_EPOCHS_TABLE).unwrap();
+                epochs.insert(0, data.as_slice()).unwrap();
+                let mut meta = write_txn.open_table(META_TABLE).unwrap();
+                meta.insert(SCHEMA_VERSION_KEY, "2").unwrap();
+            }
+            write_txn.commit().unwrap();
+        }
+
+        let storage = Storage::new(&db_path).unwrap();
+        let epoch = storage.get_epoch(0).unwrap().unwrap();
+        assert_eq!(epoch.mint_proofs, HashSet::from([mint.clone()]));
+
+        // The blob stays for a downgrade and is not chunked again on reopen
+        assert_eq!(storage.legacy_epoch_count().unwrap(), 1);
+        assert!(storage.set_codec(CodecKind::Postcard).is_err());
+        drop(storage);
+        let storage = Storage::new(&db_path).unwrap();
+        assert_eq!(
+            storage.get_epoch(0).unwrap().unwrap().mint_proofs,
+            HashSet::from([mint])
+        );
+
+        assert_eq!(storage.drop_legacy_epochs().unwrap(), 1);
+        assert_eq!(storage.legacy_epoch_count().unwrap(), 0);
+        assert!(storage.get_epoch(0).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_batch_append_crosses_chunks() {
+        let temp_dir = tempdir().unwrap();
+        let storage = Storage::new(temp_dir.path().join("test.db")).unwrap();
+        let mut epoch_state = EpochState {
+            epoch_id: 0,
+            start_time: Utc::now(),
+            mint_proofs: HashSet::new(),
+            burn_proofs: HashSet::new(),
+        };
+        storage.save_epoch(&epoch_state).unwrap();
+
+        let mut burns: Vec<BurnProof> = (0..CHUNK_SIZE + 5)
+            .map(|i| BurnProof {
+                secret: format!("burn_{}", i),
+                amount: MilliSats::from_sat(1),
+                timestamp: Utc::now(),
+                melt: None,
+            })
+            .collect();
+        burns.push(burns[0].clone());
+        let summary = storage.append_burn_proofs(0, &burns).unwrap().unwrap();
+        assert_eq!(summary.burn_count, CHUNK_SIZE as u64 + 5);
+
+        epoch_state.burn_proofs.extend(burns);
+        assert_eq!(
+            storage.get_epoch(0).unwrap().unwrap().burn_proofs,
+            epoch_state.burn_proofs
+        );
+    }
+
+    #[test]
+    fn test_rotate_epoch_is_one_transaction() {
+        let temp_dir = tempdir().unwrap();
+        let storage = Storage::new(temp_dir.path().join("test.db")).unwrap();
+        let epoch = |epoch_id| EpochState {
+            epoch_id,
+            start_time: Utc::now(),
+            mint_proofs: HashSet::new(),
+            burn_proofs: HashSet::new(),
+        };
+        storage.save_epoch(&epoch(0)).unwrap();
+        storage.save_epoch(&epoch(1)).unwrap();
+        storage.save_current_epoch(1).unwrap();
+        storage
+            .save_opening_balance(0, MilliSats::from_sat(1))
+            .unwrap();
+
+        let audit = AuditEntry {
+            timestamp: Utc::now(),
+            operation: AuditOperation::Prune {
+                epoch_ids: vec![0],
+                carried_balance: MilliSats::from_sat(3),
+                forced: false,
+            },
+        };
+        storage
+            .rotate_epoch(
+                &epoch(2),
+                &[],
+                MilliSats::from_sat(7),
+                &[0],
+                Some((1, MilliSats::from_sat(3))),
+                Some(&audit),
+            )
+            .unwrap();
+
+        let ids: Vec<u64> = storage
+            .list_epochs()
+            .unwrap()
+            .iter()
+            .map(|e| e.epoch_id)
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(storage.get_current_epoch().unwrap(), Some(2));
+        assert_eq!(
+            storage.get_opening_balance(2).unwrap(),
+            Some(MilliSats::from_sat(7))
+        );
+        assert_eq!(
+            storage.get_opening_balance(1).unwrap(),
+            Some(MilliSats::from_sat(3))
+        );
+        assert_eq!(storage.get_opening_balance(0).unwrap(), None);
+
+        let audit = storage.list_audit_entries().unwrap();
+        assert_eq!(audit.len(), 1);
+        assert!(matches!(
+            audit[0].operation,
+            AuditOperation::Prune { ref epoch_ids, .. } if epoch_ids == &[0]
+        ));
+    }
+
+    #[test]
+    fn test_errors_keep_their_source() {
+        let temp_dir = tempdir().unwrap();
+        let err = match Storage::new(temp_dir.path().join("missing").join("test.db")) {
+            Err(err) => err,
+            Ok(_) => panic!("opening a database in a missing directory succeeded"),
+        };
+
+        assert_eq!(err.code(), "database_initialization_error");
+        assert!(std::error::Error::source(&err).is_some());
+        assert_eq!(err.http_status(), 500);
+        assert_eq!(PolError::EpochNotFound(3).http_status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy_retries_only_transient_errors() {
+        let transient = || {
+            PolError::DatabaseError(redb::Error::Io(std::io::Error::from(
+                std::io::ErrorKind::Interrupted,
+            )))
+        };
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: StdDuration::ZERO,
+            max_delay: StdDuration::ZERO,
+        };
+
+        let mut calls = 0;
+        let result = policy
+            .run(|| {
+                calls += 1;
+                if calls < 3 {
+                    Err(transient())
+                } else {
+                    Ok(calls)
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<(), _> = policy
+            .run(|| {
+                calls += 1;
+                Err(PolError::DatabaseError(redb::Error::Corrupted(
+                    "bad page".to_string(),
+                )))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+
+        // A failing disk does not heal by waiting
+        let mut calls = 0;
+        let result: Result<(), _> = policy
+            .run(|| {
+                calls += 1;
+                Err(PolError::Io(std::io::Error::from_raw_os_error(5)))
+            })
+            .await;
+        assert!(!result.unwrap_err().is_retryable());
+        assert_eq!(calls, 1);
+
+        let mut calls = 0;
+        let result: Result<(), _> = policy
+            .run(|| {
+                calls += 1;
+                Err(transient())
+            })
+            .await;
+        assert!(result.unwrap_err().is_retryable());
+        assert_eq!(calls, 3);
     }
 }
@@ -0,0 +1,96 @@
+use crate::output::OutputFormat;
+use crate::serve::UNIX_PREFIX;
+use cashu_pol::PolEvent;
+use std::error::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::net::{TcpStream, UnixStream};
+use tracing::{debug, info};
+
+/// Prints events from a `serve` event feed as they arrive, until the
+/// daemon closes the connection or ctrl-c. JSON output passes each event
+/// line through unchanged; text output gives one short line per event.
+/// `address` is a TCP address or `unix:<path>`.
+pub async fn run(address: &str, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let stream: Box<dyn AsyncRead + Unpin + Send> = match address.strip_prefix(UNIX_PREFIX) {
+        Some(path) => Box::new(
+            UnixStream::connect(path)
+                .await
+                .map_err(|e| format!("{}: {}", path, e))?,
+        ),
+        None => Box::new(
+            TcpStream::connect(address)
+                .await
+                .map_err(|e| format!("{}: {}", address, e))?,
+        ),
+    };
+    info!(address, "Attached to event feed");
+
+    let mut lines = BufReader::new(stream).lines();
+    loop {
+        let line = tokio::select! {
+            result = tokio::signal::ctrl_c() => return Ok(result?),
+            line = lines.next_line() => line?,
+        };
+        let Some(line) = line else {
+            info!("Event feed closed");
+            return Ok(());
+        };
+        match format {
+            OutputFormat::Json => println!("{}", line),
+            // A newer daemon may send events this build does not know
+            OutputFormat::Text => match serde_json::from_str(&line) {
+                Ok(event) => println!("{}", describe(&event)),
+                Err(e) => debug!(error = %e, "Skipping unrecognized event"),
+            },
+        }
+    }
+}
+
+fn describe(event: &PolEvent) -> String {
+    match event {
+        PolEvent::MintRecorded { epoch_id, proof } => format!(
+            "{} mint    epoch {} {}",
+            proof.timestamp.to_rfc3339(),
+            epoch_id,
+            proof.amount
+        ),
+        PolEvent::BurnRecorded { epoch_id, proof } => format!(
+            "{} burn    epoch {} {}",
+            proof.timestamp.to_rfc3339(),
+            epoch_id,
+            proof.amount
+        ),
+        PolEvent::EpochRotated {
+            previous_epoch_id,
+            new_epoch_id,
+            pruned_epoch_ids,
+            timestamp,
+        } => format!(
+            "{} rotate  epoch {} -> {}, pruned {:?}",
+            timestamp.to_rfc3339(),
+            previous_epoch_id,
+            new_epoch_id,
+            pruned_epoch_ids
+        ),
+        PolEvent::ReportGenerated {
+            epoch_count,
+            total_outstanding_balance,
+            timestamp,
+        } => format!(
+            "{} report  {} epochs, {} outstanding",
+            timestamp.to_rfc3339(),
+            epoch_count,
+            total_outstanding_balance
+        ),
+        PolEvent::Alert {
+            epoch_id,
+            message,
+            timestamp,
+        } => format!(
+            "{} ALERT   epoch {}: {}",
+            timestamp.to_rfc3339(),
+            epoch_id,
+            message
+        ),
+    }
+}
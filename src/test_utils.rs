@@ -1,11 +1,15 @@
-use bitcoin::Amount as BitcoinAmount;
 use cdk::{
     nuts::nut00::Proof, nuts::nut01::PublicKey, nuts::nut02::Id, secret::Secret,
     Amount as CashuAmount,
 };
 use chrono::Utc;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::PathBuf;
+use tempfile::TempDir;
 
-use crate::types::{BurnProof, EpochState, MintProof};
+use crate::service::PolService;
+use crate::types::{BurnProof, MilliSats, MintProof, PolError};
 
 pub fn create_sample_proof(keyset_id: Id, amount: CashuAmount) -> Proof {
     let secret = Secret::generate();
@@ -19,8 +23,799 @@ pub fn create_sample_mint_proof(keyset_id: Id, amount: CashuAmount) -> MintProof
     let amount_u64: u64 = amount.into();
     MintProof {
         proof,
-        amount: BitcoinAmount::from_sat(amount_u64),
+        amount: MilliSats::from_sat(amount_u64),
         timestamp: Utc::now(),
+        quote: None,
+    }
+}
+
+/// Proofs for one epoch together with the balances a report should show.
+#[derive(Debug, Clone)]
+pub struct SampleEpoch {
+    pub mint_proofs: Vec<MintProof>,
+    pub burn_proofs: Vec<BurnProof>,
+    pub minted_sat: u64,
+    pub burned_sat: u64,
+    /// Outstanding liabilities once this epoch closes
+    pub expected_closing_sat: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SampleData {
+    pub epochs: Vec<SampleEpoch>,
+}
+
+impl SampleData {
+    /// Records each epoch's proofs into `service`, rotating between epochs.
+    pub async fn apply(&self, service: &PolService) -> Result<(), PolError> {
+        for (index, epoch) in self.epochs.iter().enumerate() {
+            if index > 0 {
+                service.rotate_epoch().await?;
+            }
+            for mint in &epoch.mint_proofs {
+                service
+                    .record_mint_proof(mint.proof.clone(), mint.amount)
+                    .await?;
+            }
+            for burn in &epoch.burn_proofs {
+                service
+                    .record_burn_proof(burn.secret.clone(), burn.amount)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn expected_outstanding_sat(&self) -> u64 {
+        self.epochs.last().map_or(0, |e| e.expected_closing_sat)
+    }
+}
+
+/// Builds reproducible mint/burn scenarios: the same seed always yields the
+/// same proofs. Burns redeem proofs minted in the same or earlier epochs.
+#[derive(Debug, Clone)]
+pub struct SampleDataBuilder {
+    seed: u64,
+    epochs: usize,
+    mints_per_epoch: usize,
+    burns_per_epoch: usize,
+    max_amount: u64,
+}
+
+impl SampleDataBuilder {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            epochs: 3,
+            mints_per_epoch: 4,
+            burns_per_epoch: 2,
+            max_amount: 1024,
+        }
+    }
+
+    pub fn epochs(mut self, epochs: usize) -> Self {
+        self.epochs = epochs;
+        self
+    }
+
+    pub fn mints_per_epoch(mut self, mints_per_epoch: usize) -> Self {
+        self.mints_per_epoch = mints_per_epoch;
+        self
+    }
+
+    /// Upper bound; fewer burns happen when not enough proofs are unspent.
+    pub fn burns_per_epoch(mut self, burns_per_epoch: usize) -> Self {
+        self.burns_per_epoch = burns_per_epoch;
+        self
+    }
+
+    pub fn max_amount(mut self, max_amount: u64) -> Self {
+        self.max_amount = max_amount.max(1);
+        self
+    }
+
+    pub fn build(&self) -> SampleData {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let keyset_id = Id::from_bytes(&[0; 8]).unwrap();
+        let c = PublicKey::from_slice(&[2; 33]).unwrap();
+        let mut unspent: Vec<(String, u64)> = Vec::new();
+        let mut outstanding = 0u64;
+
+        let epochs = (0..self.epochs)
+            .map(|_| {
+                let mint_proofs: Vec<MintProof> = (0..self.mints_per_epoch)
+                    .map(|_| {
+                        let amount = rng.gen_range(1..=self.max_amount);
+                        let secret = hex::encode(rng.gen::<[u8; 32]>());
+                        unspent.push((secret.clone(), amount));
+                        MintProof {
+                            proof: Proof::new(
+                                CashuAmount::from(amount),
+                                keyset_id,
+                                Secret::new(secret),
+                                c,
+                            ),
+                            amount: MilliSats::from_sat(amount),
+                            timestamp: Utc::now(),
+                            quote: None,
+                        }
+                    })
+                    .collect();
+
+                let burn_proofs: Vec<BurnProof> = (0..self.burns_per_epoch)
+                    .filter_map(|_| {
+                        if unspent.is_empty() {
+                            return None;
+                        }
+                        let (secret, amount) = unspent.swap_remove(rng.gen_range(0..unspent.len()));
+                        Some(BurnProof {
+                            secret,
+                            amount: MilliSats::from_sat(amount),
+                            timestamp: Utc::now(),
+                            melt: None,
+                        })
+                    })
+                    .collect();
+
+                let minted_sat = mint_proofs.iter().map(|p| p.amount.to_sat()).sum();
+                let burned_sat = burn_proofs.iter().map(|p| p.amount.to_sat()).sum();
+                outstanding = outstanding + minted_sat - burned_sat;
+
+                SampleEpoch {
+                    mint_proofs,
+                    burn_proofs,
+                    minted_sat,
+                    burned_sat,
+                    expected_closing_sat: outstanding,
+                }
+            })
+            .collect();
+
+        SampleData { epochs }
+    }
+}
+
+/// A database in a temporary directory that is removed on drop. The
+/// database is closed between uses so tests can reopen it with whatever
+/// service configuration they exercise.
+pub struct DbFixture {
+    dir: TempDir,
+}
+
+impl DbFixture {
+    pub async fn empty() -> Result<Self, PolError> {
+        let fixture = Self::new()?;
+        fixture.open(30, 24)?.initialize().await?;
+        Ok(fixture)
+    }
+
+    /// A database already holding `data`, recorded under the given epoch
+    /// settings.
+    pub async fn populated(
+        data: &SampleData,
+        epoch_duration_days: i64,
+        max_epoch_history: usize,
+    ) -> Result<Self, PolError> {
+        let fixture = Self::new()?;
+        let service = fixture.open(epoch_duration_days, max_epoch_history)?;
+        service.initialize().await?;
+        data.apply(&service).await?;
+        Ok(fixture)
+    }
+
+    fn new() -> Result<Self, PolError> {
+        Ok(Self {
+            dir: TempDir::new()?,
+        })
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.path().join("fixture.db")
+    }
+
+    pub fn open(
+        &self,
+        epoch_duration_days: i64,
+        max_epoch_history: usize,
+    ) -> Result<PolService, PolError> {
+        PolService::with_path(epoch_duration_days, max_epoch_history, self.path())
+    }
+}
+
+/// A fake Cashu mint on a local port, for exercising code that talks to a
+/// mint without running one. It serves NUT-01 keys, NUT-02 keysets, NUT-07
+/// checkstate, bolt11 mint and melt, and a `/ledger` dump of everything it
+/// issued and saw spent. Mints and melts are also broadcast as events.
+pub mod mock_mint {
+    use crate::reconcile::{IssuedEntry, MintLedger, SpentEntry};
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use bitcoin::hashes::{sha256, Hash};
+    use cdk::dhke::{blind_message, hash_to_curve, sign_message, unblind_message};
+    use cdk::nuts::nut00::Proof;
+    use cdk::nuts::nut01::{PublicKey, SecretKey};
+    use cdk::nuts::nut02::Id;
+    use cdk::secret::Secret;
+    use cdk::Amount as CashuAmount;
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use std::collections::{BTreeMap, HashSet};
+    use std::sync::{Arc, Mutex, PoisonError};
+    use tokio::net::TcpListener;
+    use tokio::sync::broadcast;
+    use tokio::task::JoinHandle;
+
+    /// Denominations each keyset signs, 1 to 2^15 sats
+    const DENOMINATIONS: u32 = 16;
+
+    /// NUT error code for inputs that were already spent
+    const TOKEN_ALREADY_SPENT: u32 = 11001;
+
+    /// Something the mock mint did in answer to a request.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum MockMintEvent {
+        /// Outputs were signed against a mint quote
+        Minted {
+            quote: String,
+            keyset_id: Id,
+            amount: u64,
+        },
+        /// Inputs were spent to pay a melt quote
+        Melted {
+            quote: String,
+            ys: Vec<PublicKey>,
+            amount: u64,
+        },
+    }
+
+    struct Keyset {
+        id: Id,
+        keys: BTreeMap<u64, SecretKey>,
+    }
+
+    impl Keyset {
+        fn new(index: u8) -> Self {
+            let keys = (0..DENOMINATIONS)
+                .map(|power| {
+                    let amount = 1u64 << power;
+                    let mut seed = b"mock-mint".to_vec();
+                    seed.push(index);
+                    seed.extend_from_slice(&amount.to_be_bytes());
+                    let key = SecretKey::from_slice(sha256::Hash::hash(&seed).as_byte_array())
+                        .expect("hash is a valid secret key");
+                    (amount, key)
+                })
+                .collect();
+            Self {
+                id: Id::from_bytes(&[0, 0, 0, 0, 0, 0, 0, index]).expect("valid keyset id"),
+                keys,
+            }
+        }
+
+        fn key(&self, amount: u64) -> Result<&SecretKey, (StatusCode, Json<Value>)> {
+            self.keys
+                .get(&amount)
+                .ok_or_else(|| bad_request(0, format!("No key for amount {}", amount)))
+        }
+    }
+
+    struct MockState {
+        keysets: Vec<Keyset>,
+        spent: Mutex<HashSet<Vec<u8>>>,
+        ledger: Mutex<MintLedger>,
+        events: broadcast::Sender<MockMintEvent>,
+    }
+
+    impl MockState {
+        fn keyset(&self, id: &Id) -> Result<&Keyset, (StatusCode, Json<Value>)> {
+            self.keysets
+                .iter()
+                .find(|keyset| keyset.id == *id)
+                .ok_or_else(|| bad_request(0, format!("Unknown keyset {}", id)))
+        }
+    }
+
+    pub struct MockMint {
+        url: String,
+        state: Arc<MockState>,
+        server: JoinHandle<()>,
+    }
+
+    impl MockMint {
+        /// Starts a mint with `keysets` active sat keysets on 127.0.0.1.
+        pub async fn start(keysets: u8) -> std::io::Result<Self> {
+            let (events, _) = broadcast::channel(1024);
+            let state = Arc::new(MockState {
+                keysets: (0..keysets).map(Keyset::new).collect(),
+                spent: Mutex::default(),
+                ledger: Mutex::default(),
+                events,
+            });
+            let router = Router::new()
+                .route("/v1/keys", get(keys))
+                .route("/v1/keysets", get(keysets_info))
+                .route("/v1/checkstate", post(check_state))
+                .route("/v1/mint/bolt11", post(mint))
+                .route("/v1/melt/bolt11", post(melt))
+                .route("/ledger", get(ledger))
+                .with_state(state.clone());
+
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let url = format!("http://{}", listener.local_addr()?);
+            let server = tokio::spawn(async move {
+                let _ = axum::serve(listener, router).await;
+            });
+            Ok(Self { url, state, server })
+        }
+
+        pub fn url(&self) -> &str {
+            &self.url
+        }
+
+        pub fn keyset_ids(&self) -> Vec<Id> {
+            self.state.keysets.iter().map(|keyset| keyset.id).collect()
+        }
+
+        pub fn subscribe(&self) -> broadcast::Receiver<MockMintEvent> {
+            self.state.events.subscribe()
+        }
+
+        /// Everything issued and spent so far, as served at `/ledger`.
+        pub fn ledger(&self) -> MintLedger {
+            self.state
+                .ledger
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        }
+
+        /// Mints `amount` sats from `keyset_id` over HTTP the way a wallet
+        /// would, blinding one output per denomination and unblinding the
+        /// signatures into proofs.
+        pub async fn mint(
+            &self,
+            keyset_id: Id,
+            quote: &str,
+            amount: u64,
+        ) -> Result<Vec<Proof>, reqwest::Error> {
+            let keyset = self
+                .state
+                .keysets
+                .iter()
+                .find(|keyset| keyset.id == keyset_id)
+                .expect("keyset of this mint");
+            let mut outputs = Vec::new();
+            let mut pending = Vec::new();
+            for power in 0..DENOMINATIONS {
+                let denomination = 1u64 << power;
+                if amount & denomination == 0 {
+                    continue;
+                }
+                let secret = Secret::generate();
+                let (blinded, r) =
+                    blind_message(secret.as_bytes(), None).expect("secret hashes to a point");
+                outputs.push(BlindedOutput {
+                    amount: denomination,
+                    id: keyset_id,
+                    blinded,
+                });
+                pending.push((denomination, secret, r));
+            }
+
+            let response: MintResponse = reqwest::Client::new()
+                .post(format!("{}/v1/mint/bolt11", self.url))
+                .json(&MintRequest {
+                    quote: quote.to_string(),
+                    outputs,
+                })
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            Ok(pending
+                .into_iter()
+                .zip(response.signatures)
+                .map(|((denomination, secret, r), signature)| {
+                    let key = keyset.keys[&denomination].public_key();
+                    let c = unblind_message(&signature.signature, &r, &key)
+                        .expect("signature over our output");
+                    Proof::new(CashuAmount::from(denomination), keyset_id, secret, c)
+                })
+                .collect())
+        }
+
+        /// Spends `proofs` over HTTP to pay a melt quote.
+        pub async fn melt(&self, quote: &str, proofs: &[Proof]) -> Result<(), reqwest::Error> {
+            reqwest::Client::new()
+                .post(format!("{}/v1/melt/bolt11", self.url))
+                .json(&json!({ "quote": quote, "inputs": proofs }))
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        }
+    }
+
+    impl Drop for MockMint {
+        fn drop(&mut self) {
+            self.server.abort();
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct BlindedOutput {
+        amount: u64,
+        id: Id,
+        #[serde(rename = "B_")]
+        blinded: PublicKey,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct BlindSignature {
+        amount: u64,
+        id: Id,
+        #[serde(rename = "C_")]
+        signature: PublicKey,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct MintRequest {
+        quote: String,
+        outputs: Vec<BlindedOutput>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct MintResponse {
+        signatures: Vec<BlindSignature>,
+    }
+
+    #[derive(Deserialize)]
+    struct MeltRequest {
+        quote: String,
+        inputs: Vec<Proof>,
+    }
+
+    #[derive(Deserialize)]
+    struct CheckStateRequest {
+        #[serde(rename = "Ys")]
+        ys: Vec<PublicKey>,
+    }
+
+    fn bad_request(code: u32, detail: String) -> (StatusCode, Json<Value>) {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "code": code, "detail": detail })),
+        )
+    }
+
+    async fn keys(State(state): State<Arc<MockState>>) -> Json<Value> {
+        let keysets: Vec<Value> = state
+            .keysets
+            .iter()
+            .map(|keyset| {
+                let keys: BTreeMap<String, PublicKey> = keyset
+                    .keys
+                    .iter()
+                    .map(|(amount, key)| (amount.to_string(), key.public_key()))
+                    .collect();
+                json!({ "id": keyset.id, "unit": "sat", "keys": keys })
+            })
+            .collect();
+        Json(json!({ "keysets": keysets }))
+    }
+
+    async fn keysets_info(State(state): State<Arc<MockState>>) -> Json<Value> {
+        let keysets: Vec<Value> = state
+            .keysets
+            .iter()
+            .map(|keyset| json!({ "id": keyset.id, "unit": "sat", "active": true }))
+            .collect();
+        Json(json!({ "keysets": keysets }))
+    }
+
+    async fn check_state(
+        State(state): State<Arc<MockState>>,
+        Json(request): Json<CheckStateRequest>,
+    ) -> Json<Value> {
+        let spent = state.spent.lock().unwrap_or_else(PoisonError::into_inner);
+        let states: Vec<Value> = request
+            .ys
+            .iter()
+            .map(|y| {
+                let proof_state = if spent.contains(y.to_bytes().as_slice()) {
+                    "SPENT"
+                } else {
+                    "UNSPENT"
+                };
+                json!({ "Y": y, "state": proof_state, "witness": null })
+            })
+            .collect();
+        Json(json!({ "states": states }))
+    }
+
+    async fn mint(
+        State(state): State<Arc<MockState>>,
+        Json(request): Json<MintRequest>,
+    ) -> Result<Json<MintResponse>, (StatusCode, Json<Value>)> {
+        let mut signatures = Vec::with_capacity(request.outputs.len());
+        for output in &request.outputs {
+            let key = state.keyset(&output.id)?.key(output.amount)?;
+            let signature = sign_message(key, &output.blinded)
+                .map_err(|e| bad_request(0, format!("Invalid output: {}", e)))?;
+            signatures.push(BlindSignature {
+                amount: output.amount,
+                id: output.id,
+                signature,
+            });
+        }
+
+        let mut ledger = state.ledger.lock().unwrap_or_else(PoisonError::into_inner);
+        for output in &request.outputs {
+            ledger.issued.push(IssuedEntry {
+                keyset_id: output.id,
+                amount: output.amount,
+            });
+            let _ = state.events.send(MockMintEvent::Minted {
+                quote: request.quote.clone(),
+                keyset_id: output.id,
+                amount: output.amount,
+            });
+        }
+        Ok(Json(MintResponse { signatures }))
+    }
+
+    async fn melt(
+        State(state): State<Arc<MockState>>,
+        Json(request): Json<MeltRequest>,
+    ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+        let mut inputs = Vec::with_capacity(request.inputs.len());
+        for proof in &request.inputs {
+            let amount = u64::from(proof.amount);
+            let key = state.keyset(&proof.keyset_id)?.key(amount)?;
+            let y = hash_to_curve(proof.secret.as_bytes())
+                .map_err(|e| bad_request(0, format!("Invalid secret: {}", e)))?;
+            if sign_message(key, &y).ok() != Some(proof.c) {
+                return Err(bad_request(0, "Invalid proof signature".to_string()));
+            }
+            inputs.push((y, amount));
+        }
+
+        let mut spent = state.spent.lock().unwrap_or_else(PoisonError::into_inner);
+        if inputs
+            .iter()
+            .any(|(y, _)| spent.contains(y.to_bytes().as_slice()))
+        {
+            return Err(bad_request(
+                TOKEN_ALREADY_SPENT,
+                "Token already spent".to_string(),
+            ));
+        }
+        let mut ledger = state.ledger.lock().unwrap_or_else(PoisonError::into_inner);
+        for (y, amount) in &inputs {
+            spent.insert(y.to_bytes().to_vec());
+            ledger.spent.push(SpentEntry {
+                y: Some(*y),
+                secret: None,
+                amount: *amount,
+            });
+        }
+        let _ = state.events.send(MockMintEvent::Melted {
+            quote: request.quote,
+            ys: inputs.iter().map(|(y, _)| *y).collect(),
+            amount: inputs.iter().map(|(_, amount)| amount).sum(),
+        });
+        Ok(Json(json!({ "state": "PAID", "paid": true })))
+    }
+
+    async fn ledger(State(state): State<Arc<MockState>>) -> Json<MintLedger> {
+        Json(
+            state
+                .ledger
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+        )
+    }
+}
+
+/// Proptest strategies for the core types, enabled with the `proptest`
+/// feature.
+#[cfg(feature = "proptest")]
+pub mod strategies {
+    use super::*;
+    use crate::types::{CumulativeBalance, EpochReport, EpochState, PolReport};
+    use bitcoin::Amount;
+    use chrono::{DateTime, Duration};
+    use proptest::collection::{hash_set, vec};
+    use proptest::prelude::*;
+
+    const MAX_MSAT: u64 = 1 << 40;
+
+    pub fn milli_sats() -> impl Strategy<Value = MilliSats> {
+        (1..MAX_MSAT).prop_map(MilliSats::from_msat)
+    }
+
+    pub fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+        (0i64..4_000_000_000).prop_map(|secs| DateTime::from_timestamp(secs, 0).unwrap())
+    }
+
+    fn secret() -> impl Strategy<Value = String> {
+        any::<[u8; 32]>().prop_map(hex::encode)
+    }
+
+    pub fn mint_proof() -> impl Strategy<Value = MintProof> {
+        (secret(), milli_sats(), timestamp()).prop_map(|(secret, amount, timestamp)| MintProof {
+            proof: Proof::new(
+                CashuAmount::from(amount.to_sat()),
+                Id::from_bytes(&[0; 8]).unwrap(),
+                Secret::new(secret),
+                PublicKey::from_slice(&[2; 33]).unwrap(),
+            ),
+            amount,
+            timestamp,
+            quote: None,
+        })
+    }
+
+    pub fn burn_proof() -> impl Strategy<Value = BurnProof> {
+        (secret(), milli_sats(), timestamp()).prop_map(|(secret, amount, timestamp)| BurnProof {
+            secret,
+            amount,
+            timestamp,
+            melt: None,
+        })
+    }
+
+    pub fn epoch_state() -> impl Strategy<Value = EpochState> {
+        (
+            0u64..1_000,
+            timestamp(),
+            hash_set(mint_proof(), 0..8),
+            hash_set(burn_proof(), 0..8),
+        )
+            .prop_map(
+                |(epoch_id, start_time, mint_proofs, burn_proofs)| EpochState {
+                    epoch_id,
+                    start_time,
+                    mint_proofs,
+                    burn_proofs,
+                },
+            )
+    }
+
+    /// A report over consecutive arbitrary epochs with consistent balances.
+    pub fn pol_report() -> impl Strategy<Value = PolReport> {
+        (vec(epoch_state(), 0..4), timestamp()).prop_map(|(epochs, timestamp)| {
+            let mut closing = MilliSats::ZERO;
+            let epoch_reports: Vec<EpochReport> = epochs
+                .into_iter()
+                .enumerate()
+                .map(|(index, mut epoch)| {
+                    epoch.epoch_id = index as u64;
+                    let opening = closing;
+                    let (minted, burned) = (epoch.minted().unwrap(), epoch.burned().unwrap());
+                    let outstanding = minted.saturating_sub(burned);
+                    closing = opening.try_add(minted).unwrap().saturating_sub(burned);
+                    let burn_index = epoch.burn_index_root().unwrap();
+                    EpochReport {
+                        epoch_id: epoch.epoch_id,
+                        start_time: epoch.start_time,
+                        end_time: Some(epoch.start_time + Duration::days(30)),
+                        commitment: epoch.commitment().unwrap(),
+                        mint_proofs: epoch.mint_proofs.into_iter().collect(),
+                        burn_proofs: epoch.burn_proofs.into_iter().collect(),
+                        outstanding_balance: outstanding.to_amount(),
+                        opening_balance: opening.to_amount(),
+                        closing_balance: closing.to_amount(),
+                        attestations: Vec::new(),
+                        finalized_at: None,
+                        confidential: None,
+                        keysets: Vec::new(),
+                        burn_index: Some(burn_index),
+                        aggregates: None,
+                    }
+                })
+                .collect();
+
+            PolReport {
+                cumulative_balances: epoch_reports
+                    .iter()
+                    .map(|e| CumulativeBalance {
+                        epoch_id: e.epoch_id,
+                        balance: e.closing_balance,
+                    })
+                    .collect(),
+                epoch_reports,
+                total_outstanding_balance: closing.to_amount(),
+                pruned_balance: Amount::ZERO,
+                fiat_annotation: None,
+                timestamp,
+                external_observations: Vec::new(),
+                history: None,
+                history_proofs: Vec::new(),
+            }
+        })
+    }
+
+    impl Arbitrary for MilliSats {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            milli_sats().boxed()
+        }
+    }
+
+    impl Arbitrary for MintProof {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            mint_proof().boxed()
+        }
+    }
+
+    impl Arbitrary for BurnProof {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            burn_proof().boxed()
+        }
+    }
+
+    impl Arbitrary for EpochState {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            epoch_state().boxed()
+        }
+    }
+
+    impl Arbitrary for PolReport {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            pol_report().boxed()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        proptest! {
+            #[test]
+            fn epoch_balance_is_mints_minus_burns(epoch in any::<EpochState>()) {
+                let minted: u64 = epoch.mint_proofs.iter().map(|p| p.amount.to_msat()).sum();
+                let burned: u64 = epoch.burn_proofs.iter().map(|p| p.amount.to_msat()).sum();
+                prop_assert_eq!(
+                    epoch.minted().unwrap().saturating_sub(epoch.burned().unwrap()).to_msat(),
+                    minted.saturating_sub(burned)
+                );
+            }
+
+            #[test]
+            fn epoch_state_roundtrips_through_storage_encoding(epoch in any::<EpochState>()) {
+                let decoded: EpochState =
+                    bincode::deserialize(&bincode::serialize(&epoch).unwrap()).unwrap();
+                prop_assert_eq!(decoded.commitment().unwrap(), epoch.commitment().unwrap());
+            }
+
+            #[test]
+            fn report_roundtrips_through_json(report in any::<PolReport>()) {
+                let decoded: PolReport =
+                    serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+                prop_assert_eq!(decoded.commitment().unwrap(), report.commitment().unwrap());
+            }
+        }
     }
 }
 
@@ -43,6 +838,93 @@ mod tests {
         let amount = CashuAmount::from(1000u64);
         let amount_u64: u64 = amount.into();
         let mint_proof = create_sample_mint_proof(keyset_id, amount.clone());
-        assert_eq!(mint_proof.amount, BitcoinAmount::from_sat(amount_u64));
+        assert_eq!(mint_proof.amount, MilliSats::from_sat(amount_u64));
+    }
+
+    #[tokio::test]
+    async fn test_mock_mint_follows_and_reconciles() {
+        use crate::reconcile::MintLedger;
+        use mock_mint::{MockMint, MockMintEvent};
+
+        let mint = MockMint::start(1).await.unwrap();
+        let keyset_id = mint.keyset_ids()[0];
+        let mut events = mint.subscribe();
+        let proofs = mint.mint(keyset_id, "mint-quote", 13).await.unwrap();
+        assert_eq!(proofs.len(), 3);
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            MockMintEvent::Minted { amount: 1, .. }
+        ));
+
+        let fixture = DbFixture::empty().await.unwrap();
+        let service = fixture.open(30, 24).unwrap();
+        service.initialize().await.unwrap();
+        for proof in &proofs {
+            service
+                .record_mint_proof(proof.clone(), MilliSats::from_sat(proof.amount.into()))
+                .await
+                .unwrap();
+        }
+
+        mint.melt("melt-quote", &proofs[..1]).await.unwrap();
+        assert!(mint.melt("melt-quote", &proofs[..1]).await.is_err());
+
+        let observation = service.follow_mint(mint.url()).await.unwrap();
+        assert_eq!(observation.proofs_checked, 3);
+        assert_eq!(observation.spent_observed, 1);
+
+        let ledger = MintLedger::load(&format!("{}/ledger", mint.url()))
+            .await
+            .unwrap();
+        assert_eq!(ledger.spent.len(), 1);
+        assert!(service.reconcile(&ledger).await.unwrap().is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_populated_fixture_reopens() {
+        let data = SampleDataBuilder::new(1).epochs(2).build();
+        let fixture = DbFixture::populated(&data, 30, 24).await.unwrap();
+
+        let service = fixture.open(30, 24).unwrap();
+        service.initialize().await.unwrap();
+        assert_eq!(service.current_epoch().await, 1);
+
+        let report = service.generate_report().await.unwrap();
+        assert_eq!(
+            report.total_outstanding_balance.to_sat(),
+            data.epochs.last().unwrap().expected_closing_sat
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sample_data_is_reproducible() {
+        let builder = SampleDataBuilder::new(7).epochs(4);
+        let first = builder.build();
+        let second = builder.build();
+        let secrets = |data: &SampleData| -> Vec<String> {
+            data.epochs
+                .iter()
+                .flat_map(|e| e.burn_proofs.iter().map(|p| p.secret.clone()))
+                .collect()
+        };
+        assert_eq!(secrets(&first), secrets(&second));
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let service = PolService::with_path(30, 24, temp_dir.path().join("test.db")).unwrap();
+        service.initialize().await.unwrap();
+        first.apply(&service).await.unwrap();
+
+        let report = service.generate_report().await.unwrap();
+        let closing: Vec<u64> = report
+            .epoch_reports
+            .iter()
+            .map(|e| e.closing_balance.to_sat())
+            .collect();
+        let expected: Vec<u64> = first
+            .epochs
+            .iter()
+            .map(|e| e.expected_closing_sat)
+            .collect();
+        assert_eq!(closing, expected);
     }
 }
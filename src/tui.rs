@@ -0,0 +1,299 @@
+use cashu_pol::{EpochListing, MilliSats, PolError, PolEvent, PolService, ServiceStatus};
+use chrono::{DateTime, Duration, Utc};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::error::Error;
+use std::io::{self, Stdout};
+use std::time::Duration as StdDuration;
+use tokio::sync::broadcast::error::TryRecvError;
+
+const RECENT_RECORDS: usize = 15;
+
+/// Stored state only; generating a report here would publish and broadcast
+/// it on every refresh.
+struct Snapshot {
+    status: ServiceStatus,
+    epochs: Vec<EpochListing>,
+}
+
+#[derive(PartialEq)]
+struct RecentRecord {
+    kind: &'static str,
+    epoch_id: u64,
+    amount: MilliSats,
+    timestamp: DateTime<Utc>,
+}
+
+pub async fn run(service: &PolService, refresh: StdDuration) -> Result<(), Box<dyn Error>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = event_loop(&mut terminal, service, refresh).await;
+
+    // Always hand the terminal back, even if the loop failed
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+async fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    service: &PolService,
+    refresh: StdDuration,
+) -> Result<(), Box<dyn Error>> {
+    let mut ticker = tokio::time::interval(StdDuration::from_millis(200));
+    // Subscribe first so nothing recorded while loading is missed
+    let mut events = service.subscribe_events();
+    let mut snapshot = load_snapshot(service).await?;
+    let mut recent = load_recent(service).await?;
+    let mut last_refresh = tokio::time::Instant::now();
+
+    loop {
+        terminal.draw(|frame| draw(frame, &snapshot, &recent))?;
+
+        while event::poll(StdDuration::from_millis(0))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('r') => {
+                        snapshot = load_snapshot(service).await?;
+                        recent = load_recent(service).await?;
+                        last_refresh = tokio::time::Instant::now();
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        loop {
+            match events.try_recv() {
+                Ok(event) => push_recent(&mut recent, event),
+                Err(TryRecvError::Lagged(_)) => recent = load_recent(service).await?,
+                Err(_) => break,
+            }
+        }
+
+        ticker.tick().await;
+        if last_refresh.elapsed() >= refresh {
+            snapshot = load_snapshot(service).await?;
+            last_refresh = tokio::time::Instant::now();
+        }
+    }
+}
+
+async fn load_snapshot(service: &PolService) -> Result<Snapshot, PolError> {
+    Ok(Snapshot {
+        status: service.status().await?,
+        epochs: service.epoch_listing().await?,
+    })
+}
+
+async fn load_recent(service: &PolService) -> Result<Vec<RecentRecord>, PolError> {
+    let (from, to) = (DateTime::<Utc>::MIN_UTC, Utc::now() + Duration::seconds(1));
+    let mints = service
+        .get_mint_proofs_between(from, to, 0, usize::MAX)
+        .await?;
+    let burns = service
+        .get_burn_proofs_between(from, to, 0, usize::MAX)
+        .await?;
+
+    let mints = mints
+        .items
+        .into_iter()
+        .rev()
+        .take(RECENT_RECORDS)
+        .map(|r| RecentRecord {
+            kind: "mint",
+            epoch_id: r.epoch_id,
+            amount: r.record.amount,
+            timestamp: r.record.timestamp,
+        });
+    let burns = burns
+        .items
+        .into_iter()
+        .rev()
+        .take(RECENT_RECORDS)
+        .map(|r| RecentRecord {
+            kind: "burn",
+            epoch_id: r.epoch_id,
+            amount: r.record.amount,
+            timestamp: r.record.timestamp,
+        });
+    let mut records: Vec<RecentRecord> = mints.chain(burns).collect();
+
+    records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    records.truncate(RECENT_RECORDS);
+    Ok(records)
+}
+
+fn push_recent(records: &mut Vec<RecentRecord>, event: PolEvent) {
+    let record = match event {
+        PolEvent::MintRecorded { epoch_id, proof } => RecentRecord {
+            kind: "mint",
+            epoch_id,
+            amount: proof.amount,
+            timestamp: proof.timestamp,
+        },
+        PolEvent::BurnRecorded { epoch_id, proof } => RecentRecord {
+            kind: "burn",
+            epoch_id,
+            amount: proof.amount,
+            timestamp: proof.timestamp,
+        },
+        _ => return,
+    };
+    if records.contains(&record) {
+        return;
+    }
+
+    records.push(record);
+    records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    records.truncate(RECENT_RECORDS);
+}
+
+fn draw(frame: &mut Frame, snapshot: &Snapshot, recent: &[RecentRecord]) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(8),
+            Constraint::Min(5),
+            Constraint::Length(6),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+
+    frame.render_widget(
+        Paragraph::new(epoch_lines(snapshot)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Current epoch"),
+        ),
+        rows[0],
+    );
+
+    let records: Vec<ListItem> = recent
+        .iter()
+        .map(|record| {
+            ListItem::new(format!(
+                "{}  {:<4}  epoch {:<5}  {}",
+                record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                record.kind,
+                record.epoch_id,
+                record.amount
+            ))
+        })
+        .collect();
+    frame.render_widget(
+        List::new(records).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Recent records"),
+        ),
+        rows[1],
+    );
+
+    let alert_items: Vec<ListItem> = alerts(snapshot)
+        .into_iter()
+        .map(|alert| ListItem::new(alert).style(Style::default().fg(Color::Yellow)))
+        .collect();
+    frame.render_widget(
+        List::new(alert_items).block(Block::default().borders(Borders::ALL).title("Alerts")),
+        rows[2],
+    );
+
+    frame.render_widget(Paragraph::new("q: quit  r: refresh"), rows[3]);
+}
+
+fn epoch_lines(snapshot: &Snapshot) -> Vec<Line<'static>> {
+    let status = &snapshot.status;
+    let mut lines = vec![Line::from(format!(
+        "Total outstanding: {} sat across {} epochs",
+        status.outstanding.to_sat(),
+        status.epochs
+    ))];
+
+    match snapshot
+        .epochs
+        .iter()
+        .find(|e| e.epoch_id == status.current_epoch)
+    {
+        Some(epoch) => {
+            lines.push(Line::from(format!("Epoch: {}", epoch.epoch_id)));
+            lines.push(Line::from(format!(
+                "Started: {}",
+                epoch.start_time.format("%Y-%m-%d %H:%M:%S UTC")
+            )));
+            lines.push(Line::from(format!(
+                "Proofs: {} minted, {} burned",
+                epoch.mint_count, epoch.burn_count
+            )));
+            lines.push(Line::from(format!(
+                "Outstanding: {} sat",
+                epoch.outstanding.to_sat()
+            )));
+            lines.push(Line::from(format!(
+                "Rotation in: {}",
+                format_countdown(status.rotation_due_at - Utc::now())
+            )));
+        }
+        None => lines.push(Line::from(format!(
+            "Epoch {} not found in storage",
+            status.current_epoch
+        ))),
+    }
+
+    lines
+}
+
+fn alerts(snapshot: &Snapshot) -> Vec<String> {
+    let mut alerts = Vec::new();
+
+    for epoch in &snapshot.epochs {
+        if epoch.burned > epoch.minted {
+            alerts.push(format!(
+                "Epoch {} redeemed {} more than it issued",
+                epoch.epoch_id,
+                epoch.burned.saturating_sub(epoch.minted)
+            ));
+        }
+    }
+
+    if snapshot.status.rotation_due_at < Utc::now() {
+        alerts.push(format!(
+            "Epoch {} is past its scheduled rotation",
+            snapshot.status.current_epoch
+        ));
+    }
+
+    alerts
+}
+
+fn format_countdown(remaining: Duration) -> String {
+    if remaining <= Duration::zero() {
+        return "overdue".to_string();
+    }
+
+    format!(
+        "{}d {:02}h {:02}m {:02}s",
+        remaining.num_days(),
+        remaining.num_hours() % 24,
+        remaining.num_minutes() % 60,
+        remaining.num_seconds() % 60
+    )
+}
@@ -1,21 +1,160 @@
+use crate::merkle::{self, SumNode};
+use crate::{pedersen, smt};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{schnorr::Signature, XOnlyPublicKey};
 use bitcoin::Amount;
+use cdk::dhke::hash_to_curve;
 use cdk::nuts::nut00::Proof;
-use chrono::{DateTime, Utc};
+use cdk::nuts::nut01::PublicKey;
+use cdk::nuts::nut02::Id;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Amount with millisatoshi precision. Everything recorded is kept at this
+/// precision; values are only rounded to whole sats when reported.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct MilliSats(u64);
+
+impl MilliSats {
+    pub const ZERO: Self = Self(0);
+
+    pub const fn from_msat(msat: u64) -> Self {
+        Self(msat)
+    }
+
+    pub const fn from_sat(sat: u64) -> Self {
+        Self(sat.saturating_mul(1000))
+    }
+
+    /// Like `from_sat`, but refuses amounts too large to count in msat
+    /// instead of saturating.
+    pub fn try_from_sat(sat: u64) -> Result<Self, PolError> {
+        sat.checked_mul(1000)
+            .map(Self)
+            .ok_or_else(|| PolError::InvalidAmount(format!("{} sat overflows in msat", sat)))
+    }
+
+    pub const fn to_msat(self) -> u64 {
+        self.0
+    }
+
+    /// Whole sats, rounding half up.
+    pub const fn to_sat(self) -> u64 {
+        self.0 / 1000 + (self.0 % 1000 >= 500) as u64
+    }
+
+    pub fn to_amount(self) -> Amount {
+        Amount::from_sat(self.to_sat())
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn try_add(self, rhs: Self) -> Result<Self, PolError> {
+        self.checked_add(rhs)
+            .ok_or_else(|| PolError::InvalidAmount(format!("{} + {} overflows", self, rhs)))
+    }
+
+    pub fn try_sum<I: IntoIterator<Item = Self>>(amounts: I) -> Result<Self, PolError> {
+        amounts
+            .into_iter()
+            .try_fold(Self::ZERO, |total, amount| total.try_add(amount))
+    }
+}
+
+impl From<Amount> for MilliSats {
+    fn from(amount: Amount) -> Self {
+        Self::from_sat(amount.to_sat())
+    }
+}
+
+impl fmt::Display for MilliSats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 % 1000 {
+            0 => write!(f, "{} sat", self.0 / 1000),
+            msat => write!(f, "{}.{:03} sat", self.0 / 1000, msat),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct MintProof {
     pub proof: Proof,
-    pub amount: Amount,
+    pub amount: MilliSats,
     pub timestamp: DateTime<Utc>,
+    /// Absent from reports published before quotes were recorded. Stored
+    /// epochs are read by schema version rather than through this default.
+    #[serde(default)]
+    pub quote: Option<MintQuoteInfo>,
+}
+
+/// The mint quote a proof was issued against, for reconciling issuance with
+/// incoming Lightning payments.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct MintQuoteInfo {
+    pub quote_id: Option<String>,
+    /// Payment hash of the bolt11 invoice that paid for the quote
+    pub payment_hash: Option<String>,
+    /// Payment method, e.g. "bolt11"
+    pub method: Option<String>,
+}
+
+impl MintQuoteInfo {
+    /// Whether `quote_id_or_payment_hash` names this quote.
+    pub fn matches(&self, quote_id_or_payment_hash: &str) -> bool {
+        quote_matches(&self.quote_id, &self.payment_hash, quote_id_or_payment_hash)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct BurnProof {
     pub secret: String,
-    pub amount: Amount,
+    pub amount: MilliSats,
     pub timestamp: DateTime<Utc>,
+    /// Absent from reports published before melts were recorded, like
+    /// `MintProof::quote`
+    #[serde(default)]
+    pub melt: Option<MeltQuoteInfo>,
+}
+
+/// The melt a proof was redeemed in, tracing it to an outbound Lightning
+/// payment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct MeltQuoteInfo {
+    pub quote_id: Option<String>,
+    /// Payment hash of the invoice the mint paid
+    pub payment_hash: Option<String>,
+    /// Preimage returned by the payment, proving it settled
+    pub preimage: Option<String>,
+}
+
+impl MeltQuoteInfo {
+    /// Whether `quote_id_or_payment_hash` names this melt.
+    pub fn matches(&self, quote_id_or_payment_hash: &str) -> bool {
+        quote_matches(&self.quote_id, &self.payment_hash, quote_id_or_payment_hash)
+    }
+}
+
+fn quote_matches(quote_id: &Option<String>, payment_hash: &Option<String>, id: &str) -> bool {
+    quote_id.as_deref() == Some(id) || payment_hash.as_deref() == Some(id)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,13 +165,881 @@ pub struct EpochReport {
     pub mint_proofs: Vec<MintProof>,
     pub burn_proofs: Vec<BurnProof>,
     pub outstanding_balance: Amount,
+    /// Liabilities carried over from the previous epoch
+    #[serde(default)]
+    pub opening_balance: Amount,
+    /// `opening_balance` plus this epoch's mints, less its burns
+    #[serde(default)]
+    pub closing_balance: Amount,
+    /// All zeros in reports written before epochs carried commitments
+    #[serde(default = "zero_hash")]
+    pub commitment: sha256::Hash,
+    #[serde(default)]
+    pub attestations: Vec<EpochAttestation>,
+    #[serde(default)]
+    pub finalized_at: Option<DateTime<Utc>>,
+    /// Set in confidential mode, where `mint_proofs` and `burn_proofs` are
+    /// left empty, balances are zero and amounts are only published as
+    /// Pedersen commitments
+    #[serde(default)]
+    pub confidential: Option<ConfidentialEpoch>,
+    /// Keysets that were active at some point during the epoch
+    #[serde(default)]
+    pub keysets: Vec<Id>,
+    /// Root of the sparse Merkle tree over the epoch's burned Ys, against
+    /// which `BurnIndexProof`s show a Y was or was not burned
+    #[serde(default)]
+    pub burn_index: Option<sha256::Hash>,
+    /// Set in aggregates-only reports, where `mint_proofs` and
+    /// `burn_proofs` are left empty
+    #[serde(default)]
+    pub aggregates: Option<EpochAggregates>,
+}
+
+fn zero_hash() -> sha256::Hash {
+    sha256::Hash::all_zeros()
+}
+
+/// Proof counts and totals of an epoch, published instead of the proofs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochAggregates {
+    pub mint_count: u64,
+    pub burn_count: u64,
+    pub minted: MilliSats,
+    pub burned: MilliSats,
+    /// Sum-tree roots the epoch commitment is computed over, binding
+    /// `minted` and `burned` to it. Set in reports, not in `epoch show`.
+    #[serde(default)]
+    pub mint_root: Option<SumNode>,
+    #[serde(default)]
+    pub burn_root: Option<SumNode>,
+}
+
+impl EpochAggregates {
+    /// Whether the roots give `commitment` and sum to the published totals.
+    pub fn matches(&self, epoch: &EpochReport) -> bool {
+        let (Some(mint_root), Some(burn_root), Some(burn_index)) =
+            (&self.mint_root, &self.burn_root, &epoch.burn_index)
+        else {
+            return false;
+        };
+        mint_root.sum == self.minted
+            && burn_root.sum == self.burned
+            && epoch_commitment(
+                epoch.epoch_id,
+                epoch.start_time,
+                mint_root,
+                burn_root,
+                burn_index,
+            ) == epoch.commitment
+    }
+}
+
+impl EpochReport {
+    /// Recomputes the epoch commitment from the published proofs. Only
+    /// meaningful for non-confidential reports, which list every proof.
+    pub fn recompute_commitment(&self) -> Result<sha256::Hash, PolError> {
+        EpochState {
+            epoch_id: self.epoch_id,
+            start_time: self.start_time,
+            mint_proofs: self.mint_proofs.iter().cloned().collect(),
+            burn_proofs: self.burn_proofs.iter().cloned().collect(),
+        }
+        .commitment()
+    }
+
+    /// Mints signed under a keyset that was not active during the epoch.
+    /// Only meaningful once keysets have been registered.
+    pub fn mints_outside_keysets(&self) -> Vec<&MintProof> {
+        self.mint_proofs
+            .iter()
+            .filter(|p| !self.keysets.contains(&p.proof.keyset_id))
+            .collect()
+    }
+}
+
+/// Pedersen commitment `amount * G + r * H` to one proof's amount, where
+/// `r` is derived from the proof secret, with a proof that the amount is
+/// in range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmountCommitment {
+    pub y: PublicKey,
+    pub commitment: bitcoin::secp256k1::PublicKey,
+    pub range: RangeProof,
+}
+
+impl AmountCommitment {
+    /// Lets a holder check the commitment against their own proof.
+    pub fn opens_to(&self, secret: &[u8], amount: MilliSats) -> bool {
+        pedersen::commit(amount, &pedersen::blinding_factor(secret))
+            .is_ok_and(|c| c == self.commitment)
+    }
+}
+
+/// Commitment to the liabilities carried into an epoch from pruned
+/// history, with a proof that they are not negative.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarriedCommitment {
+    pub commitment: bitcoin::secp256k1::PublicKey,
+    pub range: RangeProof,
+}
+
+/// An epoch whose amounts are only published as commitments. Totals are
+/// sums of the per-proof commitments and are never opened; `None` when a
+/// side has no proofs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidentialEpoch {
+    pub mint_commitments: Vec<AmountCommitment>,
+    pub burn_commitments: Vec<AmountCommitment>,
+    pub minted: Option<bitcoin::secp256k1::PublicKey>,
+    pub burned: Option<bitcoin::secp256k1::PublicKey>,
+    /// Set on the oldest retained epoch once earlier epochs were pruned
+    #[serde(default)]
+    pub carried: Option<CarriedCommitment>,
+}
+
+/// OR-proof that one bit commitment hides 0 or `2^i`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitProof {
+    pub commitment: bitcoin::secp256k1::PublicKey,
+    pub e0: bitcoin::secp256k1::SecretKey,
+    pub e1: bitcoin::secp256k1::SecretKey,
+    pub s0: bitcoin::secp256k1::SecretKey,
+    pub s1: bitcoin::secp256k1::SecretKey,
+}
+
+/// Bit-decomposition range proof that a Pedersen commitment hides a value
+/// in `[0, 2^64)`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RangeProof {
+    pub bits: Vec<BitProof>,
+}
+
+/// Proof that the net committed liabilities of a confidential report are
+/// between zero and `upper_bound`, without opening them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiabilityBound {
+    pub commitment: bitcoin::secp256k1::PublicKey,
+    pub upper_bound: MilliSats,
+    /// The committed value is non-negative
+    pub lower: RangeProof,
+    /// `upper_bound` minus the committed value is non-negative
+    pub upper: RangeProof,
+}
+
+impl LiabilityBound {
+    pub fn verify(&self) -> bool {
+        pedersen::verify_range(&self.commitment, &self.lower, pedersen::RANGE_BITS)
+            && pedersen::complement(self.upper_bound, &self.commitment).is_some_and(|complement| {
+                pedersen::verify_range(&complement, &self.upper, pedersen::RANGE_BITS)
+            })
+    }
+
+    /// Also checks that the bound covers the commitments published in
+    /// `report`.
+    pub fn verify_against(&self, report: &PolReport) -> bool {
+        let epochs: Option<Vec<ConfidentialEpoch>> = report
+            .epoch_reports
+            .iter()
+            .map(|e| e.confidential.clone())
+            .collect();
+        epochs.is_some_and(|epochs| {
+            pedersen::net_commitment(&epochs) == Some(self.commitment) && self.verify()
+        })
+    }
+}
+
+impl ConfidentialEpoch {
+    /// Checks that every commitment is range-proven and that the per-proof
+    /// ones add up to the published totals.
+    pub fn verify(&self) -> bool {
+        let side = |commitments: &[AmountCommitment], total| {
+            let points: Vec<_> = commitments.iter().map(|c| c.commitment).collect();
+            pedersen::sum_commitments(&points) == total
+                && commitments.iter().all(|c| {
+                    pedersen::verify_range(&c.commitment, &c.range, pedersen::PROOF_RANGE_BITS)
+                })
+        };
+        let carried = self.carried.as_ref().map_or(true, |carried| {
+            pedersen::verify_range(&carried.commitment, &carried.range, pedersen::RANGE_BITS)
+        });
+        carried
+            && side(&self.mint_commitments, self.minted)
+            && side(&self.burn_commitments, self.burned)
+    }
+}
+
+/// A mint keyset and the period it was used to sign new proofs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeysetRecord {
+    pub id: Id,
+    pub unit: Option<String>,
+    /// Public key per denomination, when known
+    pub keys: BTreeMap<u64, PublicKey>,
+    pub active_from: DateTime<Utc>,
+    pub active_until: Option<DateTime<Utc>>,
+}
+
+impl KeysetRecord {
+    /// Whether the keyset was active at any point in `[start, end)`.
+    pub fn active_during(&self, start: DateTime<Utc>, end: Option<DateTime<Utc>>) -> bool {
+        end.map_or(true, |end| self.active_from < end)
+            && self.active_until.map_or(true, |until| until > start)
+    }
+}
+
+/// What a follower has learned about a mint through its public API. A mint
+/// only reveals its keysets and the spent state of proofs the follower
+/// already knows about, so liabilities built from this are partial.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalObservation {
+    pub mint_url: String,
+    pub polls: u64,
+    pub last_polled: DateTime<Utc>,
+    /// Proofs whose state was checked on the last poll
+    pub proofs_checked: usize,
+    /// Burns recorded because the mint reported a proof spent, across all polls
+    pub spent_observed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochFootprint {
+    pub epoch_id: u64,
+    pub start_time: DateTime<Utc>,
+    pub mint_proofs: usize,
+    pub burn_proofs: usize,
+    /// Serialized size of the epoch record
+    pub bytes: u64,
+}
+
+/// Where an epoch stands: still recording, closed by rotation, or sealed
+/// so it can no longer change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EpochStatus {
+    Open,
+    Closed,
+    Sealed,
+}
+
+/// One retained epoch as listed by `epochs list`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochListing {
+    pub epoch_id: u64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub mint_count: u64,
+    pub burn_count: u64,
+    pub minted: MilliSats,
+    pub burned: MilliSats,
+    pub outstanding: MilliSats,
+    pub status: EpochStatus,
+}
+
+/// Everything stored about one epoch, as printed by `epoch show`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochDetails {
+    pub epoch_id: u64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub status: EpochStatus,
+    pub aggregates: EpochAggregates,
+    pub outstanding: MilliSats,
+    /// The sealed commitment, or the one the epoch's proofs give today
+    pub commitment: sha256::Hash,
+    pub burn_index_root: sha256::Hash,
+    pub seal: Option<FinalizedEpoch>,
+    pub attestations: Vec<EpochAttestation>,
+    /// Present when a proof listing was asked for
+    pub mint_proofs: Option<Page<ListedMint>>,
+    pub burn_proofs: Option<Page<ListedBurn>>,
+}
+
+/// A mint proof as listed to operators, by Y and without the secret or
+/// `C` that would let whoever reads the listing spend it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListedMint {
+    #[serde(rename = "Y")]
+    pub y: PublicKey,
+    pub keyset_id: Id,
+    pub amount: MilliSats,
+    pub timestamp: DateTime<Utc>,
+    pub quote: Option<MintQuoteInfo>,
+}
+
+/// A burn proof as listed to operators, like `ListedMint`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListedBurn {
+    #[serde(rename = "Y")]
+    pub y: PublicKey,
+    pub amount: MilliSats,
+    pub timestamp: DateTime<Utc>,
+    pub melt: Option<MeltQuoteInfo>,
+}
+
+/// Issuance, redemption and the resulting balance over one time bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeriesPoint {
+    /// Start of the bucket
+    pub time: DateTime<Utc>,
+    pub issued: MilliSats,
+    pub redeemed: MilliSats,
+    /// Outstanding balance at the end of the bucket
+    pub outstanding: MilliSats,
+}
+
+/// Database size and growth, with projections for capacity planning.
+/// Projections assume the growth rate seen so far holds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageStats {
+    /// Size of the database file, including free pages and other tables
+    pub db_bytes: u64,
+    pub epochs: Vec<EpochFootprint>,
+    pub mint_proofs: usize,
+    pub burn_proofs: usize,
+    /// Epoch bytes written per day since the oldest retained epoch started
+    pub bytes_per_day: f64,
+    pub max_epoch_history: usize,
+    /// When the oldest epochs start being pruned; `None` once they are
+    pub retention_full_at: Option<DateTime<Utc>>,
+    /// Epoch bytes held once `max_epoch_history` epochs are retained
+    pub bytes_at_retention: u64,
+    pub disk_limit_bytes: Option<u64>,
+    /// When the database file reaches `disk_limit_bytes`, if it ever does
+    /// before retention caps its growth
+    pub disk_limit_at: Option<DateTime<Utc>>,
+}
+
+/// What an operator checks first: where the open epoch stands, what is
+/// owed, how much is stored and whether publication is keeping up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    pub current_epoch: u64,
+    pub epoch_started_at: DateTime<Utc>,
+    /// When the open epoch is due to rotate
+    pub rotation_due_at: DateTime<Utc>,
+    /// Negative once rotation is overdue
+    pub seconds_until_rotation: i64,
+    /// Running balance across retained epochs. Recorded amounts are all
+    /// in the sat unit, so there is a single total.
+    pub outstanding: MilliSats,
+    pub epochs: usize,
+    pub mint_proofs: u64,
+    pub burn_proofs: u64,
+    pub db_bytes: u64,
+    pub publications: Vec<PublicationStatus>,
+}
+
+/// Latest delivery outcome of one report sink.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicationStatus {
+    pub sink: String,
+    pub last_attempt: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Whether a report is still waiting to be delivered
+    pub pending: bool,
+}
+
+/// Seal over a closed epoch. Once stored, the epoch can no longer change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalizedEpoch {
+    pub epoch_id: u64,
+    pub commitment: sha256::Hash,
+    /// Signature over `commitment` by the service's signer, if one was set
+    pub signature: Option<ReportSignature>,
+    pub finalized_at: DateTime<Utc>,
+}
+
+/// An external party's signature over an epoch commitment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochAttestation {
+    pub epoch_id: u64,
+    pub commitment: sha256::Hash,
+    pub public_key: XOnlyPublicKey,
+    pub signature: Signature,
+    pub statement: Option<String>,
+    pub submitted_at: DateTime<Utc>,
+}
+
+impl EpochAttestation {
+    pub fn verify(&self) -> Result<(), PolError> {
+        crate::signer::verify_signature(&self.commitment, &self.signature, &self.public_key)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PolReport {
     pub epoch_reports: Vec<EpochReport>,
+    /// Running balance at the end of the latest epoch, including
+    /// liabilities carried over from pruned epochs
     pub total_outstanding_balance: Amount,
+    /// Outstanding liabilities at the end of each retained epoch, in order
+    pub cumulative_balances: Vec<CumulativeBalance>,
+    /// Liabilities left by epochs that have already been pruned
+    pub pruned_balance: Amount,
+    pub fiat_annotation: Option<FiatAnnotation>,
     pub timestamp: DateTime<Utc>,
+    /// Set when some of the data was observed from outside the mint, in
+    /// which case the figures are only a partial approximation
+    #[serde(default)]
+    pub external_observations: Vec<ExternalObservation>,
+    /// Head of the log of closed epochs at generation time
+    #[serde(default)]
+    pub history: Option<HistoryHead>,
+    /// Proofs that each closed epoch is logged under `history` with the
+    /// commitment and closing balance reported for it
+    #[serde(default)]
+    pub history_proofs: Vec<HistoryProof>,
+}
+
+/// Approximate fiat value of the report total, for readers only. It is not
+/// part of what the mint attests to. Values are integers in minor units of
+/// `currency` so signed reports hash the same everywhere.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiatAnnotation {
+    pub currency: String,
+    /// Digits after the decimal point in `currency`
+    pub decimals: u8,
+    pub btc_price: u64,
+    pub total_outstanding_value: u64,
+    pub source: String,
+    pub captured_at: DateTime<Utc>,
+    pub disclaimer: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CumulativeBalance {
+    pub epoch_id: u64,
+    pub balance: Amount,
+}
+
+/// A way in which a report disagrees with itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportMismatch {
+    /// Unset for checks over the whole report
+    pub epoch_id: Option<u64>,
+    pub check: String,
+    pub detail: String,
+}
+
+impl ReportMismatch {
+    pub(crate) fn new(epoch_id: Option<u64>, check: &str, detail: String) -> Self {
+        Self {
+            epoch_id,
+            check: check.to_string(),
+            detail,
+        }
+    }
+}
+
+impl PolReport {
+    /// SHA-256 over the report's JSON encoding, which is what gets signed.
+    pub fn commitment(&self) -> Result<sha256::Hash, PolError> {
+        let data = serde_json::to_vec(self)
+            .map_err(|e| PolError::ReportGenerationFailed(e.to_string()))?;
+        Ok(sha256::Hash::hash(&data))
+    }
+
+    /// Checks the report against itself, with nothing but its own contents:
+    /// published proofs against commitments and balances, confidential sums,
+    /// and the balance chain linking consecutive epochs. Balances are only
+    /// published in whole sats, so a closing balance may be off by one sat
+    /// from the recomputed one.
+    pub fn check_consistency(&self) -> Result<Vec<ReportMismatch>, PolError> {
+        let mut mismatches = Vec::new();
+
+        for epoch in &self.epoch_reports {
+            let id = Some(epoch.epoch_id);
+            if let Some(confidential) = &epoch.confidential {
+                if !confidential.verify() {
+                    mismatches.push(ReportMismatch::new(
+                        id,
+                        "confidential_totals",
+                        "commitments are out of range or do not sum to the totals".to_string(),
+                    ));
+                }
+                continue;
+            }
+
+            // Without proofs the totals are checked against the commitment
+            // through the published sum roots
+            let (minted, burned) = match &epoch.aggregates {
+                Some(aggregates) => {
+                    if !aggregates.matches(epoch) {
+                        mismatches.push(ReportMismatch::new(
+                            id,
+                            "aggregates",
+                            "totals are not bound to the commitment".to_string(),
+                        ));
+                    }
+                    (aggregates.minted, aggregates.burned)
+                }
+                None => {
+                    let commitment = epoch.recompute_commitment()?;
+                    if commitment != epoch.commitment {
+                        mismatches.push(ReportMismatch::new(
+                            id,
+                            "commitment",
+                            format!("published {}, recomputed {}", epoch.commitment, commitment),
+                        ));
+                    }
+
+                    if let Some(burn_index) = epoch.burn_index {
+                        let recomputed = EpochState {
+                            epoch_id: epoch.epoch_id,
+                            start_time: epoch.start_time,
+                            mint_proofs: Default::default(),
+                            burn_proofs: epoch.burn_proofs.iter().cloned().collect(),
+                        }
+                        .burn_index_root()?;
+                        if recomputed != burn_index {
+                            mismatches.push(ReportMismatch::new(
+                                id,
+                                "burn_index",
+                                format!("published {}, recomputed {}", burn_index, recomputed),
+                            ));
+                        }
+                    }
+
+                    (
+                        MilliSats::try_sum(epoch.mint_proofs.iter().map(|p| p.amount))?,
+                        MilliSats::try_sum(epoch.burn_proofs.iter().map(|p| p.amount))?,
+                    )
+                }
+            };
+            if let Some(head) = &self.history {
+                let proof = self
+                    .history_proofs
+                    .iter()
+                    .find(|proof| proof.entry.epoch_id == epoch.epoch_id);
+                match proof {
+                    Some(proof) if !proof.verify(head) => {
+                        mismatches.push(ReportMismatch::new(
+                            id,
+                            "history",
+                            format!("entry {} is not in the history log", proof.index),
+                        ));
+                    }
+                    Some(proof)
+                        if proof.entry.commitment != epoch.commitment
+                            || proof.entry.closing_balance.to_amount() != epoch.closing_balance =>
+                    {
+                        mismatches.push(ReportMismatch::new(
+                            id,
+                            "history",
+                            format!(
+                                "logged {} closing at {}, reported {} closing at {}",
+                                proof.entry.commitment,
+                                proof.entry.closing_balance.to_amount(),
+                                epoch.commitment,
+                                epoch.closing_balance
+                            ),
+                        ));
+                    }
+                    None if epoch.end_time.is_some() => {
+                        mismatches.push(ReportMismatch::new(
+                            id,
+                            "history",
+                            "closed epoch has no history log proof".to_string(),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+
+            let net = minted.saturating_sub(burned);
+            if net.to_amount() != epoch.outstanding_balance {
+                mismatches.push(ReportMismatch::new(
+                    id,
+                    "outstanding_balance",
+                    format!(
+                        "published {}, proofs sum to {}",
+                        epoch.outstanding_balance,
+                        net.to_amount()
+                    ),
+                ));
+            }
+
+            let closing = MilliSats::from(epoch.opening_balance)
+                .try_add(minted)?
+                .saturating_sub(burned);
+            let closing_sat = closing.to_sat();
+            if closing_sat.abs_diff(epoch.closing_balance.to_sat()) > 1 {
+                mismatches.push(ReportMismatch::new(
+                    id,
+                    "closing_balance",
+                    format!(
+                        "published {}, opening plus proofs gives {}",
+                        epoch.closing_balance,
+                        closing.to_amount()
+                    ),
+                ));
+            }
+        }
+
+        for pair in self.epoch_reports.windows(2) {
+            let (previous, next) = (&pair[0], &pair[1]);
+            if next.epoch_id <= previous.epoch_id || next.start_time < previous.start_time {
+                mismatches.push(ReportMismatch::new(
+                    Some(next.epoch_id),
+                    "epoch_order",
+                    format!("follows epoch {} out of order", previous.epoch_id),
+                ));
+            }
+            if previous.end_time.is_none() {
+                mismatches.push(ReportMismatch::new(
+                    Some(previous.epoch_id),
+                    "epoch_chain",
+                    "epoch is open but is not the last one".to_string(),
+                ));
+            }
+            if next.opening_balance != previous.closing_balance {
+                mismatches.push(ReportMismatch::new(
+                    Some(next.epoch_id),
+                    "epoch_chain",
+                    format!(
+                        "opens at {} but epoch {} closed at {}",
+                        next.opening_balance, previous.epoch_id, previous.closing_balance
+                    ),
+                ));
+            }
+        }
+
+        let closings: Vec<(u64, Amount)> = self
+            .epoch_reports
+            .iter()
+            .map(|e| (e.epoch_id, e.closing_balance))
+            .collect();
+        let cumulative: Vec<(u64, Amount)> = self
+            .cumulative_balances
+            .iter()
+            .map(|c| (c.epoch_id, c.balance))
+            .collect();
+        if cumulative != closings {
+            mismatches.push(ReportMismatch::new(
+                None,
+                "cumulative_balances",
+                "do not match the epochs' closing balances".to_string(),
+            ));
+        }
+
+        let first_opening = self
+            .epoch_reports
+            .first()
+            .map_or(Amount::ZERO, |e| e.opening_balance);
+        if self.pruned_balance != first_opening {
+            mismatches.push(ReportMismatch::new(
+                None,
+                "pruned_balance",
+                format!(
+                    "published {}, oldest epoch opens at {}",
+                    self.pruned_balance, first_opening
+                ),
+            ));
+        }
+
+        // The total is the running balance the closing balances chain to
+        let outstanding = self
+            .epoch_reports
+            .last()
+            .map_or(self.pruned_balance, |e| e.closing_balance);
+        if outstanding != self.total_outstanding_balance {
+            mismatches.push(ReportMismatch::new(
+                None,
+                "total_outstanding_balance",
+                format!(
+                    "published {}, latest closing balance is {}",
+                    self.total_outstanding_balance, outstanding
+                ),
+            ));
+        }
+
+        Ok(mismatches)
+    }
+}
+
+/// A stored record together with the epoch it was recorded in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochRecord<T> {
+    pub epoch_id: u64,
+    pub record: T,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Orders proofs by when they were recorded, breaking ties by Y so the
+/// order is total and consecutive pages neither overlap nor skip.
+pub(crate) fn sort_by_time_and_y<T>(
+    items: Vec<T>,
+    key: impl Fn(&T) -> Result<(DateTime<Utc>, PublicKey), PolError>,
+) -> Result<Vec<T>, PolError> {
+    let mut keyed = items
+        .into_iter()
+        .map(|item| {
+            let (timestamp, y) = key(&item)?;
+            Ok(((timestamp, y.to_bytes()), item))
+        })
+        .collect::<Result<Vec<_>, PolError>>()?;
+    keyed.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    Ok(keyed.into_iter().map(|(_, item)| item).collect())
+}
+
+impl<T> Page<T> {
+    pub(crate) fn from_sorted(all: Vec<T>, offset: usize, limit: usize) -> Self {
+        let total = all.len();
+        let items = all.into_iter().skip(offset).take(limit).collect();
+        Self {
+            items,
+            total,
+            offset,
+            limit,
+        }
+    }
+}
+
+/// Which keys may sign a report and how many of them must.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignaturePolicy {
+    pub threshold: usize,
+    pub signers: Vec<XOnlyPublicKey>,
+}
+
+impl SignaturePolicy {
+    pub fn new(threshold: usize, signers: Vec<XOnlyPublicKey>) -> Result<Self, PolError> {
+        if threshold == 0 || threshold > signers.len() {
+            return Err(PolError::InvalidSignature(format!(
+                "Threshold {} is not satisfiable by {} signers",
+                threshold,
+                signers.len()
+            )));
+        }
+
+        Ok(Self { threshold, signers })
+    }
+
+    pub fn single(signer: XOnlyPublicKey) -> Self {
+        Self {
+            threshold: 1,
+            signers: vec![signer],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportSignature {
+    pub public_key: XOnlyPublicKey,
+    pub signature: Signature,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedReport {
+    pub report: PolReport,
+    pub policy: SignaturePolicy,
+    pub commitment: sha256::Hash,
+    pub signatures: Vec<ReportSignature>,
+}
+
+impl SignedReport {
+    /// SHA-256 over the report together with its signature policy, so the
+    /// policy cannot be swapped out after signing.
+    pub fn commitment_for(
+        report: &PolReport,
+        policy: &SignaturePolicy,
+    ) -> Result<sha256::Hash, PolError> {
+        let data = serde_json::to_vec(&(report, policy))
+            .map_err(|e| PolError::ReportGenerationFailed(e.to_string()))?;
+        Ok(sha256::Hash::hash(&data))
+    }
+
+    pub fn add_signature(&mut self, signature: ReportSignature) -> Result<(), PolError> {
+        if !self.policy.signers.contains(&signature.public_key) {
+            return Err(PolError::InvalidSignature(format!(
+                "Key {} is not part of the signature policy",
+                signature.public_key
+            )));
+        }
+        crate::signer::verify_signature(
+            &self.commitment,
+            &signature.signature,
+            &signature.public_key,
+        )?;
+
+        self.signatures
+            .retain(|s| s.public_key != signature.public_key);
+        self.signatures.push(signature);
+        Ok(())
+    }
+
+    /// Number of distinct policy keys with a valid signature.
+    pub fn valid_signers(&self) -> usize {
+        self.valid_signers_among(&self.policy.signers)
+    }
+
+    fn valid_signers_among(&self, keys: &[XOnlyPublicKey]) -> usize {
+        self.signatures
+            .iter()
+            .filter(|s| keys.contains(&s.public_key))
+            .filter(|s| {
+                crate::signer::verify_signature(&self.commitment, &s.signature, &s.public_key)
+                    .is_ok()
+            })
+            .map(|s| s.public_key)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Keys with a valid signature over the report, or none when the
+    /// commitment does not match the report. Which of them to trust is up
+    /// to the caller.
+    pub fn signed_by(&self) -> Vec<XOnlyPublicKey> {
+        if !self.commitment_matches() {
+            return Vec::new();
+        }
+        let mut keys: Vec<XOnlyPublicKey> = self
+            .signatures
+            .iter()
+            .filter(|s| {
+                crate::signer::verify_signature(&self.commitment, &s.signature, &s.public_key)
+                    .is_ok()
+            })
+            .map(|s| s.public_key)
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+
+    fn commitment_matches(&self) -> bool {
+        Self::commitment_for(&self.report, &self.policy).is_ok_and(|c| c == self.commitment)
+    }
+
+    /// Checks the report against a policy the caller pinned out of band.
+    /// The policy embedded in the report is chosen by whoever produced it,
+    /// so it is never trusted on its own.
+    pub fn verify(&self, trusted: &SignaturePolicy) -> Result<(), PolError> {
+        if !self.commitment_matches() {
+            return Err(PolError::InvalidSignature(
+                "Commitment does not match report contents".to_string(),
+            ));
+        }
+
+        let valid = self.valid_signers_among(&trusted.signers);
+        if valid < trusted.threshold {
+            return Err(PolError::InvalidSignature(format!(
+                "{} of {} required signatures by trusted keys present",
+                valid, trusted.threshold
+            )));
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +1050,726 @@ pub struct EpochState {
     pub burn_proofs: HashSet<BurnProof>,
 }
 
+/// An epoch's proof counts and totals, stored apart from its proofs so they
+/// can be read without loading them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochSummary {
+    pub epoch_id: u64,
+    pub start_time: DateTime<Utc>,
+    pub mint_count: u64,
+    pub burn_count: u64,
+    pub minted: MilliSats,
+    pub burned: MilliSats,
+}
+
+impl EpochSummary {
+    pub fn aggregates(&self) -> EpochAggregates {
+        EpochAggregates {
+            mint_count: self.mint_count,
+            burn_count: self.burn_count,
+            minted: self.minted,
+            burned: self.burned,
+            mint_root: None,
+            burn_root: None,
+        }
+    }
+
+    pub fn outstanding(&self) -> MilliSats {
+        self.minted.saturating_sub(self.burned)
+    }
+}
+
+/// How ids are assigned to new epochs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EpochIdMode {
+    /// Ids count up from 0 as epochs are created.
+    #[default]
+    Sequential,
+    /// Ids are `floor(unix_time / epoch_duration)` and epochs start on
+    /// multiples of the duration, so independent observers of the same mint
+    /// agree on epoch ids.
+    TimeDerived,
+}
+
+impl EpochIdMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sequential => "sequential",
+            Self::TimeDerived => "time-derived",
+        }
+    }
+}
+
+impl fmt::Display for EpochIdMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for EpochIdMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sequential" => Ok(Self::Sequential),
+            "time-derived" => Ok(Self::TimeDerived),
+            other => Err(format!("Unknown epoch id mode: {}", other)),
+        }
+    }
+}
+
+/// One record read by `record --stdin`, a JSON object per line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProofRecord {
+    Mint {
+        proof: Proof,
+        #[serde(default)]
+        quote: Option<MintQuoteInfo>,
+    },
+    Burn {
+        secret: String,
+        /// Amount in sats
+        amount: u64,
+        #[serde(default)]
+        melt: Option<MeltQuoteInfo>,
+    },
+}
+
+/// Whether the proofs in a token are recorded as issued or redeemed, with
+/// the mint quote or melt they belong to when known.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenDirection {
+    Mint(Option<MintQuoteInfo>),
+    Burn(Option<MeltQuoteInfo>),
+}
+
+/// Administrative operations that rewrite epoch history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AuditOperation {
+    Merge {
+        epoch_ids: Vec<u64>,
+        merged_into: u64,
+        forced: bool,
+    },
+    Resegment {
+        /// `(epoch_id, start_time)` of every epoch before the operation
+        previous_epochs: Vec<(u64, DateTime<Utc>)>,
+        new_epoch_ids: Vec<u64>,
+        epoch_duration_secs: i64,
+        forced: bool,
+    },
+    Prune {
+        epoch_ids: Vec<u64>,
+        /// Liabilities carried into the oldest retained epoch
+        carried_balance: MilliSats,
+        forced: bool,
+    },
+    /// Seals made under the pre-sum-tree commitment, replaced by seals over
+    /// the current one
+    Reseal {
+        /// `(epoch_id, legacy commitment)` of every replaced seal
+        previous_seals: Vec<(u64, sha256::Hash)>,
+    },
+}
+
+/// Which closed epochs `prune` removes, always oldest first.
+#[derive(Debug, Clone, Copy)]
+pub enum PruneRule {
+    /// Epochs that ended longer ago than this
+    OlderThan(Duration),
+    /// All but this many of the newest epochs, counting the open one
+    KeepLatest(usize),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub operation: AuditOperation,
+}
+
+/// Where a proof, identified by its secret or Y, shows up in storage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofLookup {
+    pub y: PublicKey,
+    pub minted: Vec<EpochRecord<MintProof>>,
+    pub burned: Vec<EpochRecord<BurnProof>>,
+}
+
+pub(crate) fn secret_to_y(secret: &[u8]) -> Result<PublicKey, PolError> {
+    hash_to_curve(secret).map_err(|e| PolError::InvalidProof(e.to_string()))
+}
+
+/// Which of an epoch's two trees a leaf belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeafKind {
+    Mint,
+    Burn,
+}
+
+impl LeafKind {
+    fn tag(self) -> &'static [u8] {
+        match self {
+            Self::Mint => b"mint",
+            Self::Burn => b"burn",
+        }
+    }
+}
+
+impl MintProof {
+    pub fn y(&self) -> Result<PublicKey, PolError> {
+        secret_to_y(self.proof.secret.as_bytes())
+    }
+
+    pub(crate) fn leaf(&self) -> Result<SumNode, PolError> {
+        Ok(proof_leaf(LeafKind::Mint, &self.y()?, self.amount))
+    }
+
+    pub fn listing(&self) -> Result<ListedMint, PolError> {
+        Ok(ListedMint {
+            y: self.y()?,
+            keyset_id: self.proof.keyset_id,
+            amount: self.amount,
+            timestamp: self.timestamp,
+            quote: self.quote.clone(),
+        })
+    }
+}
+
+impl BurnProof {
+    pub fn y(&self) -> Result<PublicKey, PolError> {
+        secret_to_y(self.secret.as_bytes())
+    }
+
+    pub(crate) fn leaf(&self) -> Result<SumNode, PolError> {
+        Ok(proof_leaf(LeafKind::Burn, &self.y()?, self.amount))
+    }
+
+    pub fn listing(&self) -> Result<ListedBurn, PolError> {
+        Ok(ListedBurn {
+            y: self.y()?,
+            amount: self.amount,
+            timestamp: self.timestamp,
+            melt: self.melt.clone(),
+        })
+    }
+}
+
+/// Leaves commit to the proof's Y = hash_to_curve(secret) rather than the
+/// secret itself, so published trees never reveal spendable data. Each
+/// leaf carries the proof's amount into the sum tree.
+fn proof_leaf(kind: LeafKind, y: &PublicKey, amount: MilliSats) -> SumNode {
+    let mut data = kind.tag().to_vec();
+    data.extend_from_slice(&y.to_bytes());
+    data.extend_from_slice(&amount.to_msat().to_be_bytes());
+    SumNode {
+        hash: merkle::leaf_hash(&data),
+        sum: amount,
+    }
+}
+
+fn sorted_leaves<I>(leaves: I) -> Result<Vec<SumNode>, PolError>
+where
+    I: Iterator<Item = Result<SumNode, PolError>>,
+{
+    let mut leaves = leaves.collect::<Result<Vec<_>, _>>()?;
+    leaves.sort_unstable_by_key(|leaf| leaf.hash);
+    Ok(leaves)
+}
+
+fn legacy_leaves<I>(leaves: I) -> Result<Vec<sha256::Hash>, PolError>
+where
+    I: Iterator<Item = Result<SumNode, PolError>>,
+{
+    Ok(sorted_leaves(leaves)?
+        .into_iter()
+        .map(|leaf| leaf.hash)
+        .collect())
+}
+
+/// Binds the epoch's identity to both sum-tree roots and the burn index,
+/// so the commitment fixes the epoch's minted and burned totals as well as
+/// its proof sets, and a sealed epoch also fixes what was never burned.
+fn epoch_commitment(
+    epoch_id: u64,
+    start_time: DateTime<Utc>,
+    mint_root: &SumNode,
+    burn_root: &SumNode,
+    burn_index: &sha256::Hash,
+) -> sha256::Hash {
+    let mut engine = sha256::Hash::engine();
+    engine.input(&epoch_id.to_be_bytes());
+    engine.input(&start_time.timestamp().to_be_bytes());
+    for root in [mint_root, burn_root] {
+        engine.input(root.hash.as_byte_array());
+        engine.input(&root.sum.to_msat().to_be_bytes());
+    }
+    engine.input(burn_index.as_byte_array());
+    sha256::Hash::from_engine(engine)
+}
+
+/// Proof that a Y was counted in an epoch, checkable against the epoch
+/// commitment published in a report without revealing the secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub epoch_id: u64,
+    pub start_time: DateTime<Utc>,
+    pub kind: LeafKind,
+    pub amount: MilliSats,
+    pub leaf_index: u64,
+    pub tree_size: u64,
+    /// Sibling nodes from the leaf up to the root of its tree
+    pub path: Vec<SumNode>,
+    /// Root of the mint tree; its sum is the epoch's minted total
+    pub mint_root: SumNode,
+    /// Root of the burn tree; its sum is the epoch's burned total
+    pub burn_root: SumNode,
+    pub burn_index: sha256::Hash,
+}
+
+impl InclusionProof {
+    /// Checks that `y` is a leaf of this epoch and that the epoch hashes to
+    /// `commitment`. Since the roots carry their sums into the commitment,
+    /// this also proves `amount` was counted in the epoch's totals.
+    pub fn verify(&self, y: &PublicKey, commitment: &sha256::Hash) -> bool {
+        let (Ok(index), Ok(tree_size)) = (
+            usize::try_from(self.leaf_index),
+            usize::try_from(self.tree_size),
+        ) else {
+            return false;
+        };
+        let root = match self.kind {
+            LeafKind::Mint => self.mint_root,
+            LeafKind::Burn => self.burn_root,
+        };
+        let leaf = proof_leaf(self.kind, y, self.amount);
+
+        merkle::root_from_path(leaf, index, tree_size, &self.path) == Some(root)
+            && epoch_commitment(
+                self.epoch_id,
+                self.start_time,
+                &self.mint_root,
+                &self.burn_root,
+                &self.burn_index,
+            ) == *commitment
+    }
+
+    /// Whether the committed totals net out to the epoch's published
+    /// balance. Only meaningful once `verify` has passed.
+    pub fn matches_balance(&self, epoch: &EpochReport) -> bool {
+        self.epoch_id == epoch.epoch_id
+            && self
+                .mint_root
+                .sum
+                .saturating_sub(self.burn_root.sum)
+                .to_amount()
+                == epoch.outstanding_balance
+    }
+}
+
+/// A closed epoch as appended to the history log at rotation. The log is
+/// never pruned or rewritten, so it keeps covering epochs that reports no
+/// longer retain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub epoch_id: u64,
+    pub commitment: sha256::Hash,
+    /// Liabilities outstanding once the epoch closed
+    pub closing_balance: MilliSats,
+}
+
+impl HistoryEntry {
+    pub fn leaf(&self) -> sha256::Hash {
+        let mut data = self.epoch_id.to_be_bytes().to_vec();
+        data.extend_from_slice(self.commitment.as_byte_array());
+        data.extend_from_slice(&self.closing_balance.to_msat().to_be_bytes());
+        merkle::leaf_hash(&data)
+    }
+}
+
+/// Size and Merkle root of the history log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryHead {
+    pub size: u64,
+    pub root: sha256::Hash,
+}
+
+impl HistoryHead {
+    pub fn of(entries: &[HistoryEntry]) -> Self {
+        let leaves: Vec<_> = entries.iter().map(HistoryEntry::leaf).collect();
+        Self {
+            size: entries.len() as u64,
+            root: merkle::merkle_root(&leaves),
+        }
+    }
+}
+
+/// Proof that `entry` is leaf `index` of the history log with head `head`.
+/// An epoch rewritten after it was logged is logged again, so a report can
+/// only show the commitment the log holds for it last.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryProof {
+    pub entry: HistoryEntry,
+    pub index: u64,
+    pub path: Vec<sha256::Hash>,
+}
+
+impl HistoryProof {
+    pub fn verify(&self, head: &HistoryHead) -> bool {
+        let (Ok(index), Ok(size)) = (usize::try_from(self.index), usize::try_from(head.size))
+        else {
+            return false;
+        };
+        merkle::root_from_path(self.entry.leaf(), index, size, &self.path) == Some(head.root)
+    }
+}
+
+/// Certificate-transparency-style proof that the history log at `new` only
+/// appended to the log at `old`. A mint that rewrote an epoch it had
+/// already published cannot produce one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyProof {
+    pub old: HistoryHead,
+    pub new: HistoryHead,
+    pub path: Vec<sha256::Hash>,
+}
+
+impl ConsistencyProof {
+    pub fn verify(&self) -> bool {
+        merkle::verify_consistency(
+            self.old.size,
+            self.new.size,
+            &self.old.root,
+            &self.new.root,
+            &self.path,
+        )
+    }
+
+    /// Checks the proof links two published reports, older first.
+    pub fn verify_reports(&self, old: &PolReport, new: &PolReport) -> bool {
+        old.history == Some(self.old) && new.history == Some(self.new) && self.verify()
+    }
+}
+
+/// Sparse Merkle proof that a Y was, or was not, burned in an epoch, checked
+/// against the epoch commitment, which covers the burn index. Proving
+/// absence is what lets a wallet contest a redemption the mint claims but
+/// never happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BurnIndexProof {
+    pub epoch_id: u64,
+    pub start_time: DateTime<Utc>,
+    pub y: PublicKey,
+    /// Total burned under `y` in the epoch; `None` proves it was not burned
+    pub amount: Option<MilliSats>,
+    /// Non-empty siblings on the path to `y`, with their depths
+    pub siblings: Vec<(u16, sha256::Hash)>,
+    pub burn_index: sha256::Hash,
+    pub mint_root: SumNode,
+    pub burn_root: SumNode,
+}
+
+impl BurnIndexProof {
+    /// Checks the path against the burn index and that the epoch hashes to
+    /// `commitment`, the value a seal or published report fixes.
+    pub fn verify(&self, commitment: &sha256::Hash) -> bool {
+        let leaf = self.amount.map(|amount| burn_index_leaf(&self.y, amount));
+        smt::root_from_proof(&burn_index_key(&self.y), leaf, &self.siblings)
+            == Some(self.burn_index)
+            && epoch_commitment(
+                self.epoch_id,
+                self.start_time,
+                &self.mint_root,
+                &self.burn_root,
+                &self.burn_index,
+            ) == *commitment
+    }
+}
+
+/// Burned Ys are keyed by their hash so keys spread evenly over the tree.
+fn burn_index_key(y: &PublicKey) -> [u8; 32] {
+    sha256::Hash::hash(&y.to_bytes()).to_byte_array()
+}
+
+fn burn_index_leaf(y: &PublicKey, amount: MilliSats) -> sha256::Hash {
+    proof_leaf(LeafKind::Burn, y, amount).hash
+}
+
+/// Signed evidence, handed back to a wallet at record time, that a proof
+/// was recorded in an epoch. Once the epoch is published, an inclusion
+/// proof for the same Y shows whether the mint kept its word.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    pub kind: LeafKind,
+    pub epoch_id: u64,
+    pub y: PublicKey,
+    pub amount: MilliSats,
+    pub timestamp: DateTime<Utc>,
+    pub signature: ReportSignature,
+}
+
+impl Receipt {
+    /// What the mint signs: every field but the signature.
+    pub fn digest(
+        kind: LeafKind,
+        epoch_id: u64,
+        y: &PublicKey,
+        amount: MilliSats,
+        timestamp: DateTime<Utc>,
+    ) -> sha256::Hash {
+        let mut engine = sha256::Hash::engine();
+        engine.input(b"cashu-pol/receipt");
+        engine.input(kind.tag());
+        engine.input(&epoch_id.to_be_bytes());
+        engine.input(&y.to_bytes());
+        engine.input(&amount.to_msat().to_be_bytes());
+        engine.input(&timestamp.timestamp_millis().to_be_bytes());
+        sha256::Hash::from_engine(engine)
+    }
+
+    pub fn verify(&self) -> Result<(), PolError> {
+        let digest = Self::digest(
+            self.kind,
+            self.epoch_id,
+            &self.y,
+            self.amount,
+            self.timestamp,
+        );
+        crate::signer::verify_signature(
+            &digest,
+            &self.signature.signature,
+            &self.signature.public_key,
+        )
+    }
+
+    /// Checks the receipt's signature and that `proof` shows this exact
+    /// record counted in an epoch hashing to `commitment`.
+    pub fn verify_inclusion(&self, proof: &InclusionProof, commitment: &sha256::Hash) -> bool {
+        self.verify().is_ok()
+            && proof.epoch_id == self.epoch_id
+            && proof.kind == self.kind
+            && proof.amount == self.amount
+            && proof.verify(&self.y, commitment)
+    }
+
+    /// Checks the receipt against a published report: the epoch it names
+    /// must list the record, or, when proof lists are withheld, one of
+    /// `proofs` must show it counted in the epoch's commitment.
+    pub fn verify_in_report(&self, report: &PolReport, proofs: &[InclusionProof]) -> bool {
+        let Some(epoch) = report
+            .epoch_reports
+            .iter()
+            .find(|e| e.epoch_id == self.epoch_id)
+        else {
+            return false;
+        };
+        if proofs
+            .iter()
+            .any(|proof| self.verify_inclusion(proof, &epoch.commitment))
+        {
+            return true;
+        }
+
+        let listed = match self.kind {
+            LeafKind::Mint => epoch
+                .mint_proofs
+                .iter()
+                .any(|p| p.amount == self.amount && p.y().is_ok_and(|y| y == self.y)),
+            LeafKind::Burn => epoch
+                .burn_proofs
+                .iter()
+                .any(|p| p.amount == self.amount && p.y().is_ok_and(|y| y == self.y)),
+        };
+        // Listed proofs only count if they are the ones the commitment covers
+        listed
+            && self.verify().is_ok()
+            && epoch
+                .recompute_commitment()
+                .is_ok_and(|commitment| commitment == epoch.commitment)
+    }
+}
+
+impl EpochState {
+    pub fn minted(&self) -> Result<MilliSats, PolError> {
+        MilliSats::try_sum(self.mint_proofs.iter().map(|p| p.amount))
+    }
+
+    pub fn burned(&self) -> Result<MilliSats, PolError> {
+        MilliSats::try_sum(self.burn_proofs.iter().map(|p| p.amount))
+    }
+
+    pub fn summary(&self) -> Result<EpochSummary, PolError> {
+        Ok(EpochSummary {
+            epoch_id: self.epoch_id,
+            start_time: self.start_time,
+            mint_count: self.mint_proofs.len() as u64,
+            burn_count: self.burn_proofs.len() as u64,
+            minted: self.minted()?,
+            burned: self.burned()?,
+        })
+    }
+
+    /// Commitment to the epoch's identity and full proof sets.
+    pub fn commitment(&self) -> Result<sha256::Hash, PolError> {
+        let (mint_root, burn_root) = self.sum_roots()?;
+        Ok(epoch_commitment(
+            self.epoch_id,
+            self.start_time,
+            &mint_root,
+            &burn_root,
+            &self.burn_index_root()?,
+        ))
+    }
+
+    pub(crate) fn sum_roots(&self) -> Result<(SumNode, SumNode), PolError> {
+        let mint_root = merkle::merkle_root(&sorted_leaves(
+            self.mint_proofs.iter().map(MintProof::leaf),
+        )?);
+        let burn_root = merkle::merkle_root(&sorted_leaves(
+            self.burn_proofs.iter().map(BurnProof::leaf),
+        )?);
+        Ok((mint_root, burn_root))
+    }
+
+    /// Commitment under the scheme used before sum trees, where leaves and
+    /// nodes carried no amounts. Only used to recognise seals made with it.
+    pub(crate) fn legacy_commitment(&self) -> Result<sha256::Hash, PolError> {
+        let mint_root = merkle::merkle_root(&legacy_leaves(
+            self.mint_proofs.iter().map(MintProof::leaf),
+        )?);
+        let burn_root = merkle::merkle_root(&legacy_leaves(
+            self.burn_proofs.iter().map(BurnProof::leaf),
+        )?);
+        let mut engine = sha256::Hash::engine();
+        engine.input(&self.epoch_id.to_be_bytes());
+        engine.input(&self.start_time.timestamp().to_be_bytes());
+        engine.input(mint_root.as_byte_array());
+        engine.input(burn_root.as_byte_array());
+        Ok(sha256::Hash::from_engine(engine))
+    }
+
+    /// Burned amounts per Y, keyed for the sparse Merkle tree and sorted.
+    fn burn_index_entries(&self) -> Result<Vec<([u8; 32], sha256::Hash)>, PolError> {
+        let mut burned: BTreeMap<[u8; 32], (PublicKey, MilliSats)> = BTreeMap::new();
+        for proof in &self.burn_proofs {
+            let y = proof.y()?;
+            let entry = burned
+                .entry(burn_index_key(&y))
+                .or_insert((y, MilliSats::ZERO));
+            entry.1 = entry.1.try_add(proof.amount)?;
+        }
+        Ok(burned
+            .into_iter()
+            .map(|(key, (y, amount))| (key, burn_index_leaf(&y, amount)))
+            .collect())
+    }
+
+    pub fn burn_index_root(&self) -> Result<sha256::Hash, PolError> {
+        Ok(smt::root(&self.burn_index_entries()?))
+    }
+
+    /// Proof that `y` was or was not burned in this epoch.
+    pub fn burn_index_proof(&self, y: &PublicKey) -> Result<BurnIndexProof, PolError> {
+        let mut amount = None;
+        for proof in &self.burn_proofs {
+            if proof.y()? == *y {
+                amount = Some(amount.unwrap_or(MilliSats::ZERO).try_add(proof.amount)?);
+            }
+        }
+        let entries = self.burn_index_entries()?;
+        let (mint_root, burn_root) = self.sum_roots()?;
+        Ok(BurnIndexProof {
+            epoch_id: self.epoch_id,
+            start_time: self.start_time,
+            y: *y,
+            amount,
+            siblings: smt::proof(&entries, &burn_index_key(y)),
+            burn_index: smt::root(&entries),
+            mint_root,
+            burn_root,
+        })
+    }
+
+    /// Inclusion proofs for every leaf with the given Y, mints first.
+    pub fn inclusion_proofs(&self, y: &PublicKey) -> Result<Vec<InclusionProof>, PolError> {
+        let mint_leaves = sorted_leaves(self.mint_proofs.iter().map(MintProof::leaf))?;
+        let burn_leaves = sorted_leaves(self.burn_proofs.iter().map(BurnProof::leaf))?;
+        let mint_root = merkle::merkle_root(&mint_leaves);
+        let burn_root = merkle::merkle_root(&burn_leaves);
+        let burn_index = self.burn_index_root()?;
+
+        let mut matches = Vec::new();
+        for proof in &self.mint_proofs {
+            if proof.y()? == *y {
+                matches.push((LeafKind::Mint, proof.amount));
+            }
+        }
+        for proof in &self.burn_proofs {
+            if proof.y()? == *y {
+                matches.push((LeafKind::Burn, proof.amount));
+            }
+        }
+
+        let mut proofs = Vec::with_capacity(matches.len());
+        for (kind, amount) in matches {
+            let leaves = match kind {
+                LeafKind::Mint => &mint_leaves,
+                LeafKind::Burn => &burn_leaves,
+            };
+            let leaf = proof_leaf(kind, y, amount);
+            let index = leaves
+                .binary_search_by_key(&leaf.hash, |l| l.hash)
+                .map_err(|_| {
+                    PolError::ReportGenerationFailed("Leaf missing from its own tree".to_string())
+                })?;
+            proofs.push(InclusionProof {
+                epoch_id: self.epoch_id,
+                start_time: self.start_time,
+                kind,
+                amount,
+                leaf_index: index as u64,
+                tree_size: leaves.len() as u64,
+                path: merkle::inclusion_path(leaves, index),
+                mint_root,
+                burn_root,
+                burn_index,
+            });
+        }
+
+        Ok(proofs)
+    }
+}
+
+/// A persisted artifact that no longer matches what the stored proofs
+/// produce.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditMismatch {
+    pub epoch_id: u64,
+    pub artifact: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SelfAuditReport {
+    pub checked_epochs: usize,
+    pub checked_seals: usize,
+    pub checked_attestations: usize,
+    /// Seals that match the pre-sum-tree commitment; see `reseal_epochs`
+    pub legacy_seals: Vec<u64>,
+    pub mismatches: Vec<AuditMismatch>,
+}
+
+impl SelfAuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Boxed source of a serialization failure (bincode or JSON).
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 #[derive(Debug, thiserror::Error)]
 pub enum PolError {
     #[error("Invalid epoch: {0}")]
@@ -55,26 +1782,146 @@ pub enum PolError {
     ReportGenerationFailed(String),
 
     #[error("Database error: {0}")]
-    DatabaseError(String),
+    DatabaseError(#[source] redb::Error),
 
     #[error("Database transaction error: {0}")]
-    DatabaseTransactionError(String),
+    DatabaseTransactionError(#[source] redb::Error),
 
     #[error("Database serialization error: {0}")]
-    DatabaseSerializationError(String),
+    DatabaseSerializationError(#[source] BoxError),
 
     #[error("Database deserialization error: {0}")]
-    DatabaseDeserializationError(String),
+    DatabaseDeserializationError(#[source] BoxError),
 
     #[error("Database initialization error: {0}")]
-    DatabaseInitializationError(String),
+    DatabaseInitializationError(#[source] redb::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
 
     #[error("Epoch not found: {0}")]
     EpochNotFound(u64),
 
+    #[error("Epoch {0} is finalized")]
+    EpochFinalized(u64),
+
     #[error("Invalid proof: {0}")]
     InvalidProof(String),
 
     #[error("Invalid amount: {0}")]
     InvalidAmount(String),
+
+    #[error("Invalid range: {0}")]
+    InvalidRange(String),
+
+    #[error("Report publication failed: {0}")]
+    PublicationFailed(String),
+
+    #[error("Signing failed: {0}")]
+    SigningFailed(String),
+
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Keyset not found: {0}")]
+    KeysetNotFound(String),
+
+    #[error("Mint unreachable: {0}")]
+    MintUnreachable(String),
+
+    #[error("Archive failed: {0}")]
+    ArchiveFailed(String),
+
+    #[error("Export failed: {0}")]
+    ExportFailed(String),
+
+    #[error("Invalid bundle: {0}")]
+    InvalidBundle(String),
+
+    #[error("Self-audit found {0} mismatches")]
+    SelfAuditFailed(usize),
+
+    #[error("Malformed report: {0}")]
+    MalformedReport(String),
+}
+
+impl PolError {
+    /// Stable machine-readable identifier for the error kind. Codes never
+    /// change once published, even if messages do.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidEpoch(_) => "invalid_epoch",
+            Self::ProofVerificationFailed(_) => "proof_verification_failed",
+            Self::ReportGenerationFailed(_) => "report_generation_failed",
+            Self::DatabaseError(_) => "database_error",
+            Self::DatabaseTransactionError(_) => "database_transaction_error",
+            Self::DatabaseSerializationError(_) => "database_serialization_error",
+            Self::DatabaseDeserializationError(_) => "database_deserialization_error",
+            Self::DatabaseInitializationError(_) => "database_initialization_error",
+            Self::Io(_) => "io_error",
+            Self::EpochNotFound(_) => "epoch_not_found",
+            Self::EpochFinalized(_) => "epoch_finalized",
+            Self::InvalidProof(_) => "invalid_proof",
+            Self::InvalidAmount(_) => "invalid_amount",
+            Self::InvalidRange(_) => "invalid_range",
+            Self::PublicationFailed(_) => "publication_failed",
+            Self::SigningFailed(_) => "signing_failed",
+            Self::InvalidSignature(_) => "invalid_signature",
+            Self::KeysetNotFound(_) => "keyset_not_found",
+            Self::MintUnreachable(_) => "mint_unreachable",
+            Self::ArchiveFailed(_) => "archive_failed",
+            Self::ExportFailed(_) => "export_failed",
+            Self::InvalidBundle(_) => "invalid_bundle",
+            Self::SelfAuditFailed(_) => "self_audit_failed",
+            Self::MalformedReport(_) => "malformed_report",
+        }
+    }
+
+    /// HTTP status a server answers with for this error. Bad input is the
+    /// client's fault, unreachable mints and sinks are upstream failures,
+    /// and transient storage errors are worth retrying.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Self::InvalidEpoch(_)
+            | Self::InvalidProof(_)
+            | Self::InvalidAmount(_)
+            | Self::InvalidRange(_)
+            | Self::InvalidSignature(_)
+            | Self::InvalidBundle(_)
+            | Self::MalformedReport(_) => 400,
+            Self::EpochNotFound(_) | Self::KeysetNotFound(_) => 404,
+            Self::EpochFinalized(_) => 409,
+            Self::ProofVerificationFailed(_) | Self::SelfAuditFailed(_) => 422,
+            Self::MintUnreachable(_) | Self::PublicationFailed(_) => 502,
+            _ if self.is_retryable() => 503,
+            _ => 500,
+        }
+    }
+
+    /// Whether the failure is transient (lock contention, interrupted or
+    /// timed out I/O) and the operation may succeed if tried again.
+    /// Everything else, including hard I/O errors, corruption and bad data,
+    /// is fatal.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::DatabaseError(e)
+            | Self::DatabaseTransactionError(e)
+            | Self::DatabaseInitializationError(e) => match e {
+                redb::Error::DatabaseAlreadyOpen => true,
+                redb::Error::Io(io) => is_transient_io(io),
+                _ => false,
+            },
+            Self::Io(io) => is_transient_io(io),
+            _ => false,
+        }
+    }
+}
+
+fn is_transient_io(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        std::io::ErrorKind::Interrupted
+            | std::io::ErrorKind::WouldBlock
+            | std::io::ErrorKind::TimedOut
+    )
 }
@@ -0,0 +1,90 @@
+use crate::output::{self, OutputFormat};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+/// Outcome of a verification command. Each status has its own exit code so
+/// scripts can branch without parsing output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Verified,
+    Mismatch,
+    NotFound,
+    Error,
+}
+
+impl Status {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Verified => 0,
+            Self::Mismatch => 1,
+            Self::NotFound => 2,
+            Self::Error => 3,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Verdict {
+    pub status: Status,
+    pub checked: BTreeMap<&'static str, usize>,
+    pub mismatches: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Verdict {
+    /// Verified when there are no mismatches, otherwise a mismatch.
+    pub fn from_checks<M: Serialize>(
+        checked: impl IntoIterator<Item = (&'static str, usize)>,
+        mismatches: &[M],
+    ) -> Self {
+        let mismatches: Vec<Value> = mismatches
+            .iter()
+            .map(|m| serde_json::to_value(m).unwrap_or(Value::Null))
+            .collect();
+        Self {
+            status: if mismatches.is_empty() {
+                Status::Verified
+            } else {
+                Status::Mismatch
+            },
+            checked: checked.into_iter().collect(),
+            mismatches,
+            details: None,
+            error: None,
+        }
+    }
+
+    pub fn not_found(checked: impl IntoIterator<Item = (&'static str, usize)>) -> Self {
+        Self {
+            status: Status::NotFound,
+            ..Self::from_checks::<Value>(checked, &[])
+        }
+    }
+
+    pub fn error(error: impl Display) -> Self {
+        Self {
+            status: Status::Error,
+            error: Some(error.to_string()),
+            ..Self::from_checks::<Value>([], &[])
+        }
+    }
+
+    pub fn with_details(mut self, details: impl Serialize) -> Self {
+        self.details = serde_json::to_value(details).ok();
+        self
+    }
+
+    /// Prints the verdict on stdout and exits with its status code.
+    pub fn exit(self, format: OutputFormat) -> ! {
+        if let Err(e) = output::print(format, &self) {
+            eprintln!("Failed to serialize verdict: {}", e);
+        }
+        std::process::exit(self.status.exit_code())
+    }
+}